@@ -36,8 +36,9 @@ Wasabi.analysis = {
     },
 
     // ifLocation === location of the matching if block for else
-    end(location, type, beginLocation, ifLocation) {
-        console.log(location, "end", type, "(begin @", beginLocation, ", if begin @", ifLocation, ")");
+    // values === results of the block (empty if the block was left by a branch or return)
+    end(location, type, beginLocation, ifLocation, values) {
+        console.log(location, "end", type, "(begin @", beginLocation, ", if begin @", ifLocation, "), values =", values);
     },
 
     drop(location, value) {
@@ -49,8 +50,9 @@ Wasabi.analysis = {
     },
 
     // indirectTableIdx === undefined iff direct call, for indirect calls it is a number
-    call_pre(location, targetFunc, args, indirectTableIdx) {
-        console.log(location, (indirectTableIdx === undefined ? "direct" : "indirect"), "call", "to func #", targetFunc, "args =", args);
+    // tailCall === true for return_call(_indirect), then no call_post follows in this function
+    call_pre(location, targetFunc, args, indirectTableIdx, tailCall) {
+        console.log(location, (indirectTableIdx === undefined ? "direct" : "indirect"), (tailCall ? "tail call" : "call"), "to func #", targetFunc, "args =", args);
     },
 
     call_post(location, values) {
@@ -81,12 +83,60 @@ Wasabi.analysis = {
         console.log(location, op, "value =", value, "to =", memarg);
     },
 
-    memory_size(location, currentSizePages) {
-        console.log(location, "memory_size, size (in pages) =", currentSizePages);
+    memory_size(location, currentSizePages, memory) {
+        console.log(location, "memory_size, memory =", memory, "size (in pages) =", currentSizePages);
     },
 
-    memory_grow(location, byPages, previousSizePages) {
-        console.log(location, "memory_grow, delta (in pages) =", byPages, "previous size (in pages) =", previousSizePages);
+    memory_grow(location, byPages, previousSizePages, memory) {
+        console.log(location, "memory_grow, memory =", memory, "delta (in pages) =", byPages, "previous size (in pages) =", previousSizePages);
+    },
+
+    memory_init(location, dataIndex, destination, source, length, memory) {
+        console.log(location, "memory_init, memory =", memory, "data segment =", dataIndex, "destination =", destination, "source =", source, "length =", length);
+    },
+
+    data_drop(location, dataIndex) {
+        console.log(location, "data_drop, data segment =", dataIndex);
+    },
+
+    memory_copy(location, destination, source, length, destinationMemory, sourceMemory) {
+        console.log(location, "memory_copy, destination =", destination, "(memory", destinationMemory + ")", "source =", source, "(memory", sourceMemory + ")", "length =", length);
+    },
+
+    memory_fill(location, destination, value, length, memory) {
+        console.log(location, "memory_fill, memory =", memory, "destination =", destination, "value =", value, "length =", length);
+    },
+
+    table_init(location, elemIndex, destination, source, length, table) {
+        console.log(location, "table_init, table #", table, "element segment =", elemIndex, "destination =", destination, "source =", source, "length =", length);
+    },
+
+    elem_drop(location, elemIndex) {
+        console.log(location, "elem_drop, element segment =", elemIndex);
+    },
+
+    table_copy(location, destination, source, length, destinationTable, sourceTable) {
+        console.log(location, "table_copy, destination =", destination, "(table", destinationTable + ")", "source =", source, "(table", sourceTable + ")", "length =", length);
+    },
+
+    table_get(location, table, index, value) {
+        console.log(location, "table_get, table #", table, "index =", index, "value =", value);
+    },
+
+    table_set(location, table, index, value) {
+        console.log(location, "table_set, table #", table, "index =", index, "value =", value);
+    },
+
+    table_size(location, table, currentSize) {
+        console.log(location, "table_size, table #", table, "size =", currentSize);
+    },
+
+    table_grow(location, table, initValue, delta, previousSize) {
+        console.log(location, "table_grow, table #", table, "init value =", initValue, "delta =", delta, "previous size =", previousSize);
+    },
+
+    table_fill(location, table, index, value, length) {
+        console.log(location, "table_fill, table #", table, "index =", index, "value =", value, "length =", length);
     },
 
     local(location, op, localIndex, value) {
@@ -96,4 +146,48 @@ Wasabi.analysis = {
     global(location, op, globalIndex, value) {
         console.log(location, op, "global #", globalIndex, "value =", value);
     },
+
+    simd(location, op, inputs, result, immediate) {
+        console.log(location, op, "inputs =", inputs, "result =", result, "immediate =", immediate);
+    },
+
+    atomic_load(location, op, memarg, value, order) {
+        console.log(location, op, "value =", value, "from =", memarg, "order =", order);
+    },
+
+    atomic_store(location, op, memarg, value, order) {
+        console.log(location, op, "value =", value, "to =", memarg, "order =", order);
+    },
+
+    atomic_rmw(location, op, memarg, operand, oldValue, order) {
+        console.log(location, op, "operand =", operand, "old value =", oldValue, "at =", memarg, "order =", order);
+    },
+
+    atomic_cmpxchg(location, op, memarg, expected, replacement, oldValue, order) {
+        console.log(location, op, "expected =", expected, "replacement =", replacement, "old value =", oldValue, "at =", memarg, "order =", order);
+    },
+
+    atomic_wait(location, op, memarg, expected, timeout, result) {
+        console.log(location, op, "expected =", expected, "timeout =", timeout, "result =", result, "at =", memarg);
+    },
+
+    atomic_notify(location, memarg, count, woken) {
+        console.log(location, "memory.atomic.notify, count =", count, "woken =", woken, "at =", memarg);
+    },
+
+    atomic_fence(location, order) {
+        console.log(location, "atomic.fence, order =", order);
+    },
+
+    throw_(location, tag, values) {
+        console.log(location, "throw, tag =", tag, ", values =", values);
+    },
+
+    rethrow(location, catchLocation) {
+        console.log(location, "rethrow, exception caught @", catchLocation);
+    },
+
+    catch_(location, tag, values) {
+        console.log(location, tag === undefined ? "catch_all" : "catch", ", tag =", tag, ", values =", values);
+    },
 };
\ No newline at end of file
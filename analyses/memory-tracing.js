@@ -1,15 +1,26 @@
 {
 	// TODO possibly compress accesses to avoid OOM
-	const accesses /* : [{func, instr, addr, write: bool}] */ = [];
+	const accesses /* : [{func, instr, memory, addr, write: bool, length?}] */ = [];
 
-	function access({func, instr}, {addr, offset}, write) {
-		accesses.push({func, instr, addr: addr+offset, write});
+	function access({func, instr}, {addr, offset, memory}, write) {
+		accesses.push({func, instr, memory, addr: addr+offset, write});
+	};
+
+	// bulk memory operations access a whole range of bytes at once
+	function rangeAccess({func, instr}, memory, addr, length, write) {
+		accesses.push({func, instr, memory, addr, length, write});
 	};
 
 	Wasabi.analysis = {
 		load(loc, op, memarg) { access(loc, memarg, false) },
 		store(loc, op, memarg) { access(loc, memarg, true) },
+		memory_copy(loc, destination, source, length, destinationMemory, sourceMemory) {
+			rangeAccess(loc, sourceMemory, source, length, false);
+			rangeAccess(loc, destinationMemory, destination, length, true);
+		},
+		memory_fill(loc, destination, value, length, memory) { rangeAccess(loc, memory, destination, length, true) },
+		memory_init(loc, dataIndex, destination, source, length, memory) { rangeAccess(loc, memory, destination, length, true) },
 	};
 
 	Wasabi.analysisResult = accesses;
-}
\ No newline at end of file
+}
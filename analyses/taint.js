@@ -140,7 +140,7 @@
             }
         },
 
-        call_pre(location, targetFunc, args, indirectTableIdx) {
+        call_pre(location, targetFunc, args, indirectTableIdx, tailCall) {
             if (indirectTableIdx !== undefined) {
                 values().pop();
             }
@@ -156,6 +156,10 @@
                     console.log("Tainted value reached sink at ", location);
                 }
             }
+            // the callee of a tail call replaces the current function (and its call_post pops its frame)
+            if (tailCall) {
+                stack.pop();
+            }
             stack.push({
                 blocks:[],
                 locals:argTaints,
@@ -25,14 +25,37 @@ let Wasabi = {
         "store",
         "memory_size",
         "memory_grow",
+        "memory_init",
+        "data_drop",
+        "memory_copy",
+        "memory_fill",
+        "table_init",
+        "elem_drop",
+        "table_copy",
+        "table_get",
+        "table_set",
+        "table_size",
+        "table_grow",
+        "table_fill",
         "local",
-        "global"
+        "global",
+        "simd",
+        "atomic_load",
+        "atomic_store",
+        "atomic_rmw",
+        "atomic_cmpxchg",
+        "atomic_wait",
+        "atomic_notify",
+        "atomic_fence",
+        "throw_",
+        "rethrow",
+        "catch_"
     ],
 
-    // map a table index to a function index
-    resolveTableIdx: function (tableIdx) {
-        if (Wasabi.module.exports === undefined || Wasabi.module.table === undefined) {
-            console.warn("cannot resolve table index without exports and table (possible reason: exports and table are not available during Wasm start function)");
+    // map an index into the given table (default: the first one) to a function index
+    resolveTableIdx: function (tableIdx, table = 0) {
+        if (Wasabi.module.exports === undefined || Wasabi.module.tables === undefined || Wasabi.module.tables[table] === undefined) {
+            console.warn("cannot resolve table index without exports and tables (possible reason: exports and tables are not available during Wasm start function)");
             return undefined;
         }
 
@@ -43,9 +66,18 @@ let Wasabi = {
         // dirty HACK subtract the "name index" of the first function, should correct for the wrong property
         // const firstFunctionIdx = parseInt(Wasabi.module.exports[Wasabi.module.info.firstFunctionExportName].name);
         // const functionIdx = parseInt(Wasabi.module.table.get(tableIdx).name) - firstFunctionIdx;
-        const functionIdx = parseInt(Wasabi.module.table.get(tableIdx).name);
+        const func = Wasabi.module.tables[table].get(tableIdx);
+        if (func === null) {
+            return undefined;
+        }
+        const functionIdx = parseInt(func.name);
+        if (isNaN(functionIdx) || functionIdx >= Wasabi.module.info.functions.length) {
+            // fall back to the statically known (initial) content of the table, see ModuleInfo in Rust
+            const staticFunctionIdx = Wasabi.module.info.tableContents[table][tableIdx];
+            return (staticFunctionIdx === undefined) ? 0 : staticFunctionIdx;
+        }
 
-        return (functionIdx >= Wasabi.module.info.functions.length) ? 0 : functionIdx;
+        return functionIdx;
     },
 
     // call end hooks for all "intermediate" or "implicitly ended blocks" of a branch table
@@ -62,8 +94,47 @@ let Wasabi = {
                 type,
                 {func, instr: begin},
                 // not undefined only for block type "else"
-                (begin_if === undefined) ? undefined : {func, instr: begin_if});
+                (begin_if === undefined) ? undefined : {func, instr: begin_if},
+                // block results are not available when leaving the block via a branch
+                []);
+        }
+    },
+
+    // call end hooks for all blocks that are unwound by an exception, from the innermost block (at the
+    // throw or call site) outwards up to and including the handler block (both given by their id,
+    // i.e., begin instruction index, see FunctionInfo in Rust)
+    endUnwoundBlocks: function(func, innermostBlock, handlerBlock) {
+        const blocks = Wasabi.module.info.functions[func].blocks;
+        for (let id = innermostBlock; ; ) {
+            const [block, parent] = blocks[id];
+            const [type, begin, end, begin_if] = block;
+            Wasabi.analysis.end(
+                {func, instr: end},
+                type,
+                {func, instr: begin},
+                (begin_if === undefined) ? undefined : {func, instr: begin_if},
+                // block results are not available when leaving the block via an exception
+                []);
+            if (id === handlerBlock) {
+                break;
+            }
+            id = parent;
+        }
+    },
+
+    // map a location {func, instr} to the byte offset of the instruction in the original binary,
+    // undefined if not known (e.g., for imported functions or the instr -1 of function begin hooks)
+    originalOffset: function(loc) {
+        const func = Wasabi.module.info.functions[loc.func];
+        if (func.instrOffsets === null) {
+            return undefined;
+        }
+        // offsets are delta-encoded (see FunctionInfo in Rust), decode once per function
+        if (func.instrOffsetsDecoded === undefined) {
+            let offset = 0;
+            func.instrOffsetsDecoded = func.instrOffsets.map(delta => offset += delta);
         }
+        return func.instrOffsetsDecoded[loc.instr];
     },
 
     loc2func: function(loc) {
@@ -83,7 +154,7 @@ let Wasabi = {
         // TODO flatten info into module itself, by using Object.assign in generated code
         info: undefined, lowlevelHooks: undefined,
         // filled after instantiation
-        exports: undefined, table: undefined,
+        exports: undefined, tables: undefined,
     },
 
     // filled by user or with empty hooks (as fallback) before instantiation
@@ -103,10 +174,10 @@ let Wasabi = {
         br_if(location, conditionalTarget, condition) {},
         br_table(location, table, defaultTarget, tableIdx) {},
         begin(location, type) {},
-        end(location, type, beginLocation, ifLocation) {},
+        end(location, type, beginLocation, ifLocation, values) {},
         drop(location, value) {},
         select(location, cond, first, second) {},
-        call_pre(location, targetFunc, args, indirectTableIdx) {},
+        call_pre(location, targetFunc, args, indirectTableIdx, tailCall) {},
         call_post(location, values) {},
         return_(location, values) {},
         const_(location, value) {},
@@ -114,10 +185,33 @@ let Wasabi = {
         binary(location, op, first, second, result) {},
         load(location, op, memarg, value) {},
         store(location, op, memarg, value) {},
-        memory_size(location, currentSizePages) {},
-        memory_grow(location, byPages, previousSizePages) {},
+        memory_size(location, currentSizePages, memory) {},
+        memory_grow(location, byPages, previousSizePages, memory) {},
+        memory_init(location, dataIndex, destination, source, length, memory) {},
+        data_drop(location, dataIndex) {},
+        memory_copy(location, destination, source, length, destinationMemory, sourceMemory) {},
+        memory_fill(location, destination, value, length, memory) {},
+        table_init(location, elemIndex, destination, source, length, table) {},
+        elem_drop(location, elemIndex) {},
+        table_copy(location, destination, source, length, destinationTable, sourceTable) {},
+        table_get(location, table, index, value) {},
+        table_set(location, table, index, value) {},
+        table_size(location, table, currentSize) {},
+        table_grow(location, table, initValue, delta, previousSize) {},
+        table_fill(location, table, index, value, length) {},
         local(location, op, localIndex, value) {},
         global(location, op, globalIndex, value) {},
+        simd(location, op, inputs, result, immediate) {},
+        atomic_load(location, op, memarg, value, order) {},
+        atomic_store(location, op, memarg, value, order) {},
+        atomic_rmw(location, op, memarg, operand, oldValue, order) {},
+        atomic_cmpxchg(location, op, memarg, expected, replacement, oldValue, order) {},
+        atomic_wait(location, op, memarg, expected, timeout, result) {},
+        atomic_notify(location, memarg, count, woken) {},
+        atomic_fence(location, order) {},
+        throw_(location, tag, values) {},
+        rethrow(location, catchLocation) {},
+        catch_(location, tag, values) {},
     }
 
     const oldInstantiate = WebAssembly.instantiate;
@@ -137,10 +231,10 @@ let Wasabi = {
         importObjectWithHooks.__wasabi_hooks = Wasabi.module.lowlevelHooks;
 
         const result = oldInstantiate(sourceBuffer, importObjectWithHooks);
-        // as soon as instance is available, save exports and table
+        // as soon as instance is available, save exports and tables
         result.then(({module, instance}) => {
             Wasabi.module.exports = instance.exports;
-            Wasabi.module.table = instance.exports[Wasabi.module.info.tableExportName];
+            Wasabi.module.tables = Wasabi.module.info.tableExportNames.map(name => instance.exports[name]);
         });
         return result;
     };
@@ -4,37 +4,27 @@ extern crate walkdir;
 use std::path::{Path, PathBuf};
 use std::io;
 
-/// call WABT's wasm-validate tool on a file (WABT needs to be on $PATH)
-pub fn wasm_validate(path: impl AsRef<Path>) -> Result<(), String> {
-    use std::process::Command;
-
-    let path = path.as_ref();
-    let validate_output = Command::new("wasm-validate")
-        .arg(path)
-        .output()
-        .map_err(|err| err.to_string())?;
+/// return all *.wasm files under a root directory
+pub fn wasm_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    files_with_extension(root_dir, "wasm")
+}
 
-    if validate_output.status.success() {
-        Ok(())
-    } else {
-        Err(format!("invalid wasm file {}\n{}",
-                    path.display(),
-                    String::from_utf8_lossy(&validate_output.stderr)))
-    }
+/// return all *.wat files (text format) under a root directory
+pub fn wat_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    files_with_extension(root_dir, "wat")
 }
 
-/// return all *.wasm files under a root directory
-pub fn wasm_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+fn files_with_extension(root_dir: impl AsRef<Path>, extension: &str) -> Result<Vec<PathBuf>, String> {
     use walkdir::WalkDir;
 
-    let mut wasm_files = Vec::new();
+    let mut files = Vec::new();
     for entry in WalkDir::new(&root_dir) {
         let path = entry.map_err(|err| err.to_string())?.path().to_owned();
-        if let Some("wasm") = path.extension().and_then(|os_str| os_str.to_str()) {
-            wasm_files.push(path);
+        if path.extension().and_then(|os_str| os_str.to_str()) == Some(extension) {
+            files.push(path);
         }
     }
-    Ok(wasm_files)
+    Ok(files)
 }
 
 pub fn output_file(test_input_file: impl AsRef<Path>, output_subdir: &'static str) -> io::Result<PathBuf> {
@@ -10,7 +10,7 @@ use proc_macro::TokenStream;
 use quote::Tokens;
 use syn::{Attribute, Data, DataEnum, DataStruct, DeriveInput, Field, Fields, Ident, Lit, Meta, MetaNameValue, Path, PathArguments, PathSegment, Type, TypePath, Variant};
 
-#[proc_macro_derive(WasmBinary, attributes(tag))]
+#[proc_macro_derive(WasmBinary, attributes(tag, prefix))]
 pub fn derive_wasm(input: TokenStream) -> TokenStream {
     let input: DeriveInput = syn::parse(input).unwrap();
     let data_name = &input.ident;
@@ -18,6 +18,7 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
     let decode_expr = match &input.data {
         &Data::Struct(DataStruct { ref fields, .. }) => {
             let tag: Option<u8> = attributes_to_tag(&input.attrs);
+            assert!(attributes_to_prefix(&input.attrs).is_none(), "#[prefix = ...] is only supported on enum variants");
 
             let decode_tag = tag.map(|tag| quote! {
                 let byte = u8::decode(reader)?;
@@ -33,10 +34,27 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
             })
         }
         &Data::Enum(DataEnum { ref variants, .. }) => {
-            let decode_variants = variants.iter().map(|variant| decode_variant(data_name, variant));
+            let decode_variants = variants.iter()
+                .filter(|variant| attributes_to_prefix(&variant.attrs).is_none())
+                .map(|variant| decode_variant(data_name, variant));
+
+            // variants with a prefix byte are grouped under it and then matched on the (LEB128) tag
+            let mut prefixes: Vec<u8> = variants.iter().filter_map(|variant| attributes_to_prefix(&variant.attrs)).collect();
+            prefixes.sort();
+            prefixes.dedup();
+            let decode_prefixed_variants = prefixes.into_iter().map(|prefix| {
+                let decode_variants = variants.iter()
+                    .filter(|variant| attributes_to_prefix(&variant.attrs) == Some(prefix))
+                    .map(|variant| decode_variant(data_name, variant));
+                quote!( #prefix => match u32::decode(reader)? {
+                    #( #decode_variants )*
+                    tag => Self::error(format!("expected tag for {} after prefix 0x{:02x}, got {}", stringify!(#data_name), #prefix, tag))?
+                }, )
+            });
 
             quote!(match u8::decode(reader)? {
                 #( #decode_variants )*
+                #( #decode_prefixed_variants )*
                 byte => Self::error(format!("expected tag for {}, got 0x{:02x}", stringify!(#data_name), byte))?
             })
         }
@@ -45,8 +63,8 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
 
     let encode_match_arms = match &input.data {
         &Data::Struct(DataStruct { ref fields, .. }) => {
-            let tag: Option<u8> = attributes_to_tag(&input.attrs);
-            encode_fields(&parse_quote!(#data_name), tag, &fields)
+            let tags: Vec<Tokens> = attributes_to_tag(&input.attrs).into_iter().map(|tag| quote!(#tag)).collect();
+            encode_fields(&parse_quote!(#data_name), &tags, &fields)
         }
         &Data::Enum(DataEnum { ref variants, .. }) => {
             let encode_variants = variants.iter().map(|variant| encode_variant(data_name, variant));
@@ -58,8 +76,8 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     quote!(
         impl #impl_generics WasmBinary for #data_name #ty_generics #where_clause {
-            fn decode<R: ::std::io::Read>(reader: &mut R) -> ::std::io::Result<Self> {
-                Ok(#decode_expr)
+            fn decode<R: ::std::io::Read>(reader: &mut R) -> Result<Self, ::binary::DecodeError> {
+                ::binary::decode_with_context(reader, stringify!(#data_name), |reader| Ok(#decode_expr))
             }
             fn encode<W: ::std::io::Write>(&self, writer: &mut W) -> ::std::io::Result<usize> {
                 let mut bytes_written = 0;
@@ -73,21 +91,40 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
 }
 
 /// Take the first `#[tag = <byte literal>]` attribute and return the value of `byte literal`.
+/// Other attributes (e.g., doc comments) are ignored.
 fn attributes_to_tag(attributes: &[Attribute]) -> Option<u8> {
-    let attribute = attributes.first()?;
+    attribute_value(attributes, "tag", u8::max_value() as u64).map(|tag| tag as u8)
+}
+
+/// Multi-byte opcodes, e.g., `#[prefix = 0xfc] #[tag = 0x00]` is encoded as the byte 0xfc followed
+/// by the tag as LEB128 u32 (not as a single byte).
+fn attributes_to_prefix(attributes: &[Attribute]) -> Option<u8> {
+    attribute_value(attributes, "prefix", u8::max_value() as u64).map(|prefix| prefix as u8)
+}
+
+/// tag of a prefixed variant, can be larger than a byte
+fn attributes_to_prefixed_tag(attributes: &[Attribute]) -> Option<u32> {
+    attribute_value(attributes, "tag", u32::max_value() as u64).map(|tag| tag as u32)
+}
+
+fn attribute_value(attributes: &[Attribute], name: &str, max: u64) -> Option<u64> {
+    let attribute = attributes.iter().find(|attribute| attribute.path == Path::from(Ident::from(name)))?;
     match attribute.interpret_meta() {
         Some(Meta::NameValue(MetaNameValue { ident, lit: Lit::Int(ref uint), .. }))
-        if ident.to_string() == "tag" && uint.value() <= u8::max_value() as u64 =>
-            Some(uint.value() as u8),
-        _ => panic!("attribute must be of type #[tag = <u8 literal>], got {}", quote!(#attribute))
+        if ident.to_string() == name && uint.value() <= max =>
+            Some(uint.value()),
+        _ => panic!("attribute must be of type #[{} = <integer literal <= {}>], got {}", name, max, quote!(#attribute))
     }
 }
 
 /* for decode() */
 
 fn decode_variant(super_name: &Ident, variant: &Variant) -> Tokens {
-    let tag = attributes_to_tag(&variant.attrs)
-        .expect(&format!("every enum variant needs a tag, but {} does not have one", variant.ident));
+    // prefix was already matched, see derive_wasm()
+    let tag = match attributes_to_prefix(&variant.attrs) {
+        None => attributes_to_tag(&variant.attrs).map(|tag| quote!(#tag)),
+        Some(_) => attributes_to_prefixed_tag(&variant.attrs).map(|tag| quote!(#tag)),
+    }.expect(&format!("every enum variant needs a tag, but {} does not have one", variant.ident));
     let variant_name = &variant.ident;
     let name = parse_quote!(#super_name::#variant_name);
 
@@ -106,7 +143,14 @@ fn decode_fields(name: &TypePath, fields: &Fields) -> Tokens {
 
 fn decode_field(field: &Field) -> Tokens {
     let field_name = field.ident;
-    let field_ty = remove_type_arguments(&field.ty);
+    let field_ty = match field.ty {
+        // [T; N]::decode() is not valid syntax either, but <[T; N]>::decode() is
+        Type::Array(ref array) => quote!(<#array>),
+        ref ty => {
+            let ty = remove_type_arguments(ty);
+            quote!(#ty)
+        }
+    };
     quote!( #( #field_name: )* #field_ty::decode(reader)? )
 }
 
@@ -128,17 +172,25 @@ fn remove_type_arguments(ty: &Type) -> Type {
 /* for encode() */
 
 fn encode_variant(super_name: &Ident, variant: &Variant) -> Tokens {
-    let tag = attributes_to_tag(&variant.attrs);
+    // either just the tag byte, or the prefix byte followed by the tag as u32 (i.e., LEB128)
+    let tags: Vec<Tokens> = match attributes_to_prefix(&variant.attrs) {
+        None => attributes_to_tag(&variant.attrs).into_iter().map(|tag| quote!(#tag)).collect(),
+        Some(prefix) => {
+            let tag = attributes_to_prefixed_tag(&variant.attrs)
+                .expect(&format!("prefixed enum variant {} needs a tag", variant.ident));
+            vec![quote!(#prefix), quote!(#tag)]
+        }
+    };
     let variant_name = &variant.ident;
     let name = parse_quote!(#super_name::#variant_name);
 
-    encode_fields(&name, tag, &variant.fields)
+    encode_fields(&name, &tags, &variant.fields)
 }
 
-fn encode_fields(name: &TypePath, tag: Option<u8>, fields: &Fields) -> Tokens {
+fn encode_fields(name: &TypePath, tags: &[Tokens], fields: &Fields) -> Tokens {
     let field_names: &Vec<_> = &fields.iter().enumerate().map(encode_field_name).collect();
     let body = quote!({
-        #( bytes_written += #tag.encode(writer)?; )*
+        #( bytes_written += #tags.encode(writer)?; )*
         #( bytes_written += #field_names.encode(writer)? );*
     });
     match *fields {
@@ -0,0 +1,61 @@
+use super::lowlevel::{DataMode, ExportType, ImportType};
+
+/* Zero-copy view of a module in the binary format: decoding only splits the module into sections
+   (see binary.rs), and function bodies, names, and data are handed out as sub-slices of the input,
+   i.e., without allocating for every item. For the fully decoded module, see to_lowlevel(). */
+
+#[derive(Debug, Clone)]
+pub struct Module<'a> {
+    /// the whole module, including the header
+    pub bytes: &'a [u8],
+    pub sections: Vec<Section<'a>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Section<'a> {
+    /// as in the binary, i.e., 0 for custom sections
+    pub id: u8,
+    /// byte offset of the section content (i.e., after id and size) in the module
+    pub offset: usize,
+    pub content: &'a [u8],
+}
+
+/// locals and instructions of a function, not decoded yet
+#[derive(Debug, Copy, Clone)]
+pub struct FunctionBody<'a> {
+    /// byte offset of the body (i.e., after its size) in the module
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CustomSection<'a> {
+    pub name: &'a str,
+    pub content: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct Data<'a> {
+    pub mode: DataMode,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct Import<'a> {
+    pub module: &'a str,
+    pub name: &'a str,
+    pub type_: ImportType,
+}
+
+#[derive(Debug, Clone)]
+pub struct Export<'a> {
+    pub name: &'a str,
+    pub type_: ExportType,
+}
+
+impl<'a> Module<'a> {
+    /// first section with the given id (as in the binary), custom sections can appear multiple times
+    pub fn section(&self, id: u8) -> Option<&Section<'a>> {
+        self.sections.iter().find(|section| section.id == id)
+    }
+}
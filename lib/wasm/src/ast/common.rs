@@ -13,6 +13,8 @@ pub enum Val {
     I64(i64),
     F32(f32),
     F64(f64),
+    // SIMD proposal, lanes in little-endian order (i.e., lane 0 is in the lowest bits)
+    V128(u128),
 }
 
 impl Val {
@@ -22,6 +24,7 @@ impl Val {
             Val::I64(_) => ValType::I64,
             Val::F32(_) => ValType::F32,
             Val::F64(_) => ValType::F64,
+            Val::V128(_) => ValType::V128,
         }
     }
 }
@@ -36,6 +39,11 @@ pub enum ValType {
     #[tag = 0x7e] I64,
     #[tag = 0x7d] F32,
     #[tag = 0x7c] F64,
+    // SIMD proposal
+    #[tag = 0x7b] V128,
+    // reference types proposal
+    #[tag = 0x70] FuncRef,
+    #[tag = 0x6f] ExternRef,
 }
 
 impl fmt::Display for ValType {
@@ -53,6 +61,9 @@ impl ValType {
             ValType::I64 => 'I',
             ValType::F32 => 'f',
             ValType::F64 => 'F',
+            ValType::V128 => 'v',
+            ValType::FuncRef => 'r',
+            ValType::ExternRef => 'e',
         }
     }
 }
@@ -88,24 +99,38 @@ impl<'a> From<&'a InstrType> for FunctionType {
     }
 }
 
-#[derive(WasmBinary, Debug, Clone, PartialEq, Eq, Hash, Serialize, new)]
+#[derive(WasmBinary, Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, new)]
 #[tag = 0x60]
 pub struct FunctionType {
     pub params: Vec<ValType>,
     pub results: Vec<ValType>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct BlockType(pub Option<ValType>);
-
 #[derive(WasmBinary, Debug, Clone)]
 pub struct TableType(pub ElemType, pub Limits);
 
-#[derive(WasmBinary, Debug, Copy, Clone)]
+/// the reference types that tables can hold (and that ref.null takes as immediate), i.e., the
+/// subset of ValType that can be encoded with the same tags
+#[derive(WasmBinary, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ElemType {
-    // only value in WASM version 1
-    #[tag = 0x70]
-    Anyfunc,
+    // only value in WASM version 1 (there called anyfunc)
+    #[tag = 0x70] FuncRef,
+    #[tag = 0x6f] ExternRef,
+}
+
+impl From<ElemType> for ValType {
+    fn from(elem_type: ElemType) -> Self {
+        match elem_type {
+            ElemType::FuncRef => ValType::FuncRef,
+            ElemType::ExternRef => ValType::ExternRef,
+        }
+    }
+}
+
+impl fmt::Display for ElemType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ValType::from(*self).fmt(f)
+    }
 }
 
 #[derive(WasmBinary, Debug, Clone)]
@@ -113,8 +138,12 @@ pub struct MemoryType(pub Limits);
 
 #[derive(Debug, Copy, Clone)]
 pub struct Limits {
-    pub initial_size: u32,
-    pub max_size: Option<u32>,
+    pub initial_size: u64,
+    pub max_size: Option<u64>,
+    /// threads proposal, only for memories (which then also require a maximum size)
+    pub shared: bool,
+    /// memory64 proposal, only for memories: I64 if addresses (and sizes in pages) are 64-bit, otherwise I32
+    pub index_type: ValType,
 }
 
 #[derive(WasmBinary, Debug, Copy, Clone)]
@@ -127,6 +156,101 @@ pub enum Mutability {
 }
 
 
+/* Custom sections */
+
+/// known (i.e., non-custom) sections, in the order in which they must appear in a binary
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SectionId {
+    Type,
+    Import,
+    Function,
+    Table,
+    Memory,
+    Tag,
+    Global,
+    Export,
+    Start,
+    Element,
+    DataCount,
+    Code,
+    Data,
+}
+
+/// where a custom section appeared relative to the known sections, so that it can be put back there
+/// when encoding (e.g., some tools require custom sections before/after specific known sections).
+/// If the known section is not present anymore, the custom section is placed where it would have been.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SectionPosition {
+    First,
+    After(SectionId),
+    /// e.g., for newly added custom sections
+    Last,
+}
+
+/// custom section that is not further decoded, but just passed through
+#[derive(Debug, Clone)]
+pub struct RawCustomSection {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+/// see https://github.com/WebAssembly/tool-conventions/blob/master/ProducersSection.md
+#[derive(WasmBinary, Debug, Clone, Default, PartialEq)]
+pub struct ProducersSection {
+    pub fields: Vec<ProducersField>,
+}
+
+/// field name is one of "language", "processed-by", or "sdk"
+#[derive(WasmBinary, Debug, Clone, PartialEq)]
+pub struct ProducersField {
+    pub name: String,
+    pub values: Vec<ProducerVersion>,
+}
+
+#[derive(WasmBinary, Debug, Clone, PartialEq)]
+pub struct ProducerVersion {
+    pub name: String,
+    pub version: String,
+}
+
+impl ProducersSection {
+    /// adds a producer to the field, or updates its version if the producer is already listed
+    pub fn add(&mut self, field: &str, name: &str, version: &str) {
+        let field_idx = match self.fields.iter().position(|f| f.name == field) {
+            Some(idx) => idx,
+            None => {
+                self.fields.push(ProducersField { name: field.to_string(), values: Vec::new() });
+                self.fields.len() - 1
+            }
+        };
+        let values = &mut self.fields[field_idx].values;
+        match values.iter_mut().find(|value| value.name == name) {
+            Some(value) => value.version = version.to_string(),
+            None => values.push(ProducerVersion { name: name.to_string(), version: version.to_string() }),
+        }
+    }
+}
+
+/// see https://github.com/WebAssembly/tool-conventions/blob/master/Linking.md#target-features-section
+#[derive(WasmBinary, Debug, Clone, Default, PartialEq)]
+pub struct TargetFeaturesSection {
+    pub features: Vec<TargetFeature>,
+}
+
+#[derive(WasmBinary, Debug, Clone, PartialEq)]
+pub struct TargetFeature {
+    pub prefix: FeaturePrefix,
+    pub name: String,
+}
+
+#[derive(WasmBinary, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FeaturePrefix {
+    #[tag = 0x2b] Used,
+    #[tag = 0x2d] Disallowed,
+    #[tag = 0x3d] Required,
+}
+
+
 /* Indices */
 
 #[derive(WasmBinary)]
@@ -140,7 +264,7 @@ impl<T> From<usize> for Idx<T> {
 // custom Debug: print index type T, don't print PhantomData
 impl<T> fmt::Debug for Idx<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let type_name = unsafe { ::std::intrinsics::type_name::<T>() };
+        let type_name = ::std::any::type_name::<T>();
         let type_name = type_name.split("::").last().unwrap();
         f.write_str(type_name)?;
         f.write_char(' ')?;
@@ -197,12 +321,3 @@ pub struct Local;
 
 #[derive(Debug)]
 pub struct Label;
-
-
-/* Code */
-
-#[derive(WasmBinary, Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
-pub struct Memarg {
-    pub alignment: u32,
-    pub offset: u32,
-}
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use super::*;
 use super::highlevel as hl;
 use super::lowlevel as ll;
@@ -13,10 +13,27 @@ impl From<ll::Module> for hl::Module {
     fn from(ll::Module { sections }: ll::Module) -> Self {
         let mut module = hl::Module::default();
         let mut types: Vec<FunctionType> = Vec::new();
+        // applied only after all sections were converted, so that all named functions exist
+        let mut name_section = None;
+        // custom sections are placed after the last known section
+        let mut position = SectionPosition::First;
 
         for section in sections {
+            let section_id = section.id();
             match section {
-                ll::Section::Custom(bytes) => module.custom_sections.push(bytes),
+                ll::Section::Custom(custom) => {
+                    let custom = match custom {
+                        ll::CustomSection::Name(section) => {
+                            name_section = Some(section);
+                            hl::CustomSection::Name
+                        }
+                        ll::CustomSection::Producers(section) => hl::CustomSection::Producers(section),
+                        ll::CustomSection::TargetFeatures(section) => hl::CustomSection::TargetFeatures(section),
+                        ll::CustomSection::SourceMappingUrl(url) => hl::CustomSection::SourceMappingUrl(url),
+                        ll::CustomSection::Raw(section) => hl::CustomSection::Raw(section),
+                    };
+                    module.custom_sections.push((position, custom));
+                }
                 ll::Section::Type(ll::WithSize(types_)) => types = types_,
 
                 /* Imported functions, tables, memories, and globals are first added to the respective index spaces... */
@@ -31,17 +48,17 @@ impl From<ll::Module> for hl::Module {
                                 import,
                                 code: None,
                                 export,
+                                name: None,
+                                local_names: BTreeMap::new(),
                             }),
                             ll::ImportType::Table(type_) => module.tables.push(hl::Table {
                                 type_,
                                 import,
-                                elements: Vec::new(),
                                 export,
                             }),
                             ll::ImportType::Memory(type_) => module.memories.push(hl::Memory {
                                 type_,
                                 import,
-                                data: Vec::new(),
                                 export,
                             }),
                             ll::ImportType::Global(type_) => module.globals.push(hl::Global {
@@ -50,6 +67,11 @@ impl From<ll::Module> for hl::Module {
                                 init: None,
                                 export,
                             }),
+                            ll::ImportType::Tag(ll::TagType::Exception(type_idx)) => module.tags.push(hl::Tag {
+                                type_: types[type_idx.0].clone(),
+                                import,
+                                export,
+                            }),
                         }
                     }
                 }
@@ -63,6 +85,8 @@ impl From<ll::Module> for hl::Module {
                             import: None,
                             code: None,
                             export: Vec::new(),
+                            name: None,
+                            local_names: BTreeMap::new(),
                         });
                     }
                 }
@@ -71,7 +95,6 @@ impl From<ll::Module> for hl::Module {
                         module.tables.push(hl::Table {
                             type_,
                             import: None,
-                            elements: Vec::new(),
                             export: Vec::new(),
                         });
                     }
@@ -81,7 +104,15 @@ impl From<ll::Module> for hl::Module {
                         module.memories.push(hl::Memory {
                             type_,
                             import: None,
-                            data: Vec::new(),
+                            export: Vec::new(),
+                        });
+                    }
+                }
+                ll::Section::Tag(ll::WithSize(tags)) => {
+                    for ll::TagType::Exception(type_idx) in tags {
+                        module.tags.push(hl::Tag {
+                            type_: types[type_idx.0].clone(),
+                            import: None,
                             export: Vec::new(),
                         });
                     }
@@ -106,6 +137,7 @@ impl From<ll::Module> for hl::Module {
                             ll::ExportType::Table(idx) => module.tables[idx.0].export.push(name),
                             ll::ExportType::Memory(idx) => module.memories[idx.0].export.push(name),
                             ll::ExportType::Global(idx) => module.globals[idx.0].export.push(name),
+                            ll::ExportType::Tag(idx) => module.tags[idx.0].export.push(name),
                         }
                     }
                 }
@@ -115,12 +147,24 @@ impl From<ll::Module> for hl::Module {
 
                 ll::Section::Element(ll::WithSize(elements)) => {
                     for element in elements {
-                        module.tables[element.table_idx.0].elements.push(hl::Element {
-                            offset: from_lowlevel_expr(element.offset, &types),
-                            functions: element.init.into_iter().map(|idx| idx.0.into()).collect(),
+                        module.elements.push(hl::Element {
+                            mode: match element.mode {
+                                ll::ElementMode::Active(table_idx, offset) => hl::ElementMode::Active {
+                                    table: table_idx.0.into(),
+                                    offset: from_lowlevel_expr(offset, &types),
+                                },
+                                ll::ElementMode::Passive => hl::ElementMode::Passive,
+                                ll::ElementMode::Declarative => hl::ElementMode::Declarative,
+                            },
+                            items: match element.init {
+                                ll::ElementItems::Functions(functions) => hl::ElementItems::Functions(functions.into_iter().map(|idx| idx.0.into()).collect()),
+                                ll::ElementItems::Expressions(type_, exprs) => hl::ElementItems::Expressions(type_, exprs.into_iter().map(|expr| from_lowlevel_expr(expr, &types)).collect()),
+                            },
                         })
                     }
                 }
+                // only needed for validation, the data segments themselves follow after the code
+                ll::Section::DataCount(_) => {}
                 ll::Section::Code(ll::WithSize(code)) => {
                     let imported_function_count = module.functions.iter()
                         .filter(|f| f.import.is_some())
@@ -134,29 +178,72 @@ impl From<ll::Module> for hl::Module {
                 }
                 ll::Section::Data(ll::WithSize(data)) => {
                     for data in data {
-                        module.memories[data.memory_idx.0].data.push(hl::Data {
-                            offset: from_lowlevel_expr(data.offset, &types),
+                        module.data.push(hl::Data {
+                            mode: match data.mode {
+                                ll::DataMode::Active(memory_idx, offset) => hl::DataMode::Active {
+                                    memory: memory_idx.0.into(),
+                                    offset: from_lowlevel_expr(offset, &types),
+                                },
+                                ll::DataMode::Passive => hl::DataMode::Passive,
+                            },
                             bytes: data.init,
                         })
                     }
                 }
             }
+
+            if let Some(section_id) = section_id {
+                position = SectionPosition::After(section_id);
+            }
+        }
+
+        if let Some(name_section) = name_section {
+            from_lowlevel_names(name_section, &mut module);
         }
 
         module
     }
 }
 
-fn from_lowlevel_code(code: ll::Code, types: &[FunctionType]) -> hl::Code {
+/// the name section is not validated, so silently ignore names of non-existing functions
+fn from_lowlevel_names(names: ll::NameSection, module: &mut hl::Module) {
+    for subsection in names.subsections {
+        match subsection {
+            ll::NameSubSection::Module(ll::WithSize(name)) => module.name = Some(name),
+            ll::NameSubSection::Function(ll::WithSize(function_names)) => {
+                for ll::FunctionName { function_idx, name } in function_names {
+                    if let Some(function) = module.functions.get_mut(function_idx.0) {
+                        function.name = Some(name);
+                    }
+                }
+            }
+            ll::NameSubSection::Local(ll::WithSize(local_names)) => {
+                for ll::LocalNames { function_idx, names } in local_names {
+                    if let Some(function) = module.functions.get_mut(function_idx.0) {
+                        function.local_names = names.into_iter()
+                            .map(|ll::LocalName { local_idx, name }| (local_idx, name))
+                            .collect();
+                    }
+                }
+            }
+            ll::NameSubSection::Unknown(..) => {}
+        }
+    }
+}
+
+fn from_lowlevel_code(mut code: ll::Code, types: &[FunctionType]) -> hl::Code {
     let mut locals = Vec::new();
     for local in code.locals {
         for _ in 0..local.count {
             locals.push(local.type_);
         }
     }
+    // instructions are converted one-to-one, so the offsets stay valid
+    let original_offsets = code.body.1.take();
     hl::Code {
         locals,
         body: from_lowlevel_expr(code.body, types),
+        original_offsets,
     }
 }
 
@@ -164,17 +251,40 @@ fn from_lowlevel_expr(expr: ll::Expr, types: &[FunctionType]) -> hl::Expr {
     expr.0.into_iter().map(|instr| from_lowlevel_instr(instr, types)).collect()
 }
 
+fn from_lowlevel_block_type(block_type: ll::BlockType, types: &[FunctionType]) -> hl::BlockType {
+    match block_type {
+        ll::BlockType::Empty => hl::BlockType::default(),
+        ll::BlockType::Value(val_type) => FunctionType::new(vec![], vec![val_type]),
+        ll::BlockType::Index(type_idx) => types[type_idx.0].clone(),
+    }
+}
+
+fn from_lowlevel_memarg(memarg: ll::Memarg) -> hl::Memarg {
+    hl::Memarg {
+        alignment: memarg.alignment,
+        offset: memarg.offset,
+        memory: memarg.memory.0.into(),
+    }
+}
+
 fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
     match instr {
         ll::Instr::Unreachable => hl::Instr::Unreachable,
         ll::Instr::Nop => hl::Instr::Nop,
 
-        ll::Instr::Block(block_type) => hl::Instr::Block(block_type),
-        ll::Instr::Loop(block_type) => hl::Instr::Loop(block_type),
-        ll::Instr::If(block_type) => hl::Instr::If(block_type),
+        ll::Instr::Block(block_type) => hl::Instr::Block(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::Loop(block_type) => hl::Instr::Loop(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::If(block_type) => hl::Instr::If(from_lowlevel_block_type(block_type, types)),
         ll::Instr::Else => hl::Instr::Else,
         ll::Instr::End => hl::Instr::End,
 
+        ll::Instr::Try(block_type) => hl::Instr::Try(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::Catch(tag_idx) => hl::Instr::Catch(tag_idx.0.into()),
+        ll::Instr::CatchAll => hl::Instr::CatchAll,
+        ll::Instr::Delegate(label_idx) => hl::Instr::Delegate(label_idx),
+        ll::Instr::Throw(tag_idx) => hl::Instr::Throw(tag_idx.0.into()),
+        ll::Instr::Rethrow(label_idx) => hl::Instr::Rethrow(label_idx),
+
         ll::Instr::Br(label_idx) => hl::Instr::Br(label_idx),
         ll::Instr::BrIf(label_idx) => hl::Instr::BrIf(label_idx),
         ll::Instr::BrTable(label_idx_table, default) => hl::Instr::BrTable(label_idx_table, default),
@@ -182,43 +292,64 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
         ll::Instr::Return => hl::Instr::Return,
         ll::Instr::Call(function_idx) => hl::Instr::Call(function_idx.0.into()),
         ll::Instr::CallIndirect(type_idx, table_idx) => hl::Instr::CallIndirect(types[type_idx.0].clone(), table_idx.0.into()),
+        ll::Instr::ReturnCall(function_idx) => hl::Instr::ReturnCall(function_idx.0.into()),
+        ll::Instr::ReturnCallIndirect(type_idx, table_idx) => hl::Instr::ReturnCallIndirect(types[type_idx.0].clone(), table_idx.0.into()),
 
         ll::Instr::Drop => hl::Instr::Drop,
         ll::Instr::Select => hl::Instr::Select,
+        // exactly one type, see Expr::decode
+        ll::Instr::TypedSelect(types) => hl::Instr::TypedSelect(types[0]),
 
         ll::Instr::GetLocal(local_idx) => hl::Instr::Local(hl::LocalOp::GetLocal, local_idx.0.into()),
         ll::Instr::SetLocal(local_idx) => hl::Instr::Local(hl::LocalOp::SetLocal, local_idx.0.into()),
         ll::Instr::TeeLocal(local_idx) => hl::Instr::Local(hl::LocalOp::TeeLocal, local_idx.0.into()),
         ll::Instr::GetGlobal(global_idx) => hl::Instr::Global(hl::GlobalOp::GetGlobal, global_idx.0.into()),
         ll::Instr::SetGlobal(global_idx) => hl::Instr::Global(hl::GlobalOp::SetGlobal, global_idx.0.into()),
-
-        ll::Instr::I32Load(memarg) => hl::Instr::Load(hl::LoadOp::I32Load, memarg),
-        ll::Instr::I64Load(memarg) => hl::Instr::Load(hl::LoadOp::I64Load, memarg),
-        ll::Instr::F32Load(memarg) => hl::Instr::Load(hl::LoadOp::F32Load, memarg),
-        ll::Instr::F64Load(memarg) => hl::Instr::Load(hl::LoadOp::F64Load, memarg),
-        ll::Instr::I32Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8S, memarg),
-        ll::Instr::I32Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8U, memarg),
-        ll::Instr::I32Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16S, memarg),
-        ll::Instr::I32Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16U, memarg),
-        ll::Instr::I64Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8S, memarg),
-        ll::Instr::I64Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8U, memarg),
-        ll::Instr::I64Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16S, memarg),
-        ll::Instr::I64Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16U, memarg),
-        ll::Instr::I64Load32S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32S, memarg),
-        ll::Instr::I64Load32U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32U, memarg),
-        ll::Instr::I32Store(memarg) => hl::Instr::Store(hl::StoreOp::I32Store, memarg),
-        ll::Instr::I64Store(memarg) => hl::Instr::Store(hl::StoreOp::I64Store, memarg),
-        ll::Instr::F32Store(memarg) => hl::Instr::Store(hl::StoreOp::F32Store, memarg),
-        ll::Instr::F64Store(memarg) => hl::Instr::Store(hl::StoreOp::F64Store, memarg),
-        ll::Instr::I32Store8(memarg) => hl::Instr::Store(hl::StoreOp::I32Store8, memarg),
-        ll::Instr::I32Store16(memarg) => hl::Instr::Store(hl::StoreOp::I32Store16, memarg),
-        ll::Instr::I64Store8(memarg) => hl::Instr::Store(hl::StoreOp::I64Store8, memarg),
-        ll::Instr::I64Store16(memarg) => hl::Instr::Store(hl::StoreOp::I64Store16, memarg),
-        ll::Instr::I64Store32(memarg) => hl::Instr::Store(hl::StoreOp::I64Store32, memarg),
+        ll::Instr::TableGet(table_idx) => hl::Instr::Table(hl::TableOp::TableGet, table_idx.0.into()),
+        ll::Instr::TableSet(table_idx) => hl::Instr::Table(hl::TableOp::TableSet, table_idx.0.into()),
+        ll::Instr::TableSize(table_idx) => hl::Instr::Table(hl::TableOp::TableSize, table_idx.0.into()),
+        ll::Instr::TableGrow(table_idx) => hl::Instr::Table(hl::TableOp::TableGrow, table_idx.0.into()),
+        ll::Instr::TableFill(table_idx) => hl::Instr::Table(hl::TableOp::TableFill, table_idx.0.into()),
+
+        ll::Instr::I32Load(memarg) => hl::Instr::Load(hl::LoadOp::I32Load, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load(memarg) => hl::Instr::Load(hl::LoadOp::I64Load, from_lowlevel_memarg(memarg)),
+        ll::Instr::F32Load(memarg) => hl::Instr::Load(hl::LoadOp::F32Load, from_lowlevel_memarg(memarg)),
+        ll::Instr::F64Load(memarg) => hl::Instr::Load(hl::LoadOp::F64Load, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8S, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16S, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8S, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16S, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load32S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32S, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Load32U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Store(memarg) => hl::Instr::Store(hl::StoreOp::I32Store, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Store(memarg) => hl::Instr::Store(hl::StoreOp::I64Store, from_lowlevel_memarg(memarg)),
+        ll::Instr::F32Store(memarg) => hl::Instr::Store(hl::StoreOp::F32Store, from_lowlevel_memarg(memarg)),
+        ll::Instr::F64Store(memarg) => hl::Instr::Store(hl::StoreOp::F64Store, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Store8(memarg) => hl::Instr::Store(hl::StoreOp::I32Store8, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32Store16(memarg) => hl::Instr::Store(hl::StoreOp::I32Store16, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Store8(memarg) => hl::Instr::Store(hl::StoreOp::I64Store8, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Store16(memarg) => hl::Instr::Store(hl::StoreOp::I64Store16, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64Store32(memarg) => hl::Instr::Store(hl::StoreOp::I64Store32, from_lowlevel_memarg(memarg)),
 
         ll::Instr::MemorySize(memory_idx) => hl::Instr::MemorySize(memory_idx.0.into()),
         ll::Instr::MemoryGrow(memory_idx) => hl::Instr::MemoryGrow(memory_idx.0.into()),
 
+        ll::Instr::MemoryInit(data_idx, memory_idx) => hl::Instr::MemoryInit(data_idx.0.into(), memory_idx.0.into()),
+        ll::Instr::DataDrop(data_idx) => hl::Instr::DataDrop(data_idx.0.into()),
+        ll::Instr::MemoryCopy(dst_idx, src_idx) => hl::Instr::MemoryCopy(dst_idx.0.into(), src_idx.0.into()),
+        ll::Instr::MemoryFill(memory_idx) => hl::Instr::MemoryFill(memory_idx.0.into()),
+        ll::Instr::TableInit(elem_idx, table_idx) => hl::Instr::TableInit(elem_idx.0.into(), table_idx.0.into()),
+        ll::Instr::ElemDrop(elem_idx) => hl::Instr::ElemDrop(elem_idx.0.into()),
+        ll::Instr::TableCopy(dst_idx, src_idx) => hl::Instr::TableCopy(dst_idx.0.into(), src_idx.0.into()),
+
+        ll::Instr::RefNull(type_) => hl::Instr::RefNull(type_),
+        ll::Instr::RefIsNull => hl::Instr::RefIsNull,
+        ll::Instr::RefFunc(function_idx) => hl::Instr::RefFunc(function_idx.0.into()),
+
         ll::Instr::I32Const(immediate) => hl::Instr::Const(Val::I32(immediate)),
         ll::Instr::I64Const(immediate) => hl::Instr::Const(Val::I64(immediate)),
         ll::Instr::F32Const(immediate) => hl::Instr::Const(Val::F32(immediate)),
@@ -347,6 +478,326 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
         ll::Instr::I64ReinterpretF64 => hl::Instr::Numeric(hl::NumericOp::I64ReinterpretF64),
         ll::Instr::F32ReinterpretI32 => hl::Instr::Numeric(hl::NumericOp::F32ReinterpretI32),
         ll::Instr::F64ReinterpretI64 => hl::Instr::Numeric(hl::NumericOp::F64ReinterpretI64),
+
+        ll::Instr::I32Extend8S => hl::Instr::Numeric(hl::NumericOp::I32Extend8S),
+        ll::Instr::I32Extend16S => hl::Instr::Numeric(hl::NumericOp::I32Extend16S),
+        ll::Instr::I64Extend8S => hl::Instr::Numeric(hl::NumericOp::I64Extend8S),
+        ll::Instr::I64Extend16S => hl::Instr::Numeric(hl::NumericOp::I64Extend16S),
+        ll::Instr::I64Extend32S => hl::Instr::Numeric(hl::NumericOp::I64Extend32S),
+
+        ll::Instr::I32TruncSatSF32 => hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF32),
+        ll::Instr::I32TruncSatUF32 => hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF32),
+        ll::Instr::I32TruncSatSF64 => hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF64),
+        ll::Instr::I32TruncSatUF64 => hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF64),
+        ll::Instr::I64TruncSatSF32 => hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF32),
+        ll::Instr::I64TruncSatUF32 => hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF32),
+        ll::Instr::I64TruncSatSF64 => hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF64),
+        ll::Instr::I64TruncSatUF64 => hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF64),
+
+        ll::Instr::V128Load(memarg) => hl::Instr::Load(hl::LoadOp::V128Load, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load8x8S(memarg) => hl::Instr::Load(hl::LoadOp::V128Load8x8S, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load8x8U(memarg) => hl::Instr::Load(hl::LoadOp::V128Load8x8U, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load16x4S(memarg) => hl::Instr::Load(hl::LoadOp::V128Load16x4S, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load16x4U(memarg) => hl::Instr::Load(hl::LoadOp::V128Load16x4U, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load32x2S(memarg) => hl::Instr::Load(hl::LoadOp::V128Load32x2S, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load32x2U(memarg) => hl::Instr::Load(hl::LoadOp::V128Load32x2U, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load8Splat(memarg) => hl::Instr::Load(hl::LoadOp::V128Load8Splat, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load16Splat(memarg) => hl::Instr::Load(hl::LoadOp::V128Load16Splat, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load32Splat(memarg) => hl::Instr::Load(hl::LoadOp::V128Load32Splat, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load64Splat(memarg) => hl::Instr::Load(hl::LoadOp::V128Load64Splat, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Store(memarg) => hl::Instr::Store(hl::StoreOp::V128Store, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Const(immediate) => hl::Instr::Const(Val::V128(immediate)),
+        ll::Instr::I8x16Shuffle(lanes) => hl::Instr::Shuffle(lanes),
+        ll::Instr::I8x16Swizzle => hl::Instr::Simd(hl::SimdOp::I8x16Swizzle),
+        ll::Instr::I8x16Splat => hl::Instr::Simd(hl::SimdOp::I8x16Splat),
+        ll::Instr::I16x8Splat => hl::Instr::Simd(hl::SimdOp::I16x8Splat),
+        ll::Instr::I32x4Splat => hl::Instr::Simd(hl::SimdOp::I32x4Splat),
+        ll::Instr::I64x2Splat => hl::Instr::Simd(hl::SimdOp::I64x2Splat),
+        ll::Instr::F32x4Splat => hl::Instr::Simd(hl::SimdOp::F32x4Splat),
+        ll::Instr::F64x2Splat => hl::Instr::Simd(hl::SimdOp::F64x2Splat),
+        ll::Instr::I8x16ExtractLaneS(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ExtractLaneS, lane),
+        ll::Instr::I8x16ExtractLaneU(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ExtractLaneU, lane),
+        ll::Instr::I8x16ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ReplaceLane, lane),
+        ll::Instr::I16x8ExtractLaneS(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ExtractLaneS, lane),
+        ll::Instr::I16x8ExtractLaneU(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ExtractLaneU, lane),
+        ll::Instr::I16x8ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ReplaceLane, lane),
+        ll::Instr::I32x4ExtractLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I32x4ExtractLane, lane),
+        ll::Instr::I32x4ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I32x4ReplaceLane, lane),
+        ll::Instr::I64x2ExtractLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I64x2ExtractLane, lane),
+        ll::Instr::I64x2ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::I64x2ReplaceLane, lane),
+        ll::Instr::F32x4ExtractLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::F32x4ExtractLane, lane),
+        ll::Instr::F32x4ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::F32x4ReplaceLane, lane),
+        ll::Instr::F64x2ExtractLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::F64x2ExtractLane, lane),
+        ll::Instr::F64x2ReplaceLane(lane) => hl::Instr::SimdLane(hl::SimdLaneOp::F64x2ReplaceLane, lane),
+        ll::Instr::I8x16Eq => hl::Instr::Simd(hl::SimdOp::I8x16Eq),
+        ll::Instr::I8x16Ne => hl::Instr::Simd(hl::SimdOp::I8x16Ne),
+        ll::Instr::I8x16LtS => hl::Instr::Simd(hl::SimdOp::I8x16LtS),
+        ll::Instr::I8x16LtU => hl::Instr::Simd(hl::SimdOp::I8x16LtU),
+        ll::Instr::I8x16GtS => hl::Instr::Simd(hl::SimdOp::I8x16GtS),
+        ll::Instr::I8x16GtU => hl::Instr::Simd(hl::SimdOp::I8x16GtU),
+        ll::Instr::I8x16LeS => hl::Instr::Simd(hl::SimdOp::I8x16LeS),
+        ll::Instr::I8x16LeU => hl::Instr::Simd(hl::SimdOp::I8x16LeU),
+        ll::Instr::I8x16GeS => hl::Instr::Simd(hl::SimdOp::I8x16GeS),
+        ll::Instr::I8x16GeU => hl::Instr::Simd(hl::SimdOp::I8x16GeU),
+        ll::Instr::I16x8Eq => hl::Instr::Simd(hl::SimdOp::I16x8Eq),
+        ll::Instr::I16x8Ne => hl::Instr::Simd(hl::SimdOp::I16x8Ne),
+        ll::Instr::I16x8LtS => hl::Instr::Simd(hl::SimdOp::I16x8LtS),
+        ll::Instr::I16x8LtU => hl::Instr::Simd(hl::SimdOp::I16x8LtU),
+        ll::Instr::I16x8GtS => hl::Instr::Simd(hl::SimdOp::I16x8GtS),
+        ll::Instr::I16x8GtU => hl::Instr::Simd(hl::SimdOp::I16x8GtU),
+        ll::Instr::I16x8LeS => hl::Instr::Simd(hl::SimdOp::I16x8LeS),
+        ll::Instr::I16x8LeU => hl::Instr::Simd(hl::SimdOp::I16x8LeU),
+        ll::Instr::I16x8GeS => hl::Instr::Simd(hl::SimdOp::I16x8GeS),
+        ll::Instr::I16x8GeU => hl::Instr::Simd(hl::SimdOp::I16x8GeU),
+        ll::Instr::I32x4Eq => hl::Instr::Simd(hl::SimdOp::I32x4Eq),
+        ll::Instr::I32x4Ne => hl::Instr::Simd(hl::SimdOp::I32x4Ne),
+        ll::Instr::I32x4LtS => hl::Instr::Simd(hl::SimdOp::I32x4LtS),
+        ll::Instr::I32x4LtU => hl::Instr::Simd(hl::SimdOp::I32x4LtU),
+        ll::Instr::I32x4GtS => hl::Instr::Simd(hl::SimdOp::I32x4GtS),
+        ll::Instr::I32x4GtU => hl::Instr::Simd(hl::SimdOp::I32x4GtU),
+        ll::Instr::I32x4LeS => hl::Instr::Simd(hl::SimdOp::I32x4LeS),
+        ll::Instr::I32x4LeU => hl::Instr::Simd(hl::SimdOp::I32x4LeU),
+        ll::Instr::I32x4GeS => hl::Instr::Simd(hl::SimdOp::I32x4GeS),
+        ll::Instr::I32x4GeU => hl::Instr::Simd(hl::SimdOp::I32x4GeU),
+        ll::Instr::F32x4Eq => hl::Instr::Simd(hl::SimdOp::F32x4Eq),
+        ll::Instr::F32x4Ne => hl::Instr::Simd(hl::SimdOp::F32x4Ne),
+        ll::Instr::F32x4Lt => hl::Instr::Simd(hl::SimdOp::F32x4Lt),
+        ll::Instr::F32x4Gt => hl::Instr::Simd(hl::SimdOp::F32x4Gt),
+        ll::Instr::F32x4Le => hl::Instr::Simd(hl::SimdOp::F32x4Le),
+        ll::Instr::F32x4Ge => hl::Instr::Simd(hl::SimdOp::F32x4Ge),
+        ll::Instr::F64x2Eq => hl::Instr::Simd(hl::SimdOp::F64x2Eq),
+        ll::Instr::F64x2Ne => hl::Instr::Simd(hl::SimdOp::F64x2Ne),
+        ll::Instr::F64x2Lt => hl::Instr::Simd(hl::SimdOp::F64x2Lt),
+        ll::Instr::F64x2Gt => hl::Instr::Simd(hl::SimdOp::F64x2Gt),
+        ll::Instr::F64x2Le => hl::Instr::Simd(hl::SimdOp::F64x2Le),
+        ll::Instr::F64x2Ge => hl::Instr::Simd(hl::SimdOp::F64x2Ge),
+        ll::Instr::V128Not => hl::Instr::Simd(hl::SimdOp::V128Not),
+        ll::Instr::V128And => hl::Instr::Simd(hl::SimdOp::V128And),
+        ll::Instr::V128Andnot => hl::Instr::Simd(hl::SimdOp::V128Andnot),
+        ll::Instr::V128Or => hl::Instr::Simd(hl::SimdOp::V128Or),
+        ll::Instr::V128Xor => hl::Instr::Simd(hl::SimdOp::V128Xor),
+        ll::Instr::V128Bitselect => hl::Instr::Simd(hl::SimdOp::V128Bitselect),
+        ll::Instr::V128AnyTrue => hl::Instr::Simd(hl::SimdOp::V128AnyTrue),
+        ll::Instr::V128Load8Lane(memarg, lane) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load8Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Load16Lane(memarg, lane) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load16Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Load32Lane(memarg, lane) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load32Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Load64Lane(memarg, lane) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load64Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Store8Lane(memarg, lane) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store8Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Store16Lane(memarg, lane) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store16Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Store32Lane(memarg, lane) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store32Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Store64Lane(memarg, lane) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store64Lane, from_lowlevel_memarg(memarg), lane),
+        ll::Instr::V128Load32Zero(memarg) => hl::Instr::Load(hl::LoadOp::V128Load32Zero, from_lowlevel_memarg(memarg)),
+        ll::Instr::V128Load64Zero(memarg) => hl::Instr::Load(hl::LoadOp::V128Load64Zero, from_lowlevel_memarg(memarg)),
+        ll::Instr::F32x4DemoteF64x2Zero => hl::Instr::Simd(hl::SimdOp::F32x4DemoteF64x2Zero),
+        ll::Instr::F64x2PromoteLowF32x4 => hl::Instr::Simd(hl::SimdOp::F64x2PromoteLowF32x4),
+        ll::Instr::I8x16Abs => hl::Instr::Simd(hl::SimdOp::I8x16Abs),
+        ll::Instr::I8x16Neg => hl::Instr::Simd(hl::SimdOp::I8x16Neg),
+        ll::Instr::I8x16Popcnt => hl::Instr::Simd(hl::SimdOp::I8x16Popcnt),
+        ll::Instr::I8x16AllTrue => hl::Instr::Simd(hl::SimdOp::I8x16AllTrue),
+        ll::Instr::I8x16Bitmask => hl::Instr::Simd(hl::SimdOp::I8x16Bitmask),
+        ll::Instr::I8x16NarrowI16x8S => hl::Instr::Simd(hl::SimdOp::I8x16NarrowI16x8S),
+        ll::Instr::I8x16NarrowI16x8U => hl::Instr::Simd(hl::SimdOp::I8x16NarrowI16x8U),
+        ll::Instr::F32x4Ceil => hl::Instr::Simd(hl::SimdOp::F32x4Ceil),
+        ll::Instr::F32x4Floor => hl::Instr::Simd(hl::SimdOp::F32x4Floor),
+        ll::Instr::F32x4Trunc => hl::Instr::Simd(hl::SimdOp::F32x4Trunc),
+        ll::Instr::F32x4Nearest => hl::Instr::Simd(hl::SimdOp::F32x4Nearest),
+        ll::Instr::I8x16Shl => hl::Instr::Simd(hl::SimdOp::I8x16Shl),
+        ll::Instr::I8x16ShrS => hl::Instr::Simd(hl::SimdOp::I8x16ShrS),
+        ll::Instr::I8x16ShrU => hl::Instr::Simd(hl::SimdOp::I8x16ShrU),
+        ll::Instr::I8x16Add => hl::Instr::Simd(hl::SimdOp::I8x16Add),
+        ll::Instr::I8x16AddSatS => hl::Instr::Simd(hl::SimdOp::I8x16AddSatS),
+        ll::Instr::I8x16AddSatU => hl::Instr::Simd(hl::SimdOp::I8x16AddSatU),
+        ll::Instr::I8x16Sub => hl::Instr::Simd(hl::SimdOp::I8x16Sub),
+        ll::Instr::I8x16SubSatS => hl::Instr::Simd(hl::SimdOp::I8x16SubSatS),
+        ll::Instr::I8x16SubSatU => hl::Instr::Simd(hl::SimdOp::I8x16SubSatU),
+        ll::Instr::F64x2Ceil => hl::Instr::Simd(hl::SimdOp::F64x2Ceil),
+        ll::Instr::F64x2Floor => hl::Instr::Simd(hl::SimdOp::F64x2Floor),
+        ll::Instr::I8x16MinS => hl::Instr::Simd(hl::SimdOp::I8x16MinS),
+        ll::Instr::I8x16MinU => hl::Instr::Simd(hl::SimdOp::I8x16MinU),
+        ll::Instr::I8x16MaxS => hl::Instr::Simd(hl::SimdOp::I8x16MaxS),
+        ll::Instr::I8x16MaxU => hl::Instr::Simd(hl::SimdOp::I8x16MaxU),
+        ll::Instr::F64x2Trunc => hl::Instr::Simd(hl::SimdOp::F64x2Trunc),
+        ll::Instr::I8x16AvgrU => hl::Instr::Simd(hl::SimdOp::I8x16AvgrU),
+        ll::Instr::I16x8ExtaddPairwiseI8x16S => hl::Instr::Simd(hl::SimdOp::I16x8ExtaddPairwiseI8x16S),
+        ll::Instr::I16x8ExtaddPairwiseI8x16U => hl::Instr::Simd(hl::SimdOp::I16x8ExtaddPairwiseI8x16U),
+        ll::Instr::I32x4ExtaddPairwiseI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4ExtaddPairwiseI16x8S),
+        ll::Instr::I32x4ExtaddPairwiseI16x8U => hl::Instr::Simd(hl::SimdOp::I32x4ExtaddPairwiseI16x8U),
+        ll::Instr::I16x8Abs => hl::Instr::Simd(hl::SimdOp::I16x8Abs),
+        ll::Instr::I16x8Neg => hl::Instr::Simd(hl::SimdOp::I16x8Neg),
+        ll::Instr::I16x8Q15mulrSatS => hl::Instr::Simd(hl::SimdOp::I16x8Q15mulrSatS),
+        ll::Instr::I16x8AllTrue => hl::Instr::Simd(hl::SimdOp::I16x8AllTrue),
+        ll::Instr::I16x8Bitmask => hl::Instr::Simd(hl::SimdOp::I16x8Bitmask),
+        ll::Instr::I16x8NarrowI32x4S => hl::Instr::Simd(hl::SimdOp::I16x8NarrowI32x4S),
+        ll::Instr::I16x8NarrowI32x4U => hl::Instr::Simd(hl::SimdOp::I16x8NarrowI32x4U),
+        ll::Instr::I16x8ExtendLowI8x16S => hl::Instr::Simd(hl::SimdOp::I16x8ExtendLowI8x16S),
+        ll::Instr::I16x8ExtendHighI8x16S => hl::Instr::Simd(hl::SimdOp::I16x8ExtendHighI8x16S),
+        ll::Instr::I16x8ExtendLowI8x16U => hl::Instr::Simd(hl::SimdOp::I16x8ExtendLowI8x16U),
+        ll::Instr::I16x8ExtendHighI8x16U => hl::Instr::Simd(hl::SimdOp::I16x8ExtendHighI8x16U),
+        ll::Instr::I16x8Shl => hl::Instr::Simd(hl::SimdOp::I16x8Shl),
+        ll::Instr::I16x8ShrS => hl::Instr::Simd(hl::SimdOp::I16x8ShrS),
+        ll::Instr::I16x8ShrU => hl::Instr::Simd(hl::SimdOp::I16x8ShrU),
+        ll::Instr::I16x8Add => hl::Instr::Simd(hl::SimdOp::I16x8Add),
+        ll::Instr::I16x8AddSatS => hl::Instr::Simd(hl::SimdOp::I16x8AddSatS),
+        ll::Instr::I16x8AddSatU => hl::Instr::Simd(hl::SimdOp::I16x8AddSatU),
+        ll::Instr::I16x8Sub => hl::Instr::Simd(hl::SimdOp::I16x8Sub),
+        ll::Instr::I16x8SubSatS => hl::Instr::Simd(hl::SimdOp::I16x8SubSatS),
+        ll::Instr::I16x8SubSatU => hl::Instr::Simd(hl::SimdOp::I16x8SubSatU),
+        ll::Instr::F64x2Nearest => hl::Instr::Simd(hl::SimdOp::F64x2Nearest),
+        ll::Instr::I16x8Mul => hl::Instr::Simd(hl::SimdOp::I16x8Mul),
+        ll::Instr::I16x8MinS => hl::Instr::Simd(hl::SimdOp::I16x8MinS),
+        ll::Instr::I16x8MinU => hl::Instr::Simd(hl::SimdOp::I16x8MinU),
+        ll::Instr::I16x8MaxS => hl::Instr::Simd(hl::SimdOp::I16x8MaxS),
+        ll::Instr::I16x8MaxU => hl::Instr::Simd(hl::SimdOp::I16x8MaxU),
+        ll::Instr::I16x8AvgrU => hl::Instr::Simd(hl::SimdOp::I16x8AvgrU),
+        ll::Instr::I16x8ExtmulLowI8x16S => hl::Instr::Simd(hl::SimdOp::I16x8ExtmulLowI8x16S),
+        ll::Instr::I16x8ExtmulHighI8x16S => hl::Instr::Simd(hl::SimdOp::I16x8ExtmulHighI8x16S),
+        ll::Instr::I16x8ExtmulLowI8x16U => hl::Instr::Simd(hl::SimdOp::I16x8ExtmulLowI8x16U),
+        ll::Instr::I16x8ExtmulHighI8x16U => hl::Instr::Simd(hl::SimdOp::I16x8ExtmulHighI8x16U),
+        ll::Instr::I32x4Abs => hl::Instr::Simd(hl::SimdOp::I32x4Abs),
+        ll::Instr::I32x4Neg => hl::Instr::Simd(hl::SimdOp::I32x4Neg),
+        ll::Instr::I32x4AllTrue => hl::Instr::Simd(hl::SimdOp::I32x4AllTrue),
+        ll::Instr::I32x4Bitmask => hl::Instr::Simd(hl::SimdOp::I32x4Bitmask),
+        ll::Instr::I32x4ExtendLowI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4ExtendLowI16x8S),
+        ll::Instr::I32x4ExtendHighI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4ExtendHighI16x8S),
+        ll::Instr::I32x4ExtendLowI16x8U => hl::Instr::Simd(hl::SimdOp::I32x4ExtendLowI16x8U),
+        ll::Instr::I32x4ExtendHighI16x8U => hl::Instr::Simd(hl::SimdOp::I32x4ExtendHighI16x8U),
+        ll::Instr::I32x4Shl => hl::Instr::Simd(hl::SimdOp::I32x4Shl),
+        ll::Instr::I32x4ShrS => hl::Instr::Simd(hl::SimdOp::I32x4ShrS),
+        ll::Instr::I32x4ShrU => hl::Instr::Simd(hl::SimdOp::I32x4ShrU),
+        ll::Instr::I32x4Add => hl::Instr::Simd(hl::SimdOp::I32x4Add),
+        ll::Instr::I32x4Sub => hl::Instr::Simd(hl::SimdOp::I32x4Sub),
+        ll::Instr::I32x4Mul => hl::Instr::Simd(hl::SimdOp::I32x4Mul),
+        ll::Instr::I32x4MinS => hl::Instr::Simd(hl::SimdOp::I32x4MinS),
+        ll::Instr::I32x4MinU => hl::Instr::Simd(hl::SimdOp::I32x4MinU),
+        ll::Instr::I32x4MaxS => hl::Instr::Simd(hl::SimdOp::I32x4MaxS),
+        ll::Instr::I32x4MaxU => hl::Instr::Simd(hl::SimdOp::I32x4MaxU),
+        ll::Instr::I32x4DotI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4DotI16x8S),
+        ll::Instr::I32x4ExtmulLowI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4ExtmulLowI16x8S),
+        ll::Instr::I32x4ExtmulHighI16x8S => hl::Instr::Simd(hl::SimdOp::I32x4ExtmulHighI16x8S),
+        ll::Instr::I32x4ExtmulLowI16x8U => hl::Instr::Simd(hl::SimdOp::I32x4ExtmulLowI16x8U),
+        ll::Instr::I32x4ExtmulHighI16x8U => hl::Instr::Simd(hl::SimdOp::I32x4ExtmulHighI16x8U),
+        ll::Instr::I64x2Abs => hl::Instr::Simd(hl::SimdOp::I64x2Abs),
+        ll::Instr::I64x2Neg => hl::Instr::Simd(hl::SimdOp::I64x2Neg),
+        ll::Instr::I64x2AllTrue => hl::Instr::Simd(hl::SimdOp::I64x2AllTrue),
+        ll::Instr::I64x2Bitmask => hl::Instr::Simd(hl::SimdOp::I64x2Bitmask),
+        ll::Instr::I64x2ExtendLowI32x4S => hl::Instr::Simd(hl::SimdOp::I64x2ExtendLowI32x4S),
+        ll::Instr::I64x2ExtendHighI32x4S => hl::Instr::Simd(hl::SimdOp::I64x2ExtendHighI32x4S),
+        ll::Instr::I64x2ExtendLowI32x4U => hl::Instr::Simd(hl::SimdOp::I64x2ExtendLowI32x4U),
+        ll::Instr::I64x2ExtendHighI32x4U => hl::Instr::Simd(hl::SimdOp::I64x2ExtendHighI32x4U),
+        ll::Instr::I64x2Shl => hl::Instr::Simd(hl::SimdOp::I64x2Shl),
+        ll::Instr::I64x2ShrS => hl::Instr::Simd(hl::SimdOp::I64x2ShrS),
+        ll::Instr::I64x2ShrU => hl::Instr::Simd(hl::SimdOp::I64x2ShrU),
+        ll::Instr::I64x2Add => hl::Instr::Simd(hl::SimdOp::I64x2Add),
+        ll::Instr::I64x2Sub => hl::Instr::Simd(hl::SimdOp::I64x2Sub),
+        ll::Instr::I64x2Mul => hl::Instr::Simd(hl::SimdOp::I64x2Mul),
+        ll::Instr::I64x2Eq => hl::Instr::Simd(hl::SimdOp::I64x2Eq),
+        ll::Instr::I64x2Ne => hl::Instr::Simd(hl::SimdOp::I64x2Ne),
+        ll::Instr::I64x2LtS => hl::Instr::Simd(hl::SimdOp::I64x2LtS),
+        ll::Instr::I64x2GtS => hl::Instr::Simd(hl::SimdOp::I64x2GtS),
+        ll::Instr::I64x2LeS => hl::Instr::Simd(hl::SimdOp::I64x2LeS),
+        ll::Instr::I64x2GeS => hl::Instr::Simd(hl::SimdOp::I64x2GeS),
+        ll::Instr::I64x2ExtmulLowI32x4S => hl::Instr::Simd(hl::SimdOp::I64x2ExtmulLowI32x4S),
+        ll::Instr::I64x2ExtmulHighI32x4S => hl::Instr::Simd(hl::SimdOp::I64x2ExtmulHighI32x4S),
+        ll::Instr::I64x2ExtmulLowI32x4U => hl::Instr::Simd(hl::SimdOp::I64x2ExtmulLowI32x4U),
+        ll::Instr::I64x2ExtmulHighI32x4U => hl::Instr::Simd(hl::SimdOp::I64x2ExtmulHighI32x4U),
+        ll::Instr::F32x4Abs => hl::Instr::Simd(hl::SimdOp::F32x4Abs),
+        ll::Instr::F32x4Neg => hl::Instr::Simd(hl::SimdOp::F32x4Neg),
+        ll::Instr::F32x4Sqrt => hl::Instr::Simd(hl::SimdOp::F32x4Sqrt),
+        ll::Instr::F32x4Add => hl::Instr::Simd(hl::SimdOp::F32x4Add),
+        ll::Instr::F32x4Sub => hl::Instr::Simd(hl::SimdOp::F32x4Sub),
+        ll::Instr::F32x4Mul => hl::Instr::Simd(hl::SimdOp::F32x4Mul),
+        ll::Instr::F32x4Div => hl::Instr::Simd(hl::SimdOp::F32x4Div),
+        ll::Instr::F32x4Min => hl::Instr::Simd(hl::SimdOp::F32x4Min),
+        ll::Instr::F32x4Max => hl::Instr::Simd(hl::SimdOp::F32x4Max),
+        ll::Instr::F32x4Pmin => hl::Instr::Simd(hl::SimdOp::F32x4Pmin),
+        ll::Instr::F32x4Pmax => hl::Instr::Simd(hl::SimdOp::F32x4Pmax),
+        ll::Instr::F64x2Abs => hl::Instr::Simd(hl::SimdOp::F64x2Abs),
+        ll::Instr::F64x2Neg => hl::Instr::Simd(hl::SimdOp::F64x2Neg),
+        ll::Instr::F64x2Sqrt => hl::Instr::Simd(hl::SimdOp::F64x2Sqrt),
+        ll::Instr::F64x2Add => hl::Instr::Simd(hl::SimdOp::F64x2Add),
+        ll::Instr::F64x2Sub => hl::Instr::Simd(hl::SimdOp::F64x2Sub),
+        ll::Instr::F64x2Mul => hl::Instr::Simd(hl::SimdOp::F64x2Mul),
+        ll::Instr::F64x2Div => hl::Instr::Simd(hl::SimdOp::F64x2Div),
+        ll::Instr::F64x2Min => hl::Instr::Simd(hl::SimdOp::F64x2Min),
+        ll::Instr::F64x2Max => hl::Instr::Simd(hl::SimdOp::F64x2Max),
+        ll::Instr::F64x2Pmin => hl::Instr::Simd(hl::SimdOp::F64x2Pmin),
+        ll::Instr::F64x2Pmax => hl::Instr::Simd(hl::SimdOp::F64x2Pmax),
+        ll::Instr::I32x4TruncSatF32x4S => hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF32x4S),
+        ll::Instr::I32x4TruncSatF32x4U => hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF32x4U),
+        ll::Instr::F32x4ConvertI32x4S => hl::Instr::Simd(hl::SimdOp::F32x4ConvertI32x4S),
+        ll::Instr::F32x4ConvertI32x4U => hl::Instr::Simd(hl::SimdOp::F32x4ConvertI32x4U),
+        ll::Instr::I32x4TruncSatF64x2SZero => hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF64x2SZero),
+        ll::Instr::I32x4TruncSatF64x2UZero => hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF64x2UZero),
+        ll::Instr::F64x2ConvertLowI32x4S => hl::Instr::Simd(hl::SimdOp::F64x2ConvertLowI32x4S),
+        ll::Instr::F64x2ConvertLowI32x4U => hl::Instr::Simd(hl::SimdOp::F64x2ConvertLowI32x4U),
+
+        ll::Instr::MemoryAtomicNotify(memarg) => hl::Instr::MemoryAtomicNotify(from_lowlevel_memarg(memarg)),
+        ll::Instr::MemoryAtomicWait32(memarg) => hl::Instr::MemoryAtomicWait32(from_lowlevel_memarg(memarg)),
+        ll::Instr::MemoryAtomicWait64(memarg) => hl::Instr::MemoryAtomicWait64(from_lowlevel_memarg(memarg)),
+        ll::Instr::AtomicFence(_) => hl::Instr::AtomicFence,
+        ll::Instr::I32AtomicLoad(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicLoad(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicLoad8U(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad8U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicLoad16U(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad16U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicLoad8U(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad8U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicLoad16U(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad16U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicLoad32U(memarg) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad32U, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicStore(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicStore(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicStore8(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore8, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicStore16(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore16, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicStore8(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore8, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicStore16(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore16, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicStore32(memarg) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore32, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwAdd(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAdd, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwAdd(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAdd, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8AddU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AddU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16AddU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AddU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8AddU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AddU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16AddU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AddU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32AddU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AddU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwSub(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwSub, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwSub(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwSub, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8SubU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8SubU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16SubU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16SubU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8SubU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8SubU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16SubU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16SubU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32SubU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32SubU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwAnd(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAnd, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwAnd(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAnd, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8AndU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AndU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16AndU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AndU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8AndU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AndU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16AndU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AndU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32AndU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AndU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwOr(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwOr, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwOr(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwOr, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8OrU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8OrU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16OrU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16OrU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8OrU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8OrU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16OrU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16OrU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32OrU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32OrU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwXor(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXor, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwXor(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXor, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8XorU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XorU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16XorU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XorU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8XorU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XorU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16XorU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XorU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32XorU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XorU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwXchg(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXchg, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwXchg(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXchg, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8XchgU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16XchgU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8XchgU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16XchgU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32XchgU(memarg) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmwCmpxchg(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmwCmpxchg, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmwCmpxchg(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmwCmpxchg, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw8CmpxchgU(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I32AtomicRmw16CmpxchgU(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw8CmpxchgU(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw16CmpxchgU(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU, from_lowlevel_memarg(memarg)),
+        ll::Instr::I64AtomicRmw32CmpxchgU(memarg) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU, from_lowlevel_memarg(memarg)),
     }
 }
 
@@ -359,6 +810,7 @@ struct EncodeState {
     table_idx: HashMap<usize, usize>,
     memory_idx: HashMap<usize, usize>,
     global_idx: HashMap<usize, usize>,
+    tag_idx: HashMap<usize, usize>,
 }
 
 macro_rules! element_idx_fns {
@@ -379,13 +831,14 @@ impl EncodeState {
         (*self.types.entry(type_).or_insert(new_idx)).into()
     }
     fn get_type_idx(&self, type_: &FunctionType) -> Idx<FunctionType> {
-        (*self.types.get(type_).expect("call_indirect with unknown type")).into()
+        (*self.types.get(type_).expect("call_indirect or block with unknown type")).into()
     }
 
     element_idx_fns!(insert_function_idx, map_function_idx, function_idx, ll::Function);
     element_idx_fns!(insert_table_idx, map_table_idx, table_idx, ll::Table);
     element_idx_fns!(insert_memory_idx, map_memory_idx, memory_idx, ll::Memory);
     element_idx_fns!(insert_global_idx, map_global_idx, global_idx, ll::Global);
+    element_idx_fns!(insert_tag_idx, map_tag_idx, tag_idx, ll::Tag);
 }
 
 impl From<hl::Module> for ll::Module {
@@ -398,21 +851,31 @@ impl From<hl::Module> for ll::Module {
             table_idx: HashMap::new(),
             memory_idx: HashMap::new(),
             global_idx: HashMap::new(),
+            tag_idx: HashMap::new(),
         };
 
         let imports = to_lowlevel_imports(&module, &mut state);
         let functions = to_lowlevel_functions(&module.functions, &mut state);
         let tables = to_lowlevel_tables(&module.tables, &mut state);
         let memories = to_lowlevel_memories(&module.memories, &mut state);
+        let tags = to_lowlevel_tags(&module.tags, &mut state);
         let globals = to_lowlevel_globals(&module.globals, &mut state);
 
         // also collect and insert types in all call_indirect instructions, maybe they are calling
         // with a signature that not function mentions (which would be unpractical, because it could
         // never be valid at runtime, but is done in the spec tests)
+        // same for multi-value block types, which are encoded as a type index
         for function in &module.functions {
             for instr in function.code.iter().flat_map(|c| c.body.iter()) {
-                if let hl::Instr::CallIndirect(ty, _) = instr {
-                    state.get_or_insert_type(ty.clone());
+                match instr {
+                    hl::Instr::CallIndirect(ty, _) | hl::Instr::ReturnCallIndirect(ty, _) => {
+                        state.get_or_insert_type(ty.clone());
+                    }
+                    hl::Instr::Block(ty) | hl::Instr::Loop(ty) | hl::Instr::If(ty) | hl::Instr::Try(ty)
+                    if !ty.params.is_empty() || ty.results.len() > 1 => {
+                        state.get_or_insert_type(ty.clone());
+                    }
+                    _ => {}
                 }
             }
         }
@@ -449,6 +912,11 @@ impl From<hl::Module> for ll::Module {
             sections.push(ll::Section::Memory(ll::WithSize(memories)));
         }
 
+        // Tag
+        if !tags.is_empty() {
+            sections.push(ll::Section::Tag(ll::WithSize(tags)));
+        }
+
         // Global
         if !globals.is_empty() {
             sections.push(ll::Section::Global(ll::WithSize(globals)));
@@ -460,26 +928,45 @@ impl From<hl::Module> for ll::Module {
             sections.push(ll::Section::Export(ll::WithSize(exports)));
         }
 
+        // must be done before the module is consumed by the element, code, and data sections, but the
+        // name section is written out last
+        let names = to_lowlevel_names(&module, &state);
+
         // Start
         for start in module.start {
             sections.push(ll::Section::Start(ll::WithSize(state.map_function_idx(start.0))));
         }
 
         // Element
-        let elements: Vec<ll::Element> = module.tables.into_iter()
-            .enumerate()
-            .flat_map(|(i, table)| table.elements.into_iter()
-                .map(|element| ll::Element {
-                    table_idx: state.map_table_idx(i),
-                    offset: to_lowlevel_expr(&element.offset, &state),
-                    init: element.functions.iter().map(|fn_idx| state.map_function_idx(fn_idx.0)).collect(),
-                })
-                .collect::<Vec<_>>())
+        let elements: Vec<ll::Element> = module.elements.iter()
+            .map(|element| ll::Element {
+                mode: match element.mode {
+                    hl::ElementMode::Active { table, ref offset } => ll::ElementMode::Active(state.map_table_idx(table.0), to_lowlevel_expr(offset, &state)),
+                    hl::ElementMode::Passive => ll::ElementMode::Passive,
+                    hl::ElementMode::Declarative => ll::ElementMode::Declarative,
+                },
+                init: match element.items {
+                    hl::ElementItems::Functions(ref functions) => ll::ElementItems::Functions(functions.iter().map(|fn_idx| state.map_function_idx(fn_idx.0)).collect()),
+                    hl::ElementItems::Expressions(type_, ref exprs) => ll::ElementItems::Expressions(type_, exprs.iter().map(|expr| to_lowlevel_expr(expr, &state)).collect()),
+                },
+            })
             .collect();
         if !elements.is_empty() {
             sections.push(ll::Section::Element(ll::WithSize(elements)));
         }
 
+        // DataCount (only required if data segments are referenced from code, so omit it otherwise
+        // to keep MVP modules free of post-MVP sections)
+        let uses_data_idx = module.functions.iter()
+            .flat_map(|function| function.code.iter().flat_map(|code| code.body.iter()))
+            .any(|instr| match instr {
+                hl::Instr::MemoryInit(_, _) | hl::Instr::DataDrop(_) => true,
+                _ => false,
+            });
+        if uses_data_idx {
+            sections.push(ll::Section::DataCount(ll::WithSize(module.data.len() as u32)));
+        }
+
         // Code
         let code: Vec<ll::WithSize<ll::Code>> = module.functions.into_par_iter()
             .filter_map(|function|
@@ -490,29 +977,68 @@ impl From<hl::Module> for ll::Module {
         }
 
         // Data
-        let data: Vec<ll::Data> = module.memories.into_iter()
-            .enumerate()
-            .flat_map(|(i, memory)| memory.data.into_iter()
-                .map(|data| ll::Data {
-                    memory_idx: state.map_memory_idx(i),
-                    offset: to_lowlevel_expr(&data.offset, &state),
-                    init: data.bytes,
-                })
-                .collect::<Vec<_>>())
+        let data: Vec<ll::Data> = module.data.into_iter()
+            .map(|data| ll::Data {
+                mode: match data.mode {
+                    hl::DataMode::Active { memory, offset } => ll::DataMode::Active(state.map_memory_idx(memory.0), to_lowlevel_expr(&offset, &state)),
+                    hl::DataMode::Passive => ll::DataMode::Passive,
+                },
+                init: data.bytes,
+            })
             .collect();
         if !data.is_empty() {
             sections.push(ll::Section::Data(ll::WithSize(data)));
         }
 
         // Custom
-        // TODO put the pass-through custom sections in the same order as they were originally
-        // necessary, e.g., because custom section "name" must come in some specific order
-        // requires saving the order earlier when converting from ll -> hl
-        for custom in module.custom_sections {
-            sections.push(ll::Section::Custom(custom));
+        let mut custom_sections = module.custom_sections;
+        let mut names = Some(names).filter(|names| !names.subsections.is_empty());
+        // name section should come after the data section, if there was none originally
+        if names.is_some() && !custom_sections.iter().any(|(_, section)| match section {
+            hl::CustomSection::Name => true,
+            _ => false,
+        }) {
+            custom_sections.push((SectionPosition::Last, hl::CustomSection::Name));
         }
+        let custom_sections = custom_sections.into_iter()
+            .filter_map(|(position, section)| match section {
+                hl::CustomSection::Name => names.take().map(|names| (position, ll::CustomSection::Name(names))),
+                section => Some((position, to_lowlevel_custom_section(section))),
+            })
+            .collect();
 
-        ll::Module { sections }
+        ll::Module { sections: insert_custom_sections(sections, custom_sections) }
+    }
+}
+
+/// put custom sections back at their original position relative to the known sections
+fn insert_custom_sections(known_sections: Vec<ll::Section>, mut custom_sections: Vec<(SectionPosition, ll::CustomSection)>) -> Vec<ll::Section> {
+    let mut sections = Vec::with_capacity(known_sections.len() + custom_sections.len());
+    for section in known_sections {
+        let position = SectionPosition::After(section.id().expect("custom sections are inserted only here"));
+        // everything that came before this section (or a missing section before it) goes first
+        let (before, after): (Vec<_>, Vec<_>) = custom_sections.into_iter()
+            .partition(|&(custom_position, _)| custom_position < position);
+        sections.extend(before.into_iter().map(|(_, custom)| ll::Section::Custom(custom)));
+        sections.push(section);
+        custom_sections = after;
+    }
+    sections.extend(custom_sections.into_iter().map(|(_, custom)| ll::Section::Custom(custom)));
+    sections
+}
+
+fn to_lowlevel_custom_section(section: hl::CustomSection) -> ll::CustomSection {
+    match section {
+        hl::CustomSection::Producers(section) => ll::CustomSection::Producers(section),
+        hl::CustomSection::TargetFeatures(section) => ll::CustomSection::TargetFeatures(section),
+        hl::CustomSection::SourceMappingUrl(url) => ll::CustomSection::SourceMappingUrl(url),
+        hl::CustomSection::Parsed(section) => {
+            let mut content = Vec::new();
+            section.encode_content(&mut content).expect("could not encode custom section");
+            ll::CustomSection::Raw(RawCustomSection { name: section.name().to_string(), content })
+        }
+        hl::CustomSection::Raw(section) => ll::CustomSection::Raw(section),
+        hl::CustomSection::Name => unreachable!("name section is converted separately"),
     }
 }
 
@@ -538,6 +1064,7 @@ fn to_lowlevel_imports(module: &hl::Module, state: &mut EncodeState) -> Vec<ll::
     add_imports!(tables, insert_table_idx, Table, |ty| ty);
     add_imports!(memories, insert_memory_idx, Memory, |ty| ty);
     add_imports!(globals, insert_global_idx, Global, |ty| ty);
+    add_imports!(tags, insert_tag_idx, Tag, |ty| ll::TagType::Exception(state.get_or_insert_type(ty)));
 
     imports
 }
@@ -567,6 +1094,10 @@ fn to_lowlevel_memories(memories: &[hl::Memory], state: &mut EncodeState) -> Vec
     to_lowlevel_elements!(memories, state, insert_memory_idx, |memory: &hl::Memory| memory.type_.clone())
 }
 
+fn to_lowlevel_tags(tags: &[hl::Tag], state: &mut EncodeState) -> Vec<ll::TagType> {
+    to_lowlevel_elements!(tags, state, insert_tag_idx, |tag: &hl::Tag| ll::TagType::Exception(state.get_or_insert_type(tag.type_.clone())))
+}
+
 fn to_lowlevel_globals(globals: &[hl::Global], state: &mut EncodeState) -> Vec<ll::Global> {
     to_lowlevel_elements!(globals, state, insert_global_idx, |global: &hl::Global| ll::Global {
         type_: global.type_,
@@ -593,10 +1124,51 @@ fn to_lowlevel_exports(module: &hl::Module, state: &EncodeState) -> Vec<ll::Expo
     add_exports!(tables, map_table_idx, Table);
     add_exports!(memories, map_memory_idx, Memory);
     add_exports!(globals, map_global_idx, Global);
+    add_exports!(tags, map_tag_idx, Tag);
 
     exports
 }
 
+/// function indices change when converting to low-level (imported functions come first), so the
+/// name maps have to be re-created with the new indices (and sorted by them, as the spec requires)
+fn to_lowlevel_names(module: &hl::Module, state: &EncodeState) -> ll::NameSection {
+    let mut subsections = Vec::new();
+
+    if let Some(ref name) = module.name {
+        subsections.push(ll::NameSubSection::Module(ll::WithSize(name.clone())));
+    }
+
+    let mut function_names: Vec<ll::FunctionName> = module.functions.iter()
+        .enumerate()
+        .filter_map(|(i, function)| function.name.as_ref().map(|name| ll::FunctionName {
+            function_idx: state.map_function_idx(i),
+            name: name.clone(),
+        }))
+        .collect();
+    function_names.sort_by_key(|function_name| function_name.function_idx);
+    if !function_names.is_empty() {
+        subsections.push(ll::NameSubSection::Function(ll::WithSize(function_names)));
+    }
+
+    // local indices do not change, and local_names is already sorted by index
+    let mut local_names: Vec<ll::LocalNames> = module.functions.iter()
+        .enumerate()
+        .filter(|&(_, function)| !function.local_names.is_empty())
+        .map(|(i, function)| ll::LocalNames {
+            function_idx: state.map_function_idx(i),
+            names: function.local_names.iter()
+                .map(|(&local_idx, name)| ll::LocalName { local_idx, name: name.clone() })
+                .collect(),
+        })
+        .collect();
+    local_names.sort_by_key(|local_names| local_names.function_idx);
+    if !local_names.is_empty() {
+        subsections.push(ll::NameSubSection::Local(ll::WithSize(local_names)));
+    }
+
+    ll::NameSection { subsections }
+}
+
 fn to_lowlevel_code(code: hl::Code, state: &EncodeState) -> ll::Code {
     let mut locals = Vec::new();
     for type_ in code.locals {
@@ -618,7 +1190,24 @@ fn to_lowlevel_code(code: hl::Code, state: &EncodeState) -> ll::Code {
 }
 
 fn to_lowlevel_expr(expr: &[hl::Instr], state: &EncodeState) -> ll::Expr {
-    ll::Expr(expr.iter().map(|instr| to_lowlevel_instr(instr, state)).collect())
+    ll::Expr(expr.iter().map(|instr| to_lowlevel_instr(instr, state)).collect(), None)
+}
+
+/// use the compact encoding where possible, so that MVP modules stay MVP modules
+fn to_lowlevel_block_type(block_type: &hl::BlockType, state: &EncodeState) -> ll::BlockType {
+    match (block_type.params.as_slice(), block_type.results.as_slice()) {
+        ([], []) => ll::BlockType::Empty,
+        ([], [val_type]) => ll::BlockType::Value(*val_type),
+        _ => ll::BlockType::Index(state.get_type_idx(block_type)),
+    }
+}
+
+fn to_lowlevel_memarg(memarg: hl::Memarg, state: &EncodeState) -> ll::Memarg {
+    ll::Memarg {
+        alignment: memarg.alignment,
+        memory: state.map_memory_idx(memarg.memory.0),
+        offset: memarg.offset,
+    }
 }
 
 fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
@@ -626,12 +1215,19 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Unreachable => ll::Instr::Unreachable,
         hl::Instr::Nop => ll::Instr::Nop,
 
-        hl::Instr::Block(block_type) => ll::Instr::Block(block_type),
-        hl::Instr::Loop(block_type) => ll::Instr::Loop(block_type),
-        hl::Instr::If(block_type) => ll::Instr::If(block_type),
+        hl::Instr::Block(ref block_type) => ll::Instr::Block(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::Loop(ref block_type) => ll::Instr::Loop(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::If(ref block_type) => ll::Instr::If(to_lowlevel_block_type(block_type, state)),
         hl::Instr::Else => ll::Instr::Else,
         hl::Instr::End => ll::Instr::End,
 
+        hl::Instr::Try(ref block_type) => ll::Instr::Try(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::Catch(tag_idx) => ll::Instr::Catch(state.map_tag_idx(tag_idx.0)),
+        hl::Instr::CatchAll => ll::Instr::CatchAll,
+        hl::Instr::Delegate(label_idx) => ll::Instr::Delegate(label_idx),
+        hl::Instr::Throw(tag_idx) => ll::Instr::Throw(state.map_tag_idx(tag_idx.0)),
+        hl::Instr::Rethrow(label_idx) => ll::Instr::Rethrow(label_idx),
+
         hl::Instr::Br(label_idx) => ll::Instr::Br(label_idx),
         hl::Instr::BrIf(label_idx) => ll::Instr::BrIf(label_idx),
         hl::Instr::BrTable(ref label_idx_table, default) => ll::Instr::BrTable(label_idx_table.clone(), default),
@@ -639,43 +1235,64 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Return => ll::Instr::Return,
         hl::Instr::Call(function_idx) => ll::Instr::Call(state.map_function_idx(function_idx.0)),
         hl::Instr::CallIndirect(ref type_, table_idx) => ll::Instr::CallIndirect(state.get_type_idx(&type_), state.map_table_idx(table_idx.0)),
+        hl::Instr::ReturnCall(function_idx) => ll::Instr::ReturnCall(state.map_function_idx(function_idx.0)),
+        hl::Instr::ReturnCallIndirect(ref type_, table_idx) => ll::Instr::ReturnCallIndirect(state.get_type_idx(&type_), state.map_table_idx(table_idx.0)),
 
         hl::Instr::Drop => ll::Instr::Drop,
         hl::Instr::Select => ll::Instr::Select,
+        hl::Instr::TypedSelect(type_) => ll::Instr::TypedSelect(vec![type_]),
 
         hl::Instr::Local(hl::LocalOp::GetLocal, local_idx) => ll::Instr::GetLocal(local_idx.0.into()),
         hl::Instr::Local(hl::LocalOp::SetLocal, local_idx) => ll::Instr::SetLocal(local_idx.0.into()),
         hl::Instr::Local(hl::LocalOp::TeeLocal, local_idx) => ll::Instr::TeeLocal(local_idx.0.into()),
         hl::Instr::Global(hl::GlobalOp::GetGlobal, global_idx) => ll::Instr::GetGlobal(state.map_global_idx(global_idx.0)),
         hl::Instr::Global(hl::GlobalOp::SetGlobal, global_idx) => ll::Instr::SetGlobal(state.map_global_idx(global_idx.0)),
-
-        hl::Instr::Load(hl::LoadOp::I32Load, memarg) => ll::Instr::I32Load(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load, memarg) => ll::Instr::I64Load(memarg),
-        hl::Instr::Load(hl::LoadOp::F32Load, memarg) => ll::Instr::F32Load(memarg),
-        hl::Instr::Load(hl::LoadOp::F64Load, memarg) => ll::Instr::F64Load(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load8S, memarg) => ll::Instr::I32Load8S(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load8U, memarg) => ll::Instr::I32Load8U(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load16S, memarg) => ll::Instr::I32Load16S(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load16U, memarg) => ll::Instr::I32Load16U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load8S, memarg) => ll::Instr::I64Load8S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load8U, memarg) => ll::Instr::I64Load8U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load16S, memarg) => ll::Instr::I64Load16S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load16U, memarg) => ll::Instr::I64Load16U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load32S, memarg) => ll::Instr::I64Load32S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load32U, memarg) => ll::Instr::I64Load32U(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store, memarg) => ll::Instr::I32Store(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store, memarg) => ll::Instr::I64Store(memarg),
-        hl::Instr::Store(hl::StoreOp::F32Store, memarg) => ll::Instr::F32Store(memarg),
-        hl::Instr::Store(hl::StoreOp::F64Store, memarg) => ll::Instr::F64Store(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store8, memarg) => ll::Instr::I32Store8(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store16, memarg) => ll::Instr::I32Store16(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store8, memarg) => ll::Instr::I64Store8(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store16, memarg) => ll::Instr::I64Store16(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store32, memarg) => ll::Instr::I64Store32(memarg),
+        hl::Instr::Table(hl::TableOp::TableGet, table_idx) => ll::Instr::TableGet(state.map_table_idx(table_idx.0)),
+        hl::Instr::Table(hl::TableOp::TableSet, table_idx) => ll::Instr::TableSet(state.map_table_idx(table_idx.0)),
+        hl::Instr::Table(hl::TableOp::TableSize, table_idx) => ll::Instr::TableSize(state.map_table_idx(table_idx.0)),
+        hl::Instr::Table(hl::TableOp::TableGrow, table_idx) => ll::Instr::TableGrow(state.map_table_idx(table_idx.0)),
+        hl::Instr::Table(hl::TableOp::TableFill, table_idx) => ll::Instr::TableFill(state.map_table_idx(table_idx.0)),
+
+        hl::Instr::Load(hl::LoadOp::I32Load, memarg) => ll::Instr::I32Load(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load, memarg) => ll::Instr::I64Load(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::F32Load, memarg) => ll::Instr::F32Load(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::F64Load, memarg) => ll::Instr::F64Load(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I32Load8S, memarg) => ll::Instr::I32Load8S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I32Load8U, memarg) => ll::Instr::I32Load8U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I32Load16S, memarg) => ll::Instr::I32Load16S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I32Load16U, memarg) => ll::Instr::I32Load16U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load8S, memarg) => ll::Instr::I64Load8S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load8U, memarg) => ll::Instr::I64Load8U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load16S, memarg) => ll::Instr::I64Load16S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load16U, memarg) => ll::Instr::I64Load16U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load32S, memarg) => ll::Instr::I64Load32S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::I64Load32U, memarg) => ll::Instr::I64Load32U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I32Store, memarg) => ll::Instr::I32Store(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I64Store, memarg) => ll::Instr::I64Store(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::F32Store, memarg) => ll::Instr::F32Store(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::F64Store, memarg) => ll::Instr::F64Store(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I32Store8, memarg) => ll::Instr::I32Store8(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I32Store16, memarg) => ll::Instr::I32Store16(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I64Store8, memarg) => ll::Instr::I64Store8(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I64Store16, memarg) => ll::Instr::I64Store16(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::I64Store32, memarg) => ll::Instr::I64Store32(to_lowlevel_memarg(memarg, state)),
 
         hl::Instr::MemorySize(memory_idx) => ll::Instr::MemorySize(state.map_memory_idx(memory_idx.0)),
         hl::Instr::MemoryGrow(memory_idx) => ll::Instr::MemoryGrow(state.map_memory_idx(memory_idx.0)),
 
+        // segment indices are not remapped, because the segments are written out in order
+        hl::Instr::MemoryInit(data_idx, memory_idx) => ll::Instr::MemoryInit(data_idx.0.into(), state.map_memory_idx(memory_idx.0)),
+        hl::Instr::DataDrop(data_idx) => ll::Instr::DataDrop(data_idx.0.into()),
+        hl::Instr::MemoryCopy(dst_idx, src_idx) => ll::Instr::MemoryCopy(state.map_memory_idx(dst_idx.0), state.map_memory_idx(src_idx.0)),
+        hl::Instr::MemoryFill(memory_idx) => ll::Instr::MemoryFill(state.map_memory_idx(memory_idx.0)),
+        hl::Instr::TableInit(elem_idx, table_idx) => ll::Instr::TableInit(elem_idx.0.into(), state.map_table_idx(table_idx.0)),
+        hl::Instr::ElemDrop(elem_idx) => ll::Instr::ElemDrop(elem_idx.0.into()),
+        hl::Instr::TableCopy(dst_idx, src_idx) => ll::Instr::TableCopy(state.map_table_idx(dst_idx.0), state.map_table_idx(src_idx.0)),
+
+        hl::Instr::RefNull(type_) => ll::Instr::RefNull(type_),
+        hl::Instr::RefIsNull => ll::Instr::RefIsNull,
+        hl::Instr::RefFunc(function_idx) => ll::Instr::RefFunc(state.map_function_idx(function_idx.0)),
+
         hl::Instr::Const(Val::I32(immediate)) => ll::Instr::I32Const(immediate),
         hl::Instr::Const(Val::I64(immediate)) => ll::Instr::I64Const(immediate),
         hl::Instr::Const(Val::F32(immediate)) => ll::Instr::F32Const(immediate),
@@ -804,5 +1421,325 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Numeric(hl::NumericOp::I64ReinterpretF64) => ll::Instr::I64ReinterpretF64,
         hl::Instr::Numeric(hl::NumericOp::F32ReinterpretI32) => ll::Instr::F32ReinterpretI32,
         hl::Instr::Numeric(hl::NumericOp::F64ReinterpretI64) => ll::Instr::F64ReinterpretI64,
+
+        hl::Instr::Numeric(hl::NumericOp::I32Extend8S) => ll::Instr::I32Extend8S,
+        hl::Instr::Numeric(hl::NumericOp::I32Extend16S) => ll::Instr::I32Extend16S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend8S) => ll::Instr::I64Extend8S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend16S) => ll::Instr::I64Extend16S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend32S) => ll::Instr::I64Extend32S,
+
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF32) => ll::Instr::I32TruncSatSF32,
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF32) => ll::Instr::I32TruncSatUF32,
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF64) => ll::Instr::I32TruncSatSF64,
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF64) => ll::Instr::I32TruncSatUF64,
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF32) => ll::Instr::I64TruncSatSF32,
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF32) => ll::Instr::I64TruncSatUF32,
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF64) => ll::Instr::I64TruncSatSF64,
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF64) => ll::Instr::I64TruncSatUF64,
+
+        hl::Instr::Load(hl::LoadOp::V128Load, memarg) => ll::Instr::V128Load(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load8x8S, memarg) => ll::Instr::V128Load8x8S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load8x8U, memarg) => ll::Instr::V128Load8x8U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load16x4S, memarg) => ll::Instr::V128Load16x4S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load16x4U, memarg) => ll::Instr::V128Load16x4U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load32x2S, memarg) => ll::Instr::V128Load32x2S(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load32x2U, memarg) => ll::Instr::V128Load32x2U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load8Splat, memarg) => ll::Instr::V128Load8Splat(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load16Splat, memarg) => ll::Instr::V128Load16Splat(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load32Splat, memarg) => ll::Instr::V128Load32Splat(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load64Splat, memarg) => ll::Instr::V128Load64Splat(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Store(hl::StoreOp::V128Store, memarg) => ll::Instr::V128Store(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Const(Val::V128(immediate)) => ll::Instr::V128Const(immediate),
+        hl::Instr::Shuffle(lanes) => ll::Instr::I8x16Shuffle(lanes),
+        hl::Instr::Simd(hl::SimdOp::I8x16Swizzle) => ll::Instr::I8x16Swizzle,
+        hl::Instr::Simd(hl::SimdOp::I8x16Splat) => ll::Instr::I8x16Splat,
+        hl::Instr::Simd(hl::SimdOp::I16x8Splat) => ll::Instr::I16x8Splat,
+        hl::Instr::Simd(hl::SimdOp::I32x4Splat) => ll::Instr::I32x4Splat,
+        hl::Instr::Simd(hl::SimdOp::I64x2Splat) => ll::Instr::I64x2Splat,
+        hl::Instr::Simd(hl::SimdOp::F32x4Splat) => ll::Instr::F32x4Splat,
+        hl::Instr::Simd(hl::SimdOp::F64x2Splat) => ll::Instr::F64x2Splat,
+        hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ExtractLaneS, lane) => ll::Instr::I8x16ExtractLaneS(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ExtractLaneU, lane) => ll::Instr::I8x16ExtractLaneU(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I8x16ReplaceLane, lane) => ll::Instr::I8x16ReplaceLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ExtractLaneS, lane) => ll::Instr::I16x8ExtractLaneS(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ExtractLaneU, lane) => ll::Instr::I16x8ExtractLaneU(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I16x8ReplaceLane, lane) => ll::Instr::I16x8ReplaceLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I32x4ExtractLane, lane) => ll::Instr::I32x4ExtractLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I32x4ReplaceLane, lane) => ll::Instr::I32x4ReplaceLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I64x2ExtractLane, lane) => ll::Instr::I64x2ExtractLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::I64x2ReplaceLane, lane) => ll::Instr::I64x2ReplaceLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::F32x4ExtractLane, lane) => ll::Instr::F32x4ExtractLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::F32x4ReplaceLane, lane) => ll::Instr::F32x4ReplaceLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::F64x2ExtractLane, lane) => ll::Instr::F64x2ExtractLane(lane),
+        hl::Instr::SimdLane(hl::SimdLaneOp::F64x2ReplaceLane, lane) => ll::Instr::F64x2ReplaceLane(lane),
+        hl::Instr::Simd(hl::SimdOp::I8x16Eq) => ll::Instr::I8x16Eq,
+        hl::Instr::Simd(hl::SimdOp::I8x16Ne) => ll::Instr::I8x16Ne,
+        hl::Instr::Simd(hl::SimdOp::I8x16LtS) => ll::Instr::I8x16LtS,
+        hl::Instr::Simd(hl::SimdOp::I8x16LtU) => ll::Instr::I8x16LtU,
+        hl::Instr::Simd(hl::SimdOp::I8x16GtS) => ll::Instr::I8x16GtS,
+        hl::Instr::Simd(hl::SimdOp::I8x16GtU) => ll::Instr::I8x16GtU,
+        hl::Instr::Simd(hl::SimdOp::I8x16LeS) => ll::Instr::I8x16LeS,
+        hl::Instr::Simd(hl::SimdOp::I8x16LeU) => ll::Instr::I8x16LeU,
+        hl::Instr::Simd(hl::SimdOp::I8x16GeS) => ll::Instr::I8x16GeS,
+        hl::Instr::Simd(hl::SimdOp::I8x16GeU) => ll::Instr::I8x16GeU,
+        hl::Instr::Simd(hl::SimdOp::I16x8Eq) => ll::Instr::I16x8Eq,
+        hl::Instr::Simd(hl::SimdOp::I16x8Ne) => ll::Instr::I16x8Ne,
+        hl::Instr::Simd(hl::SimdOp::I16x8LtS) => ll::Instr::I16x8LtS,
+        hl::Instr::Simd(hl::SimdOp::I16x8LtU) => ll::Instr::I16x8LtU,
+        hl::Instr::Simd(hl::SimdOp::I16x8GtS) => ll::Instr::I16x8GtS,
+        hl::Instr::Simd(hl::SimdOp::I16x8GtU) => ll::Instr::I16x8GtU,
+        hl::Instr::Simd(hl::SimdOp::I16x8LeS) => ll::Instr::I16x8LeS,
+        hl::Instr::Simd(hl::SimdOp::I16x8LeU) => ll::Instr::I16x8LeU,
+        hl::Instr::Simd(hl::SimdOp::I16x8GeS) => ll::Instr::I16x8GeS,
+        hl::Instr::Simd(hl::SimdOp::I16x8GeU) => ll::Instr::I16x8GeU,
+        hl::Instr::Simd(hl::SimdOp::I32x4Eq) => ll::Instr::I32x4Eq,
+        hl::Instr::Simd(hl::SimdOp::I32x4Ne) => ll::Instr::I32x4Ne,
+        hl::Instr::Simd(hl::SimdOp::I32x4LtS) => ll::Instr::I32x4LtS,
+        hl::Instr::Simd(hl::SimdOp::I32x4LtU) => ll::Instr::I32x4LtU,
+        hl::Instr::Simd(hl::SimdOp::I32x4GtS) => ll::Instr::I32x4GtS,
+        hl::Instr::Simd(hl::SimdOp::I32x4GtU) => ll::Instr::I32x4GtU,
+        hl::Instr::Simd(hl::SimdOp::I32x4LeS) => ll::Instr::I32x4LeS,
+        hl::Instr::Simd(hl::SimdOp::I32x4LeU) => ll::Instr::I32x4LeU,
+        hl::Instr::Simd(hl::SimdOp::I32x4GeS) => ll::Instr::I32x4GeS,
+        hl::Instr::Simd(hl::SimdOp::I32x4GeU) => ll::Instr::I32x4GeU,
+        hl::Instr::Simd(hl::SimdOp::F32x4Eq) => ll::Instr::F32x4Eq,
+        hl::Instr::Simd(hl::SimdOp::F32x4Ne) => ll::Instr::F32x4Ne,
+        hl::Instr::Simd(hl::SimdOp::F32x4Lt) => ll::Instr::F32x4Lt,
+        hl::Instr::Simd(hl::SimdOp::F32x4Gt) => ll::Instr::F32x4Gt,
+        hl::Instr::Simd(hl::SimdOp::F32x4Le) => ll::Instr::F32x4Le,
+        hl::Instr::Simd(hl::SimdOp::F32x4Ge) => ll::Instr::F32x4Ge,
+        hl::Instr::Simd(hl::SimdOp::F64x2Eq) => ll::Instr::F64x2Eq,
+        hl::Instr::Simd(hl::SimdOp::F64x2Ne) => ll::Instr::F64x2Ne,
+        hl::Instr::Simd(hl::SimdOp::F64x2Lt) => ll::Instr::F64x2Lt,
+        hl::Instr::Simd(hl::SimdOp::F64x2Gt) => ll::Instr::F64x2Gt,
+        hl::Instr::Simd(hl::SimdOp::F64x2Le) => ll::Instr::F64x2Le,
+        hl::Instr::Simd(hl::SimdOp::F64x2Ge) => ll::Instr::F64x2Ge,
+        hl::Instr::Simd(hl::SimdOp::V128Not) => ll::Instr::V128Not,
+        hl::Instr::Simd(hl::SimdOp::V128And) => ll::Instr::V128And,
+        hl::Instr::Simd(hl::SimdOp::V128Andnot) => ll::Instr::V128Andnot,
+        hl::Instr::Simd(hl::SimdOp::V128Or) => ll::Instr::V128Or,
+        hl::Instr::Simd(hl::SimdOp::V128Xor) => ll::Instr::V128Xor,
+        hl::Instr::Simd(hl::SimdOp::V128Bitselect) => ll::Instr::V128Bitselect,
+        hl::Instr::Simd(hl::SimdOp::V128AnyTrue) => ll::Instr::V128AnyTrue,
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load8Lane, memarg, lane) => ll::Instr::V128Load8Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load16Lane, memarg, lane) => ll::Instr::V128Load16Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load32Lane, memarg, lane) => ll::Instr::V128Load32Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load64Lane, memarg, lane) => ll::Instr::V128Load64Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store8Lane, memarg, lane) => ll::Instr::V128Store8Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store16Lane, memarg, lane) => ll::Instr::V128Store16Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store32Lane, memarg, lane) => ll::Instr::V128Store32Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store64Lane, memarg, lane) => ll::Instr::V128Store64Lane(to_lowlevel_memarg(memarg, state), lane),
+        hl::Instr::Load(hl::LoadOp::V128Load32Zero, memarg) => ll::Instr::V128Load32Zero(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Load(hl::LoadOp::V128Load64Zero, memarg) => ll::Instr::V128Load64Zero(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::Simd(hl::SimdOp::F32x4DemoteF64x2Zero) => ll::Instr::F32x4DemoteF64x2Zero,
+        hl::Instr::Simd(hl::SimdOp::F64x2PromoteLowF32x4) => ll::Instr::F64x2PromoteLowF32x4,
+        hl::Instr::Simd(hl::SimdOp::I8x16Abs) => ll::Instr::I8x16Abs,
+        hl::Instr::Simd(hl::SimdOp::I8x16Neg) => ll::Instr::I8x16Neg,
+        hl::Instr::Simd(hl::SimdOp::I8x16Popcnt) => ll::Instr::I8x16Popcnt,
+        hl::Instr::Simd(hl::SimdOp::I8x16AllTrue) => ll::Instr::I8x16AllTrue,
+        hl::Instr::Simd(hl::SimdOp::I8x16Bitmask) => ll::Instr::I8x16Bitmask,
+        hl::Instr::Simd(hl::SimdOp::I8x16NarrowI16x8S) => ll::Instr::I8x16NarrowI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I8x16NarrowI16x8U) => ll::Instr::I8x16NarrowI16x8U,
+        hl::Instr::Simd(hl::SimdOp::F32x4Ceil) => ll::Instr::F32x4Ceil,
+        hl::Instr::Simd(hl::SimdOp::F32x4Floor) => ll::Instr::F32x4Floor,
+        hl::Instr::Simd(hl::SimdOp::F32x4Trunc) => ll::Instr::F32x4Trunc,
+        hl::Instr::Simd(hl::SimdOp::F32x4Nearest) => ll::Instr::F32x4Nearest,
+        hl::Instr::Simd(hl::SimdOp::I8x16Shl) => ll::Instr::I8x16Shl,
+        hl::Instr::Simd(hl::SimdOp::I8x16ShrS) => ll::Instr::I8x16ShrS,
+        hl::Instr::Simd(hl::SimdOp::I8x16ShrU) => ll::Instr::I8x16ShrU,
+        hl::Instr::Simd(hl::SimdOp::I8x16Add) => ll::Instr::I8x16Add,
+        hl::Instr::Simd(hl::SimdOp::I8x16AddSatS) => ll::Instr::I8x16AddSatS,
+        hl::Instr::Simd(hl::SimdOp::I8x16AddSatU) => ll::Instr::I8x16AddSatU,
+        hl::Instr::Simd(hl::SimdOp::I8x16Sub) => ll::Instr::I8x16Sub,
+        hl::Instr::Simd(hl::SimdOp::I8x16SubSatS) => ll::Instr::I8x16SubSatS,
+        hl::Instr::Simd(hl::SimdOp::I8x16SubSatU) => ll::Instr::I8x16SubSatU,
+        hl::Instr::Simd(hl::SimdOp::F64x2Ceil) => ll::Instr::F64x2Ceil,
+        hl::Instr::Simd(hl::SimdOp::F64x2Floor) => ll::Instr::F64x2Floor,
+        hl::Instr::Simd(hl::SimdOp::I8x16MinS) => ll::Instr::I8x16MinS,
+        hl::Instr::Simd(hl::SimdOp::I8x16MinU) => ll::Instr::I8x16MinU,
+        hl::Instr::Simd(hl::SimdOp::I8x16MaxS) => ll::Instr::I8x16MaxS,
+        hl::Instr::Simd(hl::SimdOp::I8x16MaxU) => ll::Instr::I8x16MaxU,
+        hl::Instr::Simd(hl::SimdOp::F64x2Trunc) => ll::Instr::F64x2Trunc,
+        hl::Instr::Simd(hl::SimdOp::I8x16AvgrU) => ll::Instr::I8x16AvgrU,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtaddPairwiseI8x16S) => ll::Instr::I16x8ExtaddPairwiseI8x16S,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtaddPairwiseI8x16U) => ll::Instr::I16x8ExtaddPairwiseI8x16U,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtaddPairwiseI16x8S) => ll::Instr::I32x4ExtaddPairwiseI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtaddPairwiseI16x8U) => ll::Instr::I32x4ExtaddPairwiseI16x8U,
+        hl::Instr::Simd(hl::SimdOp::I16x8Abs) => ll::Instr::I16x8Abs,
+        hl::Instr::Simd(hl::SimdOp::I16x8Neg) => ll::Instr::I16x8Neg,
+        hl::Instr::Simd(hl::SimdOp::I16x8Q15mulrSatS) => ll::Instr::I16x8Q15mulrSatS,
+        hl::Instr::Simd(hl::SimdOp::I16x8AllTrue) => ll::Instr::I16x8AllTrue,
+        hl::Instr::Simd(hl::SimdOp::I16x8Bitmask) => ll::Instr::I16x8Bitmask,
+        hl::Instr::Simd(hl::SimdOp::I16x8NarrowI32x4S) => ll::Instr::I16x8NarrowI32x4S,
+        hl::Instr::Simd(hl::SimdOp::I16x8NarrowI32x4U) => ll::Instr::I16x8NarrowI32x4U,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtendLowI8x16S) => ll::Instr::I16x8ExtendLowI8x16S,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtendHighI8x16S) => ll::Instr::I16x8ExtendHighI8x16S,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtendLowI8x16U) => ll::Instr::I16x8ExtendLowI8x16U,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtendHighI8x16U) => ll::Instr::I16x8ExtendHighI8x16U,
+        hl::Instr::Simd(hl::SimdOp::I16x8Shl) => ll::Instr::I16x8Shl,
+        hl::Instr::Simd(hl::SimdOp::I16x8ShrS) => ll::Instr::I16x8ShrS,
+        hl::Instr::Simd(hl::SimdOp::I16x8ShrU) => ll::Instr::I16x8ShrU,
+        hl::Instr::Simd(hl::SimdOp::I16x8Add) => ll::Instr::I16x8Add,
+        hl::Instr::Simd(hl::SimdOp::I16x8AddSatS) => ll::Instr::I16x8AddSatS,
+        hl::Instr::Simd(hl::SimdOp::I16x8AddSatU) => ll::Instr::I16x8AddSatU,
+        hl::Instr::Simd(hl::SimdOp::I16x8Sub) => ll::Instr::I16x8Sub,
+        hl::Instr::Simd(hl::SimdOp::I16x8SubSatS) => ll::Instr::I16x8SubSatS,
+        hl::Instr::Simd(hl::SimdOp::I16x8SubSatU) => ll::Instr::I16x8SubSatU,
+        hl::Instr::Simd(hl::SimdOp::F64x2Nearest) => ll::Instr::F64x2Nearest,
+        hl::Instr::Simd(hl::SimdOp::I16x8Mul) => ll::Instr::I16x8Mul,
+        hl::Instr::Simd(hl::SimdOp::I16x8MinS) => ll::Instr::I16x8MinS,
+        hl::Instr::Simd(hl::SimdOp::I16x8MinU) => ll::Instr::I16x8MinU,
+        hl::Instr::Simd(hl::SimdOp::I16x8MaxS) => ll::Instr::I16x8MaxS,
+        hl::Instr::Simd(hl::SimdOp::I16x8MaxU) => ll::Instr::I16x8MaxU,
+        hl::Instr::Simd(hl::SimdOp::I16x8AvgrU) => ll::Instr::I16x8AvgrU,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtmulLowI8x16S) => ll::Instr::I16x8ExtmulLowI8x16S,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtmulHighI8x16S) => ll::Instr::I16x8ExtmulHighI8x16S,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtmulLowI8x16U) => ll::Instr::I16x8ExtmulLowI8x16U,
+        hl::Instr::Simd(hl::SimdOp::I16x8ExtmulHighI8x16U) => ll::Instr::I16x8ExtmulHighI8x16U,
+        hl::Instr::Simd(hl::SimdOp::I32x4Abs) => ll::Instr::I32x4Abs,
+        hl::Instr::Simd(hl::SimdOp::I32x4Neg) => ll::Instr::I32x4Neg,
+        hl::Instr::Simd(hl::SimdOp::I32x4AllTrue) => ll::Instr::I32x4AllTrue,
+        hl::Instr::Simd(hl::SimdOp::I32x4Bitmask) => ll::Instr::I32x4Bitmask,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtendLowI16x8S) => ll::Instr::I32x4ExtendLowI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtendHighI16x8S) => ll::Instr::I32x4ExtendHighI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtendLowI16x8U) => ll::Instr::I32x4ExtendLowI16x8U,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtendHighI16x8U) => ll::Instr::I32x4ExtendHighI16x8U,
+        hl::Instr::Simd(hl::SimdOp::I32x4Shl) => ll::Instr::I32x4Shl,
+        hl::Instr::Simd(hl::SimdOp::I32x4ShrS) => ll::Instr::I32x4ShrS,
+        hl::Instr::Simd(hl::SimdOp::I32x4ShrU) => ll::Instr::I32x4ShrU,
+        hl::Instr::Simd(hl::SimdOp::I32x4Add) => ll::Instr::I32x4Add,
+        hl::Instr::Simd(hl::SimdOp::I32x4Sub) => ll::Instr::I32x4Sub,
+        hl::Instr::Simd(hl::SimdOp::I32x4Mul) => ll::Instr::I32x4Mul,
+        hl::Instr::Simd(hl::SimdOp::I32x4MinS) => ll::Instr::I32x4MinS,
+        hl::Instr::Simd(hl::SimdOp::I32x4MinU) => ll::Instr::I32x4MinU,
+        hl::Instr::Simd(hl::SimdOp::I32x4MaxS) => ll::Instr::I32x4MaxS,
+        hl::Instr::Simd(hl::SimdOp::I32x4MaxU) => ll::Instr::I32x4MaxU,
+        hl::Instr::Simd(hl::SimdOp::I32x4DotI16x8S) => ll::Instr::I32x4DotI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtmulLowI16x8S) => ll::Instr::I32x4ExtmulLowI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtmulHighI16x8S) => ll::Instr::I32x4ExtmulHighI16x8S,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtmulLowI16x8U) => ll::Instr::I32x4ExtmulLowI16x8U,
+        hl::Instr::Simd(hl::SimdOp::I32x4ExtmulHighI16x8U) => ll::Instr::I32x4ExtmulHighI16x8U,
+        hl::Instr::Simd(hl::SimdOp::I64x2Abs) => ll::Instr::I64x2Abs,
+        hl::Instr::Simd(hl::SimdOp::I64x2Neg) => ll::Instr::I64x2Neg,
+        hl::Instr::Simd(hl::SimdOp::I64x2AllTrue) => ll::Instr::I64x2AllTrue,
+        hl::Instr::Simd(hl::SimdOp::I64x2Bitmask) => ll::Instr::I64x2Bitmask,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtendLowI32x4S) => ll::Instr::I64x2ExtendLowI32x4S,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtendHighI32x4S) => ll::Instr::I64x2ExtendHighI32x4S,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtendLowI32x4U) => ll::Instr::I64x2ExtendLowI32x4U,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtendHighI32x4U) => ll::Instr::I64x2ExtendHighI32x4U,
+        hl::Instr::Simd(hl::SimdOp::I64x2Shl) => ll::Instr::I64x2Shl,
+        hl::Instr::Simd(hl::SimdOp::I64x2ShrS) => ll::Instr::I64x2ShrS,
+        hl::Instr::Simd(hl::SimdOp::I64x2ShrU) => ll::Instr::I64x2ShrU,
+        hl::Instr::Simd(hl::SimdOp::I64x2Add) => ll::Instr::I64x2Add,
+        hl::Instr::Simd(hl::SimdOp::I64x2Sub) => ll::Instr::I64x2Sub,
+        hl::Instr::Simd(hl::SimdOp::I64x2Mul) => ll::Instr::I64x2Mul,
+        hl::Instr::Simd(hl::SimdOp::I64x2Eq) => ll::Instr::I64x2Eq,
+        hl::Instr::Simd(hl::SimdOp::I64x2Ne) => ll::Instr::I64x2Ne,
+        hl::Instr::Simd(hl::SimdOp::I64x2LtS) => ll::Instr::I64x2LtS,
+        hl::Instr::Simd(hl::SimdOp::I64x2GtS) => ll::Instr::I64x2GtS,
+        hl::Instr::Simd(hl::SimdOp::I64x2LeS) => ll::Instr::I64x2LeS,
+        hl::Instr::Simd(hl::SimdOp::I64x2GeS) => ll::Instr::I64x2GeS,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtmulLowI32x4S) => ll::Instr::I64x2ExtmulLowI32x4S,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtmulHighI32x4S) => ll::Instr::I64x2ExtmulHighI32x4S,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtmulLowI32x4U) => ll::Instr::I64x2ExtmulLowI32x4U,
+        hl::Instr::Simd(hl::SimdOp::I64x2ExtmulHighI32x4U) => ll::Instr::I64x2ExtmulHighI32x4U,
+        hl::Instr::Simd(hl::SimdOp::F32x4Abs) => ll::Instr::F32x4Abs,
+        hl::Instr::Simd(hl::SimdOp::F32x4Neg) => ll::Instr::F32x4Neg,
+        hl::Instr::Simd(hl::SimdOp::F32x4Sqrt) => ll::Instr::F32x4Sqrt,
+        hl::Instr::Simd(hl::SimdOp::F32x4Add) => ll::Instr::F32x4Add,
+        hl::Instr::Simd(hl::SimdOp::F32x4Sub) => ll::Instr::F32x4Sub,
+        hl::Instr::Simd(hl::SimdOp::F32x4Mul) => ll::Instr::F32x4Mul,
+        hl::Instr::Simd(hl::SimdOp::F32x4Div) => ll::Instr::F32x4Div,
+        hl::Instr::Simd(hl::SimdOp::F32x4Min) => ll::Instr::F32x4Min,
+        hl::Instr::Simd(hl::SimdOp::F32x4Max) => ll::Instr::F32x4Max,
+        hl::Instr::Simd(hl::SimdOp::F32x4Pmin) => ll::Instr::F32x4Pmin,
+        hl::Instr::Simd(hl::SimdOp::F32x4Pmax) => ll::Instr::F32x4Pmax,
+        hl::Instr::Simd(hl::SimdOp::F64x2Abs) => ll::Instr::F64x2Abs,
+        hl::Instr::Simd(hl::SimdOp::F64x2Neg) => ll::Instr::F64x2Neg,
+        hl::Instr::Simd(hl::SimdOp::F64x2Sqrt) => ll::Instr::F64x2Sqrt,
+        hl::Instr::Simd(hl::SimdOp::F64x2Add) => ll::Instr::F64x2Add,
+        hl::Instr::Simd(hl::SimdOp::F64x2Sub) => ll::Instr::F64x2Sub,
+        hl::Instr::Simd(hl::SimdOp::F64x2Mul) => ll::Instr::F64x2Mul,
+        hl::Instr::Simd(hl::SimdOp::F64x2Div) => ll::Instr::F64x2Div,
+        hl::Instr::Simd(hl::SimdOp::F64x2Min) => ll::Instr::F64x2Min,
+        hl::Instr::Simd(hl::SimdOp::F64x2Max) => ll::Instr::F64x2Max,
+        hl::Instr::Simd(hl::SimdOp::F64x2Pmin) => ll::Instr::F64x2Pmin,
+        hl::Instr::Simd(hl::SimdOp::F64x2Pmax) => ll::Instr::F64x2Pmax,
+        hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF32x4S) => ll::Instr::I32x4TruncSatF32x4S,
+        hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF32x4U) => ll::Instr::I32x4TruncSatF32x4U,
+        hl::Instr::Simd(hl::SimdOp::F32x4ConvertI32x4S) => ll::Instr::F32x4ConvertI32x4S,
+        hl::Instr::Simd(hl::SimdOp::F32x4ConvertI32x4U) => ll::Instr::F32x4ConvertI32x4U,
+        hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF64x2SZero) => ll::Instr::I32x4TruncSatF64x2SZero,
+        hl::Instr::Simd(hl::SimdOp::I32x4TruncSatF64x2UZero) => ll::Instr::I32x4TruncSatF64x2UZero,
+        hl::Instr::Simd(hl::SimdOp::F64x2ConvertLowI32x4S) => ll::Instr::F64x2ConvertLowI32x4S,
+        hl::Instr::Simd(hl::SimdOp::F64x2ConvertLowI32x4U) => ll::Instr::F64x2ConvertLowI32x4U,
+
+        hl::Instr::MemoryAtomicNotify(memarg) => ll::Instr::MemoryAtomicNotify(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::MemoryAtomicWait32(memarg) => ll::Instr::MemoryAtomicWait32(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::MemoryAtomicWait64(memarg) => ll::Instr::MemoryAtomicWait64(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicFence => ll::Instr::AtomicFence(0),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad, memarg) => ll::Instr::I32AtomicLoad(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad, memarg) => ll::Instr::I64AtomicLoad(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad8U, memarg) => ll::Instr::I32AtomicLoad8U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad16U, memarg) => ll::Instr::I32AtomicLoad16U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad8U, memarg) => ll::Instr::I64AtomicLoad8U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad16U, memarg) => ll::Instr::I64AtomicLoad16U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad32U, memarg) => ll::Instr::I64AtomicLoad32U(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore, memarg) => ll::Instr::I32AtomicStore(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore, memarg) => ll::Instr::I64AtomicStore(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore8, memarg) => ll::Instr::I32AtomicStore8(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore16, memarg) => ll::Instr::I32AtomicStore16(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore8, memarg) => ll::Instr::I64AtomicStore8(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore16, memarg) => ll::Instr::I64AtomicStore16(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore32, memarg) => ll::Instr::I64AtomicStore32(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAdd, memarg) => ll::Instr::I32AtomicRmwAdd(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAdd, memarg) => ll::Instr::I64AtomicRmwAdd(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AddU, memarg) => ll::Instr::I32AtomicRmw8AddU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AddU, memarg) => ll::Instr::I32AtomicRmw16AddU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AddU, memarg) => ll::Instr::I64AtomicRmw8AddU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AddU, memarg) => ll::Instr::I64AtomicRmw16AddU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AddU, memarg) => ll::Instr::I64AtomicRmw32AddU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwSub, memarg) => ll::Instr::I32AtomicRmwSub(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwSub, memarg) => ll::Instr::I64AtomicRmwSub(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8SubU, memarg) => ll::Instr::I32AtomicRmw8SubU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16SubU, memarg) => ll::Instr::I32AtomicRmw16SubU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8SubU, memarg) => ll::Instr::I64AtomicRmw8SubU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16SubU, memarg) => ll::Instr::I64AtomicRmw16SubU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32SubU, memarg) => ll::Instr::I64AtomicRmw32SubU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAnd, memarg) => ll::Instr::I32AtomicRmwAnd(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAnd, memarg) => ll::Instr::I64AtomicRmwAnd(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AndU, memarg) => ll::Instr::I32AtomicRmw8AndU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AndU, memarg) => ll::Instr::I32AtomicRmw16AndU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AndU, memarg) => ll::Instr::I64AtomicRmw8AndU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AndU, memarg) => ll::Instr::I64AtomicRmw16AndU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AndU, memarg) => ll::Instr::I64AtomicRmw32AndU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwOr, memarg) => ll::Instr::I32AtomicRmwOr(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwOr, memarg) => ll::Instr::I64AtomicRmwOr(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8OrU, memarg) => ll::Instr::I32AtomicRmw8OrU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16OrU, memarg) => ll::Instr::I32AtomicRmw16OrU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8OrU, memarg) => ll::Instr::I64AtomicRmw8OrU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16OrU, memarg) => ll::Instr::I64AtomicRmw16OrU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32OrU, memarg) => ll::Instr::I64AtomicRmw32OrU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXor, memarg) => ll::Instr::I32AtomicRmwXor(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXor, memarg) => ll::Instr::I64AtomicRmwXor(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XorU, memarg) => ll::Instr::I32AtomicRmw8XorU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XorU, memarg) => ll::Instr::I32AtomicRmw16XorU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XorU, memarg) => ll::Instr::I64AtomicRmw8XorU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XorU, memarg) => ll::Instr::I64AtomicRmw16XorU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XorU, memarg) => ll::Instr::I64AtomicRmw32XorU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXchg, memarg) => ll::Instr::I32AtomicRmwXchg(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXchg, memarg) => ll::Instr::I64AtomicRmwXchg(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XchgU, memarg) => ll::Instr::I32AtomicRmw8XchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XchgU, memarg) => ll::Instr::I32AtomicRmw16XchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XchgU, memarg) => ll::Instr::I64AtomicRmw8XchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XchgU, memarg) => ll::Instr::I64AtomicRmw16XchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XchgU, memarg) => ll::Instr::I64AtomicRmw32XchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmwCmpxchg, memarg) => ll::Instr::I32AtomicRmwCmpxchg(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmwCmpxchg, memarg) => ll::Instr::I64AtomicRmwCmpxchg(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU, memarg) => ll::Instr::I32AtomicRmw8CmpxchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU, memarg) => ll::Instr::I32AtomicRmw16CmpxchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU, memarg) => ll::Instr::I64AtomicRmw8CmpxchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU, memarg) => ll::Instr::I64AtomicRmw16CmpxchgU(to_lowlevel_memarg(memarg, state)),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU, memarg) => ll::Instr::I64AtomicRmw32CmpxchgU(to_lowlevel_memarg(memarg, state)),
     }
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
-use self::{GlobalOp::*, LoadOp::*, LocalOp::*, StoreOp::*};
-use std::collections::HashSet;
+use self::{GlobalOp::*, LoadLaneOp::*, LoadOp::*, LocalOp::*, StoreLaneOp::*, StoreOp::*, TableOp::*};
+use custom_section::{CustomSectionType, ParsedCustomSection};
+use std::collections::{BTreeMap, HashSet};
 use super::{*, ValType::*};
 
+pub use super::visit::{VisitContext, Visitor, VisitorMut};
+
 /* High-level AST:
     - types are inlined instead of referenced by type idx (i.e., no manual handling of Type "pool")
     - Function + Code sections are merged into one list of functions,
@@ -10,18 +13,45 @@ use super::{*, ValType::*};
       their item by index.
     - similar instructions are grouped together, for easier uniform handling, e.g., T.const
       instructions, loads, stores, and numeric instructions.
+    - debug names from the "name" custom section are stored directly on the module, functions, and
+      locals, so that they stay correct when indices change.
+    - other well-known custom sections are decoded into a typed representation, unknown ones are
+      kept as raw bytes (but can be parsed with a custom_section::CustomSectionRegistry).
 */
 
 #[derive(Debug, Clone, Default)]
 pub struct Module {
+    pub name: Option<String>,
+
     pub functions: Vec<Function>,
     pub globals: Vec<Global>,
     pub tables: Vec<Table>,
     pub memories: Vec<Memory>,
+    // exception handling proposal
+    pub tags: Vec<Tag>,
+
+    // not nested inside their table/memory (unlike in the MVP), since passive and declarative
+    // segments have none and segment indices (e.g., of memory.init) are module-wide
+    pub elements: Vec<Element>,
+    pub data: Vec<Data>,
 
     pub start: Option<Idx<Function>>,
 
-    pub custom_sections: Vec<Vec<u8>>,
+    // in the order they appeared in the binary, see SectionPosition
+    pub custom_sections: Vec<(SectionPosition, CustomSection)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CustomSection {
+    /// only marks the position of the name section, its contents are merged into the rest of the
+    /// module (see above). If there is no marker, the name section is placed last.
+    Name,
+    Producers(ProducersSection),
+    TargetFeatures(TargetFeaturesSection),
+    SourceMappingUrl(String),
+    /// parsed by a user-provided parser, see custom_section::CustomSectionRegistry
+    Parsed(Box<dyn ParsedCustomSection>),
+    Raw(RawCustomSection),
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +63,9 @@ pub struct Function {
     pub code: Option<Code>,
     // functions (and other elements) can be exported multiple times under different names
     pub export: Vec<String>,
+    // debug names, i.e., not necessarily unique and not available for every function or local
+    pub name: Option<String>,
+    pub local_names: BTreeMap<Idx<Local>, String>,
 }
 
 #[derive(Debug, Clone)]
@@ -48,7 +81,6 @@ pub struct Global {
 pub struct Table {
     pub type_: TableType,
     pub import: Option<(String, String)>,
-    pub elements: Vec<Element>,
     pub export: Vec<String>,
 }
 
@@ -56,7 +88,15 @@ pub struct Table {
 pub struct Memory {
     pub type_: MemoryType,
     pub import: Option<(String, String)>,
-    pub data: Vec<Data>,
+    pub export: Vec<String>,
+}
+
+/// exception handling proposal, the params of the type are the values thrown with the tag, the
+/// results are always empty
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub type_: FunctionType,
+    pub import: Option<(String, String)>,
     pub export: Vec<String>,
 }
 
@@ -64,22 +104,64 @@ pub struct Memory {
 pub struct Code {
     pub locals: Vec<ValType>,
     pub body: Expr,
+    /// byte offset of each instruction of body in the original binary (e.g., for mapping to stack
+    /// traces or source maps), None if not decoded from a binary or if the body was modified
+    pub original_offsets: Option<Vec<usize>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Element {
-    pub offset: Expr,
-    pub functions: Vec<Idx<Function>>,
+    pub mode: ElementMode,
+    pub items: ElementItems,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementItems {
+    /// funcref items given directly as function indices (the only form in the MVP)
+    Functions(Vec<Idx<Function>>),
+    /// with reference types, every item is a constant expression, i.e., ref.func or ref.null
+    Expressions(ElemType, Vec<Expr>),
+}
+
+impl Element {
+    pub fn type_(&self) -> ElemType {
+        match self.items {
+            ElementItems::Functions(_) => ElemType::FuncRef,
+            ElementItems::Expressions(type_, _) => type_,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementMode {
+    /// copied into the table at instantiation (the only mode in the MVP)
+    Active { table: Idx<Table>, offset: Expr },
+    /// only copied by table.init
+    Passive,
+    /// just forward-declares the functions for ref.func, never copied
+    Declarative,
 }
 
 #[derive(Debug, Clone)]
 pub struct Data {
-    pub offset: Expr,
+    pub mode: DataMode,
     pub bytes: Vec<u8>,
 }
 
+#[derive(Debug, Clone)]
+pub enum DataMode {
+    /// copied into the memory at instantiation (the only mode in the MVP)
+    Active { memory: Idx<Memory>, offset: Expr },
+    /// only copied by memory.init
+    Passive,
+}
+
 pub type Expr = Vec<Instr>;
 
+/// with multi-value, blocks can have parameters and multiple results, i.e., their type is a
+/// function type (inlined, like for call_indirect). The empty block type is FunctionType::default().
+pub type BlockType = FunctionType;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instr {
     Unreachable,
@@ -91,6 +173,16 @@ pub enum Instr {
     Else,
     End,
 
+    // exception handling proposal, a try block is either ended by a sequence of catch (or a final
+    // catch_all) followed by end, or by delegate, which forwards exceptions to an outer try block
+    Try(BlockType),
+    Catch(Idx<Tag>),
+    CatchAll,
+    Delegate(Idx<Label>),
+    Throw(Idx<Tag>),
+    /// re-throws the exception caught by the catch block with the given label
+    Rethrow(Idx<Label>),
+
     Br(Idx<Label>),
     BrIf(Idx<Label>),
     BrTable(Vec<Idx<Label>>, Idx<Label>),
@@ -98,21 +190,78 @@ pub enum Instr {
     Return,
     Call(Idx<Function>),
     CallIndirect(FunctionType, Idx<Table>),
+    // tail call proposal, the callee replaces the current function, i.e., returns to its caller
+    ReturnCall(Idx<Function>),
+    ReturnCallIndirect(FunctionType, Idx<Table>),
 
     Drop,
     Select,
+    /// with reference types, select can have an explicit operand type (required for references)
+    TypedSelect(ValType),
 
     Local(LocalOp, Idx<Local>),
     Global(GlobalOp, Idx<Global>),
+    Table(TableOp, Idx<Table>),
 
     Load(LoadOp, Memarg),
     Store(StoreOp, Memarg),
+    // SIMD proposal, the u8 is the lane index
+    LoadLane(LoadLaneOp, Memarg, u8),
+    StoreLane(StoreLaneOp, Memarg, u8),
 
     MemorySize(Idx<Memory>),
     MemoryGrow(Idx<Memory>),
 
+    // bulk memory operations, copies are (destination, source)
+    MemoryInit(Idx<Data>, Idx<Memory>),
+    DataDrop(Idx<Data>),
+    MemoryCopy(Idx<Memory>, Idx<Memory>),
+    MemoryFill(Idx<Memory>),
+    TableInit(Idx<Element>, Idx<Table>),
+    ElemDrop(Idx<Element>),
+    TableCopy(Idx<Table>, Idx<Table>),
+
+    RefNull(ElemType),
+    RefIsNull,
+    RefFunc(Idx<Function>),
+
     Const(Val),
     Numeric(NumericOp),
+
+    // SIMD proposal (v128.const is Const, loads and stores are above)
+    Simd(SimdOp),
+    SimdLane(SimdLaneOp, u8),
+    /// i8x16.shuffle, each byte of the result is selected by a lane index from the 32 input bytes
+    Shuffle([u8; 16]),
+
+    // threads proposal, atomic accesses must be naturally aligned
+    AtomicLoad(AtomicLoadOp, Memarg),
+    AtomicStore(AtomicStoreOp, Memarg),
+    AtomicRmw(AtomicRmwOp, Memarg),
+    AtomicCmpxchg(AtomicCmpxchgOp, Memarg),
+    /// (address, count of waiters to wake) -> number of woken waiters
+    MemoryAtomicNotify(Memarg),
+    /// (address, expected value, timeout in ns) -> 0 = woken, 1 = value not equal, 2 = timed out
+    MemoryAtomicWait32(Memarg),
+    MemoryAtomicWait64(Memarg),
+    AtomicFence,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Memarg {
+    /// log2 of the alignment in bytes
+    pub alignment: u32,
+    /// memory64 proposal: 64-bit offsets
+    pub offset: u64,
+    /// multi-memory proposal
+    pub memory: Idx<Memory>,
+}
+
+/// default alignment of 1 byte, offset 0, first memory
+impl Default for Memarg {
+    fn default() -> Self {
+        Memarg { alignment: 0, offset: 0, memory: 0.into() }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -121,6 +270,9 @@ pub enum LocalOp { GetLocal, SetLocal, TeeLocal }
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum GlobalOp { GetGlobal, SetGlobal }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TableOp { TableGet, TableSet, TableSize, TableGrow, TableFill }
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum LoadOp {
     I32Load,
@@ -139,6 +291,21 @@ pub enum LoadOp {
     I64Load16U,
     I64Load32S,
     I64Load32U,
+
+    // SIMD proposal
+    V128Load,
+    V128Load8x8S,
+    V128Load8x8U,
+    V128Load16x4S,
+    V128Load16x4U,
+    V128Load32x2S,
+    V128Load32x2U,
+    V128Load8Splat,
+    V128Load16Splat,
+    V128Load32Splat,
+    V128Load64Splat,
+    V128Load32Zero,
+    V128Load64Zero,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -154,6 +321,25 @@ pub enum StoreOp {
     I64Store8,
     I64Store16,
     I64Store32,
+
+    // SIMD proposal
+    V128Store,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LoadLaneOp {
+    V128Load8Lane,
+    V128Load16Lane,
+    V128Load32Lane,
+    V128Load64Lane,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StoreLaneOp {
+    V128Store8Lane,
+    V128Store16Lane,
+    V128Store32Lane,
+    V128Store64Lane,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -216,6 +402,21 @@ pub enum NumericOp {
     F32ReinterpretI32,
     F64ReinterpretI64,
 
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+
+    I32TruncSatSF32,
+    I32TruncSatUF32,
+    I32TruncSatSF64,
+    I32TruncSatUF64,
+    I64TruncSatSF32,
+    I64TruncSatUF32,
+    I64TruncSatSF64,
+    I64TruncSatUF64,
+
     /* Binary */
     I32Eq,
     I32Ne,
@@ -302,6 +503,557 @@ pub enum NumericOp {
     F64Copysign,
 }
 
+/// SIMD instructions without immediates
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SimdOp {
+    /* Splat (scalar -> v128) */
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+
+    /* Tests (v128 -> i32) */
+    V128AnyTrue,
+
+    I8x16AllTrue,
+    I8x16Bitmask,
+
+    I16x8AllTrue,
+    I16x8Bitmask,
+
+    I32x4AllTrue,
+    I32x4Bitmask,
+
+    I64x2AllTrue,
+    I64x2Bitmask,
+
+    /* Unary */
+    V128Not,
+
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+
+    I64x2Abs,
+    I64x2Neg,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+
+    F32x4DemoteF64x2Zero,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+
+    F64x2PromoteLowF32x4,
+    F64x2Ceil,
+    F64x2Floor,
+    F64x2Trunc,
+    F64x2Nearest,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+
+    /* Binary */
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+
+    I8x16Swizzle,
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    I8x16AvgrU,
+
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I16x8Q15mulrSatS,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+
+    /* Shifts (by i32) */
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+
+    /* Ternary */
+    V128Bitselect,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SimdLaneOp {
+    I8x16ExtractLaneS,
+    I8x16ExtractLaneU,
+    I8x16ReplaceLane,
+    I16x8ExtractLaneS,
+    I16x8ExtractLaneU,
+    I16x8ReplaceLane,
+    I32x4ExtractLane,
+    I32x4ReplaceLane,
+    I64x2ExtractLane,
+    I64x2ReplaceLane,
+    F32x4ExtractLane,
+    F32x4ReplaceLane,
+    F64x2ExtractLane,
+    F64x2ReplaceLane,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicLoadOp {
+    I32AtomicLoad, I64AtomicLoad, I32AtomicLoad8U, I32AtomicLoad16U, I64AtomicLoad8U, I64AtomicLoad16U, I64AtomicLoad32U,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicStoreOp {
+    I32AtomicStore, I64AtomicStore, I32AtomicStore8, I32AtomicStore16, I64AtomicStore8, I64AtomicStore16, I64AtomicStore32,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicRmwOp {
+    I32AtomicRmwAdd, I64AtomicRmwAdd, I32AtomicRmw8AddU, I32AtomicRmw16AddU, I64AtomicRmw8AddU, I64AtomicRmw16AddU, I64AtomicRmw32AddU,
+    I32AtomicRmwSub, I64AtomicRmwSub, I32AtomicRmw8SubU, I32AtomicRmw16SubU, I64AtomicRmw8SubU, I64AtomicRmw16SubU, I64AtomicRmw32SubU,
+    I32AtomicRmwAnd, I64AtomicRmwAnd, I32AtomicRmw8AndU, I32AtomicRmw16AndU, I64AtomicRmw8AndU, I64AtomicRmw16AndU, I64AtomicRmw32AndU,
+    I32AtomicRmwOr, I64AtomicRmwOr, I32AtomicRmw8OrU, I32AtomicRmw16OrU, I64AtomicRmw8OrU, I64AtomicRmw16OrU, I64AtomicRmw32OrU,
+    I32AtomicRmwXor, I64AtomicRmwXor, I32AtomicRmw8XorU, I32AtomicRmw16XorU, I64AtomicRmw8XorU, I64AtomicRmw16XorU, I64AtomicRmw32XorU,
+    I32AtomicRmwXchg, I64AtomicRmwXchg, I32AtomicRmw8XchgU, I32AtomicRmw16XchgU, I64AtomicRmw8XchgU, I64AtomicRmw16XchgU, I64AtomicRmw32XchgU,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicCmpxchgOp {
+    I32AtomicRmwCmpxchg, I64AtomicRmwCmpxchg, I32AtomicRmw8CmpxchgU, I32AtomicRmw16CmpxchgU, I64AtomicRmw8CmpxchgU, I64AtomicRmw16CmpxchgU, I64AtomicRmw32CmpxchgU,
+}
+
+
+/* All variants of the instruction groups, e.g., for looking up instructions by name */
+
+impl NumericOp {
+    pub fn all() -> &'static [NumericOp] {
+        use self::NumericOp::*;
+        static ALL: &[NumericOp] = &[
+            /* Unary */
+            I32Eqz, I64Eqz,
+            I32Clz, I32Ctz, I32Popcnt,
+            I64Clz, I64Ctz, I64Popcnt,
+            F32Abs, F32Neg, F32Ceil, F32Floor, F32Trunc, F32Nearest, F32Sqrt,
+            F64Abs, F64Neg, F64Ceil, F64Floor, F64Trunc, F64Nearest, F64Sqrt,
+            I32WrapI64, I32TruncSF32, I32TruncUF32, I32TruncSF64, I32TruncUF64,
+            I64ExtendSI32, I64ExtendUI32, I64TruncSF32, I64TruncUF32, I64TruncSF64, I64TruncUF64,
+            F32ConvertSI32, F32ConvertUI32, F32ConvertSI64, F32ConvertUI64, F32DemoteF64,
+            F64ConvertSI32, F64ConvertUI32, F64ConvertSI64, F64ConvertUI64, F64PromoteF32,
+            I32ReinterpretF32, I64ReinterpretF64, F32ReinterpretI32, F64ReinterpretI64,
+            I32Extend8S, I32Extend16S, I64Extend8S, I64Extend16S, I64Extend32S,
+            I32TruncSatSF32, I32TruncSatUF32, I32TruncSatSF64, I32TruncSatUF64, I64TruncSatSF32, I64TruncSatUF32, I64TruncSatSF64, I64TruncSatUF64,
+            /* Binary */
+            I32Eq, I32Ne, I32LtS, I32LtU, I32GtS, I32GtU, I32LeS, I32LeU, I32GeS, I32GeU,
+            I64Eq, I64Ne, I64LtS, I64LtU, I64GtS, I64GtU, I64LeS, I64LeU, I64GeS, I64GeU,
+            F32Eq, F32Ne, F32Lt, F32Gt, F32Le, F32Ge,
+            F64Eq, F64Ne, F64Lt, F64Gt, F64Le, F64Ge,
+            I32Add, I32Sub, I32Mul, I32DivS, I32DivU, I32RemS, I32RemU, I32And, I32Or, I32Xor, I32Shl, I32ShrS, I32ShrU, I32Rotl, I32Rotr,
+            I64Add, I64Sub, I64Mul, I64DivS, I64DivU, I64RemS, I64RemU, I64And, I64Or, I64Xor, I64Shl, I64ShrS, I64ShrU, I64Rotl, I64Rotr,
+            F32Add, F32Sub, F32Mul, F32Div, F32Min, F32Max, F32Copysign,
+            F64Add, F64Sub, F64Mul, F64Div, F64Min, F64Max, F64Copysign,
+        ];
+        ALL
+    }
+}
+
+impl LoadOp {
+    pub fn all() -> &'static [LoadOp] {
+        static ALL: &[LoadOp] = &[
+            I32Load, I64Load, F32Load, F64Load,
+            I32Load8S, I32Load8U, I32Load16S, I32Load16U,
+            I64Load8S, I64Load8U, I64Load16S, I64Load16U, I64Load32S, I64Load32U,
+            V128Load, V128Load8x8S, V128Load8x8U, V128Load16x4S, V128Load16x4U, V128Load32x2S, V128Load32x2U, V128Load8Splat, V128Load16Splat, V128Load32Splat, V128Load64Splat, V128Load32Zero, V128Load64Zero,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, i.e., the default alignment in Memarg
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32Load8S | I32Load8U | I64Load8S | I64Load8U => 0,
+            I32Load16S | I32Load16U | I64Load16S | I64Load16U => 1,
+            I32Load | F32Load | I64Load32S | I64Load32U => 2,
+            I64Load | F64Load => 3,
+            V128Load8Splat => 0,
+            V128Load16Splat => 1,
+            V128Load32Splat | V128Load32Zero => 2,
+            V128Load8x8S | V128Load8x8U | V128Load16x4S | V128Load16x4U | V128Load32x2S | V128Load32x2U | V128Load64Splat | V128Load64Zero => 3,
+            V128Load => 4,
+        }
+    }
+}
+
+impl StoreOp {
+    pub fn all() -> &'static [StoreOp] {
+        static ALL: &[StoreOp] = &[
+            I32Store, I64Store, F32Store, F64Store,
+            I32Store8, I32Store16,
+            I64Store8, I64Store16, I64Store32,
+            V128Store,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, i.e., the default alignment in Memarg
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32Store8 | I64Store8 => 0,
+            I32Store16 | I64Store16 => 1,
+            I32Store | F32Store | I64Store32 => 2,
+            I64Store | F64Store => 3,
+            V128Store => 4,
+        }
+    }
+}
+
+impl LoadLaneOp {
+    pub fn all() -> &'static [LoadLaneOp] {
+        static ALL: &[LoadLaneOp] = &[V128Load8Lane, V128Load16Lane, V128Load32Lane, V128Load64Lane];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, i.e., the default alignment in Memarg
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            V128Load8Lane => 0,
+            V128Load16Lane => 1,
+            V128Load32Lane => 2,
+            V128Load64Lane => 3,
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        16 >> self.natural_alignment()
+    }
+}
+
+impl StoreLaneOp {
+    pub fn all() -> &'static [StoreLaneOp] {
+        static ALL: &[StoreLaneOp] = &[V128Store8Lane, V128Store16Lane, V128Store32Lane, V128Store64Lane];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, i.e., the default alignment in Memarg
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            V128Store8Lane => 0,
+            V128Store16Lane => 1,
+            V128Store32Lane => 2,
+            V128Store64Lane => 3,
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        16 >> self.natural_alignment()
+    }
+}
+
+impl SimdOp {
+    pub fn all() -> &'static [SimdOp] {
+        use self::SimdOp::*;
+        static ALL: &[SimdOp] = &[
+            /* Splat (scalar -> v128) */
+            I8x16Splat, I16x8Splat, I32x4Splat, I64x2Splat, F32x4Splat, F64x2Splat,
+            /* Tests (v128 -> i32) */
+            V128AnyTrue,
+            I8x16AllTrue, I8x16Bitmask,
+            I16x8AllTrue, I16x8Bitmask,
+            I32x4AllTrue, I32x4Bitmask,
+            I64x2AllTrue, I64x2Bitmask,
+            /* Unary */
+            V128Not,
+            I8x16Abs, I8x16Neg, I8x16Popcnt,
+            I16x8ExtaddPairwiseI8x16S, I16x8ExtaddPairwiseI8x16U, I16x8Abs, I16x8Neg, I16x8ExtendLowI8x16S, I16x8ExtendHighI8x16S, I16x8ExtendLowI8x16U, I16x8ExtendHighI8x16U,
+            I32x4ExtaddPairwiseI16x8S, I32x4ExtaddPairwiseI16x8U, I32x4Abs, I32x4Neg, I32x4ExtendLowI16x8S, I32x4ExtendHighI16x8S, I32x4ExtendLowI16x8U, I32x4ExtendHighI16x8U, I32x4TruncSatF32x4S, I32x4TruncSatF32x4U, I32x4TruncSatF64x2SZero, I32x4TruncSatF64x2UZero,
+            I64x2Abs, I64x2Neg, I64x2ExtendLowI32x4S, I64x2ExtendHighI32x4S, I64x2ExtendLowI32x4U, I64x2ExtendHighI32x4U,
+            F32x4DemoteF64x2Zero, F32x4Ceil, F32x4Floor, F32x4Trunc, F32x4Nearest, F32x4Abs, F32x4Neg, F32x4Sqrt, F32x4ConvertI32x4S, F32x4ConvertI32x4U,
+            F64x2PromoteLowF32x4, F64x2Ceil, F64x2Floor, F64x2Trunc, F64x2Nearest, F64x2Abs, F64x2Neg, F64x2Sqrt, F64x2ConvertLowI32x4S, F64x2ConvertLowI32x4U,
+            /* Binary */
+            V128And, V128Andnot, V128Or, V128Xor,
+            I8x16Swizzle, I8x16Eq, I8x16Ne, I8x16LtS, I8x16LtU, I8x16GtS, I8x16GtU, I8x16LeS, I8x16LeU, I8x16GeS, I8x16GeU, I8x16NarrowI16x8S, I8x16NarrowI16x8U, I8x16Add, I8x16AddSatS, I8x16AddSatU, I8x16Sub, I8x16SubSatS, I8x16SubSatU, I8x16MinS, I8x16MinU, I8x16MaxS, I8x16MaxU, I8x16AvgrU,
+            I16x8Eq, I16x8Ne, I16x8LtS, I16x8LtU, I16x8GtS, I16x8GtU, I16x8LeS, I16x8LeU, I16x8GeS, I16x8GeU, I16x8Q15mulrSatS, I16x8NarrowI32x4S, I16x8NarrowI32x4U, I16x8Add, I16x8AddSatS, I16x8AddSatU, I16x8Sub, I16x8SubSatS, I16x8SubSatU, I16x8Mul, I16x8MinS, I16x8MinU, I16x8MaxS, I16x8MaxU, I16x8AvgrU, I16x8ExtmulLowI8x16S, I16x8ExtmulHighI8x16S, I16x8ExtmulLowI8x16U, I16x8ExtmulHighI8x16U,
+            I32x4Eq, I32x4Ne, I32x4LtS, I32x4LtU, I32x4GtS, I32x4GtU, I32x4LeS, I32x4LeU, I32x4GeS, I32x4GeU, I32x4Add, I32x4Sub, I32x4Mul, I32x4MinS, I32x4MinU, I32x4MaxS, I32x4MaxU, I32x4DotI16x8S, I32x4ExtmulLowI16x8S, I32x4ExtmulHighI16x8S, I32x4ExtmulLowI16x8U, I32x4ExtmulHighI16x8U,
+            I64x2Add, I64x2Sub, I64x2Mul, I64x2Eq, I64x2Ne, I64x2LtS, I64x2GtS, I64x2LeS, I64x2GeS, I64x2ExtmulLowI32x4S, I64x2ExtmulHighI32x4S, I64x2ExtmulLowI32x4U, I64x2ExtmulHighI32x4U,
+            F32x4Eq, F32x4Ne, F32x4Lt, F32x4Gt, F32x4Le, F32x4Ge, F32x4Add, F32x4Sub, F32x4Mul, F32x4Div, F32x4Min, F32x4Max, F32x4Pmin, F32x4Pmax,
+            F64x2Eq, F64x2Ne, F64x2Lt, F64x2Gt, F64x2Le, F64x2Ge, F64x2Add, F64x2Sub, F64x2Mul, F64x2Div, F64x2Min, F64x2Max, F64x2Pmin, F64x2Pmax,
+            /* Shifts (by i32) */
+            I8x16Shl, I8x16ShrS, I8x16ShrU,
+            I16x8Shl, I16x8ShrS, I16x8ShrU,
+            I32x4Shl, I32x4ShrS, I32x4ShrU,
+            I64x2Shl, I64x2ShrS, I64x2ShrU,
+            /* Ternary */
+            V128Bitselect,
+        ];
+        ALL
+    }
+}
+
+impl SimdLaneOp {
+    pub fn all() -> &'static [SimdLaneOp] {
+        use self::SimdLaneOp::*;
+        static ALL: &[SimdLaneOp] = &[
+            I8x16ExtractLaneS, I8x16ExtractLaneU, I8x16ReplaceLane, I16x8ExtractLaneS, I16x8ExtractLaneU, I16x8ReplaceLane, I32x4ExtractLane, I32x4ReplaceLane, I64x2ExtractLane, I64x2ReplaceLane, F32x4ExtractLane, F32x4ReplaceLane, F64x2ExtractLane, F64x2ReplaceLane,
+        ];
+        ALL
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        use self::SimdLaneOp::*;
+        match *self {
+            I8x16ExtractLaneS | I8x16ExtractLaneU | I8x16ReplaceLane => 16,
+            I16x8ExtractLaneS | I16x8ExtractLaneU | I16x8ReplaceLane => 8,
+            I32x4ExtractLane | I32x4ReplaceLane | F32x4ExtractLane | F32x4ReplaceLane => 4,
+            I64x2ExtractLane | I64x2ReplaceLane | F64x2ExtractLane | F64x2ReplaceLane => 2,
+        }
+    }
+}
+
+impl AtomicLoadOp {
+    pub fn all() -> &'static [AtomicLoadOp] {
+        use self::AtomicLoadOp::*;
+        static ALL: &[AtomicLoadOp] = &[
+            I32AtomicLoad, I64AtomicLoad, I32AtomicLoad8U, I32AtomicLoad16U, I64AtomicLoad8U, I64AtomicLoad16U, I64AtomicLoad32U,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, atomic accesses must use exactly this alignment
+    pub fn natural_alignment(&self) -> u32 {
+        use self::AtomicLoadOp::*;
+        match *self {
+            I32AtomicLoad8U | I64AtomicLoad8U => 0,
+            I32AtomicLoad16U | I64AtomicLoad16U => 1,
+            I32AtomicLoad | I64AtomicLoad32U => 2,
+            I64AtomicLoad => 3,
+        }
+    }
+}
+
+impl AtomicStoreOp {
+    pub fn all() -> &'static [AtomicStoreOp] {
+        use self::AtomicStoreOp::*;
+        static ALL: &[AtomicStoreOp] = &[
+            I32AtomicStore, I64AtomicStore, I32AtomicStore8, I32AtomicStore16, I64AtomicStore8, I64AtomicStore16, I64AtomicStore32,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, atomic accesses must use exactly this alignment
+    pub fn natural_alignment(&self) -> u32 {
+        use self::AtomicStoreOp::*;
+        match *self {
+            I32AtomicStore8 | I64AtomicStore8 => 0,
+            I32AtomicStore16 | I64AtomicStore16 => 1,
+            I32AtomicStore | I64AtomicStore32 => 2,
+            I64AtomicStore => 3,
+        }
+    }
+}
+
+impl AtomicRmwOp {
+    pub fn all() -> &'static [AtomicRmwOp] {
+        use self::AtomicRmwOp::*;
+        static ALL: &[AtomicRmwOp] = &[
+            I32AtomicRmwAdd, I64AtomicRmwAdd, I32AtomicRmw8AddU, I32AtomicRmw16AddU, I64AtomicRmw8AddU, I64AtomicRmw16AddU, I64AtomicRmw32AddU,
+            I32AtomicRmwSub, I64AtomicRmwSub, I32AtomicRmw8SubU, I32AtomicRmw16SubU, I64AtomicRmw8SubU, I64AtomicRmw16SubU, I64AtomicRmw32SubU,
+            I32AtomicRmwAnd, I64AtomicRmwAnd, I32AtomicRmw8AndU, I32AtomicRmw16AndU, I64AtomicRmw8AndU, I64AtomicRmw16AndU, I64AtomicRmw32AndU,
+            I32AtomicRmwOr, I64AtomicRmwOr, I32AtomicRmw8OrU, I32AtomicRmw16OrU, I64AtomicRmw8OrU, I64AtomicRmw16OrU, I64AtomicRmw32OrU,
+            I32AtomicRmwXor, I64AtomicRmwXor, I32AtomicRmw8XorU, I32AtomicRmw16XorU, I64AtomicRmw8XorU, I64AtomicRmw16XorU, I64AtomicRmw32XorU,
+            I32AtomicRmwXchg, I64AtomicRmwXchg, I32AtomicRmw8XchgU, I32AtomicRmw16XchgU, I64AtomicRmw8XchgU, I64AtomicRmw16XchgU, I64AtomicRmw32XchgU,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, atomic accesses must use exactly this alignment
+    pub fn natural_alignment(&self) -> u32 {
+        use self::AtomicRmwOp::*;
+        match *self {
+            I32AtomicRmw8AddU | I64AtomicRmw8AddU | I32AtomicRmw8SubU | I64AtomicRmw8SubU | I32AtomicRmw8AndU | I64AtomicRmw8AndU | I32AtomicRmw8OrU | I64AtomicRmw8OrU | I32AtomicRmw8XorU | I64AtomicRmw8XorU | I32AtomicRmw8XchgU | I64AtomicRmw8XchgU => 0,
+            I32AtomicRmw16AddU | I64AtomicRmw16AddU | I32AtomicRmw16SubU | I64AtomicRmw16SubU | I32AtomicRmw16AndU | I64AtomicRmw16AndU | I32AtomicRmw16OrU | I64AtomicRmw16OrU | I32AtomicRmw16XorU | I64AtomicRmw16XorU | I32AtomicRmw16XchgU | I64AtomicRmw16XchgU => 1,
+            I32AtomicRmwAdd | I64AtomicRmw32AddU | I32AtomicRmwSub | I64AtomicRmw32SubU | I32AtomicRmwAnd | I64AtomicRmw32AndU | I32AtomicRmwOr | I64AtomicRmw32OrU | I32AtomicRmwXor | I64AtomicRmw32XorU | I32AtomicRmwXchg | I64AtomicRmw32XchgU => 2,
+            I64AtomicRmwAdd | I64AtomicRmwSub | I64AtomicRmwAnd | I64AtomicRmwOr | I64AtomicRmwXor | I64AtomicRmwXchg => 3,
+        }
+    }
+}
+
+impl AtomicCmpxchgOp {
+    pub fn all() -> &'static [AtomicCmpxchgOp] {
+        use self::AtomicCmpxchgOp::*;
+        static ALL: &[AtomicCmpxchgOp] = &[
+            I32AtomicRmwCmpxchg, I64AtomicRmwCmpxchg, I32AtomicRmw8CmpxchgU, I32AtomicRmw16CmpxchgU, I64AtomicRmw8CmpxchgU, I64AtomicRmw16CmpxchgU, I64AtomicRmw32CmpxchgU,
+        ];
+        ALL
+    }
+
+    /// log2 of the number of bytes accessed, atomic accesses must use exactly this alignment
+    pub fn natural_alignment(&self) -> u32 {
+        use self::AtomicCmpxchgOp::*;
+        match *self {
+            I32AtomicRmw8CmpxchgU | I64AtomicRmw8CmpxchgU => 0,
+            I32AtomicRmw16CmpxchgU | I64AtomicRmw16CmpxchgU => 1,
+            I32AtomicRmwCmpxchg | I64AtomicRmw32CmpxchgU => 2,
+            I64AtomicRmwCmpxchg => 3,
+        }
+    }
+}
+
 
 /* Type information for each instruction */
 
@@ -324,6 +1076,20 @@ impl GlobalOp {
     }
 }
 
+impl TableOp {
+    pub fn to_type(&self, elem_ty: ElemType) -> InstrType {
+        let elem_ty = elem_ty.into();
+        match *self {
+            TableGet => InstrType::new(&[I32], &[elem_ty]),
+            TableSet => InstrType::new(&[I32, elem_ty], &[]),
+            TableSize => InstrType::new(&[], &[I32]),
+            // (initial value for the new entries, delta) -> previous size or -1
+            TableGrow => InstrType::new(&[elem_ty, I32], &[I32]),
+            TableFill => InstrType::new(&[I32, elem_ty, I32], &[]),
+        }
+    }
+}
+
 impl NumericOp {
     pub fn to_type(&self) -> InstrType {
         use self::NumericOp::*;
@@ -356,6 +1122,12 @@ impl NumericOp {
             I64ReinterpretF64 => InstrType::new(&[F64], &[I64]),
             F32ReinterpretI32 => InstrType::new(&[I32], &[F32]),
             F64ReinterpretI64 => InstrType::new(&[I64], &[F64]),
+            I32Extend8S | I32Extend16S => InstrType::new(&[I32], &[I32]),
+            I64Extend8S | I64Extend16S | I64Extend32S => InstrType::new(&[I64], &[I64]),
+            I32TruncSatSF32 | I32TruncSatUF32 => InstrType::new(&[F32], &[I32]),
+            I32TruncSatSF64 | I32TruncSatUF64 => InstrType::new(&[F64], &[I32]),
+            I64TruncSatSF32 | I64TruncSatUF32 => InstrType::new(&[F32], &[I64]),
+            I64TruncSatSF64 | I64TruncSatUF64 => InstrType::new(&[F64], &[I64]),
 
             /* Binary */
 
@@ -391,6 +1163,9 @@ impl LoadOp {
             I64Load16U => InstrType::new(&[I32], &[I64]),
             I64Load32S => InstrType::new(&[I32], &[I64]),
             I64Load32U => InstrType::new(&[I32], &[I64]),
+
+            V128Load | V128Load8x8S | V128Load8x8U | V128Load16x4S | V128Load16x4U | V128Load32x2S | V128Load32x2U
+            | V128Load8Splat | V128Load16Splat | V128Load32Splat | V128Load64Splat | V128Load32Zero | V128Load64Zero => InstrType::new(&[I32], &[V128]),
         }
     }
 }
@@ -408,6 +1183,125 @@ impl StoreOp {
             I64Store8 => InstrType::new(&[I32, I64], &[]),
             I64Store16 => InstrType::new(&[I32, I64], &[]),
             I64Store32 => InstrType::new(&[I32, I64], &[]),
+
+            V128Store => InstrType::new(&[I32, V128], &[]),
+        }
+    }
+}
+
+impl LoadLaneOp {
+    /// (address, vector to insert the lane into) -> vector
+    pub fn to_type(&self) -> InstrType {
+        InstrType::new(&[I32, V128], &[V128])
+    }
+}
+
+impl StoreLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        InstrType::new(&[I32, V128], &[])
+    }
+}
+
+impl SimdOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::SimdOp::*;
+        match *self {
+            I8x16Splat => InstrType::new(&[I32], &[V128]),
+            I16x8Splat => InstrType::new(&[I32], &[V128]),
+            I32x4Splat => InstrType::new(&[I32], &[V128]),
+            I64x2Splat => InstrType::new(&[I64], &[V128]),
+            F32x4Splat => InstrType::new(&[F32], &[V128]),
+            F64x2Splat => InstrType::new(&[F64], &[V128]),
+            V128AnyTrue => InstrType::new(&[V128], &[I32]),
+            I8x16AllTrue | I8x16Bitmask => InstrType::new(&[V128], &[I32]),
+            I16x8AllTrue | I16x8Bitmask => InstrType::new(&[V128], &[I32]),
+            I32x4AllTrue | I32x4Bitmask => InstrType::new(&[V128], &[I32]),
+            I64x2AllTrue | I64x2Bitmask => InstrType::new(&[V128], &[I32]),
+
+            V128Not => InstrType::new(&[V128], &[V128]),
+            I8x16Abs | I8x16Neg | I8x16Popcnt => InstrType::new(&[V128], &[V128]),
+            I16x8ExtaddPairwiseI8x16S | I16x8ExtaddPairwiseI8x16U | I16x8Abs | I16x8Neg | I16x8ExtendLowI8x16S | I16x8ExtendHighI8x16S | I16x8ExtendLowI8x16U | I16x8ExtendHighI8x16U => InstrType::new(&[V128], &[V128]),
+            I32x4ExtaddPairwiseI16x8S | I32x4ExtaddPairwiseI16x8U | I32x4Abs | I32x4Neg | I32x4ExtendLowI16x8S | I32x4ExtendHighI16x8S | I32x4ExtendLowI16x8U | I32x4ExtendHighI16x8U | I32x4TruncSatF32x4S | I32x4TruncSatF32x4U | I32x4TruncSatF64x2SZero | I32x4TruncSatF64x2UZero => InstrType::new(&[V128], &[V128]),
+            I64x2Abs | I64x2Neg | I64x2ExtendLowI32x4S | I64x2ExtendHighI32x4S | I64x2ExtendLowI32x4U | I64x2ExtendHighI32x4U => InstrType::new(&[V128], &[V128]),
+            F32x4DemoteF64x2Zero | F32x4Ceil | F32x4Floor | F32x4Trunc | F32x4Nearest | F32x4Abs | F32x4Neg | F32x4Sqrt | F32x4ConvertI32x4S | F32x4ConvertI32x4U => InstrType::new(&[V128], &[V128]),
+            F64x2PromoteLowF32x4 | F64x2Ceil | F64x2Floor | F64x2Trunc | F64x2Nearest | F64x2Abs | F64x2Neg | F64x2Sqrt | F64x2ConvertLowI32x4S | F64x2ConvertLowI32x4U => InstrType::new(&[V128], &[V128]),
+
+            V128And | V128Andnot | V128Or | V128Xor => InstrType::new(&[V128, V128], &[V128]),
+            I8x16Swizzle | I8x16Eq | I8x16Ne | I8x16LtS | I8x16LtU | I8x16GtS | I8x16GtU | I8x16LeS | I8x16LeU | I8x16GeS | I8x16GeU | I8x16NarrowI16x8S | I8x16NarrowI16x8U | I8x16Add | I8x16AddSatS | I8x16AddSatU | I8x16Sub | I8x16SubSatS | I8x16SubSatU | I8x16MinS | I8x16MinU | I8x16MaxS | I8x16MaxU | I8x16AvgrU => InstrType::new(&[V128, V128], &[V128]),
+            I16x8Eq | I16x8Ne | I16x8LtS | I16x8LtU | I16x8GtS | I16x8GtU | I16x8LeS | I16x8LeU | I16x8GeS | I16x8GeU | I16x8Q15mulrSatS | I16x8NarrowI32x4S | I16x8NarrowI32x4U | I16x8Add | I16x8AddSatS | I16x8AddSatU | I16x8Sub | I16x8SubSatS | I16x8SubSatU | I16x8Mul | I16x8MinS | I16x8MinU | I16x8MaxS | I16x8MaxU | I16x8AvgrU | I16x8ExtmulLowI8x16S | I16x8ExtmulHighI8x16S | I16x8ExtmulLowI8x16U | I16x8ExtmulHighI8x16U => InstrType::new(&[V128, V128], &[V128]),
+            I32x4Eq | I32x4Ne | I32x4LtS | I32x4LtU | I32x4GtS | I32x4GtU | I32x4LeS | I32x4LeU | I32x4GeS | I32x4GeU | I32x4Add | I32x4Sub | I32x4Mul | I32x4MinS | I32x4MinU | I32x4MaxS | I32x4MaxU | I32x4DotI16x8S | I32x4ExtmulLowI16x8S | I32x4ExtmulHighI16x8S | I32x4ExtmulLowI16x8U | I32x4ExtmulHighI16x8U => InstrType::new(&[V128, V128], &[V128]),
+            I64x2Add | I64x2Sub | I64x2Mul | I64x2Eq | I64x2Ne | I64x2LtS | I64x2GtS | I64x2LeS | I64x2GeS | I64x2ExtmulLowI32x4S | I64x2ExtmulHighI32x4S | I64x2ExtmulLowI32x4U | I64x2ExtmulHighI32x4U => InstrType::new(&[V128, V128], &[V128]),
+            F32x4Eq | F32x4Ne | F32x4Lt | F32x4Gt | F32x4Le | F32x4Ge | F32x4Add | F32x4Sub | F32x4Mul | F32x4Div | F32x4Min | F32x4Max | F32x4Pmin | F32x4Pmax => InstrType::new(&[V128, V128], &[V128]),
+            F64x2Eq | F64x2Ne | F64x2Lt | F64x2Gt | F64x2Le | F64x2Ge | F64x2Add | F64x2Sub | F64x2Mul | F64x2Div | F64x2Min | F64x2Max | F64x2Pmin | F64x2Pmax => InstrType::new(&[V128, V128], &[V128]),
+
+            I8x16Shl | I8x16ShrS | I8x16ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I16x8Shl | I16x8ShrS | I16x8ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I32x4Shl | I32x4ShrS | I32x4ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I64x2Shl | I64x2ShrS | I64x2ShrU => InstrType::new(&[V128, I32], &[V128]),
+
+            V128Bitselect => InstrType::new(&[V128, V128, V128], &[V128]),
+        }
+    }
+}
+
+impl SimdLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::SimdLaneOp::*;
+        match *self {
+            I8x16ExtractLaneS => InstrType::new(&[V128], &[I32]),
+            I8x16ExtractLaneU => InstrType::new(&[V128], &[I32]),
+            I8x16ReplaceLane => InstrType::new(&[V128, I32], &[V128]),
+            I16x8ExtractLaneS => InstrType::new(&[V128], &[I32]),
+            I16x8ExtractLaneU => InstrType::new(&[V128], &[I32]),
+            I16x8ReplaceLane => InstrType::new(&[V128, I32], &[V128]),
+            I32x4ExtractLane => InstrType::new(&[V128], &[I32]),
+            I32x4ReplaceLane => InstrType::new(&[V128, I32], &[V128]),
+            I64x2ExtractLane => InstrType::new(&[V128], &[I64]),
+            I64x2ReplaceLane => InstrType::new(&[V128, I64], &[V128]),
+            F32x4ExtractLane => InstrType::new(&[V128], &[F32]),
+            F32x4ReplaceLane => InstrType::new(&[V128, F32], &[V128]),
+            F64x2ExtractLane => InstrType::new(&[V128], &[F64]),
+            F64x2ReplaceLane => InstrType::new(&[V128, F64], &[V128]),
+        }
+    }
+}
+
+impl AtomicLoadOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::AtomicLoadOp::*;
+        match *self {
+            I32AtomicLoad | I32AtomicLoad8U | I32AtomicLoad16U => InstrType::new(&[I32], &[I32]),
+            I64AtomicLoad | I64AtomicLoad8U | I64AtomicLoad16U | I64AtomicLoad32U => InstrType::new(&[I32], &[I64]),
+        }
+    }
+}
+
+impl AtomicStoreOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::AtomicStoreOp::*;
+        match *self {
+            I32AtomicStore | I32AtomicStore8 | I32AtomicStore16 => InstrType::new(&[I32, I32], &[]),
+            I64AtomicStore | I64AtomicStore8 | I64AtomicStore16 | I64AtomicStore32 => InstrType::new(&[I32, I64], &[]),
+        }
+    }
+}
+
+impl AtomicRmwOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::AtomicRmwOp::*;
+        match *self {
+            I32AtomicRmwAdd | I32AtomicRmw8AddU | I32AtomicRmw16AddU | I32AtomicRmwSub | I32AtomicRmw8SubU | I32AtomicRmw16SubU | I32AtomicRmwAnd | I32AtomicRmw8AndU | I32AtomicRmw16AndU | I32AtomicRmwOr | I32AtomicRmw8OrU | I32AtomicRmw16OrU | I32AtomicRmwXor | I32AtomicRmw8XorU | I32AtomicRmw16XorU | I32AtomicRmwXchg | I32AtomicRmw8XchgU | I32AtomicRmw16XchgU => InstrType::new(&[I32, I32], &[I32]),
+            I64AtomicRmwAdd | I64AtomicRmw8AddU | I64AtomicRmw16AddU | I64AtomicRmw32AddU | I64AtomicRmwSub | I64AtomicRmw8SubU | I64AtomicRmw16SubU | I64AtomicRmw32SubU | I64AtomicRmwAnd | I64AtomicRmw8AndU | I64AtomicRmw16AndU | I64AtomicRmw32AndU | I64AtomicRmwOr | I64AtomicRmw8OrU | I64AtomicRmw16OrU | I64AtomicRmw32OrU | I64AtomicRmwXor | I64AtomicRmw8XorU | I64AtomicRmw16XorU | I64AtomicRmw32XorU | I64AtomicRmwXchg | I64AtomicRmw8XchgU | I64AtomicRmw16XchgU | I64AtomicRmw32XchgU => InstrType::new(&[I32, I64], &[I64]),
+        }
+    }
+}
+
+impl AtomicCmpxchgOp {
+    pub fn to_type(&self) -> InstrType {
+        use self::AtomicCmpxchgOp::*;
+        match *self {
+            I32AtomicRmwCmpxchg | I32AtomicRmw8CmpxchgU | I32AtomicRmw16CmpxchgU => InstrType::new(&[I32, I32, I32], &[I32]),
+            I64AtomicRmwCmpxchg | I64AtomicRmw8CmpxchgU | I64AtomicRmw16CmpxchgU | I64AtomicRmw32CmpxchgU => InstrType::new(&[I32, I64, I64], &[I64]),
         }
     }
 }
@@ -423,27 +1317,83 @@ impl Instr {
             Store(ref op, _) => Some(op.to_type()),
             MemorySize(_) => Some(InstrType::new(&[], &[I32])),
             MemoryGrow(_) => Some(InstrType::new(&[I32], &[I32])),
+            MemoryInit(_, _) | MemoryCopy(_, _) | MemoryFill(_) | TableInit(_, _) | TableCopy(_, _) => Some(InstrType::new(&[I32, I32, I32], &[])),
+            DataDrop(_) | ElemDrop(_) => Some(InstrType::default()),
             Const(ref val) => Some(InstrType::new(&[], &[val.to_type()])),
             Numeric(ref op) => Some(op.to_type()),
+            LoadLane(ref op, _, _) => Some(op.to_type()),
+            StoreLane(ref op, _, _) => Some(op.to_type()),
+            Simd(ref op) => Some(op.to_type()),
+            SimdLane(ref op, _) => Some(op.to_type()),
+            Shuffle(_) => Some(InstrType::new(&[V128, V128], &[V128])),
+            AtomicLoad(ref op, _) => Some(op.to_type()),
+            AtomicStore(ref op, _) => Some(op.to_type()),
+            AtomicRmw(ref op, _) => Some(op.to_type()),
+            AtomicCmpxchg(ref op, _) => Some(op.to_type()),
+            MemoryAtomicNotify(_) => Some(InstrType::new(&[I32, I32], &[I32])),
+            MemoryAtomicWait32(_) => Some(InstrType::new(&[I32, I32, I64], &[I32])),
+            MemoryAtomicWait64(_) => Some(InstrType::new(&[I32, I64, I64], &[I32])),
+            AtomicFence => Some(InstrType::default()),
             CallIndirect(ref func_ty, _) => Some(InstrType::new(&[&func_ty.params[..], &[I32]].concat(), &func_ty.results)),
+            TypedSelect(ty) => Some(InstrType::new(&[ty, ty, I32], &[ty])),
+            RefNull(ty) => Some(InstrType::new(&[], &[ty.into()])),
+            RefFunc(_) => Some(InstrType::new(&[], &[FuncRef])),
 
             // nesting...
             Block(_) | Loop(_) | If(_) | Else | End => None,
+            Try(_) | Catch(_) | CatchAll | Delegate(_) => None,
             // depends on branch target?
             Br(_) | BrIf(_) | BrTable(_, _) => None,
+            // need to inspect tag type
+            Throw(_) | Rethrow(_) => None,
             // need to inspect function type
-            Return | Call(_) => None,
+            Return | Call(_) | ReturnCall(_) | ReturnCallIndirect(_, _) => None,
             // need abstract type stack "evaluation"
-            Drop | Select => None,
-            // need lookup in locals/globals
-            Local(_, _) | Global(_, _) => None,
+            Drop | Select | RefIsNull => None,
+            // need lookup in locals/globals/tables
+            Local(_, _) | Global(_, _) | Table(_, _) => None,
+        }
+    }
+
+    /// like to_type(), but addresses (and sizes) of memory instructions are i64 if the accessed
+    /// memory has index type I64 (memory64 proposal), see Module::memory_index_type()
+    pub fn to_type_with_memories(&self, index_type: impl Fn(Idx<Memory>) -> ValType) -> Option<InstrType> {
+        use self::Instr::*;
+        let mut ty = self.to_type()?;
+        match *self {
+            Load(_, memarg) | Store(_, memarg) | LoadLane(_, memarg, _) | StoreLane(_, memarg, _)
+            | AtomicLoad(_, memarg) | AtomicStore(_, memarg) | AtomicRmw(_, memarg) | AtomicCmpxchg(_, memarg)
+            | MemoryAtomicNotify(memarg) | MemoryAtomicWait32(memarg) | MemoryAtomicWait64(memarg) => ty.inputs[0] = index_type(memarg.memory),
+            MemorySize(memory) => ty.results[0] = index_type(memory),
+            MemoryGrow(memory) => {
+                ty.inputs[0] = index_type(memory);
+                ty.results[0] = index_type(memory);
+            }
+            // (destination, value, length)
+            MemoryFill(memory) => {
+                ty.inputs[0] = index_type(memory);
+                ty.inputs[2] = index_type(memory);
+            }
+            // (destination, offset in the data segment, length)
+            MemoryInit(_, memory) => ty.inputs[0] = index_type(memory),
+            // (destination, source, length), the length is only 64-bit if both memories are
+            MemoryCopy(dst, src) => {
+                let (dst, src) = (index_type(dst), index_type(src));
+                ty.inputs[0] = dst;
+                ty.inputs[1] = src;
+                ty.inputs[2] = if dst == I64 && src == I64 { I64 } else { I32 };
+            }
+            _ => {}
         }
+        Some(ty)
     }
 
     /// returns instruction name as in Wasm spec
     pub fn to_name(&self) -> &'static str {
         use self::Instr::*;
         use self::NumericOp::*;
+        use self::SimdLaneOp::*;
+        use self::SimdOp::*;
         match *self {
             Unreachable => "unreachable",
             Nop => "nop",
@@ -452,25 +1402,49 @@ impl Instr {
             If(_) => "if",
             Else => "else",
             End => "end",
+            Try(_) => "try",
+            Catch(_) => "catch",
+            CatchAll => "catch_all",
+            Delegate(_) => "delegate",
+            Throw(_) => "throw",
+            Rethrow(_) => "rethrow",
             Br(_) => "br",
             BrIf(_) => "br_if",
             BrTable(_, _) => "br_table",
             Return => "return",
             Call(_) => "call",
             CallIndirect(_, _) => "call_indirect",
+            ReturnCall(_) => "return_call",
+            ReturnCallIndirect(_, _) => "return_call_indirect",
             Drop => "drop",
-            Select => "select",
+            Select | TypedSelect(_) => "select",
             Local(GetLocal, _) => "get_local",
             Local(SetLocal, _) => "set_local",
             Local(TeeLocal, _) => "tee_local",
             Global(GetGlobal, _) => "get_global",
             Global(SetGlobal, _) => "set_global",
+            Table(TableGet, _) => "table.get",
+            Table(TableSet, _) => "table.set",
+            Table(TableSize, _) => "table.size",
+            Table(TableGrow, _) => "table.grow",
+            Table(TableFill, _) => "table.fill",
             MemorySize(_) => "memory.size",
             MemoryGrow(_) => "memory.grow",
+            MemoryInit(_, _) => "memory.init",
+            DataDrop(_) => "data.drop",
+            MemoryCopy(_, _) => "memory.copy",
+            MemoryFill(_) => "memory.fill",
+            TableInit(_, _) => "table.init",
+            ElemDrop(_) => "elem.drop",
+            TableCopy(_, _) => "table.copy",
+            RefNull(_) => "ref.null",
+            RefIsNull => "ref.is_null",
+            RefFunc(_) => "ref.func",
             Const(Val::I32(_)) => "i32.const",
             Const(Val::I64(_)) => "i64.const",
             Const(Val::F32(_)) => "f32.const",
             Const(Val::F64(_)) => "f64.const",
+            Const(Val::V128(_)) => "v128.const",
             Load(I32Load, _) => "i32.load",
             Load(I64Load, _) => "i64.load",
             Load(F32Load, _) => "f32.load",
@@ -485,6 +1459,19 @@ impl Instr {
             Load(I64Load16U, _) => "i64.load16_u",
             Load(I64Load32S, _) => "i64.load32_s",
             Load(I64Load32U, _) => "i64.load32_u",
+            Load(V128Load, _) => "v128.load",
+            Load(V128Load8x8S, _) => "v128.load8x8_s",
+            Load(V128Load8x8U, _) => "v128.load8x8_u",
+            Load(V128Load16x4S, _) => "v128.load16x4_s",
+            Load(V128Load16x4U, _) => "v128.load16x4_u",
+            Load(V128Load32x2S, _) => "v128.load32x2_s",
+            Load(V128Load32x2U, _) => "v128.load32x2_u",
+            Load(V128Load8Splat, _) => "v128.load8_splat",
+            Load(V128Load16Splat, _) => "v128.load16_splat",
+            Load(V128Load32Splat, _) => "v128.load32_splat",
+            Load(V128Load64Splat, _) => "v128.load64_splat",
+            Load(V128Load32Zero, _) => "v128.load32_zero",
+            Load(V128Load64Zero, _) => "v128.load64_zero",
             Store(I32Store, _) => "i32.store",
             Store(I64Store, _) => "i64.store",
             Store(F32Store, _) => "f32.store",
@@ -494,6 +1481,15 @@ impl Instr {
             Store(I64Store8, _) => "i64.store8",
             Store(I64Store16, _) => "i64.store16",
             Store(I64Store32, _) => "i64.store32",
+            Store(V128Store, _) => "v128.store",
+            LoadLane(V128Load8Lane, _, _) => "v128.load8_lane",
+            LoadLane(V128Load16Lane, _, _) => "v128.load16_lane",
+            LoadLane(V128Load32Lane, _, _) => "v128.load32_lane",
+            LoadLane(V128Load64Lane, _, _) => "v128.load64_lane",
+            StoreLane(V128Store8Lane, _, _) => "v128.store8_lane",
+            StoreLane(V128Store16Lane, _, _) => "v128.store16_lane",
+            StoreLane(V128Store32Lane, _, _) => "v128.store32_lane",
+            StoreLane(V128Store64Lane, _, _) => "v128.store64_lane",
             Numeric(I32Eqz) => "i32.eqz",
             Numeric(I64Eqz) => "i64.eqz",
             Numeric(I32Clz) => "i32.clz",
@@ -541,6 +1537,19 @@ impl Instr {
             Numeric(I64ReinterpretF64) => "i64.reinterpret/f64",
             Numeric(F32ReinterpretI32) => "f32.reinterpret/i32",
             Numeric(F64ReinterpretI64) => "f64.reinterpret/i64",
+            Numeric(I32Extend8S) => "i32.extend8_s",
+            Numeric(I32Extend16S) => "i32.extend16_s",
+            Numeric(I64Extend8S) => "i64.extend8_s",
+            Numeric(I64Extend16S) => "i64.extend16_s",
+            Numeric(I64Extend32S) => "i64.extend32_s",
+            Numeric(I32TruncSatSF32) => "i32.trunc_sat_f32_s",
+            Numeric(I32TruncSatUF32) => "i32.trunc_sat_f32_u",
+            Numeric(I32TruncSatSF64) => "i32.trunc_sat_f64_s",
+            Numeric(I32TruncSatUF64) => "i32.trunc_sat_f64_u",
+            Numeric(I64TruncSatSF32) => "i64.trunc_sat_f32_s",
+            Numeric(I64TruncSatUF32) => "i64.trunc_sat_f32_u",
+            Numeric(I64TruncSatSF64) => "i64.trunc_sat_f64_s",
+            Numeric(I64TruncSatUF64) => "i64.trunc_sat_f64_u",
             Numeric(I32Eq) => "i32.eq",
             Numeric(I32Ne) => "i32.ne",
             Numeric(I32LtS) => "i32.lt_s",
@@ -617,6 +1626,286 @@ impl Instr {
             Numeric(F64Min) => "f64.min",
             Numeric(F64Max) => "f64.max",
             Numeric(F64Copysign) => "f64.copysign",
+            Simd(I8x16Swizzle) => "i8x16.swizzle",
+            Simd(I8x16Splat) => "i8x16.splat",
+            Simd(I16x8Splat) => "i16x8.splat",
+            Simd(I32x4Splat) => "i32x4.splat",
+            Simd(I64x2Splat) => "i64x2.splat",
+            Simd(F32x4Splat) => "f32x4.splat",
+            Simd(F64x2Splat) => "f64x2.splat",
+            Simd(I8x16Eq) => "i8x16.eq",
+            Simd(I8x16Ne) => "i8x16.ne",
+            Simd(I8x16LtS) => "i8x16.lt_s",
+            Simd(I8x16LtU) => "i8x16.lt_u",
+            Simd(I8x16GtS) => "i8x16.gt_s",
+            Simd(I8x16GtU) => "i8x16.gt_u",
+            Simd(I8x16LeS) => "i8x16.le_s",
+            Simd(I8x16LeU) => "i8x16.le_u",
+            Simd(I8x16GeS) => "i8x16.ge_s",
+            Simd(I8x16GeU) => "i8x16.ge_u",
+            Simd(I16x8Eq) => "i16x8.eq",
+            Simd(I16x8Ne) => "i16x8.ne",
+            Simd(I16x8LtS) => "i16x8.lt_s",
+            Simd(I16x8LtU) => "i16x8.lt_u",
+            Simd(I16x8GtS) => "i16x8.gt_s",
+            Simd(I16x8GtU) => "i16x8.gt_u",
+            Simd(I16x8LeS) => "i16x8.le_s",
+            Simd(I16x8LeU) => "i16x8.le_u",
+            Simd(I16x8GeS) => "i16x8.ge_s",
+            Simd(I16x8GeU) => "i16x8.ge_u",
+            Simd(I32x4Eq) => "i32x4.eq",
+            Simd(I32x4Ne) => "i32x4.ne",
+            Simd(I32x4LtS) => "i32x4.lt_s",
+            Simd(I32x4LtU) => "i32x4.lt_u",
+            Simd(I32x4GtS) => "i32x4.gt_s",
+            Simd(I32x4GtU) => "i32x4.gt_u",
+            Simd(I32x4LeS) => "i32x4.le_s",
+            Simd(I32x4LeU) => "i32x4.le_u",
+            Simd(I32x4GeS) => "i32x4.ge_s",
+            Simd(I32x4GeU) => "i32x4.ge_u",
+            Simd(F32x4Eq) => "f32x4.eq",
+            Simd(F32x4Ne) => "f32x4.ne",
+            Simd(F32x4Lt) => "f32x4.lt",
+            Simd(F32x4Gt) => "f32x4.gt",
+            Simd(F32x4Le) => "f32x4.le",
+            Simd(F32x4Ge) => "f32x4.ge",
+            Simd(F64x2Eq) => "f64x2.eq",
+            Simd(F64x2Ne) => "f64x2.ne",
+            Simd(F64x2Lt) => "f64x2.lt",
+            Simd(F64x2Gt) => "f64x2.gt",
+            Simd(F64x2Le) => "f64x2.le",
+            Simd(F64x2Ge) => "f64x2.ge",
+            Simd(V128Not) => "v128.not",
+            Simd(V128And) => "v128.and",
+            Simd(V128Andnot) => "v128.andnot",
+            Simd(V128Or) => "v128.or",
+            Simd(V128Xor) => "v128.xor",
+            Simd(V128Bitselect) => "v128.bitselect",
+            Simd(V128AnyTrue) => "v128.any_true",
+            Simd(F32x4DemoteF64x2Zero) => "f32x4.demote_f64x2_zero",
+            Simd(F64x2PromoteLowF32x4) => "f64x2.promote_low_f32x4",
+            Simd(I8x16Abs) => "i8x16.abs",
+            Simd(I8x16Neg) => "i8x16.neg",
+            Simd(I8x16Popcnt) => "i8x16.popcnt",
+            Simd(I8x16AllTrue) => "i8x16.all_true",
+            Simd(I8x16Bitmask) => "i8x16.bitmask",
+            Simd(I8x16NarrowI16x8S) => "i8x16.narrow_i16x8_s",
+            Simd(I8x16NarrowI16x8U) => "i8x16.narrow_i16x8_u",
+            Simd(F32x4Ceil) => "f32x4.ceil",
+            Simd(F32x4Floor) => "f32x4.floor",
+            Simd(F32x4Trunc) => "f32x4.trunc",
+            Simd(F32x4Nearest) => "f32x4.nearest",
+            Simd(I8x16Shl) => "i8x16.shl",
+            Simd(I8x16ShrS) => "i8x16.shr_s",
+            Simd(I8x16ShrU) => "i8x16.shr_u",
+            Simd(I8x16Add) => "i8x16.add",
+            Simd(I8x16AddSatS) => "i8x16.add_sat_s",
+            Simd(I8x16AddSatU) => "i8x16.add_sat_u",
+            Simd(I8x16Sub) => "i8x16.sub",
+            Simd(I8x16SubSatS) => "i8x16.sub_sat_s",
+            Simd(I8x16SubSatU) => "i8x16.sub_sat_u",
+            Simd(F64x2Ceil) => "f64x2.ceil",
+            Simd(F64x2Floor) => "f64x2.floor",
+            Simd(I8x16MinS) => "i8x16.min_s",
+            Simd(I8x16MinU) => "i8x16.min_u",
+            Simd(I8x16MaxS) => "i8x16.max_s",
+            Simd(I8x16MaxU) => "i8x16.max_u",
+            Simd(F64x2Trunc) => "f64x2.trunc",
+            Simd(I8x16AvgrU) => "i8x16.avgr_u",
+            Simd(I16x8ExtaddPairwiseI8x16S) => "i16x8.extadd_pairwise_i8x16_s",
+            Simd(I16x8ExtaddPairwiseI8x16U) => "i16x8.extadd_pairwise_i8x16_u",
+            Simd(I32x4ExtaddPairwiseI16x8S) => "i32x4.extadd_pairwise_i16x8_s",
+            Simd(I32x4ExtaddPairwiseI16x8U) => "i32x4.extadd_pairwise_i16x8_u",
+            Simd(I16x8Abs) => "i16x8.abs",
+            Simd(I16x8Neg) => "i16x8.neg",
+            Simd(I16x8Q15mulrSatS) => "i16x8.q15mulr_sat_s",
+            Simd(I16x8AllTrue) => "i16x8.all_true",
+            Simd(I16x8Bitmask) => "i16x8.bitmask",
+            Simd(I16x8NarrowI32x4S) => "i16x8.narrow_i32x4_s",
+            Simd(I16x8NarrowI32x4U) => "i16x8.narrow_i32x4_u",
+            Simd(I16x8ExtendLowI8x16S) => "i16x8.extend_low_i8x16_s",
+            Simd(I16x8ExtendHighI8x16S) => "i16x8.extend_high_i8x16_s",
+            Simd(I16x8ExtendLowI8x16U) => "i16x8.extend_low_i8x16_u",
+            Simd(I16x8ExtendHighI8x16U) => "i16x8.extend_high_i8x16_u",
+            Simd(I16x8Shl) => "i16x8.shl",
+            Simd(I16x8ShrS) => "i16x8.shr_s",
+            Simd(I16x8ShrU) => "i16x8.shr_u",
+            Simd(I16x8Add) => "i16x8.add",
+            Simd(I16x8AddSatS) => "i16x8.add_sat_s",
+            Simd(I16x8AddSatU) => "i16x8.add_sat_u",
+            Simd(I16x8Sub) => "i16x8.sub",
+            Simd(I16x8SubSatS) => "i16x8.sub_sat_s",
+            Simd(I16x8SubSatU) => "i16x8.sub_sat_u",
+            Simd(F64x2Nearest) => "f64x2.nearest",
+            Simd(I16x8Mul) => "i16x8.mul",
+            Simd(I16x8MinS) => "i16x8.min_s",
+            Simd(I16x8MinU) => "i16x8.min_u",
+            Simd(I16x8MaxS) => "i16x8.max_s",
+            Simd(I16x8MaxU) => "i16x8.max_u",
+            Simd(I16x8AvgrU) => "i16x8.avgr_u",
+            Simd(I16x8ExtmulLowI8x16S) => "i16x8.extmul_low_i8x16_s",
+            Simd(I16x8ExtmulHighI8x16S) => "i16x8.extmul_high_i8x16_s",
+            Simd(I16x8ExtmulLowI8x16U) => "i16x8.extmul_low_i8x16_u",
+            Simd(I16x8ExtmulHighI8x16U) => "i16x8.extmul_high_i8x16_u",
+            Simd(I32x4Abs) => "i32x4.abs",
+            Simd(I32x4Neg) => "i32x4.neg",
+            Simd(I32x4AllTrue) => "i32x4.all_true",
+            Simd(I32x4Bitmask) => "i32x4.bitmask",
+            Simd(I32x4ExtendLowI16x8S) => "i32x4.extend_low_i16x8_s",
+            Simd(I32x4ExtendHighI16x8S) => "i32x4.extend_high_i16x8_s",
+            Simd(I32x4ExtendLowI16x8U) => "i32x4.extend_low_i16x8_u",
+            Simd(I32x4ExtendHighI16x8U) => "i32x4.extend_high_i16x8_u",
+            Simd(I32x4Shl) => "i32x4.shl",
+            Simd(I32x4ShrS) => "i32x4.shr_s",
+            Simd(I32x4ShrU) => "i32x4.shr_u",
+            Simd(I32x4Add) => "i32x4.add",
+            Simd(I32x4Sub) => "i32x4.sub",
+            Simd(I32x4Mul) => "i32x4.mul",
+            Simd(I32x4MinS) => "i32x4.min_s",
+            Simd(I32x4MinU) => "i32x4.min_u",
+            Simd(I32x4MaxS) => "i32x4.max_s",
+            Simd(I32x4MaxU) => "i32x4.max_u",
+            Simd(I32x4DotI16x8S) => "i32x4.dot_i16x8_s",
+            Simd(I32x4ExtmulLowI16x8S) => "i32x4.extmul_low_i16x8_s",
+            Simd(I32x4ExtmulHighI16x8S) => "i32x4.extmul_high_i16x8_s",
+            Simd(I32x4ExtmulLowI16x8U) => "i32x4.extmul_low_i16x8_u",
+            Simd(I32x4ExtmulHighI16x8U) => "i32x4.extmul_high_i16x8_u",
+            Simd(I64x2Abs) => "i64x2.abs",
+            Simd(I64x2Neg) => "i64x2.neg",
+            Simd(I64x2AllTrue) => "i64x2.all_true",
+            Simd(I64x2Bitmask) => "i64x2.bitmask",
+            Simd(I64x2ExtendLowI32x4S) => "i64x2.extend_low_i32x4_s",
+            Simd(I64x2ExtendHighI32x4S) => "i64x2.extend_high_i32x4_s",
+            Simd(I64x2ExtendLowI32x4U) => "i64x2.extend_low_i32x4_u",
+            Simd(I64x2ExtendHighI32x4U) => "i64x2.extend_high_i32x4_u",
+            Simd(I64x2Shl) => "i64x2.shl",
+            Simd(I64x2ShrS) => "i64x2.shr_s",
+            Simd(I64x2ShrU) => "i64x2.shr_u",
+            Simd(I64x2Add) => "i64x2.add",
+            Simd(I64x2Sub) => "i64x2.sub",
+            Simd(I64x2Mul) => "i64x2.mul",
+            Simd(I64x2Eq) => "i64x2.eq",
+            Simd(I64x2Ne) => "i64x2.ne",
+            Simd(I64x2LtS) => "i64x2.lt_s",
+            Simd(I64x2GtS) => "i64x2.gt_s",
+            Simd(I64x2LeS) => "i64x2.le_s",
+            Simd(I64x2GeS) => "i64x2.ge_s",
+            Simd(I64x2ExtmulLowI32x4S) => "i64x2.extmul_low_i32x4_s",
+            Simd(I64x2ExtmulHighI32x4S) => "i64x2.extmul_high_i32x4_s",
+            Simd(I64x2ExtmulLowI32x4U) => "i64x2.extmul_low_i32x4_u",
+            Simd(I64x2ExtmulHighI32x4U) => "i64x2.extmul_high_i32x4_u",
+            Simd(F32x4Abs) => "f32x4.abs",
+            Simd(F32x4Neg) => "f32x4.neg",
+            Simd(F32x4Sqrt) => "f32x4.sqrt",
+            Simd(F32x4Add) => "f32x4.add",
+            Simd(F32x4Sub) => "f32x4.sub",
+            Simd(F32x4Mul) => "f32x4.mul",
+            Simd(F32x4Div) => "f32x4.div",
+            Simd(F32x4Min) => "f32x4.min",
+            Simd(F32x4Max) => "f32x4.max",
+            Simd(F32x4Pmin) => "f32x4.pmin",
+            Simd(F32x4Pmax) => "f32x4.pmax",
+            Simd(F64x2Abs) => "f64x2.abs",
+            Simd(F64x2Neg) => "f64x2.neg",
+            Simd(F64x2Sqrt) => "f64x2.sqrt",
+            Simd(F64x2Add) => "f64x2.add",
+            Simd(F64x2Sub) => "f64x2.sub",
+            Simd(F64x2Mul) => "f64x2.mul",
+            Simd(F64x2Div) => "f64x2.div",
+            Simd(F64x2Min) => "f64x2.min",
+            Simd(F64x2Max) => "f64x2.max",
+            Simd(F64x2Pmin) => "f64x2.pmin",
+            Simd(F64x2Pmax) => "f64x2.pmax",
+            Simd(I32x4TruncSatF32x4S) => "i32x4.trunc_sat_f32x4_s",
+            Simd(I32x4TruncSatF32x4U) => "i32x4.trunc_sat_f32x4_u",
+            Simd(F32x4ConvertI32x4S) => "f32x4.convert_i32x4_s",
+            Simd(F32x4ConvertI32x4U) => "f32x4.convert_i32x4_u",
+            Simd(I32x4TruncSatF64x2SZero) => "i32x4.trunc_sat_f64x2_s_zero",
+            Simd(I32x4TruncSatF64x2UZero) => "i32x4.trunc_sat_f64x2_u_zero",
+            Simd(F64x2ConvertLowI32x4S) => "f64x2.convert_low_i32x4_s",
+            Simd(F64x2ConvertLowI32x4U) => "f64x2.convert_low_i32x4_u",
+            SimdLane(I8x16ExtractLaneS, _) => "i8x16.extract_lane_s",
+            SimdLane(I8x16ExtractLaneU, _) => "i8x16.extract_lane_u",
+            SimdLane(I8x16ReplaceLane, _) => "i8x16.replace_lane",
+            SimdLane(I16x8ExtractLaneS, _) => "i16x8.extract_lane_s",
+            SimdLane(I16x8ExtractLaneU, _) => "i16x8.extract_lane_u",
+            SimdLane(I16x8ReplaceLane, _) => "i16x8.replace_lane",
+            SimdLane(I32x4ExtractLane, _) => "i32x4.extract_lane",
+            SimdLane(I32x4ReplaceLane, _) => "i32x4.replace_lane",
+            SimdLane(I64x2ExtractLane, _) => "i64x2.extract_lane",
+            SimdLane(I64x2ReplaceLane, _) => "i64x2.replace_lane",
+            SimdLane(F32x4ExtractLane, _) => "f32x4.extract_lane",
+            SimdLane(F32x4ReplaceLane, _) => "f32x4.replace_lane",
+            SimdLane(F64x2ExtractLane, _) => "f64x2.extract_lane",
+            SimdLane(F64x2ReplaceLane, _) => "f64x2.replace_lane",
+            Shuffle(_) => "i8x16.shuffle",
+            MemoryAtomicNotify(_) => "memory.atomic.notify",
+            MemoryAtomicWait32(_) => "memory.atomic.wait32",
+            MemoryAtomicWait64(_) => "memory.atomic.wait64",
+            AtomicFence => "atomic.fence",
+            AtomicLoad(AtomicLoadOp::I32AtomicLoad, _) => "i32.atomic.load",
+            AtomicLoad(AtomicLoadOp::I64AtomicLoad, _) => "i64.atomic.load",
+            AtomicLoad(AtomicLoadOp::I32AtomicLoad8U, _) => "i32.atomic.load8_u",
+            AtomicLoad(AtomicLoadOp::I32AtomicLoad16U, _) => "i32.atomic.load16_u",
+            AtomicLoad(AtomicLoadOp::I64AtomicLoad8U, _) => "i64.atomic.load8_u",
+            AtomicLoad(AtomicLoadOp::I64AtomicLoad16U, _) => "i64.atomic.load16_u",
+            AtomicLoad(AtomicLoadOp::I64AtomicLoad32U, _) => "i64.atomic.load32_u",
+            AtomicStore(AtomicStoreOp::I32AtomicStore, _) => "i32.atomic.store",
+            AtomicStore(AtomicStoreOp::I64AtomicStore, _) => "i64.atomic.store",
+            AtomicStore(AtomicStoreOp::I32AtomicStore8, _) => "i32.atomic.store8",
+            AtomicStore(AtomicStoreOp::I32AtomicStore16, _) => "i32.atomic.store16",
+            AtomicStore(AtomicStoreOp::I64AtomicStore8, _) => "i64.atomic.store8",
+            AtomicStore(AtomicStoreOp::I64AtomicStore16, _) => "i64.atomic.store16",
+            AtomicStore(AtomicStoreOp::I64AtomicStore32, _) => "i64.atomic.store32",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwAdd, _) => "i32.atomic.rmw.add",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwAdd, _) => "i64.atomic.rmw.add",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8AddU, _) => "i32.atomic.rmw8.add_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16AddU, _) => "i32.atomic.rmw16.add_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8AddU, _) => "i64.atomic.rmw8.add_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16AddU, _) => "i64.atomic.rmw16.add_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32AddU, _) => "i64.atomic.rmw32.add_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwSub, _) => "i32.atomic.rmw.sub",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwSub, _) => "i64.atomic.rmw.sub",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8SubU, _) => "i32.atomic.rmw8.sub_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16SubU, _) => "i32.atomic.rmw16.sub_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8SubU, _) => "i64.atomic.rmw8.sub_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16SubU, _) => "i64.atomic.rmw16.sub_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32SubU, _) => "i64.atomic.rmw32.sub_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwAnd, _) => "i32.atomic.rmw.and",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwAnd, _) => "i64.atomic.rmw.and",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8AndU, _) => "i32.atomic.rmw8.and_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16AndU, _) => "i32.atomic.rmw16.and_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8AndU, _) => "i64.atomic.rmw8.and_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16AndU, _) => "i64.atomic.rmw16.and_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32AndU, _) => "i64.atomic.rmw32.and_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwOr, _) => "i32.atomic.rmw.or",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwOr, _) => "i64.atomic.rmw.or",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8OrU, _) => "i32.atomic.rmw8.or_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16OrU, _) => "i32.atomic.rmw16.or_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8OrU, _) => "i64.atomic.rmw8.or_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16OrU, _) => "i64.atomic.rmw16.or_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32OrU, _) => "i64.atomic.rmw32.or_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwXor, _) => "i32.atomic.rmw.xor",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwXor, _) => "i64.atomic.rmw.xor",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8XorU, _) => "i32.atomic.rmw8.xor_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16XorU, _) => "i32.atomic.rmw16.xor_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8XorU, _) => "i64.atomic.rmw8.xor_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16XorU, _) => "i64.atomic.rmw16.xor_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32XorU, _) => "i64.atomic.rmw32.xor_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmwXchg, _) => "i32.atomic.rmw.xchg",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmwXchg, _) => "i64.atomic.rmw.xchg",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw8XchgU, _) => "i32.atomic.rmw8.xchg_u",
+            AtomicRmw(AtomicRmwOp::I32AtomicRmw16XchgU, _) => "i32.atomic.rmw16.xchg_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw8XchgU, _) => "i64.atomic.rmw8.xchg_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw16XchgU, _) => "i64.atomic.rmw16.xchg_u",
+            AtomicRmw(AtomicRmwOp::I64AtomicRmw32XchgU, _) => "i64.atomic.rmw32.xchg_u",
+            AtomicCmpxchg(AtomicCmpxchgOp::I32AtomicRmwCmpxchg, _) => "i32.atomic.rmw.cmpxchg",
+            AtomicCmpxchg(AtomicCmpxchgOp::I64AtomicRmwCmpxchg, _) => "i64.atomic.rmw.cmpxchg",
+            AtomicCmpxchg(AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU, _) => "i32.atomic.rmw8.cmpxchg_u",
+            AtomicCmpxchg(AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU, _) => "i32.atomic.rmw16.cmpxchg_u",
+            AtomicCmpxchg(AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU, _) => "i64.atomic.rmw8.cmpxchg_u",
+            AtomicCmpxchg(AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU, _) => "i64.atomic.rmw16.cmpxchg_u",
+            AtomicCmpxchg(AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU, _) => "i64.atomic.rmw32.cmpxchg_u",
         }
     }
 }
@@ -632,8 +1921,11 @@ impl Module {
             code: Some(Code {
                 locals,
                 body,
+                original_offsets: None,
             }),
             export: Vec::new(),
+            name: None,
+            local_names: BTreeMap::new(),
         });
         (self.functions.len() - 1).into()
     }
@@ -644,6 +1936,8 @@ impl Module {
             import: Some((module, name)),
             code: None,
             export: Vec::new(),
+            name: None,
+            local_names: BTreeMap::new(),
         });
         (self.functions.len() - 1).into()
     }
@@ -658,11 +1952,44 @@ impl Module {
         (self.globals.len() - 1).into()
     }
 
+    /// memory64 proposal: I64 if the memory is addressed with 64-bit addresses, otherwise I32
+    pub fn memory_index_type(&self, idx: Idx<Memory>) -> ValType {
+        (self.memories[idx.0].type_.0).index_type
+    }
+
     pub fn function(&mut self, idx: Idx<Function>) -> &mut Function { &mut self.functions[idx.0] }
     pub fn functions(&mut self) -> impl Iterator<Item=(Idx<Function>, &mut Function)> {
         self.functions.iter_mut().enumerate().map(|(i, f)| (i.into(), f))
     }
 
+    /// adds an empty producers section if there is none yet
+    pub fn producers_mut(&mut self) -> &mut ProducersSection {
+        let idx = match self.custom_sections.iter().position(|(_, section)| match section {
+            CustomSection::Producers(_) => true,
+            _ => false,
+        }) {
+            Some(idx) => idx,
+            None => {
+                self.custom_sections.push((SectionPosition::Last, CustomSection::Producers(ProducersSection::default())));
+                self.custom_sections.len() - 1
+            }
+        };
+        match &mut self.custom_sections[idx].1 {
+            CustomSection::Producers(producers) => producers,
+            _ => unreachable!(),
+        }
+    }
+
+    /// first custom section that was parsed as type T (see custom_section::CustomSectionRegistry)
+    pub fn custom_section<T: CustomSectionType>(&self) -> Option<&T> {
+        self.custom_sections.iter()
+            .filter_map(|(_, section)| match section {
+                CustomSection::Parsed(parsed) => parsed.as_any().downcast_ref::<T>(),
+                _ => None,
+            })
+            .next()
+    }
+
     pub fn types(&self) -> HashSet<&FunctionType> {
         let mut types = HashSet::new();
         for function in &self.functions {
@@ -678,7 +2005,8 @@ impl Function {
     }
 
     pub fn modify_instr(&mut self, f: impl Fn(Instr) -> Vec<Instr>) {
-        if let Some(Code { ref mut body, .. }) = self.code {
+        if let Some(Code { ref mut body, ref mut original_offsets, .. }) = self.code {
+            *original_offsets = None;
             let new_body = Vec::with_capacity(body.len());
             let old_body = ::std::mem::replace(body, new_body);
             for instr in old_body.into_iter() {
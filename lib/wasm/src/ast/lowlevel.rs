@@ -14,7 +14,7 @@ pub struct WithSize<T>(pub T);
 
 #[derive(WasmBinary, Debug, Clone)]
 pub enum Section {
-    #[tag = 0] Custom(Vec<u8>),
+    #[tag = 0] Custom(CustomSection),
     #[tag = 1] Type(WithSize<Vec<FunctionType>>),
     #[tag = 2] Import(WithSize<Vec<Import>>),
     #[tag = 3] Function(WithSize<Vec<Idx<FunctionType>>>),
@@ -26,6 +26,91 @@ pub enum Section {
     #[tag = 9] Element(WithSize<Vec<Element>>),
     #[tag = 10] Code(WithSize<Vec<WithSize<Code>>>),
     #[tag = 11] Data(WithSize<Vec<Data>>),
+    /// number of data segments, needed for single-pass validation of memory.init and data.drop
+    #[tag = 12] DataCount(WithSize<u32>),
+    // exception handling proposal, between Memory and Global
+    #[tag = 13] Tag(WithSize<Vec<TagType>>),
+}
+
+impl Section {
+    /// None for custom sections
+    pub fn id(&self) -> Option<SectionId> {
+        match self {
+            Section::Custom(_) => None,
+            Section::Type(_) => Some(SectionId::Type),
+            Section::Import(_) => Some(SectionId::Import),
+            Section::Function(_) => Some(SectionId::Function),
+            Section::Table(_) => Some(SectionId::Table),
+            Section::Memory(_) => Some(SectionId::Memory),
+            Section::Global(_) => Some(SectionId::Global),
+            Section::Export(_) => Some(SectionId::Export),
+            Section::Start(_) => Some(SectionId::Start),
+            Section::Element(_) => Some(SectionId::Element),
+            Section::Code(_) => Some(SectionId::Code),
+            Section::Data(_) => Some(SectionId::Data),
+            Section::DataCount(_) => Some(SectionId::DataCount),
+            Section::Tag(_) => Some(SectionId::Tag),
+        }
+    }
+}
+
+/* Custom sections */
+
+#[derive(Debug, Clone)]
+pub enum CustomSection {
+    Name(NameSection),
+    Producers(ProducersSection),
+    TargetFeatures(TargetFeaturesSection),
+    SourceMappingUrl(String),
+    Raw(RawCustomSection),
+}
+
+impl CustomSection {
+    pub fn name(&self) -> &str {
+        match self {
+            CustomSection::Name(_) => "name",
+            CustomSection::Producers(_) => "producers",
+            CustomSection::TargetFeatures(_) => "target_features",
+            CustomSection::SourceMappingUrl(_) => "sourceMappingURL",
+            CustomSection::Raw(section) => &section.name,
+        }
+    }
+}
+
+/// see https://github.com/WebAssembly/design/blob/master/BinaryEncoding.md#name-section
+#[derive(Debug, Clone, Default)]
+pub struct NameSection {
+    pub subsections: Vec<NameSubSection>,
+}
+
+/// needs manual WasmBinary impl, because of the fallback for unknown subsection ids
+#[derive(Debug, Clone)]
+pub enum NameSubSection {
+    Module(WithSize<String>),
+    Function(WithSize<Vec<FunctionName>>),
+    Local(WithSize<Vec<LocalNames>>),
+    /// id and content of any other subsection, e.g., label, type, or global names from the extended
+    /// name section proposal. Not converted to the high-level AST, since the indices they refer to
+    /// may change during instrumentation.
+    Unknown(u8, Vec<u8>),
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct FunctionName {
+    pub function_idx: Idx<Function>,
+    pub name: String,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct LocalNames {
+    pub function_idx: Idx<Function>,
+    pub names: Vec<LocalName>,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct LocalName {
+    pub local_idx: Idx<Local>,
+    pub name: String,
 }
 
 #[derive(WasmBinary, Debug, Clone)]
@@ -34,22 +119,46 @@ pub struct Global {
     pub init: Expr,
 }
 
-#[derive(WasmBinary, Debug, Clone)]
+/// exception handling proposal, the parameters of the tag type are the values thrown with it
+#[derive(WasmBinary, Debug, Copy, Clone)]
+pub enum TagType {
+    // attribute, the only one so far
+    #[tag = 0x00] Exception(Idx<FunctionType>),
+}
+
+/// manual WasmBinary impl, because the mode is encoded as bit flags (see binary.rs)
+#[derive(Debug, Clone)]
 pub struct Element {
-    // always 0x00 in WASM version 1
-    pub table_idx: Idx<Table>,
-    pub offset: Expr,
-    pub init: Vec<Idx<Function>>,
+    pub mode: ElementMode,
+    pub init: ElementItems,
 }
 
-#[derive(WasmBinary, Debug, Clone)]
+#[derive(Debug, Clone)]
+pub enum ElementMode {
+    Active(Idx<Table>, Expr),
+    Passive,
+    Declarative,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementItems {
+    Functions(Vec<Idx<Function>>),
+    Expressions(ElemType, Vec<Expr>),
+}
+
+/// manual WasmBinary impl, because the mode is encoded as bit flags (see binary.rs)
+#[derive(Debug, Clone)]
 pub struct Data {
-    // always 0x00 in WASM version 1
-    pub memory_idx: Idx<Memory>,
-    pub offset: Expr,
+    pub mode: DataMode,
     pub init: Vec<u8>,
 }
 
+#[derive(Debug, Clone)]
+pub enum DataMode {
+    Active(Idx<Memory>, Expr),
+    Passive,
+}
+
 #[derive(WasmBinary, Debug, Clone)]
 pub struct Import {
     pub module: String,
@@ -69,6 +178,7 @@ pub enum ImportType {
     #[tag = 0x1] Table(TableType),
     #[tag = 0x2] Memory(MemoryType),
     #[tag = 0x3] Global(GlobalType),
+    #[tag = 0x4] Tag(TagType),
 }
 
 #[derive(WasmBinary, Debug, Clone)]
@@ -77,6 +187,7 @@ pub enum ExportType {
     #[tag = 0x1] Table(Idx<Table>),
     #[tag = 0x2] Memory(Idx<Memory>),
     #[tag = 0x3] Global(Idx<Global>),
+    #[tag = 0x4] Tag(Idx<Tag>),
 }
 
 // Markers for Idx<T>, since in low-level format Function, Table, and Memory have not one type,
@@ -91,6 +202,9 @@ pub struct Table;
 #[derive(Debug)]
 pub struct Memory;
 
+#[derive(Debug)]
+pub struct Tag;
+
 
 /* Code */
 
@@ -106,8 +220,29 @@ pub struct Locals {
     pub type_: ValType,
 }
 
+/// second field: byte offset of each instruction in the module it was decoded from (if known, i.e.,
+/// only when decoding a whole Module), not encoded
 #[derive(Debug, Clone)]
-pub struct Expr(pub Vec<Instr>);
+pub struct Expr(pub Vec<Instr>, pub Option<Vec<usize>>);
+
+/// see binary.rs for the encoding, which has no tag but relies on the three cases being disjoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockType {
+    Empty,
+    Value(ValType),
+    /// with multi-value: blocks with parameters or more than one result
+    Index(Idx<FunctionType>),
+}
+
+/// see binary.rs for the encoding, where the memory index is only present if it is not 0
+#[derive(Debug, Copy, Clone)]
+pub struct Memarg {
+    pub alignment: u32,
+    /// multi-memory proposal
+    pub memory: Idx<Memory>,
+    /// memory64 proposal: 64-bit offsets
+    pub offset: u64,
+}
 
 #[derive(WasmBinary, Debug, Clone)]
 pub enum Instr {
@@ -119,6 +254,12 @@ pub enum Instr {
     #[tag = 0x03] Loop(BlockType),
     #[tag = 0x04] If(BlockType),
     #[tag = 0x05] Else,
+    // exception handling proposal, catch and catch_all end the previous try/catch body like else,
+    // delegate ends the try block like end
+    #[tag = 0x06] Try(BlockType),
+    #[tag = 0x07] Catch(Idx<Tag>),
+    #[tag = 0x08] Throw(Idx<Tag>),
+    #[tag = 0x09] Rethrow(Idx<Label>),
     #[tag = 0x0b] End,
 
     #[tag = 0x0c] Br(Idx<Label>),
@@ -127,16 +268,25 @@ pub enum Instr {
 
     #[tag = 0x0f] Return,
     #[tag = 0x10] Call(Idx<Function>),
-    #[tag = 0x11] CallIndirect(Idx<FunctionType>, /* unused, always 0x00 in WASM version 1 */ Idx<Table>),
+    #[tag = 0x11] CallIndirect(Idx<FunctionType>, /* always 0x00 without reference types */ Idx<Table>),
+    // tail call proposal
+    #[tag = 0x12] ReturnCall(Idx<Function>),
+    #[tag = 0x13] ReturnCallIndirect(Idx<FunctionType>, Idx<Table>),
+    #[tag = 0x18] Delegate(Idx<Label>),
+    #[tag = 0x19] CatchAll,
 
     #[tag = 0x1a] Drop,
     #[tag = 0x1b] Select,
+    // reference types proposal, the vector must contain exactly one type (checked in Expr::decode)
+    #[tag = 0x1c] TypedSelect(Vec<ValType>),
 
     #[tag = 0x20] GetLocal(Idx<Local>),
     #[tag = 0x21] SetLocal(Idx<Local>),
     #[tag = 0x22] TeeLocal(Idx<Local>),
     #[tag = 0x23] GetGlobal(Idx<Global>),
     #[tag = 0x24] SetGlobal(Idx<Global>),
+    #[tag = 0x25] TableGet(Idx<Table>),
+    #[tag = 0x26] TableSet(Idx<Table>),
 
     #[tag = 0x28] I32Load(Memarg),
     #[tag = 0x29] I64Load(Memarg),
@@ -162,8 +312,8 @@ pub enum Instr {
     #[tag = 0x3d] I64Store16(Memarg),
     #[tag = 0x3e] I64Store32(Memarg),
 
-    #[tag = 0x3f] MemorySize(/* unused, always 0x00 in WASM version 1 */ Idx<Memory>),
-    #[tag = 0x40] MemoryGrow(/* unused, always 0x00 in WASM version 1 */ Idx<Memory>),
+    #[tag = 0x3f] MemorySize(/* always 0x00 without multi-memory */ Idx<Memory>),
+    #[tag = 0x40] MemoryGrow(/* always 0x00 without multi-memory */ Idx<Memory>),
 
     #[tag = 0x41] I32Const(i32),
     #[tag = 0x42] I64Const(i64),
@@ -293,4 +443,347 @@ pub enum Instr {
     #[tag = 0xbd] I64ReinterpretF64,
     #[tag = 0xbe] F32ReinterpretI32,
     #[tag = 0xbf] F64ReinterpretI64,
+
+    // sign-extension operators proposal
+    #[tag = 0xc0] I32Extend8S,
+    #[tag = 0xc1] I32Extend16S,
+    #[tag = 0xc2] I64Extend8S,
+    #[tag = 0xc3] I64Extend16S,
+    #[tag = 0xc4] I64Extend32S,
+
+    // reference types proposal
+    #[tag = 0xd0] RefNull(ElemType),
+    #[tag = 0xd1] RefIsNull,
+    #[tag = 0xd2] RefFunc(Idx<Function>),
+
+    // non-trapping float-to-int conversions proposal
+    #[prefix = 0xfc] #[tag = 0x00] I32TruncSatSF32,
+    #[prefix = 0xfc] #[tag = 0x01] I32TruncSatUF32,
+    #[prefix = 0xfc] #[tag = 0x02] I32TruncSatSF64,
+    #[prefix = 0xfc] #[tag = 0x03] I32TruncSatUF64,
+    #[prefix = 0xfc] #[tag = 0x04] I64TruncSatSF32,
+    #[prefix = 0xfc] #[tag = 0x05] I64TruncSatUF32,
+    #[prefix = 0xfc] #[tag = 0x06] I64TruncSatSF64,
+    #[prefix = 0xfc] #[tag = 0x07] I64TruncSatUF64,
+
+    // bulk memory operations proposal
+    #[prefix = 0xfc] #[tag = 0x08] MemoryInit(Idx<Data>, /* always 0x00 without multi-memory */ Idx<Memory>),
+    #[prefix = 0xfc] #[tag = 0x09] DataDrop(Idx<Data>),
+    #[prefix = 0xfc] #[tag = 0x0a] MemoryCopy(/* destination */ Idx<Memory>, /* source */ Idx<Memory>),
+    #[prefix = 0xfc] #[tag = 0x0b] MemoryFill(Idx<Memory>),
+    #[prefix = 0xfc] #[tag = 0x0c] TableInit(Idx<Element>, Idx<Table>),
+    #[prefix = 0xfc] #[tag = 0x0d] ElemDrop(Idx<Element>),
+    #[prefix = 0xfc] #[tag = 0x0e] TableCopy(/* destination */ Idx<Table>, /* source */ Idx<Table>),
+
+    // reference types proposal
+    #[prefix = 0xfc] #[tag = 0x0f] TableGrow(Idx<Table>),
+    #[prefix = 0xfc] #[tag = 0x10] TableSize(Idx<Table>),
+    #[prefix = 0xfc] #[tag = 0x11] TableFill(Idx<Table>),
+
+    // SIMD proposal, all u8 immediates are lane indices
+    #[prefix = 0xfd] #[tag = 0x00] V128Load(Memarg),
+    #[prefix = 0xfd] #[tag = 0x01] V128Load8x8S(Memarg),
+    #[prefix = 0xfd] #[tag = 0x02] V128Load8x8U(Memarg),
+    #[prefix = 0xfd] #[tag = 0x03] V128Load16x4S(Memarg),
+    #[prefix = 0xfd] #[tag = 0x04] V128Load16x4U(Memarg),
+    #[prefix = 0xfd] #[tag = 0x05] V128Load32x2S(Memarg),
+    #[prefix = 0xfd] #[tag = 0x06] V128Load32x2U(Memarg),
+    #[prefix = 0xfd] #[tag = 0x07] V128Load8Splat(Memarg),
+    #[prefix = 0xfd] #[tag = 0x08] V128Load16Splat(Memarg),
+    #[prefix = 0xfd] #[tag = 0x09] V128Load32Splat(Memarg),
+    #[prefix = 0xfd] #[tag = 0x0a] V128Load64Splat(Memarg),
+    #[prefix = 0xfd] #[tag = 0x0b] V128Store(Memarg),
+    #[prefix = 0xfd] #[tag = 0x0c] V128Const(u128),
+    #[prefix = 0xfd] #[tag = 0x0d] I8x16Shuffle([u8; 16]),
+    #[prefix = 0xfd] #[tag = 0x0e] I8x16Swizzle,
+    #[prefix = 0xfd] #[tag = 0x0f] I8x16Splat,
+    #[prefix = 0xfd] #[tag = 0x10] I16x8Splat,
+    #[prefix = 0xfd] #[tag = 0x11] I32x4Splat,
+    #[prefix = 0xfd] #[tag = 0x12] I64x2Splat,
+    #[prefix = 0xfd] #[tag = 0x13] F32x4Splat,
+    #[prefix = 0xfd] #[tag = 0x14] F64x2Splat,
+    #[prefix = 0xfd] #[tag = 0x15] I8x16ExtractLaneS(u8),
+    #[prefix = 0xfd] #[tag = 0x16] I8x16ExtractLaneU(u8),
+    #[prefix = 0xfd] #[tag = 0x17] I8x16ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x18] I16x8ExtractLaneS(u8),
+    #[prefix = 0xfd] #[tag = 0x19] I16x8ExtractLaneU(u8),
+    #[prefix = 0xfd] #[tag = 0x1a] I16x8ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x1b] I32x4ExtractLane(u8),
+    #[prefix = 0xfd] #[tag = 0x1c] I32x4ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x1d] I64x2ExtractLane(u8),
+    #[prefix = 0xfd] #[tag = 0x1e] I64x2ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x1f] F32x4ExtractLane(u8),
+    #[prefix = 0xfd] #[tag = 0x20] F32x4ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x21] F64x2ExtractLane(u8),
+    #[prefix = 0xfd] #[tag = 0x22] F64x2ReplaceLane(u8),
+    #[prefix = 0xfd] #[tag = 0x23] I8x16Eq,
+    #[prefix = 0xfd] #[tag = 0x24] I8x16Ne,
+    #[prefix = 0xfd] #[tag = 0x25] I8x16LtS,
+    #[prefix = 0xfd] #[tag = 0x26] I8x16LtU,
+    #[prefix = 0xfd] #[tag = 0x27] I8x16GtS,
+    #[prefix = 0xfd] #[tag = 0x28] I8x16GtU,
+    #[prefix = 0xfd] #[tag = 0x29] I8x16LeS,
+    #[prefix = 0xfd] #[tag = 0x2a] I8x16LeU,
+    #[prefix = 0xfd] #[tag = 0x2b] I8x16GeS,
+    #[prefix = 0xfd] #[tag = 0x2c] I8x16GeU,
+    #[prefix = 0xfd] #[tag = 0x2d] I16x8Eq,
+    #[prefix = 0xfd] #[tag = 0x2e] I16x8Ne,
+    #[prefix = 0xfd] #[tag = 0x2f] I16x8LtS,
+    #[prefix = 0xfd] #[tag = 0x30] I16x8LtU,
+    #[prefix = 0xfd] #[tag = 0x31] I16x8GtS,
+    #[prefix = 0xfd] #[tag = 0x32] I16x8GtU,
+    #[prefix = 0xfd] #[tag = 0x33] I16x8LeS,
+    #[prefix = 0xfd] #[tag = 0x34] I16x8LeU,
+    #[prefix = 0xfd] #[tag = 0x35] I16x8GeS,
+    #[prefix = 0xfd] #[tag = 0x36] I16x8GeU,
+    #[prefix = 0xfd] #[tag = 0x37] I32x4Eq,
+    #[prefix = 0xfd] #[tag = 0x38] I32x4Ne,
+    #[prefix = 0xfd] #[tag = 0x39] I32x4LtS,
+    #[prefix = 0xfd] #[tag = 0x3a] I32x4LtU,
+    #[prefix = 0xfd] #[tag = 0x3b] I32x4GtS,
+    #[prefix = 0xfd] #[tag = 0x3c] I32x4GtU,
+    #[prefix = 0xfd] #[tag = 0x3d] I32x4LeS,
+    #[prefix = 0xfd] #[tag = 0x3e] I32x4LeU,
+    #[prefix = 0xfd] #[tag = 0x3f] I32x4GeS,
+    #[prefix = 0xfd] #[tag = 0x40] I32x4GeU,
+    #[prefix = 0xfd] #[tag = 0x41] F32x4Eq,
+    #[prefix = 0xfd] #[tag = 0x42] F32x4Ne,
+    #[prefix = 0xfd] #[tag = 0x43] F32x4Lt,
+    #[prefix = 0xfd] #[tag = 0x44] F32x4Gt,
+    #[prefix = 0xfd] #[tag = 0x45] F32x4Le,
+    #[prefix = 0xfd] #[tag = 0x46] F32x4Ge,
+    #[prefix = 0xfd] #[tag = 0x47] F64x2Eq,
+    #[prefix = 0xfd] #[tag = 0x48] F64x2Ne,
+    #[prefix = 0xfd] #[tag = 0x49] F64x2Lt,
+    #[prefix = 0xfd] #[tag = 0x4a] F64x2Gt,
+    #[prefix = 0xfd] #[tag = 0x4b] F64x2Le,
+    #[prefix = 0xfd] #[tag = 0x4c] F64x2Ge,
+    #[prefix = 0xfd] #[tag = 0x4d] V128Not,
+    #[prefix = 0xfd] #[tag = 0x4e] V128And,
+    #[prefix = 0xfd] #[tag = 0x4f] V128Andnot,
+    #[prefix = 0xfd] #[tag = 0x50] V128Or,
+    #[prefix = 0xfd] #[tag = 0x51] V128Xor,
+    #[prefix = 0xfd] #[tag = 0x52] V128Bitselect,
+    #[prefix = 0xfd] #[tag = 0x53] V128AnyTrue,
+    #[prefix = 0xfd] #[tag = 0x54] V128Load8Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x55] V128Load16Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x56] V128Load32Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x57] V128Load64Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x58] V128Store8Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x59] V128Store16Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x5a] V128Store32Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x5b] V128Store64Lane(Memarg, u8),
+    #[prefix = 0xfd] #[tag = 0x5c] V128Load32Zero(Memarg),
+    #[prefix = 0xfd] #[tag = 0x5d] V128Load64Zero(Memarg),
+    #[prefix = 0xfd] #[tag = 0x5e] F32x4DemoteF64x2Zero,
+    #[prefix = 0xfd] #[tag = 0x5f] F64x2PromoteLowF32x4,
+    #[prefix = 0xfd] #[tag = 0x60] I8x16Abs,
+    #[prefix = 0xfd] #[tag = 0x61] I8x16Neg,
+    #[prefix = 0xfd] #[tag = 0x62] I8x16Popcnt,
+    #[prefix = 0xfd] #[tag = 0x63] I8x16AllTrue,
+    #[prefix = 0xfd] #[tag = 0x64] I8x16Bitmask,
+    #[prefix = 0xfd] #[tag = 0x65] I8x16NarrowI16x8S,
+    #[prefix = 0xfd] #[tag = 0x66] I8x16NarrowI16x8U,
+    #[prefix = 0xfd] #[tag = 0x67] F32x4Ceil,
+    #[prefix = 0xfd] #[tag = 0x68] F32x4Floor,
+    #[prefix = 0xfd] #[tag = 0x69] F32x4Trunc,
+    #[prefix = 0xfd] #[tag = 0x6a] F32x4Nearest,
+    #[prefix = 0xfd] #[tag = 0x6b] I8x16Shl,
+    #[prefix = 0xfd] #[tag = 0x6c] I8x16ShrS,
+    #[prefix = 0xfd] #[tag = 0x6d] I8x16ShrU,
+    #[prefix = 0xfd] #[tag = 0x6e] I8x16Add,
+    #[prefix = 0xfd] #[tag = 0x6f] I8x16AddSatS,
+    #[prefix = 0xfd] #[tag = 0x70] I8x16AddSatU,
+    #[prefix = 0xfd] #[tag = 0x71] I8x16Sub,
+    #[prefix = 0xfd] #[tag = 0x72] I8x16SubSatS,
+    #[prefix = 0xfd] #[tag = 0x73] I8x16SubSatU,
+    #[prefix = 0xfd] #[tag = 0x74] F64x2Ceil,
+    #[prefix = 0xfd] #[tag = 0x75] F64x2Floor,
+    #[prefix = 0xfd] #[tag = 0x76] I8x16MinS,
+    #[prefix = 0xfd] #[tag = 0x77] I8x16MinU,
+    #[prefix = 0xfd] #[tag = 0x78] I8x16MaxS,
+    #[prefix = 0xfd] #[tag = 0x79] I8x16MaxU,
+    #[prefix = 0xfd] #[tag = 0x7a] F64x2Trunc,
+    #[prefix = 0xfd] #[tag = 0x7b] I8x16AvgrU,
+    #[prefix = 0xfd] #[tag = 0x7c] I16x8ExtaddPairwiseI8x16S,
+    #[prefix = 0xfd] #[tag = 0x7d] I16x8ExtaddPairwiseI8x16U,
+    #[prefix = 0xfd] #[tag = 0x7e] I32x4ExtaddPairwiseI16x8S,
+    #[prefix = 0xfd] #[tag = 0x7f] I32x4ExtaddPairwiseI16x8U,
+    #[prefix = 0xfd] #[tag = 0x80] I16x8Abs,
+    #[prefix = 0xfd] #[tag = 0x81] I16x8Neg,
+    #[prefix = 0xfd] #[tag = 0x82] I16x8Q15mulrSatS,
+    #[prefix = 0xfd] #[tag = 0x83] I16x8AllTrue,
+    #[prefix = 0xfd] #[tag = 0x84] I16x8Bitmask,
+    #[prefix = 0xfd] #[tag = 0x85] I16x8NarrowI32x4S,
+    #[prefix = 0xfd] #[tag = 0x86] I16x8NarrowI32x4U,
+    #[prefix = 0xfd] #[tag = 0x87] I16x8ExtendLowI8x16S,
+    #[prefix = 0xfd] #[tag = 0x88] I16x8ExtendHighI8x16S,
+    #[prefix = 0xfd] #[tag = 0x89] I16x8ExtendLowI8x16U,
+    #[prefix = 0xfd] #[tag = 0x8a] I16x8ExtendHighI8x16U,
+    #[prefix = 0xfd] #[tag = 0x8b] I16x8Shl,
+    #[prefix = 0xfd] #[tag = 0x8c] I16x8ShrS,
+    #[prefix = 0xfd] #[tag = 0x8d] I16x8ShrU,
+    #[prefix = 0xfd] #[tag = 0x8e] I16x8Add,
+    #[prefix = 0xfd] #[tag = 0x8f] I16x8AddSatS,
+    #[prefix = 0xfd] #[tag = 0x90] I16x8AddSatU,
+    #[prefix = 0xfd] #[tag = 0x91] I16x8Sub,
+    #[prefix = 0xfd] #[tag = 0x92] I16x8SubSatS,
+    #[prefix = 0xfd] #[tag = 0x93] I16x8SubSatU,
+    #[prefix = 0xfd] #[tag = 0x94] F64x2Nearest,
+    #[prefix = 0xfd] #[tag = 0x95] I16x8Mul,
+    #[prefix = 0xfd] #[tag = 0x96] I16x8MinS,
+    #[prefix = 0xfd] #[tag = 0x97] I16x8MinU,
+    #[prefix = 0xfd] #[tag = 0x98] I16x8MaxS,
+    #[prefix = 0xfd] #[tag = 0x99] I16x8MaxU,
+    #[prefix = 0xfd] #[tag = 0x9b] I16x8AvgrU,
+    #[prefix = 0xfd] #[tag = 0x9c] I16x8ExtmulLowI8x16S,
+    #[prefix = 0xfd] #[tag = 0x9d] I16x8ExtmulHighI8x16S,
+    #[prefix = 0xfd] #[tag = 0x9e] I16x8ExtmulLowI8x16U,
+    #[prefix = 0xfd] #[tag = 0x9f] I16x8ExtmulHighI8x16U,
+    #[prefix = 0xfd] #[tag = 0xa0] I32x4Abs,
+    #[prefix = 0xfd] #[tag = 0xa1] I32x4Neg,
+    #[prefix = 0xfd] #[tag = 0xa3] I32x4AllTrue,
+    #[prefix = 0xfd] #[tag = 0xa4] I32x4Bitmask,
+    #[prefix = 0xfd] #[tag = 0xa7] I32x4ExtendLowI16x8S,
+    #[prefix = 0xfd] #[tag = 0xa8] I32x4ExtendHighI16x8S,
+    #[prefix = 0xfd] #[tag = 0xa9] I32x4ExtendLowI16x8U,
+    #[prefix = 0xfd] #[tag = 0xaa] I32x4ExtendHighI16x8U,
+    #[prefix = 0xfd] #[tag = 0xab] I32x4Shl,
+    #[prefix = 0xfd] #[tag = 0xac] I32x4ShrS,
+    #[prefix = 0xfd] #[tag = 0xad] I32x4ShrU,
+    #[prefix = 0xfd] #[tag = 0xae] I32x4Add,
+    #[prefix = 0xfd] #[tag = 0xb1] I32x4Sub,
+    #[prefix = 0xfd] #[tag = 0xb5] I32x4Mul,
+    #[prefix = 0xfd] #[tag = 0xb6] I32x4MinS,
+    #[prefix = 0xfd] #[tag = 0xb7] I32x4MinU,
+    #[prefix = 0xfd] #[tag = 0xb8] I32x4MaxS,
+    #[prefix = 0xfd] #[tag = 0xb9] I32x4MaxU,
+    #[prefix = 0xfd] #[tag = 0xba] I32x4DotI16x8S,
+    #[prefix = 0xfd] #[tag = 0xbc] I32x4ExtmulLowI16x8S,
+    #[prefix = 0xfd] #[tag = 0xbd] I32x4ExtmulHighI16x8S,
+    #[prefix = 0xfd] #[tag = 0xbe] I32x4ExtmulLowI16x8U,
+    #[prefix = 0xfd] #[tag = 0xbf] I32x4ExtmulHighI16x8U,
+    #[prefix = 0xfd] #[tag = 0xc0] I64x2Abs,
+    #[prefix = 0xfd] #[tag = 0xc1] I64x2Neg,
+    #[prefix = 0xfd] #[tag = 0xc3] I64x2AllTrue,
+    #[prefix = 0xfd] #[tag = 0xc4] I64x2Bitmask,
+    #[prefix = 0xfd] #[tag = 0xc7] I64x2ExtendLowI32x4S,
+    #[prefix = 0xfd] #[tag = 0xc8] I64x2ExtendHighI32x4S,
+    #[prefix = 0xfd] #[tag = 0xc9] I64x2ExtendLowI32x4U,
+    #[prefix = 0xfd] #[tag = 0xca] I64x2ExtendHighI32x4U,
+    #[prefix = 0xfd] #[tag = 0xcb] I64x2Shl,
+    #[prefix = 0xfd] #[tag = 0xcc] I64x2ShrS,
+    #[prefix = 0xfd] #[tag = 0xcd] I64x2ShrU,
+    #[prefix = 0xfd] #[tag = 0xce] I64x2Add,
+    #[prefix = 0xfd] #[tag = 0xd1] I64x2Sub,
+    #[prefix = 0xfd] #[tag = 0xd5] I64x2Mul,
+    #[prefix = 0xfd] #[tag = 0xd6] I64x2Eq,
+    #[prefix = 0xfd] #[tag = 0xd7] I64x2Ne,
+    #[prefix = 0xfd] #[tag = 0xd8] I64x2LtS,
+    #[prefix = 0xfd] #[tag = 0xd9] I64x2GtS,
+    #[prefix = 0xfd] #[tag = 0xda] I64x2LeS,
+    #[prefix = 0xfd] #[tag = 0xdb] I64x2GeS,
+    #[prefix = 0xfd] #[tag = 0xdc] I64x2ExtmulLowI32x4S,
+    #[prefix = 0xfd] #[tag = 0xdd] I64x2ExtmulHighI32x4S,
+    #[prefix = 0xfd] #[tag = 0xde] I64x2ExtmulLowI32x4U,
+    #[prefix = 0xfd] #[tag = 0xdf] I64x2ExtmulHighI32x4U,
+    #[prefix = 0xfd] #[tag = 0xe0] F32x4Abs,
+    #[prefix = 0xfd] #[tag = 0xe1] F32x4Neg,
+    #[prefix = 0xfd] #[tag = 0xe3] F32x4Sqrt,
+    #[prefix = 0xfd] #[tag = 0xe4] F32x4Add,
+    #[prefix = 0xfd] #[tag = 0xe5] F32x4Sub,
+    #[prefix = 0xfd] #[tag = 0xe6] F32x4Mul,
+    #[prefix = 0xfd] #[tag = 0xe7] F32x4Div,
+    #[prefix = 0xfd] #[tag = 0xe8] F32x4Min,
+    #[prefix = 0xfd] #[tag = 0xe9] F32x4Max,
+    #[prefix = 0xfd] #[tag = 0xea] F32x4Pmin,
+    #[prefix = 0xfd] #[tag = 0xeb] F32x4Pmax,
+    #[prefix = 0xfd] #[tag = 0xec] F64x2Abs,
+    #[prefix = 0xfd] #[tag = 0xed] F64x2Neg,
+    #[prefix = 0xfd] #[tag = 0xef] F64x2Sqrt,
+    #[prefix = 0xfd] #[tag = 0xf0] F64x2Add,
+    #[prefix = 0xfd] #[tag = 0xf1] F64x2Sub,
+    #[prefix = 0xfd] #[tag = 0xf2] F64x2Mul,
+    #[prefix = 0xfd] #[tag = 0xf3] F64x2Div,
+    #[prefix = 0xfd] #[tag = 0xf4] F64x2Min,
+    #[prefix = 0xfd] #[tag = 0xf5] F64x2Max,
+    #[prefix = 0xfd] #[tag = 0xf6] F64x2Pmin,
+    #[prefix = 0xfd] #[tag = 0xf7] F64x2Pmax,
+    #[prefix = 0xfd] #[tag = 0xf8] I32x4TruncSatF32x4S,
+    #[prefix = 0xfd] #[tag = 0xf9] I32x4TruncSatF32x4U,
+    #[prefix = 0xfd] #[tag = 0xfa] F32x4ConvertI32x4S,
+    #[prefix = 0xfd] #[tag = 0xfb] F32x4ConvertI32x4U,
+    #[prefix = 0xfd] #[tag = 0xfc] I32x4TruncSatF64x2SZero,
+    #[prefix = 0xfd] #[tag = 0xfd] I32x4TruncSatF64x2UZero,
+    #[prefix = 0xfd] #[tag = 0xfe] F64x2ConvertLowI32x4S,
+    #[prefix = 0xfd] #[tag = 0xff] F64x2ConvertLowI32x4U,
+
+    // threads proposal
+    #[prefix = 0xfe] #[tag = 0x00] MemoryAtomicNotify(Memarg),
+    #[prefix = 0xfe] #[tag = 0x01] MemoryAtomicWait32(Memarg),
+    #[prefix = 0xfe] #[tag = 0x02] MemoryAtomicWait64(Memarg),
+    #[prefix = 0xfe] #[tag = 0x03] AtomicFence(/* memory order, always 0x00 (sequentially consistent) */ u8),
+    #[prefix = 0xfe] #[tag = 0x10] I32AtomicLoad(Memarg),
+    #[prefix = 0xfe] #[tag = 0x11] I64AtomicLoad(Memarg),
+    #[prefix = 0xfe] #[tag = 0x12] I32AtomicLoad8U(Memarg),
+    #[prefix = 0xfe] #[tag = 0x13] I32AtomicLoad16U(Memarg),
+    #[prefix = 0xfe] #[tag = 0x14] I64AtomicLoad8U(Memarg),
+    #[prefix = 0xfe] #[tag = 0x15] I64AtomicLoad16U(Memarg),
+    #[prefix = 0xfe] #[tag = 0x16] I64AtomicLoad32U(Memarg),
+    #[prefix = 0xfe] #[tag = 0x17] I32AtomicStore(Memarg),
+    #[prefix = 0xfe] #[tag = 0x18] I64AtomicStore(Memarg),
+    #[prefix = 0xfe] #[tag = 0x19] I32AtomicStore8(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1a] I32AtomicStore16(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1b] I64AtomicStore8(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1c] I64AtomicStore16(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1d] I64AtomicStore32(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1e] I32AtomicRmwAdd(Memarg),
+    #[prefix = 0xfe] #[tag = 0x1f] I64AtomicRmwAdd(Memarg),
+    #[prefix = 0xfe] #[tag = 0x20] I32AtomicRmw8AddU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x21] I32AtomicRmw16AddU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x22] I64AtomicRmw8AddU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x23] I64AtomicRmw16AddU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x24] I64AtomicRmw32AddU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x25] I32AtomicRmwSub(Memarg),
+    #[prefix = 0xfe] #[tag = 0x26] I64AtomicRmwSub(Memarg),
+    #[prefix = 0xfe] #[tag = 0x27] I32AtomicRmw8SubU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x28] I32AtomicRmw16SubU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x29] I64AtomicRmw8SubU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2a] I64AtomicRmw16SubU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2b] I64AtomicRmw32SubU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2c] I32AtomicRmwAnd(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2d] I64AtomicRmwAnd(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2e] I32AtomicRmw8AndU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x2f] I32AtomicRmw16AndU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x30] I64AtomicRmw8AndU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x31] I64AtomicRmw16AndU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x32] I64AtomicRmw32AndU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x33] I32AtomicRmwOr(Memarg),
+    #[prefix = 0xfe] #[tag = 0x34] I64AtomicRmwOr(Memarg),
+    #[prefix = 0xfe] #[tag = 0x35] I32AtomicRmw8OrU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x36] I32AtomicRmw16OrU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x37] I64AtomicRmw8OrU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x38] I64AtomicRmw16OrU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x39] I64AtomicRmw32OrU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3a] I32AtomicRmwXor(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3b] I64AtomicRmwXor(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3c] I32AtomicRmw8XorU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3d] I32AtomicRmw16XorU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3e] I64AtomicRmw8XorU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x3f] I64AtomicRmw16XorU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x40] I64AtomicRmw32XorU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x41] I32AtomicRmwXchg(Memarg),
+    #[prefix = 0xfe] #[tag = 0x42] I64AtomicRmwXchg(Memarg),
+    #[prefix = 0xfe] #[tag = 0x43] I32AtomicRmw8XchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x44] I32AtomicRmw16XchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x45] I64AtomicRmw8XchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x46] I64AtomicRmw16XchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x47] I64AtomicRmw32XchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x48] I32AtomicRmwCmpxchg(Memarg),
+    #[prefix = 0xfe] #[tag = 0x49] I64AtomicRmwCmpxchg(Memarg),
+    #[prefix = 0xfe] #[tag = 0x4a] I32AtomicRmw8CmpxchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x4b] I32AtomicRmw16CmpxchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x4c] I64AtomicRmw8CmpxchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x4d] I64AtomicRmw16CmpxchgU(Memarg),
+    #[prefix = 0xfe] #[tag = 0x4e] I64AtomicRmw32CmpxchgU(Memarg),
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
+pub mod borrowed;
 pub mod highlevel;
 pub mod lowlevel;
+pub mod structured;
 
 mod common;
 pub use self::common::*;
 
-mod convert;
\ No newline at end of file
+mod convert;
+mod visit;
\ No newline at end of file
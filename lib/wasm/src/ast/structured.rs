@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::fmt;
+use super::{Idx, Label};
+use super::highlevel::{BlockType, Instr, Tag};
+
+/* Tree-shaped (structured) alternative to the flat instruction sequence of highlevel::Expr, i.e.,
+   nested blocks own their bodies instead of being delimited by else/catch/end instructions.
+   Converting to and from the flat form is lossless (except for Code::original_offsets, which refer
+   to the flat instructions), so transformations can insert, move, or remove whole blocks on the
+   tree and flatten it again afterwards.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// any instruction that is not block-structured, i.e., never block, loop, if, try, or their
+    /// else, catch, delegate, and end instructions
+    Instr(Instr),
+    Block {
+        type_: BlockType,
+        body: Vec<Node>,
+    },
+    Loop {
+        type_: BlockType,
+        body: Vec<Node>,
+    },
+    If {
+        type_: BlockType,
+        then: Vec<Node>,
+        /// None if there is no else instruction (which is different from an empty else arm)
+        else_: Option<Vec<Node>>,
+    },
+    Try {
+        type_: BlockType,
+        body: Vec<Node>,
+        catches: Vec<Catch>,
+        /// try-delegate has no catches
+        delegate: Option<Idx<Label>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    /// None for catch_all
+    pub tag: Option<Idx<Tag>>,
+    pub body: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NestingError {
+    /// index of the offending flat instruction (the instruction count if blocks are not closed)
+    pub instr: Idx<Instr>,
+    pub message: String,
+}
+
+impl fmt::Display for NestingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, at instruction #{}", self.message, self.instr.0)
+    }
+}
+
+impl Error for NestingError {}
+
+/// converts a flat body (or any other expression), including its final end instruction
+pub fn structure(instrs: &[Instr]) -> Result<Vec<Node>, NestingError> {
+    let mut parser = Parser { instrs, pos: 0 };
+    let nodes = parser.nodes_until(&[Delimiter::End])?.0;
+    if parser.pos < instrs.len() {
+        return Err(parser.error(parser.pos, "instructions after final end"));
+    }
+    Ok(nodes)
+}
+
+/// converts back to the flat form, including the final end instruction
+pub fn flatten(nodes: &[Node]) -> Vec<Instr> {
+    let mut instrs = Vec::new();
+    flatten_into(nodes, &mut instrs);
+    instrs.push(Instr::End);
+    instrs
+}
+
+fn flatten_into(nodes: &[Node], instrs: &mut Vec<Instr>) {
+    for node in nodes {
+        match *node {
+            Node::Instr(ref instr) => instrs.push(instr.clone()),
+            Node::Block { ref type_, ref body } => {
+                instrs.push(Instr::Block(type_.clone()));
+                flatten_into(body, instrs);
+                instrs.push(Instr::End);
+            }
+            Node::Loop { ref type_, ref body } => {
+                instrs.push(Instr::Loop(type_.clone()));
+                flatten_into(body, instrs);
+                instrs.push(Instr::End);
+            }
+            Node::If { ref type_, ref then, ref else_ } => {
+                instrs.push(Instr::If(type_.clone()));
+                flatten_into(then, instrs);
+                if let Some(else_) = else_ {
+                    instrs.push(Instr::Else);
+                    flatten_into(else_, instrs);
+                }
+                instrs.push(Instr::End);
+            }
+            Node::Try { ref type_, ref body, ref catches, delegate } => {
+                instrs.push(Instr::Try(type_.clone()));
+                flatten_into(body, instrs);
+                for catch in catches {
+                    instrs.push(match catch.tag {
+                        Some(tag) => Instr::Catch(tag),
+                        None => Instr::CatchAll,
+                    });
+                    flatten_into(&catch.body, instrs);
+                }
+                instrs.push(match delegate {
+                    Some(label) => Instr::Delegate(label),
+                    None => Instr::End,
+                });
+            }
+        }
+    }
+}
+
+impl Node {
+    /// number of flat instructions, including else, catch, and end instructions
+    pub fn instr_count(&self) -> usize {
+        let count = |nodes: &[Node]| nodes.iter().map(Node::instr_count).sum::<usize>();
+        match *self {
+            Node::Instr(_) => 1,
+            Node::Block { ref body, .. } | Node::Loop { ref body, .. } => 2 + count(body),
+            Node::If { ref then, ref else_, .. } => 2 + count(then) + else_.as_ref().map(|else_| 1 + count(else_)).unwrap_or(0),
+            Node::Try { ref body, ref catches, .. } => 2 + count(body) + catches.iter().map(|catch| 1 + count(&catch.body)).sum::<usize>(),
+        }
+    }
+}
+
+/// the flat instructions that end a sequence of nodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delimiter {
+    End,
+    Else,
+    Catch(Option<Idx<Tag>>),
+    Delegate(Idx<Label>),
+}
+
+struct Parser<'a> {
+    instrs: &'a [Instr],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// parse nodes until one of the expected delimiters, which is consumed and returned
+    fn nodes_until(&mut self, expected: &[Delimiter]) -> Result<(Vec<Node>, Delimiter), NestingError> {
+        let mut nodes = Vec::new();
+        loop {
+            let begin = self.pos;
+            let instr = match self.instrs.get(begin) {
+                Some(instr) => instr,
+                None => return Err(self.error(begin, "block was not closed")),
+            };
+            self.pos += 1;
+
+            let delimiter = match *instr {
+                Instr::End => Some(Delimiter::End),
+                Instr::Else => Some(Delimiter::Else),
+                Instr::Catch(tag) => Some(Delimiter::Catch(Some(tag))),
+                Instr::CatchAll => Some(Delimiter::Catch(None)),
+                Instr::Delegate(label) => Some(Delimiter::Delegate(label)),
+                _ => None,
+            };
+            if let Some(delimiter) = delimiter {
+                let is_expected = expected.iter().any(|expected| match (*expected, delimiter) {
+                    (Delimiter::Catch(_), Delimiter::Catch(_)) | (Delimiter::Delegate(_), Delimiter::Delegate(_)) => true,
+                    (expected, delimiter) => expected == delimiter,
+                });
+                return if is_expected {
+                    Ok((nodes, delimiter))
+                } else {
+                    Err(self.error(begin, format!("unexpected {}", instr.to_name())))
+                };
+            }
+
+            nodes.push(match *instr {
+                Instr::Block(ref type_) => Node::Block {
+                    type_: type_.clone(),
+                    body: self.nodes_until(&[Delimiter::End])?.0,
+                },
+                Instr::Loop(ref type_) => Node::Loop {
+                    type_: type_.clone(),
+                    body: self.nodes_until(&[Delimiter::End])?.0,
+                },
+                Instr::If(ref type_) => {
+                    let (then, delimiter) = self.nodes_until(&[Delimiter::Else, Delimiter::End])?;
+                    let else_ = match delimiter {
+                        Delimiter::Else => Some(self.nodes_until(&[Delimiter::End])?.0),
+                        _ => None,
+                    };
+                    Node::If { type_: type_.clone(), then, else_ }
+                }
+                Instr::Try(ref type_) => {
+                    let any_catch = Delimiter::Catch(None);
+                    let (body, mut delimiter) = self.nodes_until(&[any_catch, Delimiter::Delegate(0.into()), Delimiter::End])?;
+                    let mut catches = Vec::new();
+                    while let Delimiter::Catch(tag) = delimiter {
+                        let (body, next) = self.nodes_until(&[any_catch, Delimiter::End])?;
+                        catches.push(Catch { tag, body });
+                        delimiter = next;
+                    }
+                    let delegate = match delimiter {
+                        Delimiter::Delegate(label) => Some(label),
+                        _ => None,
+                    };
+                    Node::Try { type_: type_.clone(), body, catches, delegate }
+                }
+                ref instr => Node::Instr(instr.clone()),
+            });
+        }
+    }
+
+    fn error(&self, instr: usize, message: impl Into<String>) -> NestingError {
+        NestingError { instr: instr.into(), message: message.into() }
+    }
+}
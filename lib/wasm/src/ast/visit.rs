@@ -0,0 +1,244 @@
+use std::mem;
+use super::{FunctionType, Idx, Label, Local, Val, ValType};
+use super::highlevel::{BlockType, Code, Function, Global, GlobalOp, Instr, LoadOp, LocalOp, Memarg, Module, NumericOp, StoreOp, Table, Tag};
+
+/* Visitors over the instructions of all functions (re-exported from highlevel), so that analyses
+   and instrumentations only override the callbacks for the instruction kinds they are interested
+   in, instead of matching on every Instr by hand. VisitorMut can additionally insert instructions
+   before and after the visited one, replace it, and add locals (see VisitContext).
+*/
+
+/// passed to every callback of Visitor and VisitorMut
+#[derive(Debug)]
+pub struct VisitContext {
+    pub function: Idx<Function>,
+    /// index in the original body, i.e., not counting inserted instructions
+    pub instr: Idx<Instr>,
+    /// number of blocks (block, loop, if, try) enclosing the instruction, 0 in the function body.
+    /// The begin, else, catch, and end instructions of a block are outside of it.
+    pub block_depth: usize,
+    pub function_type: FunctionType,
+    /// parameters, then locals (including those added during visiting)
+    local_types: Vec<ValType>,
+    added_locals: Vec<ValType>,
+    before: Vec<Instr>,
+    after: Vec<Instr>,
+    replacement: Option<Vec<Instr>>,
+}
+
+impl VisitContext {
+    fn new(function_idx: Idx<Function>, function: &Function, code: &Code) -> Self {
+        VisitContext {
+            function: function_idx,
+            instr: 0.into(),
+            block_depth: 0,
+            function_type: function.type_.clone(),
+            local_types: function.type_.params.iter().chain(code.locals.iter()).cloned().collect(),
+            added_locals: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            replacement: None,
+        }
+    }
+
+    pub fn local_types(&self) -> &[ValType] {
+        &self.local_types
+    }
+
+    pub fn local_type(&self, local: Idx<Local>) -> ValType {
+        self.local_types[local.0]
+    }
+
+    /// only for VisitorMut, the local is added to the function after visiting it
+    pub fn add_local(&mut self, ty: ValType) -> Idx<Local> {
+        self.local_types.push(ty);
+        self.added_locals.push(ty);
+        (self.local_types.len() - 1).into()
+    }
+
+    /// only for VisitorMut, after previously inserted instructions
+    pub fn insert_before(&mut self, instrs: impl IntoIterator<Item=Instr>) {
+        self.before.extend(instrs);
+    }
+
+    /// only for VisitorMut, after previously inserted instructions
+    pub fn insert_after(&mut self, instrs: impl IntoIterator<Item=Instr>) {
+        self.after.extend(instrs);
+    }
+
+    /// only for VisitorMut, e.g., with no instructions to remove the visited one
+    pub fn replace(&mut self, instrs: impl IntoIterator<Item=Instr>) {
+        self.replacement = Some(instrs.into_iter().collect());
+    }
+
+    fn is_modified(&self) -> bool {
+        !self.before.is_empty() || !self.after.is_empty() || self.replacement.is_some()
+    }
+
+    /// update the block depth before visiting instr, returns the depth after it
+    fn enter(&mut self, instr: &Instr) -> usize {
+        match *instr {
+            Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Try(_) => self.block_depth + 1,
+            Instr::Else | Instr::Catch(_) | Instr::CatchAll => {
+                self.block_depth -= 1;
+                self.block_depth + 1
+            }
+            // the final end of the function is already at depth 0
+            Instr::End | Instr::Delegate(_) if self.block_depth > 0 => {
+                self.block_depth -= 1;
+                self.block_depth
+            }
+            _ => self.block_depth,
+        }
+    }
+}
+
+// both traits have the same callbacks, only the context is mutable for VisitorMut
+macro_rules! visitor_trait {
+    ($(#[$attr:meta])* $name:ident, $ctx:ty) => {
+        $(#[$attr])*
+        pub trait $name {
+            /// before the first instruction of every non-imported function
+            fn visit_function_begin(&mut self, _ctx: $ctx) {}
+
+            /// dispatches to the callbacks below, override to handle all instructions uniformly
+            fn visit_instr(&mut self, ctx: $ctx, instr: &Instr) {
+                match *instr {
+                    Instr::Block(ref type_) => self.visit_block(ctx, type_),
+                    Instr::Loop(ref type_) => self.visit_loop(ctx, type_),
+                    Instr::If(ref type_) => self.visit_if(ctx, type_),
+                    Instr::Else => self.visit_else(ctx),
+                    Instr::Try(ref type_) => self.visit_try(ctx, type_),
+                    Instr::Catch(tag) => self.visit_catch(ctx, Some(tag)),
+                    Instr::CatchAll => self.visit_catch(ctx, None),
+                    Instr::Delegate(label) => self.visit_delegate(ctx, label),
+                    Instr::End => self.visit_end(ctx),
+                    Instr::Br(label) => self.visit_br(ctx, label),
+                    Instr::BrIf(label) => self.visit_br_if(ctx, label),
+                    Instr::BrTable(ref table, default) => self.visit_br_table(ctx, table, default),
+                    Instr::Return => self.visit_return(ctx),
+                    Instr::Call(function) => self.visit_call(ctx, function),
+                    Instr::CallIndirect(ref type_, table) => self.visit_call_indirect(ctx, type_, table),
+                    Instr::ReturnCall(function) => self.visit_return_call(ctx, function),
+                    Instr::ReturnCallIndirect(ref type_, table) => self.visit_return_call_indirect(ctx, type_, table),
+                    Instr::Local(op, local) => self.visit_local(ctx, op, local),
+                    Instr::Global(op, global) => self.visit_global(ctx, op, global),
+                    Instr::Load(op, memarg) => self.visit_load(ctx, op, memarg),
+                    Instr::Store(op, memarg) => self.visit_store(ctx, op, memarg),
+                    Instr::Const(val) => self.visit_const(ctx, val),
+                    Instr::Numeric(op) => self.visit_numeric(ctx, op),
+                    ref instr => self.visit_other(ctx, instr),
+                }
+            }
+
+            fn visit_block(&mut self, _ctx: $ctx, _type: &BlockType) {}
+            fn visit_loop(&mut self, _ctx: $ctx, _type: &BlockType) {}
+            fn visit_if(&mut self, _ctx: $ctx, _type: &BlockType) {}
+            fn visit_else(&mut self, _ctx: $ctx) {}
+            fn visit_try(&mut self, _ctx: $ctx, _type: &BlockType) {}
+            /// tag is None for catch_all
+            fn visit_catch(&mut self, _ctx: $ctx, _tag: Option<Idx<Tag>>) {}
+            fn visit_delegate(&mut self, _ctx: $ctx, _label: Idx<Label>) {}
+            /// for all blocks and the function itself
+            fn visit_end(&mut self, _ctx: $ctx) {}
+
+            fn visit_br(&mut self, _ctx: $ctx, _label: Idx<Label>) {}
+            fn visit_br_if(&mut self, _ctx: $ctx, _label: Idx<Label>) {}
+            fn visit_br_table(&mut self, _ctx: $ctx, _table: &[Idx<Label>], _default: Idx<Label>) {}
+            fn visit_return(&mut self, _ctx: $ctx) {}
+
+            fn visit_call(&mut self, _ctx: $ctx, _function: Idx<Function>) {}
+            fn visit_call_indirect(&mut self, _ctx: $ctx, _type: &FunctionType, _table: Idx<Table>) {}
+            /// by default handled like a call
+            fn visit_return_call(&mut self, ctx: $ctx, function: Idx<Function>) {
+                self.visit_call(ctx, function)
+            }
+            /// by default handled like an indirect call
+            fn visit_return_call_indirect(&mut self, ctx: $ctx, type_: &FunctionType, table: Idx<Table>) {
+                self.visit_call_indirect(ctx, type_, table)
+            }
+
+            fn visit_local(&mut self, _ctx: $ctx, _op: LocalOp, _local: Idx<Local>) {}
+            fn visit_global(&mut self, _ctx: $ctx, _op: GlobalOp, _global: Idx<Global>) {}
+            fn visit_load(&mut self, _ctx: $ctx, _op: LoadOp, _memarg: Memarg) {}
+            fn visit_store(&mut self, _ctx: $ctx, _op: StoreOp, _memarg: Memarg) {}
+            fn visit_const(&mut self, _ctx: $ctx, _val: Val) {}
+            fn visit_numeric(&mut self, _ctx: $ctx, _op: NumericOp) {}
+
+            /// all instructions without a more specific callback above
+            fn visit_other(&mut self, _ctx: $ctx, _instr: &Instr) {}
+        }
+    };
+}
+
+visitor_trait!(
+    /// read-only, e.g., for analyses
+    Visitor, &VisitContext);
+visitor_trait!(
+    /// can modify the visited function bodies via the context
+    VisitorMut, &mut VisitContext);
+
+impl Module {
+    pub fn visit(&self, visitor: &mut impl Visitor) {
+        for (fidx, function) in self.functions.iter().enumerate() {
+            function.visit(fidx.into(), visitor);
+        }
+    }
+
+    pub fn visit_mut(&mut self, visitor: &mut impl VisitorMut) {
+        for (fidx, function) in self.functions() {
+            function.visit_mut(fidx, visitor);
+        }
+    }
+}
+
+impl Function {
+    /// idx is the index of this function in the module, imported functions are not visited
+    pub fn visit(&self, idx: Idx<Function>, visitor: &mut impl Visitor) {
+        let code = match self.code {
+            Some(ref code) => code,
+            None => return,
+        };
+        let mut ctx = VisitContext::new(idx, self, code);
+        visitor.visit_function_begin(&ctx);
+        for (iidx, instr) in code.body.iter().enumerate() {
+            ctx.instr = iidx.into();
+            let depth_after = ctx.enter(instr);
+            visitor.visit_instr(&ctx, instr);
+            ctx.block_depth = depth_after;
+        }
+    }
+
+    /// like visit(), but applies the insertions and replacements to the body afterwards
+    pub fn visit_mut(&mut self, idx: Idx<Function>, visitor: &mut impl VisitorMut) {
+        let mut ctx = match self.code {
+            Some(ref code) => VisitContext::new(idx, self, code),
+            None => return,
+        };
+        let code = self.code.as_mut().unwrap();
+        let old_body = mem::replace(&mut code.body, Vec::new());
+        code.body.reserve(old_body.len());
+
+        let mut modified = false;
+        visitor.visit_function_begin(&mut ctx);
+        for (iidx, instr) in old_body.into_iter().enumerate() {
+            ctx.instr = iidx.into();
+            let depth_after = ctx.enter(&instr);
+            visitor.visit_instr(&mut ctx, &instr);
+            ctx.block_depth = depth_after;
+
+            modified |= ctx.is_modified();
+            code.body.append(&mut ctx.before);
+            match ctx.replacement.take() {
+                Some(replacement) => code.body.extend(replacement),
+                None => code.body.push(instr),
+            }
+            code.body.append(&mut ctx.after);
+        }
+
+        code.locals.append(&mut ctx.added_locals);
+        if modified {
+            code.original_offsets = None;
+        }
+    }
+}
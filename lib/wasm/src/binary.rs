@@ -4,7 +4,8 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use leb128::*;
 use rayon::prelude::*;
 use std::error::Error;
-use std::io;
+use std::fmt;
+use std::io::{self, Read};
 use std::marker::PhantomData;
 use std::mem::size_of;
 
@@ -12,23 +13,218 @@ use std::mem::size_of;
 
 
 pub trait WasmBinary: Sized {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self>;
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError>;
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize>;
 
-    /// convenience method
-    fn error<E>(reason: E) -> io::Result<Self>
-        where E: Into<Box<Error + Send + Sync>>
-    {
-        Err(io::Error::new(io::ErrorKind::InvalidData, reason))
+    /// convenience method for decoding errors
+    fn error(message: impl Into<String>) -> Result<Self, DecodeError> {
+        Err(DecodeError::new(message))
     }
 }
 
 
+/* Decoding errors */
+
+#[derive(Debug)]
+pub struct DecodeError {
+    /// byte offset in the module where the innermost value that failed to decode starts
+    /// (None if decoding did not start at a Module, i.e., the offset is not known)
+    pub offset: Option<usize>,
+    /// section id as in the binary (i.e., 0 for custom sections)
+    pub section: Option<u8>,
+    /// index of the function body in the code section, i.e., NOT counting imported functions
+    pub function: Option<usize>,
+    /// types that were being decoded when the error occurred, innermost first
+    pub context: Vec<&'static str>,
+    pub message: String,
+    kind: io::ErrorKind,
+}
+
+impl DecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        DecodeError {
+            offset: None,
+            section: None,
+            function: None,
+            context: Vec::new(),
+            message: message.into(),
+            kind: io::ErrorKind::InvalidData,
+        }
+    }
+
+    /// e.g., io::ErrorKind::UnexpectedEof if the input ended prematurely
+    pub fn kind(&self) -> io::ErrorKind {
+        self.kind
+    }
+
+    fn in_section(mut self, section: u8) -> Self {
+        self.section.get_or_insert(section);
+        self
+    }
+
+    fn in_function(mut self, function: usize) -> Self {
+        self.function.get_or_insert(function);
+        self
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(offset) = self.offset {
+            write!(f, ", at byte offset 0x{:x}", offset)?;
+        }
+        if let Some(section) = self.section {
+            write!(f, ", in section {}", section)?;
+        }
+        if let Some(function) = self.function {
+            write!(f, ", in function body #{}", function)?;
+        }
+        if !self.context.is_empty() {
+            write!(f, " (while decoding {})", self.context.join(" in "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> Self {
+        DecodeError {
+            kind: e.kind(),
+            ..DecodeError::new(e.to_string())
+        }
+    }
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(e: DecodeError) -> Self {
+        io::Error::new(e.kind, e)
+    }
+}
+
+/// decode with the given type name as context frame for errors, used by derive(WasmBinary)
+pub fn decode_with_context<R: io::Read, T>(
+    reader: &mut R,
+    context: &'static str,
+    decode: impl FnOnce(&mut R) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError> {
+    let offset = reader.offset();
+    decode(reader).map_err(|mut e| {
+        if e.offset.is_none() {
+            e.offset = offset;
+        }
+        e.context.push(context);
+        e
+    })
+}
+
+/// reads from the module in memory and keeps track of the byte offset in it, Module::decode() wraps
+/// the input in it
+struct SliceReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self::with_offset(bytes, 0)
+    }
+
+    fn with_offset(bytes: &'a [u8], offset: usize) -> Self {
+        SliceReader { bytes, offset }
+    }
+
+    /// borrow the next len bytes, i.e., without copying
+    fn take_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if len > self.bytes.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("expected {} bytes, but only {} remaining", len, self.bytes.len())).into());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        self.offset += len;
+        Ok(taken)
+    }
+
+    /// size-prefixed, like Vec<u8>
+    fn take_vec(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = usize::decode(self)?;
+        self.take_slice(len)
+    }
+
+    /// size-prefixed and UTF-8, like String
+    fn take_str(&mut self) -> Result<&'a str, DecodeError> {
+        let bytes = self.take_vec()?;
+        ::std::str::from_utf8(bytes).map_err(|e| DecodeError::new(format!("utf-8 conversion error: {}", e.to_string())))
+    }
+}
+
+impl<'a> io::Read for SliceReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.bytes.read(buf)?;
+        self.offset += bytes_read;
+        Ok(bytes_read)
+    }
+}
+
+/// Uses trait specialization, such that decode() impls can ask any reader for its offset, but only
+/// SliceReader knows it.
+trait ReaderOffset {
+    fn offset(&self) -> Option<usize>;
+}
+
+impl<R: io::Read> ReaderOffset for R {
+    default fn offset(&self) -> Option<usize> { None }
+}
+
+impl<'a> ReaderOffset for SliceReader<'a> {
+    fn offset(&self) -> Option<usize> { Some(self.offset) }
+}
+
+// decode() impls get the reader as &mut R, which is itself io::Read, so forward to R
+impl<'a, R: io::Read> ReaderOffset for &'a mut R {
+    fn offset(&self) -> Option<usize> { (**self).offset() }
+}
+
+/// Uses trait specialization (like ReaderOffset), such that decode() impls can borrow bytes
+/// directly from the input if it is a slice, instead of copying them into buffers first.
+trait ReaderSlice {
+    /// the not yet consumed input, None if the reader is not backed by a slice
+    fn remaining(&self) -> Option<&[u8]>;
+    /// consume bytes that were decoded from remaining()
+    fn advance(&mut self, len: usize);
+}
+
+impl<R: io::Read> ReaderSlice for R {
+    default fn remaining(&self) -> Option<&[u8]> { None }
+    default fn advance(&mut self, _: usize) { unreachable!("reader is not backed by a slice") }
+}
+
+impl<'a> ReaderSlice for &'a [u8] {
+    fn remaining(&self) -> Option<&[u8]> { Some(self) }
+    fn advance(&mut self, len: usize) { *self = &self[len..] }
+}
+
+impl<'a> ReaderSlice for SliceReader<'a> {
+    fn remaining(&self) -> Option<&[u8]> { Some(self.bytes) }
+    fn advance(&mut self, len: usize) {
+        self.bytes = &self.bytes[len..];
+        self.offset += len;
+    }
+}
+
+impl<'a, R: io::Read> ReaderSlice for &'a mut R {
+    fn remaining(&self) -> Option<&[u8]> { (**self).remaining() }
+    fn advance(&mut self, len: usize) { (**self).advance(len) }
+}
+
+
 /* Primitive types */
 
 impl WasmBinary for u8 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_u8()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_u8()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_u8(*self)?;
@@ -37,8 +233,18 @@ impl WasmBinary for u8 {
 }
 
 impl WasmBinary for u32 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_leb128()?)
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_leb128(*self)
+    }
+}
+
+/// memory64 proposal: offsets in Memarg and memory limits
+impl WasmBinary for u64 {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_leb128()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
@@ -46,20 +252,20 @@ impl WasmBinary for u32 {
 }
 
 impl WasmBinary for usize {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_leb128()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         if *self > u32::max_value() as usize {
-            Self::error("WASM spec does not allow unsigned larger than u32")?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "WASM spec does not allow unsigned larger than u32"));
         }
         writer.write_leb128(*self)
     }
 }
 
 impl WasmBinary for i32 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_leb128()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
@@ -67,8 +273,8 @@ impl WasmBinary for i32 {
 }
 
 impl WasmBinary for i64 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_leb128()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
@@ -76,8 +282,8 @@ impl WasmBinary for i64 {
 }
 
 impl WasmBinary for f32 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_f32::<LittleEndian>()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_f32::<LittleEndian>()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_f32::<LittleEndian>(*self)?;
@@ -86,8 +292,8 @@ impl WasmBinary for f32 {
 }
 
 impl WasmBinary for f64 {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_f64::<LittleEndian>()
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(reader.read_f64::<LittleEndian>()?)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_f64::<LittleEndian>(*self)?;
@@ -95,11 +301,34 @@ impl WasmBinary for f64 {
     }
 }
 
+/// v128.const immediate, not LEB128 but plain little-endian bytes
+impl WasmBinary for u128 {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(u128::from_le_bytes(<[u8; 16]>::decode(reader)?))
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        self.to_le_bytes().encode(writer)
+    }
+}
+
+/// lane indices of i8x16.shuffle
+impl WasmBinary for [u8; 16] {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let mut bytes = [0; 16];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(self)?;
+        Ok(16)
+    }
+}
+
 
 /* Generic "AST combinators" */
 
 impl<T: WasmBinary> WasmBinary for WithSize<T> {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
         let _forget_original_size = u32::decode(reader)?;
         Ok(WithSize(T::decode(reader)?))
     }
@@ -118,7 +347,7 @@ impl<T: WasmBinary> WasmBinary for WithSize<T> {
 }
 
 impl<T: WasmBinary> WasmBinary for Vec<T> {
-    default fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    default fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
         let size = usize::decode(reader)?;
 
         let mut vec: Vec<T> = Vec::with_capacity(size * size_of::<T>());
@@ -138,13 +367,32 @@ impl<T: WasmBinary> WasmBinary for Vec<T> {
     }
 }
 
+/// bytes (e.g., of data segments and strings) are read at once, not one by one
+impl WasmBinary for Vec<u8> {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let size = usize::decode(reader)?;
+
+        // do not trust the size for allocating, the input could end before
+        let mut vec = Vec::new();
+        reader.take(size as u64).read_to_end(&mut vec)?;
+        if vec.len() < size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("expected {} bytes, but only {} remaining", size, vec.len())).into());
+        }
+        Ok(vec)
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let bytes_written = self.len().encode(writer)?;
+        writer.write_all(self)?;
+        Ok(bytes_written + self.len())
+    }
+}
+
 impl WasmBinary for String {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
         // reuse Vec<u8> implementation, then consume buf so no re-allocation is necessary
         let buf: Vec<u8> = Vec::decode(reader)?;
-        String::from_utf8(buf).map_err(|e| io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("utf-8 conversion error: {}", e.to_string())))
+        String::from_utf8(buf).map_err(|e| DecodeError::new(format!("utf-8 conversion error: {}", e.to_string())))
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
@@ -159,26 +407,32 @@ impl WasmBinary for String {
 /// Uses trait specialization (https://github.com/rust-lang/rfcs/blob/master/text/1210-impl-specialization.md)
 /// to provide parallel decoding/encoding (right now only Code section has the necessary Vec<WithSize<T>> structure).
 impl<T: WasmBinary + Send + Sync> WasmBinary for Vec<WithSize<T>> {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
         let num_elements = usize::decode(reader)?;
 
-        // read all elements into buffers of the given size (non-parallel, but hopefully fast)
-        let mut bufs = Vec::with_capacity(num_elements * size_of::<Vec<u8>>());
-        for _ in 0..num_elements {
-            let num_bytes = usize::decode(reader)?;
-            let mut buf = vec![0u8; num_bytes];
-            reader.read_exact(&mut buf)?;
-            bufs.push(buf);
-        }
-
-        // parallel decode of each buffer
-        let decoded: io::Result<Vec<WithSize<T>>> = bufs.into_par_iter()
-            .map(|buf| -> io::Result<WithSize<T>> {
-                Ok(WithSize(T::decode(&mut &buf[..])?))
-            })
-            .collect();
+        // split the input into the elements of the given size, without copying if it is a slice
+        let decoded = if let Some(input) = reader.remaining() {
+            let mut input_reader = SliceReader::with_offset(input, reader.offset().unwrap_or(0));
+            let mut elements = Vec::with_capacity(num_elements);
+            for _ in 0..num_elements {
+                let num_bytes = usize::decode(&mut input_reader)?;
+                let offset = reader.offset().map(|_| input_reader.offset);
+                elements.push((input_reader.take_slice(num_bytes)?, offset));
+            }
+            let consumed = input.len() - input_reader.bytes.len();
+            (WithSize::decode_parallel(elements), consumed)
+        } else {
+            // otherwise read all elements into buffers (non-parallel, but hopefully fast)
+            let mut bufs = Vec::with_capacity(num_elements);
+            for _ in 0..num_elements {
+                let buf: Vec<u8> = Vec::decode(reader)?;
+                bufs.push(buf);
+            }
+            return WithSize::decode_parallel(bufs.iter().map(|buf| (&buf[..], None)).collect());
+        };
 
-        Ok(decoded?)
+        reader.advance(decoded.1);
+        decoded.0
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
@@ -204,64 +458,302 @@ impl<T: WasmBinary + Send + Sync> WasmBinary for Vec<WithSize<T>> {
 }
 
 
+impl<T: WasmBinary + Send + Sync> WithSize<T> {
+    /// elements: the bytes of each element and their offset in the module (if known)
+    fn decode_parallel(elements: Vec<(&[u8], Option<usize>)>) -> Result<Vec<Self>, DecodeError> {
+        // NOTE the index of the element is recorded as function index on errors, since the code
+        // section is the only Vec<WithSize<T>>
+        elements.into_par_iter()
+            .enumerate()
+            .map(|(i, (bytes, offset))| -> Result<WithSize<T>, DecodeError> {
+                let element = match offset {
+                    Some(offset) => T::decode(&mut SliceReader::with_offset(bytes, offset)),
+                    None => T::decode(&mut &bytes[..]),
+                };
+                Ok(WithSize(element.map_err(|e| e.in_function(i))?))
+            })
+            .collect()
+    }
+}
+
+
 /* Special cases that cannot be derived and need a manual impl */
 
+/// decodes from slices directly, other readers are read into memory first (see also
+/// borrowed::Module for decoding only the structure of a module, without copying)
 impl WasmBinary for Module {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        let mut magic_number = [0u8; 4];
-        reader.read_exact(&mut magic_number)?;
-        if &magic_number != b"\0asm" {
-            return Self::error("magic bytes do not match");
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        if let Some(bytes) = reader.remaining() {
+            let module = decode_module(bytes);
+            let len = bytes.len();
+            reader.advance(len);
+            return module;
         }
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        decode_module(&bytes)
+    }
 
-        let version = reader.read_u32::<LittleEndian>()?;
-        if version != 1 {
-            return Self::error("not version 1");
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(b"\0asm")?;
+        writer.write_all(&[1, 0, 0, 0])?;
+        let mut bytes_written = 8;
+        for section in &self.sections {
+            bytes_written += section.encode(writer)?;
         }
+        Ok(bytes_written)
+    }
+}
+
+fn decode_module(bytes: &[u8]) -> Result<Module, DecodeError> {
+    let reader = &mut SliceReader::new(bytes);
+    decode_header(reader)?;
+
+    let mut sections = Vec::new();
+    // peek at the section id, so that errors inside the section can refer to it
+    while let Some(&section_id) = reader.bytes.first() {
+        sections.push(Section::decode(reader).map_err(|e| e.in_section(section_id))?);
+    }
+
+    Ok(Module { sections })
+}
+
+fn decode_header(reader: &mut SliceReader) -> Result<(), DecodeError> {
+    if reader.take_slice(4)? != b"\0asm" {
+        return Err(DecodeError::new("magic bytes do not match"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != 1 {
+        return Err(DecodeError::new("not version 1"));
+    }
+
+    Ok(())
+}
+
+/// only splits the module into sections, their content is decoded on demand by the accessors
+impl<'a> borrowed::Module<'a> {
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let reader = &mut SliceReader::new(bytes);
+        decode_header(reader)?;
 
         let mut sections = Vec::new();
-        loop {
-            match Section::decode(reader) {
-                Ok(section) => sections.push(section),
-                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e)
-            };
+        while let Some(&id) = reader.bytes.first() {
+            let section = decode_with_context(reader, "Section", |reader| {
+                reader.take_slice(1)?;
+                let size = usize::decode(reader)?;
+                let offset = reader.offset;
+                Ok(borrowed::Section { id, offset, content: reader.take_slice(size)? })
+            });
+            sections.push(section.map_err(|e| e.in_section(id))?);
         }
 
-        Ok(Module { sections })
+        Ok(borrowed::Module { bytes, sections })
+    }
+
+    /// fully decodes the module, same as lowlevel::Module::decode()
+    pub fn to_lowlevel(&self) -> Result<Module, DecodeError> {
+        decode_module(self.bytes)
+    }
+
+    pub fn function_bodies(&self) -> Result<Vec<borrowed::FunctionBody<'a>>, DecodeError> {
+        self.decode_items(10, |reader| {
+            let size = usize::decode(reader)?;
+            let offset = reader.offset;
+            Ok(borrowed::FunctionBody { offset, bytes: reader.take_slice(size)? })
+        })
+    }
+
+    pub fn data(&self) -> Result<Vec<borrowed::Data<'a>>, DecodeError> {
+        self.decode_items(11, |reader| decode_with_context(reader, "Data", |reader| {
+            let mode = decode_data_mode(reader)?;
+            Ok(borrowed::Data { mode, bytes: reader.take_vec()? })
+        }))
+    }
+
+    pub fn imports(&self) -> Result<Vec<borrowed::Import<'a>>, DecodeError> {
+        self.decode_items(2, |reader| decode_with_context(reader, "Import", |reader| {
+            Ok(borrowed::Import {
+                module: reader.take_str()?,
+                name: reader.take_str()?,
+                type_: ImportType::decode(reader)?,
+            })
+        }))
+    }
+
+    pub fn exports(&self) -> Result<Vec<borrowed::Export<'a>>, DecodeError> {
+        self.decode_items(7, |reader| decode_with_context(reader, "Export", |reader| {
+            Ok(borrowed::Export {
+                name: reader.take_str()?,
+                type_: ExportType::decode(reader)?,
+            })
+        }))
+    }
+
+    /// name and raw content of all custom sections, in order
+    pub fn custom_sections(&self) -> Result<Vec<borrowed::CustomSection<'a>>, DecodeError> {
+        self.sections.iter()
+            .filter(|section| section.id == 0)
+            .map(|section| {
+                let reader = &mut SliceReader::with_offset(section.content, section.offset);
+                let name = reader.take_str().map_err(|e| e.in_section(0))?;
+                Ok(borrowed::CustomSection { name, content: reader.bytes })
+            })
+            .collect()
+    }
+
+    /// decode the vector in the section with the given id (empty if there is no such section)
+    fn decode_items<T>(&self, id: u8, mut decode: impl FnMut(&mut SliceReader<'a>) -> Result<T, DecodeError>) -> Result<Vec<T>, DecodeError> {
+        let section = match self.section(id) {
+            Some(section) => *section,
+            None => return Ok(Vec::new()),
+        };
+        let reader = &mut SliceReader::with_offset(section.content, section.offset);
+        let items = (|| {
+            let num_items = usize::decode(reader)?;
+            let mut items = Vec::new();
+            for _ in 0..num_items {
+                items.push(decode(reader)?);
+            }
+            Ok(items)
+        })();
+        items.map_err(|e: DecodeError| e.in_section(id))
+    }
+}
+
+/// needs manual impl because the name of a custom section determines how its content is decoded
+impl WasmBinary for CustomSection {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_with_context(reader, "CustomSection", Self::decode_inner)
     }
+
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
-        writer.write_all(b"\0asm")?;
-        writer.write_all(&[1, 0, 0, 0])?;
-        let mut bytes_written = 8;
-        for section in &self.sections {
-            bytes_written += section.encode(writer)?;
+        let mut buf = Vec::new();
+        self.name().to_string().encode(&mut buf)?;
+        match self {
+            CustomSection::Name(section) => section.encode(&mut buf)?,
+            CustomSection::Producers(section) => section.encode(&mut buf)?,
+            CustomSection::TargetFeatures(section) => section.encode(&mut buf)?,
+            CustomSection::SourceMappingUrl(url) => url.encode(&mut buf)?,
+            CustomSection::Raw(section) => {
+                buf.extend_from_slice(&section.content);
+                section.content.len()
+            }
+        };
+        buf.encode(writer)
+    }
+}
+
+impl CustomSection {
+    fn decode_inner<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        // size-prefixed like all other sections, section name is part of the content
+        let bytes: Vec<u8> = Vec::decode(reader)?;
+        let mut content = &bytes[..];
+        let name = String::decode(&mut content)?;
+
+        let typed = match name.as_str() {
+            "name" => decode_custom_content(content).map(CustomSection::Name),
+            "producers" => decode_custom_content(content).map(CustomSection::Producers),
+            "target_features" => decode_custom_content(content).map(CustomSection::TargetFeatures),
+            "sourceMappingURL" => decode_custom_content(content).map(CustomSection::SourceMappingUrl),
+            _ => Self::error("unknown custom section"),
+        };
+
+        // malformed custom sections must not make the module invalid, so fall back to raw bytes
+        Ok(typed.unwrap_or_else(|_| CustomSection::Raw(RawCustomSection { name, content: content.to_vec() })))
+    }
+}
+
+/// typed custom sections must span the whole content, otherwise re-encoding would lose bytes
+pub fn decode_custom_content<T: WasmBinary>(mut content: &[u8]) -> Result<T, DecodeError> {
+    let section = T::decode(&mut content)?;
+    if !content.is_empty() {
+        return T::error(format!("{} trailing bytes after custom section content", content.len()));
+    }
+    Ok(section)
+}
+
+/// needs manual impl because subsections are not a size-prefixed vector, but simply continue until
+/// the end of the section
+impl WasmBinary for NameSection {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut content = &bytes[..];
+
+        // a subsection that is cut off is an error, not the end of the section
+        let mut subsections = Vec::new();
+        while !content.is_empty() {
+            subsections.push(NameSubSection::decode(&mut content)?);
+        }
+        Ok(NameSection { subsections })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        for subsection in &self.subsections {
+            bytes_written += subsection.encode(writer)?;
         }
         Ok(bytes_written)
     }
 }
 
+/// needs manual impl because subsections with unknown ids are kept as raw bytes (every subsection
+/// is size-prefixed, so they can be skipped without knowing their content)
+impl WasmBinary for NameSubSection {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let id = u8::decode(reader)?;
+        let content: Vec<u8> = Vec::decode(reader)?;
+        Ok(match id {
+            0 => NameSubSection::Module(WithSize(decode_custom_content(&content)?)),
+            1 => NameSubSection::Function(WithSize(decode_custom_content(&content)?)),
+            2 => NameSubSection::Local(WithSize(decode_custom_content(&content)?)),
+            id => NameSubSection::Unknown(id, content),
+        })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        Ok(match *self {
+            NameSubSection::Module(ref name) => 0u8.encode(writer)? + name.encode(writer)?,
+            NameSubSection::Function(ref names) => 1u8.encode(writer)? + names.encode(writer)?,
+            NameSubSection::Local(ref names) => 2u8.encode(writer)? + names.encode(writer)?,
+            NameSubSection::Unknown(id, ref content) => id.encode(writer)? + content.encode(writer)?,
+        })
+    }
+}
+
 /// needs manual impl because of block handling: End op-code terminates body, but only if block stack is empty
 impl WasmBinary for Expr {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        let mut instructions = Vec::new();
-
-        let mut block_depth = 0;
-        while block_depth >= 0 {
-            let instr = Instr::decode(reader)?;
-
-            block_depth += match instr {
-                Instr::Block(..) | Instr::Loop(..) | Instr::If(..) => 1,
-                // Else ends a block, but also starts a new one
-                Instr::Else => -1 + 1,
-                Instr::End => -1,
-                _ => 0
-            };
-
-            instructions.push(instr);
-        }
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_with_context(reader, "Expr", |reader| {
+            let mut instructions = Vec::new();
+            let mut offsets = Vec::new();
+
+            let mut block_depth = 0;
+            while block_depth >= 0 {
+                offsets.extend(reader.offset());
+                let instr = Instr::decode(reader)?;
+
+                block_depth += match instr {
+                    Instr::Block(..) | Instr::Loop(..) | Instr::If(..) | Instr::Try(..) => 1,
+                    // Else (and Catch/CatchAll) end a block, but also start a new one
+                    Instr::Else | Instr::Catch(..) | Instr::CatchAll => -1 + 1,
+                    // Delegate ends a try block without an End
+                    Instr::End | Instr::Delegate(..) => -1,
+                    // the encoding allows a vector of types for future extensions, but currently
+                    // only a single type is valid (and representable in the high-level AST)
+                    Instr::TypedSelect(ref types) if types.len() != 1 =>
+                        return Self::error(format!("typed select must have exactly one type, got {}", types.len())),
+                    _ => 0
+                };
+
+                instructions.push(instr);
+            }
 
-        Ok(Expr(instructions))
+            // either all or no offsets are known
+            let offsets = if offsets.is_empty() { None } else { Some(offsets) };
+            Ok(Expr(instructions, offsets))
+        })
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         let mut bytes_written = 0;
@@ -273,21 +765,29 @@ impl WasmBinary for Expr {
 }
 
 /// needs manual impl because of compressed format: even though BlockType is "logically" an enum,
-/// it has no tag, because they know that 0x40 (empty block) and ValType are disjoint.
+/// it has no tag, because they know that 0x40 (empty block), ValType and type indices are disjoint:
+/// type indices are encoded as signed (!) 33-bit LEB128, so they are never negative when decoded,
+/// whereas 0x40 and all ValTypes are single-byte negative numbers in that encoding.
 impl WasmBinary for BlockType {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        Ok(BlockType(match u8::decode(reader)? {
-            0x40 => None,
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok(match u8::decode(reader)? {
+            0x40 => BlockType::Empty,
+            byte @ 0x7b...0x7f | byte @ 0x6f...0x70 => BlockType::Value(ValType::decode(&mut &[byte][..])?),
             byte => {
-                let mut buf = [byte; 1];
-                Some(ValType::decode(&mut &buf[..])?)
+                let buf = [byte; 1];
+                let idx = i64::decode(&mut (&buf[..]).chain(reader))?;
+                if idx < 0 || idx > u32::max_value() as i64 {
+                    return Self::error(format!("invalid block type 0x{:02x}", byte));
+                }
+                BlockType::Index((idx as usize).into())
             }
-        }))
+        })
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         match self {
-            &BlockType(None) => 0x40u8.encode(writer),
-            &BlockType(Some(ref val_type)) => val_type.encode(writer)
+            &BlockType::Empty => 0x40u8.encode(writer),
+            &BlockType::Value(ref val_type) => val_type.encode(writer),
+            &BlockType::Index(idx) => (idx.0 as i64).encode(writer),
         }
     }
 }
@@ -295,29 +795,43 @@ impl WasmBinary for BlockType {
 /// needs manual impl because the tag if max is present comes at the beginning of the struct, not
 /// before the max field.
 impl WasmBinary for Limits {
-    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        Ok(match u8::decode(reader)? {
-            0x00 => Limits {
-                initial_size: u32::decode(reader)?,
-                max_size: None,
-            },
-            0x01 => Limits {
-                initial_size: u32::decode(reader)?,
-                max_size: Some(u32::decode(reader)?),
-            },
-            byte => Self::error(format!("expected tag for Limits, got 0x{:02x}", byte))?
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        // bit 0: has maximum size, bit 1: shared (threads proposal), bit 2: 64-bit index (memory64 proposal)
+        decode_with_context(reader, "Limits", |reader| {
+            let flags = u8::decode(reader)?;
+            if flags > 0x07 {
+                return Self::error(format!("expected tag for Limits, got 0x{:02x}", flags));
+            }
+            let index_type = if flags & 0x04 != 0 { ValType::I64 } else { ValType::I32 };
+            // sizes of 32-bit memories and tables must still fit into u32
+            let size = |reader: &mut R| -> Result<u64, DecodeError> {
+                match index_type {
+                    ValType::I64 => u64::decode(reader),
+                    _ => u32::decode(reader).map(u64::from),
+                }
+            };
+            let initial_size = size(reader)?;
+            let max_size = if flags & 0x01 != 0 { Some(size(reader)?) } else { None };
+            Ok(Limits {
+                initial_size,
+                max_size,
+                shared: flags & 0x02 != 0,
+                index_type,
+            })
         })
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         let mut bytes_written = 0;
+        let shared_flag = if self.shared { 0x02u8 } else { 0x00 };
+        let index_flag = if self.index_type == ValType::I64 { 0x04u8 } else { 0x00 };
         match self.max_size {
             None => {
-                bytes_written += 0x00u8.encode(writer)?;
+                bytes_written += (shared_flag | index_flag).encode(writer)?;
                 bytes_written += self.initial_size.encode(writer)?;
             }
             Some(ref max_size) => {
-                bytes_written += 0x01u8.encode(writer)?;
+                bytes_written += (shared_flag | index_flag | 0x01).encode(writer)?;
                 bytes_written += self.initial_size.encode(writer)?;
                 bytes_written += max_size.encode(writer)?;
             }
@@ -326,7 +840,167 @@ impl WasmBinary for Limits {
     }
 }
 
+/// needs manual impl because with the multi-memory proposal, bit 6 of the alignment field signals
+/// that an explicit memory index follows (otherwise, the memory index is 0).
+impl WasmBinary for Memarg {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_with_context(reader, "Memarg", |reader| {
+            let flags = u32::decode(reader)?;
+            let memory = if flags & 0x40 != 0 { Idx::decode(reader)? } else { 0.into() };
+            Ok(Memarg {
+                alignment: flags & !0x40,
+                memory,
+                offset: u64::decode(reader)?,
+            })
+        })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        if self.memory.0 == 0 {
+            bytes_written += self.alignment.encode(writer)?;
+        } else {
+            bytes_written += (self.alignment | 0x40).encode(writer)?;
+            bytes_written += self.memory.encode(writer)?;
+        }
+        bytes_written += self.offset.encode(writer)?;
+        Ok(bytes_written)
+    }
+}
+
+/// needs manual impl because since the bulk memory proposal, the first field is a bitfield that
+/// determines which fields follow: bit 0 is set for passive and declarative segments, bit 1 marks
+/// declarative segments (if bit 0 is set) or an explicit table index (if not), and bit 2 means the
+/// items are constant expressions instead of function indices. Flags 0 and 4 (the MVP encodings)
+/// have no explicit element kind (always 0x00, i.e., funcref) or element type.
+impl WasmBinary for Element {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_with_context(reader, "Element", |reader| {
+            let flags = u32::decode(reader)?;
+            if flags > 7 {
+                return Self::error(format!("invalid element segment flags {}", flags));
+            }
+            let mode = match flags & 0b011 {
+                0b000 => ElementMode::Active(0.into(), Expr::decode(reader)?),
+                0b010 => {
+                    let table_idx = Idx::decode(reader)?;
+                    ElementMode::Active(table_idx, Expr::decode(reader)?)
+                }
+                0b001 => ElementMode::Passive,
+                _ => ElementMode::Declarative,
+            };
+            let has_type = flags & 0b011 != 0;
+            let init = if flags & 0b100 == 0 {
+                if has_type {
+                    decode_elem_kind(reader)?;
+                }
+                ElementItems::Functions(Vec::decode(reader)?)
+            } else {
+                let type_ = if has_type { ElemType::decode(reader)? } else { ElemType::FuncRef };
+                ElementItems::Expressions(type_, Vec::decode(reader)?)
+            };
+            Ok(Element { mode, init })
+        })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let expressions_flag: u32 = match self.init {
+            ElementItems::Functions(_) => 0b000,
+            ElementItems::Expressions(_, _) => 0b100,
+        };
+        let mut bytes_written = 0;
+        // MVP encoding (i.e., without explicit element kind/type), if possible
+        let has_type = match (&self.mode, &self.init) {
+            (&ElementMode::Active(table_idx, ref offset), &ElementItems::Functions(_))
+            | (&ElementMode::Active(table_idx, ref offset), &ElementItems::Expressions(ElemType::FuncRef, _)) if table_idx.0 == 0 => {
+                bytes_written += expressions_flag.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+                false
+            }
+            (&ElementMode::Active(table_idx, ref offset), _) => {
+                bytes_written += (expressions_flag | 0b010).encode(writer)?;
+                bytes_written += table_idx.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+                true
+            }
+            (&ElementMode::Passive, _) => {
+                bytes_written += (expressions_flag | 0b001).encode(writer)?;
+                true
+            }
+            (&ElementMode::Declarative, _) => {
+                bytes_written += (expressions_flag | 0b011).encode(writer)?;
+                true
+            }
+        };
+        match self.init {
+            ElementItems::Functions(ref functions) => {
+                if has_type {
+                    bytes_written += 0x00u8.encode(writer)?;
+                }
+                bytes_written += functions.encode(writer)?;
+            }
+            ElementItems::Expressions(type_, ref exprs) => {
+                if has_type {
+                    bytes_written += type_.encode(writer)?;
+                }
+                bytes_written += exprs.encode(writer)?;
+            }
+        }
+        Ok(bytes_written)
+    }
+}
+
+fn decode_elem_kind<R: io::Read>(reader: &mut R) -> Result<(), DecodeError> {
+    match u8::decode(reader)? {
+        0x00 => Ok(()),
+        byte => Err(DecodeError::new(format!("expected element kind 0x00 (funcref), got 0x{:02x}", byte))),
+    }
+}
+
+/// see Element, flags 0 and 2 are active segments (2 with explicit memory index), 1 is passive.
+impl WasmBinary for Data {
+    fn decode<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_with_context(reader, "Data", |reader| {
+            let mode = decode_data_mode(reader)?;
+            Ok(Data { mode, init: Vec::decode(reader)? })
+        })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        match self.mode {
+            DataMode::Active(memory_idx, ref offset) if memory_idx.0 == 0 => {
+                bytes_written += 0u32.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+            }
+            DataMode::Active(memory_idx, ref offset) => {
+                bytes_written += 2u32.encode(writer)?;
+                bytes_written += memory_idx.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+            }
+            DataMode::Passive => {
+                bytes_written += 1u32.encode(writer)?;
+            }
+        }
+        bytes_written += self.init.encode(writer)?;
+        Ok(bytes_written)
+    }
+}
+
+/// shared with borrowed::Module::data()
+fn decode_data_mode<R: io::Read>(reader: &mut R) -> Result<DataMode, DecodeError> {
+    Ok(match u32::decode(reader)? {
+        0 => DataMode::Active(0.into(), Expr::decode(reader)?),
+        1 => DataMode::Passive,
+        2 => {
+            let memory_idx = Idx::decode(reader)?;
+            DataMode::Active(memory_idx, Expr::decode(reader)?)
+        }
+        flags => return Err(DecodeError::new(format!("invalid data segment flags {}", flags))),
+    })
+}
+
 impl<T> WasmBinary for PhantomData<T> {
-    fn decode<R: io::Read>(_: &mut R) -> io::Result<Self> { Ok(PhantomData) }
+    fn decode<R: io::Read>(_: &mut R) -> Result<Self, DecodeError> { Ok(PhantomData) }
     fn encode<W: io::Write>(&self, _: &mut W) -> io::Result<usize> { Ok(0) }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use ast::Idx;
+use ast::highlevel::{ElementItems, ElementMode, Function, Instr, Module};
+use const_eval::{ConstEvaluator, ConstValue};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/* Static call graph of a module, with one edge per call site and callee. Direct calls (including
+   tail calls) have exactly one callee. For indirect calls, the candidates are all functions in
+   element segments of the called table (active ones, and passive ones that table.init can copy into
+   any table) whose type matches the call. Functions that only get into a table at runtime (via
+   table.set, table.grow, or from JavaScript) are not considered.
+*/
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallGraph {
+    /// ordered by caller and call instruction
+    pub edges: Vec<CallEdge>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct CallEdge {
+    pub caller: Idx<Function>,
+    /// the call instruction in the body of the caller
+    pub instr: Idx<Instr>,
+    pub callee: Idx<Function>,
+    pub kind: CallKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    Direct,
+    /// callee is only a candidate
+    Indirect,
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> Self {
+        let const_eval = ConstEvaluator::new(module);
+        let mut table_functions = vec![BTreeSet::new(); module.tables.len()];
+        for element in &module.elements {
+            let functions: Vec<Idx<Function>> = match element.items {
+                ElementItems::Functions(ref functions) => functions.clone(),
+                ElementItems::Expressions(_, ref exprs) => exprs.iter()
+                    .filter_map(|expr| match const_eval.eval(expr) {
+                        Some(ConstValue::RefFunc(function)) => Some(function),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+            match element.mode {
+                ElementMode::Active { table, .. } => table_functions[table.0].extend(functions),
+                ElementMode::Passive => for functions_in_table in &mut table_functions {
+                    functions_in_table.extend(functions.iter().cloned());
+                },
+                ElementMode::Declarative => {}
+            }
+        }
+
+        let mut edges = Vec::new();
+        for (caller, function) in module.functions.iter().enumerate() {
+            let body = match function.code {
+                Some(ref code) => &code.body,
+                None => continue,
+            };
+            for (iidx, instr) in body.iter().enumerate() {
+                let edge = |callee: Idx<Function>, kind| CallEdge { caller: caller.into(), instr: iidx.into(), callee, kind };
+                match *instr {
+                    Instr::Call(callee) | Instr::ReturnCall(callee) => edges.push(edge(callee, CallKind::Direct)),
+                    Instr::CallIndirect(ref type_, table) | Instr::ReturnCallIndirect(ref type_, table) => edges.extend(
+                        table_functions[table.0].iter()
+                            .filter(|callee| module.functions[callee.0].type_ == *type_)
+                            .map(|&callee| edge(callee, CallKind::Indirect))),
+                    _ => {}
+                }
+            }
+        }
+
+        CallGraph { edges }
+    }
+
+    pub fn callees<'a>(&'a self, caller: Idx<Function>) -> impl Iterator<Item=&'a CallEdge> + 'a {
+        self.edges.iter().filter(move |edge| edge.caller == caller)
+    }
+
+    pub fn callers<'a>(&'a self, callee: Idx<Function>) -> impl Iterator<Item=&'a CallEdge> + 'a {
+        self.edges.iter().filter(move |edge| edge.callee == callee)
+    }
+
+    /// in Graphviz format, with one node per function and one edge per caller/callee pair (dashed
+    /// if there are only indirect calls)
+    pub fn to_dot(&self, module: &Module) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph call_graph {\n");
+        for (fidx, function) in module.functions.iter().enumerate() {
+            let name = match (&function.name, function.export.first(), &function.import) {
+                (Some(name), _, _) | (None, Some(name), _) => name.clone(),
+                (None, None, Some((module, name))) => format!("{}.{}", module, name),
+                (None, None, None) => format!("function {}", fidx),
+            };
+            writeln!(dot, "  f{} [label=\"{}\"];", fidx, name.replace('\\', "\\\\").replace('"', "\\\"")).unwrap();
+        }
+        let edges: BTreeSet<(usize, usize, CallKind)> = self.edges.iter()
+            .map(|edge| (edge.caller.0, edge.callee.0, edge.kind))
+            .collect();
+        for &(caller, callee, kind) in &edges {
+            match kind {
+                CallKind::Direct => writeln!(dot, "  f{} -> f{};", caller, callee),
+                CallKind::Indirect if !edges.contains(&(caller, callee, CallKind::Direct)) =>
+                    writeln!(dot, "  f{} -> f{} [style=dashed];", caller, callee),
+                CallKind::Indirect => Ok(()),
+            }.unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,147 @@
+use ast::Idx;
+use super::{BasicBlock, Cfg, Edge};
+
+/* Dominator and post-dominator trees with the algorithm by Cooper, Harvey, and Kennedy ("A Simple,
+   Fast Dominance Algorithm"), which is plenty fast for the (reducible) CFGs of Wasm functions.
+*/
+
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    root: Idx<BasicBlock>,
+    /// immediate dominator of every block, None for the root and blocks not reachable from it
+    idoms: Vec<Option<Idx<BasicBlock>>>,
+}
+
+impl DominatorTree {
+    /// rooted at the entry block
+    pub fn dominators(cfg: &Cfg) -> Self {
+        let (successors, predecessors) = cfg.adjacency();
+        Self::new(cfg.entry(), &successors, &predecessors)
+    }
+
+    /// rooted at the exit block, i.e., on the reversed CFG. Blocks that never reach the exit (e.g.,
+    /// infinite loops) have no post-dominators.
+    pub fn post_dominators(cfg: &Cfg) -> Self {
+        let (successors, predecessors) = cfg.adjacency();
+        Self::new(cfg.exit(), &predecessors, &successors)
+    }
+
+    fn new(root: Idx<BasicBlock>, successors: &[Vec<usize>], predecessors: &[Vec<usize>]) -> Self {
+        // postorder numbers of all blocks reachable from root
+        let mut postorder = Vec::with_capacity(successors.len());
+        let mut postorder_number = vec![None; successors.len()];
+        let mut visited = vec![false; successors.len()];
+        let mut stack = vec![(root.0, 0)];
+        visited[root.0] = true;
+        while let Some((block, next_successor)) = stack.pop() {
+            match successors[block].get(next_successor) {
+                Some(&successor) => {
+                    stack.push((block, next_successor + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                }
+                None => {
+                    postorder_number[block] = Some(postorder.len());
+                    postorder.push(block);
+                }
+            }
+        }
+
+        let mut idoms: Vec<Option<usize>> = vec![None; successors.len()];
+        idoms[root.0] = Some(root.0);
+        let intersect = |idoms: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while postorder_number[a] < postorder_number[b] {
+                    a = idoms[a].unwrap();
+                }
+                while postorder_number[b] < postorder_number[a] {
+                    b = idoms[b].unwrap();
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // in reverse postorder, skipping the root
+            for &block in postorder.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &predecessors[block] {
+                    if idoms[predecessor].is_some() {
+                        new_idom = Some(match new_idom {
+                            Some(new_idom) => intersect(&idoms, predecessor, new_idom),
+                            None => predecessor,
+                        });
+                    }
+                }
+                if new_idom != idoms[block] {
+                    idoms[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idoms[root.0] = None;
+
+        DominatorTree {
+            root,
+            idoms: idoms.into_iter().map(|idom| idom.map(Idx::from)).collect(),
+        }
+    }
+
+    pub fn root(&self) -> Idx<BasicBlock> {
+        self.root
+    }
+
+    pub fn idom(&self, block: Idx<BasicBlock>) -> Option<Idx<BasicBlock>> {
+        self.idoms[block.0]
+    }
+
+    pub fn is_reachable(&self, block: Idx<BasicBlock>) -> bool {
+        block == self.root || self.idoms[block.0].is_some()
+    }
+
+    /// every block dominates itself
+    pub fn dominates(&self, dominator: Idx<BasicBlock>, block: Idx<BasicBlock>) -> bool {
+        if !self.is_reachable(block) {
+            return false;
+        }
+        let mut current = Some(block);
+        while let Some(block) = current {
+            if block == dominator {
+                return true;
+            }
+            current = self.idom(block);
+        }
+        false
+    }
+
+    /// immediately dominated blocks, i.e., the children in the tree
+    pub fn children(&self, block: Idx<BasicBlock>) -> Vec<Idx<BasicBlock>> {
+        (0..self.idoms.len())
+            .filter(|&child| self.idoms[child] == Some(block))
+            .map(Idx::from)
+            .collect()
+    }
+}
+
+/// for every block, the branches (edges) it is control dependent on, i.e., whether the block is
+/// executed depends on which of the edges out of the branching block is taken
+pub fn control_dependences(cfg: &Cfg, post_dominators: &DominatorTree) -> Vec<Vec<Edge>> {
+    let mut dependences = vec![Vec::new(); cfg.blocks.len()];
+    for edge in &cfg.edges {
+        // walk up the post-dominator tree, from the target to the immediate post-dominator of the source
+        let end = post_dominators.idom(edge.from);
+        let mut current = Some(edge.to);
+        while current != end {
+            let block = match current {
+                Some(block) => block,
+                None => break,
+            };
+            dependences[block.0].push(*edge);
+            current = post_dominators.idom(block);
+        }
+    }
+    dependences
+}
@@ -0,0 +1,74 @@
+use ast::Idx;
+use super::{BasicBlock, Cfg, Edge};
+use super::dominators::DominatorTree;
+
+/* Natural loops, i.e., the blocks of a back edge (whose target dominates its source), merged for
+   back edges with the same target (the loop header). Since Wasm control flow is structured, every
+   header starts with a loop instruction that some branch targets.
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    pub header: Idx<BasicBlock>,
+    /// sorted, including the header
+    pub blocks: Vec<Idx<BasicBlock>>,
+    /// 1 for outermost loops
+    pub depth: usize,
+    pub back_edges: Vec<Edge>,
+    /// edges from blocks of the loop to blocks outside of it
+    pub exits: Vec<Edge>,
+}
+
+impl NaturalLoop {
+    pub fn contains(&self, block: Idx<BasicBlock>) -> bool {
+        self.blocks.binary_search(&block).is_ok()
+    }
+}
+
+/// ordered by header
+pub fn natural_loops(cfg: &Cfg, dominators: &DominatorTree) -> Vec<NaturalLoop> {
+    let (_, predecessors) = cfg.adjacency();
+
+    let mut headers: Vec<Idx<BasicBlock>> = cfg.edges.iter()
+        .filter(|edge| dominators.dominates(edge.to, edge.from))
+        .map(|edge| edge.to)
+        .collect();
+    headers.sort();
+    headers.dedup();
+
+    let mut loops: Vec<NaturalLoop> = headers.into_iter().map(|header| {
+        let back_edges: Vec<Edge> = cfg.predecessors(header)
+            .filter(|edge| dominators.dominates(header, edge.from))
+            .cloned()
+            .collect();
+
+        // all blocks that reach a back edge without going through the header
+        let mut in_loop = vec![false; cfg.blocks.len()];
+        in_loop[header.0] = true;
+        let mut worklist: Vec<usize> = back_edges.iter().map(|edge| edge.from.0).collect();
+        while let Some(block) = worklist.pop() {
+            if !in_loop[block] {
+                in_loop[block] = true;
+                worklist.extend(&predecessors[block]);
+            }
+        }
+        let blocks: Vec<Idx<BasicBlock>> = (0..cfg.blocks.len()).filter(|&block| in_loop[block]).map(Idx::from).collect();
+
+        let exits = cfg.edges.iter()
+            .filter(|edge| in_loop[edge.from.0] && !in_loop[edge.to.0])
+            .cloned()
+            .collect();
+
+        NaturalLoop { header, blocks, depth: 0, back_edges, exits }
+    }).collect();
+
+    // a loop is nested in every loop that contains its header (including itself)
+    let depths: Vec<usize> = loops.iter()
+        .map(|loop_| loops.iter().filter(|outer| outer.contains(loop_.header)).count())
+        .collect();
+    for (loop_, depth) in loops.iter_mut().zip(depths) {
+        loop_.depth = depth;
+    }
+
+    loops
+}
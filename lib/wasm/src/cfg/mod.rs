@@ -0,0 +1,286 @@
+use ast::{Idx, Label};
+use ast::highlevel::Instr;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub mod dominators;
+pub mod loops;
+
+/* Control-flow graph of a function body, i.e., its basic blocks and the typed edges between them.
+   Branch labels are resolved as in Wasabi's BlockStack: to the loop instruction when targeting a
+   loop (backward), and to the end instruction of the targeted block otherwise (forward).
+   Exceptional control flow is only modeled for throw and rethrow, not for calls that may throw.
+   The target of try-delegate is not resolved either, i.e., exceptions thrown in the body of a
+   try-delegate flow to the handlers of all enclosing try blocks (an over-approximation).
+*/
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Cfg {
+    /// the entry block is the first, the (empty) exit block the last
+    pub blocks: Vec<BasicBlock>,
+    /// ordered by source block
+    pub edges: Vec<Edge>,
+}
+
+/// instructions [start, end) of the function body
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct BasicBlock {
+    pub start: Idx<Instr>,
+    pub end: Idx<Instr>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub from: Idx<BasicBlock>,
+    pub to: Idx<BasicBlock>,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// sequential control flow, including entering and leaving blocks
+    Fallthrough,
+    /// br, br_if with non-zero condition, and if into its then arm
+    BranchTaken,
+    /// br_if with zero condition, and if into its else arm (or past its end if there is none)
+    BranchNotTaken,
+    /// br_table, with the index into its label table
+    BrTableCase(usize),
+    BrTableDefault,
+    /// return, tail calls, and the final end of the function, always to the exit block
+    Return,
+    /// always to the exit block, since unreachable traps
+    Unreachable,
+    /// throw and rethrow, to the catch handlers of all enclosing try blocks up to the first
+    /// catch_all, or to the exit block if there is none
+    Throw,
+}
+
+impl Cfg {
+    /// an empty body (which is not valid, since it lacks the final end) gives a CFG with only the
+    /// exit block, which is then also the entry
+    pub fn new(body: &[Instr]) -> Self {
+        let successors = instr_successors(body);
+        let exit = body.len();
+
+        // a block starts at the function entry, at every target of non-sequential control flow,
+        // and after every instruction that does not simply continue with the next one
+        let mut is_leader = vec![false; body.len() + 1];
+        is_leader[0] = true;
+        is_leader[exit] = true;
+        for (iidx, successors) in successors.iter().enumerate() {
+            match successors.as_slice() {
+                [(EdgeKind::Fallthrough, next)] if *next == iidx + 1 => {}
+                _ => {
+                    is_leader[iidx + 1] = true;
+                    for &(_, target) in successors {
+                        is_leader[target] = true;
+                    }
+                }
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_of_instr = Vec::with_capacity(body.len() + 1);
+        for iidx in 0..body.len() + 1 {
+            if is_leader[iidx] {
+                blocks.push(BasicBlock { start: iidx.into(), end: iidx.into() });
+            }
+            block_of_instr.push(Idx::from(blocks.len() - 1));
+            blocks.last_mut().unwrap().end = (iidx + 1).min(exit).into();
+        }
+
+        let block_of_instr = &block_of_instr;
+        let edges = blocks[..blocks.len() - 1].iter().enumerate()
+            .flat_map(|(from, block)| successors[block.end.0 - 1].iter().map(move |&(kind, target)| Edge {
+                from: from.into(),
+                to: block_of_instr[target],
+                kind,
+            }))
+            .collect();
+
+        Cfg { blocks, edges }
+    }
+
+    pub fn entry(&self) -> Idx<BasicBlock> {
+        0.into()
+    }
+
+    pub fn exit(&self) -> Idx<BasicBlock> {
+        (self.blocks.len() - 1).into()
+    }
+
+    pub fn successors<'a>(&'a self, block: Idx<BasicBlock>) -> impl Iterator<Item=&'a Edge> + 'a {
+        self.edges.iter().filter(move |edge| edge.from == block)
+    }
+
+    pub fn predecessors<'a>(&'a self, block: Idx<BasicBlock>) -> impl Iterator<Item=&'a Edge> + 'a {
+        self.edges.iter().filter(move |edge| edge.to == block)
+    }
+
+    /// successors and predecessors of every block, e.g., for graph algorithms
+    fn adjacency(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut successors = vec![Vec::new(); self.blocks.len()];
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for edge in &self.edges {
+            successors[edge.from.0].push(edge.to.0);
+            predecessors[edge.to.0].push(edge.from.0);
+        }
+        (successors, predecessors)
+    }
+
+    /// the block that contains the instruction
+    pub fn block_of(&self, instr: Idx<Instr>) -> Idx<BasicBlock> {
+        match self.blocks.binary_search_by_key(&instr, |block| block.start) {
+            Ok(block) => block.into(),
+            Err(next_block) => (next_block - 1).into(),
+        }
+    }
+
+    /// in Graphviz format, with the instructions of each block as node labels
+    pub fn to_dot(&self, body: &[Instr]) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph cfg {\n");
+        dot.push_str("  node [shape=box, fontname=monospace];\n");
+        for (bidx, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for iidx in block.start.0..block.end.0 {
+                write!(label, "{}: {}\\l", iidx, body[iidx].to_name()).unwrap();
+            }
+            if Idx::from(bidx) == self.exit() {
+                label.push_str("exit");
+            }
+            writeln!(dot, "  b{} [label=\"{}\"];", bidx, label).unwrap();
+        }
+        for edge in &self.edges {
+            let label = match edge.kind {
+                EdgeKind::Fallthrough => None,
+                EdgeKind::BranchTaken => Some("taken".to_string()),
+                EdgeKind::BranchNotTaken => Some("not taken".to_string()),
+                EdgeKind::BrTableCase(case) => Some(format!("case {}", case)),
+                EdgeKind::BrTableDefault => Some("default".to_string()),
+                EdgeKind::Return => Some("return".to_string()),
+                EdgeKind::Unreachable => Some("unreachable".to_string()),
+                EdgeKind::Throw => Some("throw".to_string()),
+            };
+            match label {
+                Some(label) => writeln!(dot, "  b{} -> b{} [label=\"{}\"];", edge.from.0, edge.to.0, label),
+                None => writeln!(dot, "  b{} -> b{};", edge.from.0, edge.to.0),
+            }.unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// the end (or delegate) instruction of every block, by the index of its begin instruction. None
+/// for all other instructions, and for blocks that are not closed (only in invalid bodies).
+pub fn block_ends(body: &[Instr]) -> Vec<Option<Idx<Instr>>> {
+    block_nesting(body).0.into_iter().map(|end| end.map(Idx::from)).collect()
+}
+
+/// end and else/catch instructions of every block, by the index of its begin
+fn block_nesting(body: &[Instr]) -> (Vec<Option<usize>>, HashMap<usize, Vec<usize>>) {
+    use self::Instr::*;
+
+    let mut ends = vec![None; body.len()];
+    let mut arms: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut begins = Vec::new();
+    for (iidx, instr) in body.iter().enumerate() {
+        match *instr {
+            Block(_) | Loop(_) | If(_) | Try(_) => begins.push(iidx),
+            // else/catch outside of a block (in invalid bodies) are ignored here
+            Else | Catch(_) | CatchAll => if let Some(&begin) = begins.last() {
+                arms.entry(begin).or_insert_with(Vec::new).push(iidx);
+            },
+            // the final end of the function has no begin
+            End | Delegate(_) => if let Some(begin) = begins.pop() {
+                ends[begin] = Some(iidx);
+            },
+            _ => {}
+        }
+    }
+    (ends, arms)
+}
+
+/// control-flow successors of every instruction, body.len() stands for the exit of the function
+fn instr_successors(body: &[Instr]) -> Vec<Vec<(EdgeKind, usize)>> {
+    use self::Instr::*;
+
+    if body.is_empty() {
+        return Vec::new();
+    }
+
+    // first pass: block structure, blocks that are not closed end with the function
+    let (ends, arms) = block_nesting(body);
+    let ends: Vec<usize> = ends.into_iter().map(|end| end.unwrap_or(body.len() - 1)).collect();
+
+    // second pass: resolve labels with a stack of the enclosing blocks (None for the function)
+    struct Frame {
+        begin: Option<usize>,
+        /// inside the body of a try block, i.e., exceptions are caught by its handlers
+        catches: bool,
+    }
+    let target = |frames: &[Frame], label: Idx<Label>| -> usize {
+        match frames[frames.len() - 1 - label.0].begin {
+            Some(begin) => if let Loop(_) = body[begin] { begin } else { ends[begin] },
+            None => body.len() - 1,
+        }
+    };
+    let exit = body.len();
+
+    let mut frames = vec![Frame { begin: None, catches: false }];
+    body.iter().enumerate().map(|(iidx, instr)| {
+        let next = iidx + 1;
+        match *instr {
+            Block(_) | Loop(_) | Try(_) => {
+                frames.push(Frame { begin: Some(iidx), catches: if let Try(_) = *instr { true } else { false } });
+                vec![(EdgeKind::Fallthrough, next)]
+            }
+            If(_) => {
+                frames.push(Frame { begin: Some(iidx), catches: false });
+                let else_or_end = arms.get(&iidx).map(|arms| arms[0] + 1).unwrap_or(ends[iidx]);
+                vec![(EdgeKind::BranchTaken, next), (EdgeKind::BranchNotTaken, else_or_end)]
+            }
+            // reached at the end of the then arm or of the try body/previous handler
+            Else | Catch(_) | CatchAll => {
+                let frame = frames.last_mut().expect("invalid block nesting: else/catch outside of block");
+                frame.catches = false;
+                vec![(EdgeKind::Fallthrough, ends[frame.begin.expect("invalid block nesting: else/catch outside of block")])]
+            }
+            End | Delegate(_) => {
+                frames.pop();
+                if frames.is_empty() {
+                    vec![(EdgeKind::Return, exit)]
+                } else {
+                    vec![(EdgeKind::Fallthrough, next)]
+                }
+            }
+            Br(label) => vec![(EdgeKind::BranchTaken, target(&frames, label))],
+            BrIf(label) => vec![(EdgeKind::BranchTaken, target(&frames, label)), (EdgeKind::BranchNotTaken, next)],
+            BrTable(ref table, default) => table.iter()
+                .enumerate()
+                .map(|(case, &label)| (EdgeKind::BrTableCase(case), target(&frames, label)))
+                .chain(Some((EdgeKind::BrTableDefault, target(&frames, default))))
+                .collect(),
+            Return | ReturnCall(_) | ReturnCallIndirect(..) => vec![(EdgeKind::Return, exit)],
+            Unreachable => vec![(EdgeKind::Unreachable, exit)],
+            Throw(_) | Rethrow(_) => {
+                let mut handlers = Vec::new();
+                for frame in frames.iter().rev().filter(|frame| frame.catches) {
+                    for &handler in arms.get(&frame.begin.unwrap()).into_iter().flatten() {
+                        // the handler body starts after the catch instruction
+                        handlers.push((EdgeKind::Throw, handler + 1));
+                        if let CatchAll = body[handler] {
+                            return handlers;
+                        }
+                    }
+                }
+                handlers.push((EdgeKind::Throw, exit));
+                handlers
+            }
+            _ => vec![(EdgeKind::Fallthrough, next)],
+        }
+    }).collect()
+}
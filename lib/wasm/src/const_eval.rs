@@ -0,0 +1,185 @@
+use ast::{ElemType, Idx, Val};
+use ast::highlevel::{DataMode, ElementItems, ElementMode, Function, Global, GlobalOp, Instr, Memory, Module, Table};
+use std::collections::{BTreeMap, HashMap};
+
+/* Evaluation of constant expressions (global initializers, offsets and items of data and element
+   segments), e.g., to find out where data segments end up in memory or which function is in which
+   table slot. Imported globals are only known at instantiation, so their values can be supplied.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConstValue {
+    Num(Val),
+    /// since the reference types proposal, constant expressions can also produce references
+    RefNull(ElemType),
+    RefFunc(Idx<Function>),
+}
+
+/// bytes [start, end) of a memory that are initialized by an active data segment
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct DataRange {
+    pub memory: Idx<Memory>,
+    pub start: u64,
+    pub end: u64,
+}
+
+pub struct ConstEvaluator<'a> {
+    module: &'a Module,
+    imported_globals: HashMap<Idx<Global>, ConstValue>,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(module: &'a Module) -> Self {
+        ConstEvaluator { module, imported_globals: HashMap::new() }
+    }
+
+    /// without a supplied value, all expressions reading the imported global are statically unknown
+    pub fn imported_global(&mut self, idx: Idx<Global>, value: ConstValue) -> &mut Self {
+        self.imported_globals.insert(idx, value);
+        self
+    }
+
+    /// None if the value is not statically known or expr is not a constant expression
+    pub fn eval(&self, expr: &[Instr]) -> Option<ConstValue> {
+        self.eval_with_globals(expr, self.module.globals.len())
+    }
+
+    /// initial value of a global
+    pub fn global(&self, idx: Idx<Global>) -> Option<ConstValue> {
+        match self.module.globals.get(idx.0)?.init {
+            // only preceding globals may be read, which also rules out cycles in invalid modules
+            Some(ref init) => self.eval_with_globals(init, idx.0),
+            None => self.imported_globals.get(&idx).cloned(),
+        }
+    }
+
+    fn eval_with_globals(&self, expr: &[Instr], global_count: usize) -> Option<ConstValue> {
+        match expr {
+            [Instr::Const(val), Instr::End] => Some(ConstValue::Num(*val)),
+            [Instr::RefNull(ty), Instr::End] => Some(ConstValue::RefNull(*ty)),
+            [Instr::RefFunc(idx), Instr::End] => Some(ConstValue::RefFunc(*idx)),
+            [Instr::Global(GlobalOp::GetGlobal, idx), Instr::End] if idx.0 < global_count => self.global(*idx),
+            _ => None,
+        }
+    }
+
+    /// i32 offsets are unsigned, i64 offsets only appear for memories with 64-bit addresses
+    fn offset(&self, expr: &[Instr]) -> Option<u64> {
+        match self.eval(expr)? {
+            ConstValue::Num(Val::I32(offset)) => Some(offset as u32 as u64),
+            ConstValue::Num(Val::I64(offset)) => Some(offset as u64),
+            _ => None,
+        }
+    }
+
+    /// one entry per data segment, None for passive segments or if the offset is not statically known
+    pub fn data_ranges(&self) -> Vec<Option<DataRange>> {
+        self.module.data.iter().map(|data| match data.mode {
+            DataMode::Active { memory, ref offset } => self.offset(offset).map(|start| DataRange {
+                memory,
+                start,
+                end: start.saturating_add(data.bytes.len() as u64),
+            }),
+            DataMode::Passive => None,
+        }).collect()
+    }
+
+    /// content of a memory after instantiation, up to the last byte initialized by a data segment
+    /// (all following bytes are zero). None if the memory is imported (its content is only known at
+    /// runtime), an offset is not statically known, or a segment is out of bounds (which traps).
+    /// NOTE the image is allocated up to the highest initialized byte, so a single segment at a
+    /// large offset allocates (up to the initial memory size, i.e., possibly gigabytes). Use
+    /// data_ranges() to only find out where segments are placed.
+    pub fn memory_image(&self, memory: Idx<Memory>) -> Option<Vec<u8>> {
+        let memory_size = match *self.module.memories.get(memory.0)? {
+            Memory { import: Some(_), .. } => return None,
+            ref memory => memory.type_.0.initial_size.checked_mul(1 << 16)?,
+        };
+        let mut image = Vec::new();
+        for data in &self.module.data {
+            if let DataMode::Active { memory: data_memory, ref offset } = data.mode {
+                if data_memory != memory {
+                    continue;
+                }
+                let start = self.offset(offset)?;
+                let end = start.checked_add(data.bytes.len() as u64).filter(|&end| end <= memory_size)?;
+                if end as usize > image.len() {
+                    image.resize(end as usize, 0);
+                }
+                image[start as usize..end as usize].copy_from_slice(&data.bytes);
+            }
+        }
+        Some(image)
+    }
+
+    /// content of each slot of a table (of its initial size) after instantiation, None for slots
+    /// that are not statically known, e.g., of imported tables, or if an element segment has an
+    /// unknown offset (then it could have overwritten any slot). Empty for non-existing tables.
+    /// NOTE allocates one entry per slot, so avoid it for tables with a huge initial size.
+    pub fn table_layout(&self, table_idx: Idx<Table>) -> Vec<Option<ConstValue>> {
+        let table = match self.module.tables.get(table_idx.0) {
+            Some(table) => table,
+            None => return Vec::new(),
+        };
+        let mut layout = vec![
+            match table.import {
+                Some(_) => None,
+                None => Some(ConstValue::RefNull(table.type_.0)),
+            };
+            table.type_.1.initial_size as usize
+        ];
+        for segment in self.table_segments(table_idx, table.type_.1.initial_size as u64) {
+            match segment {
+                Some((offset, items)) => layout[offset..offset + items.len()].copy_from_slice(&items),
+                None => for slot in &mut layout {
+                    *slot = None;
+                },
+            }
+        }
+        layout
+    }
+
+    /// like table_layout(), but only the slots that statically known functions are written to, i.e.,
+    /// without allocating the whole table. Empty for non-existing tables.
+    pub fn table_functions(&self, table_idx: Idx<Table>) -> BTreeMap<usize, Idx<Function>> {
+        let mut functions = BTreeMap::new();
+        let table = match self.module.tables.get(table_idx.0) {
+            Some(table) => table,
+            None => return functions,
+        };
+        for segment in self.table_segments(table_idx, table.type_.1.initial_size as u64) {
+            match segment {
+                Some((offset, items)) => for (slot, item) in (offset..).zip(items) {
+                    match item {
+                        Some(ConstValue::RefFunc(function)) => functions.insert(slot, function),
+                        // null or not statically known
+                        _ => functions.remove(&slot),
+                    };
+                },
+                None => functions.clear(),
+            }
+        }
+        functions
+    }
+
+    /// offset and item values of the active element segments of the table, in order, None if the
+    /// offset is not statically known. Out-of-bounds segments (which trap at instantiation) are
+    /// skipped.
+    fn table_segments(&self, table_idx: Idx<Table>, table_size: u64) -> Vec<Option<(usize, Vec<Option<ConstValue>>)>> {
+        self.module.elements.iter().filter_map(|element| {
+            let offset = match element.mode {
+                ElementMode::Active { table, ref offset } if table == table_idx => self.offset(offset),
+                _ => return None,
+            };
+            let items: Vec<Option<ConstValue>> = match element.items {
+                ElementItems::Functions(ref functions) => functions.iter().map(|&idx| Some(ConstValue::RefFunc(idx))).collect(),
+                ElementItems::Expressions(_, ref exprs) => exprs.iter().map(|expr| self.eval(expr)).collect(),
+            };
+            match offset {
+                Some(offset) if offset.saturating_add(items.len() as u64) <= table_size => Some(Some((offset as usize, items))),
+                Some(_) => None,
+                None => Some(None),
+            }
+        }).collect()
+    }
+}
@@ -0,0 +1,86 @@
+use ast::highlevel::{CustomSection, Module};
+use ast::RawCustomSection;
+use binary::decode_custom_content;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use WasmBinary;
+
+/* Pluggable parsers for custom sections that are not natively supported by this crate. */
+
+/// implement this for your own custom section type and register it with a CustomSectionRegistry
+pub trait CustomSectionType: WasmBinary + fmt::Debug + Clone + Send + Sync + 'static {
+    /// name of the custom section in the binary, e.g., "linking"
+    const NAME: &'static str;
+}
+
+/// object-safe version of CustomSectionType, so that parsed sections can be stored in a hl::Module
+pub trait ParsedCustomSection: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+    /// encode only the content, i.e., without section id, size, and name
+    fn encode_content(&self, buf: &mut Vec<u8>) -> io::Result<usize>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn clone_box(&self) -> Box<dyn ParsedCustomSection>;
+}
+
+impl<T: CustomSectionType> ParsedCustomSection for T {
+    fn name(&self) -> &str { T::NAME }
+    fn encode_content(&self, buf: &mut Vec<u8>) -> io::Result<usize> { self.encode(buf) }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    fn clone_box(&self) -> Box<dyn ParsedCustomSection> { Box::new(self.clone()) }
+}
+
+impl Clone for Box<dyn ParsedCustomSection> {
+    fn clone(&self) -> Self { self.clone_box() }
+}
+
+type Parser = fn(&[u8]) -> io::Result<Box<dyn ParsedCustomSection>>;
+
+fn parse<T: CustomSectionType>(content: &[u8]) -> io::Result<Box<dyn ParsedCustomSection>> {
+    Ok(Box::new(decode_custom_content::<T>(content)?))
+}
+
+#[derive(Default)]
+pub struct CustomSectionRegistry {
+    parsers: HashMap<&'static str, Parser>,
+}
+
+impl CustomSectionRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// replaces a previously registered parser for the same section name
+    pub fn register<T: CustomSectionType>(&mut self) -> &mut Self {
+        self.parsers.insert(T::NAME, parse::<T>);
+        self
+    }
+
+    /// None if no parser is registered for the section name
+    pub fn parse(&self, section: &RawCustomSection) -> Option<io::Result<Box<dyn ParsedCustomSection>>> {
+        self.parsers.get(section.name.as_str())
+            .map(|parser| parser(&section.content))
+    }
+
+    /// replace all raw custom sections of the module for which a parser is registered by their
+    /// parsed representation. Sections that fail to parse are left untouched.
+    pub fn parse_module(&self, module: &mut Module) -> io::Result<()> {
+        let mut first_error = None;
+        for (_, custom) in &mut module.custom_sections {
+            let parsed = match custom {
+                CustomSection::Raw(raw) => self.parse(raw),
+                _ => None,
+            };
+            match parsed {
+                Some(Ok(parsed)) => *custom = CustomSection::Parsed(parsed),
+                Some(Err(e)) => if first_error.is_none() { first_error = Some(e) },
+                None => {}
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
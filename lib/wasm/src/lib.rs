@@ -15,7 +15,13 @@ extern crate test_utilities;
 
 pub mod ast;
 mod binary;
-pub use self::binary::WasmBinary;
+pub mod call_graph;
+pub mod cfg;
+pub mod const_eval;
+pub use self::binary::{DecodeError, WasmBinary};
+pub mod custom_section;
+pub mod text;
+pub mod validate;
 
 #[cfg(test)]
 mod tests;
@@ -25,13 +31,13 @@ mod tests;
  */
 
 use ast::{highlevel, lowlevel};
-use std::fs::File;
-use std::io::{self, BufReader, BufWriter};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
 use std::path::Path;
 
 impl lowlevel::Module {
     pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Self::decode(&mut BufReader::new(File::open(path)?))
+        Ok(Self::decode(&mut &fs::read(path)?[..])?)
     }
 
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<usize> {
@@ -48,4 +54,9 @@ impl highlevel::Module {
         let module: lowlevel::Module = self.into();
         module.to_file(path)
     }
+
+    /// parse the text format instead of the binary format
+    pub fn from_wat_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(text::parse_module(&fs::read_to_string(path)?)?)
+    }
 }
@@ -1,11 +1,22 @@
-use ast::{highlevel, lowlevel};
-use binary::WasmBinary;
+use ast::{borrowed, ElemType, FunctionType, highlevel, Idx, lowlevel, RawCustomSection, SectionId, SectionPosition, Val, ValType::*};
+use ast::structured::{flatten, Node, structure};
+use binary::{DecodeError, WasmBinary};
+use call_graph::{CallGraph, CallKind};
+use cfg::{block_ends, BasicBlock, Cfg, EdgeKind};
+use cfg::dominators::{control_dependences, DominatorTree};
+use cfg::loops::natural_loops;
+use const_eval::{ConstEvaluator, ConstValue, DataRange};
+use custom_section::{CustomSectionRegistry, CustomSectionType};
+use text::{parse_module, print_module, PrintOptions};
+use validate::validate;
 use std::fs::File;
 use std::io::{self, Read};
+use std::path::Path;
 use test::Bencher;
 use test_utilities::*;
 
 const TEST_INPUTS: &'static str = "../../tests/inputs";
+const INVALID_INPUTS: &'static str = "../../tests/invalid";
 const LARGE_WASM_FILE: &'static str = "../../tests/inputs/real-world/bananabread/bb.wasm";
 
 #[test]
@@ -18,11 +29,1010 @@ fn decode_encode_is_valid_wasm() {
         module.to_file(output_path)
             .expect(&format!("could not encode wasm to file '{}'", output_path.display()));
 
-        wasm_validate(output_path)
-            .expect(&format!("could not validate wasm output file '{}'", output_path.display()));
+        assert_valid_file(output_path);
     }
 }
 
+#[test]
+fn parse_wat_is_valid_wasm() {
+    for path in wat_files(TEST_INPUTS).unwrap() {
+        let module = highlevel::Module::from_wat_file(&path)
+            .expect(&format!("could not parse wat file '{}'", path.display()));
+
+        let output_path = &output_file(path.with_extension("wasm"), "parse-wat").unwrap();
+        module.to_file(output_path)
+            .expect(&format!("could not encode wasm to file '{}'", output_path.display()));
+
+        assert_valid_file(output_path);
+    }
+}
+
+#[test]
+fn invalid_modules_are_rejected() {
+    let wasm_modules = wasm_files(INVALID_INPUTS).unwrap().into_iter()
+        .map(|path| (highlevel::Module::from_file(&path), path));
+    let wat_modules = wat_files(INVALID_INPUTS).unwrap().into_iter()
+        .map(|path| (highlevel::Module::from_wat_file(&path), path));
+    for (module, path) in wasm_modules.chain(wat_modules) {
+        // some invalid modules are already rejected by the parser
+        if let Ok(module) = module {
+            assert!(validate(&module).is_err(), "invalid module '{}' was not rejected", path.display());
+        }
+    }
+}
+
+#[test]
+fn validation_errors_point_to_instruction() {
+    let module = parse_module(r#"(module
+        (func)
+        (func (param i32) (result i32)
+            get_local 0
+            i64.const 1
+            i32.add))"#).unwrap();
+    let error = validate(&module).unwrap_err();
+    assert_eq!(error.function, Some(1.into()));
+    assert_eq!(error.instr, Some(2.into()));
+    assert!(error.message.starts_with("i32.add:"), "{}", error);
+
+    let cases = [
+        "(func (result i32) i32.const 0 i32.const 1)",
+        "(func (result i32) (if (i32.const 1) (then (i32.const 0))))",
+        "(func (block (result i32) (br 0)))",
+        "(func (br 1))",
+        "(func (local i32) get_local 1 drop)",
+        "(global i32 (i32.const 0)) (func i32.const 1 set_global 0)",
+        "(memory 1) (func i32.const 0 i32.load align=8 drop)",
+        "(func i32.const 0 i64.const 0 i32.const 1 select drop)",
+        "(func (param i32) (block (block (br_table 0 1 (i32.const 0) (get_local 0))) drop))",
+        "(func i32.const 0 i32.load drop)",
+        "(func (block (param i32) drop))",
+        "(func i32.const 0 (if (param i32) (result i64) (i32.const 1) (then drop i64.const 0)))",
+        "(func (param funcref funcref) get_local 0 get_local 1 i32.const 0 select drop)",
+        "(func $f ref.func $f drop)",
+        "(table 1 externref) (func i32.const 0 call_indirect)",
+        "(table 1 funcref) (func i32.const 0 ref.null extern table.set)",
+        "(func (result i32) unreachable)",
+    ];
+    for (i, case) in cases.iter().enumerate() {
+        let module = parse_module(&format!("(module {})", case)).unwrap();
+        // all valid except for the last case
+        assert_eq!(validate(&module).is_ok(), i == cases.len() - 1, "{}", case);
+    }
+}
+
+#[test]
+fn parse_wat_folded_and_flat() {
+    let folded = parse_module(r#"(module
+        (func $f (export "f") (param $x i32) (result i32) (local $y i64)
+            (block $b (result i32)
+                (br_if $b (i32.const 0x1 ;; comment
+                ) (get_local $x))
+                (i32.add (local.get $x) (i32.const -1)))))"#).unwrap();
+    let flat = parse_module(r#"(module
+        (func $f (export "f") (param $x i32) (result i32) (local $y i64)
+            block $b (result i32)
+                i32.const 1
+                get_local $x
+                br_if $b
+                local.get 0
+                i32.const 0xffffffff
+                i32.add
+            end $b))"#).unwrap();
+
+    use ast::highlevel::{Instr::*, LocalOp::*, NumericOp::*};
+    use ast::Val;
+    let expected = vec![
+        Block(FunctionType::new(vec![], vec![I32])), Const(Val::I32(1)), Local(GetLocal, 0.into()), BrIf(0.into()),
+        Local(GetLocal, 0.into()), Const(Val::I32(-1)), Numeric(I32Add), End, End];
+    for module in &[folded, flat] {
+        let function = &module.functions[0];
+        assert_eq!(function.code.as_ref().unwrap().body, expected);
+        assert_eq!(function.export, vec!["f".to_string()]);
+        assert_eq!(function.name, Some("f".into()));
+        assert_eq!(function.local_names.get(&1.into()), Some(&"y".into()));
+    }
+
+    let error = parse_module("(module (func call $g))").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 20));
+    let error = parse_module(r#"(module (export "f" (func 9)))"#).unwrap_err();
+    assert_eq!((error.line, error.column), (1, 27));
+    assert!(error.to_string().contains("unknown function index 9"));
+
+    // underscores only between digits
+    for literal in &["i32.const 0x_1", "i32.const 1__0", "i32.const 0x1_", "i64.const 0x+1", "f32.const 0x_1p0"] {
+        assert!(parse_module(&format!("(module (func {} drop))", literal)).is_err(), "{}", literal);
+    }
+    assert!(parse_module("(module (func i32.const 0xf_f drop f64.const 0x1_0.8p1 drop))").is_ok());
+}
+
+#[test]
+fn hex_float_literals_are_rounded_once() {
+    let module = parse_module(r#"(module (func
+        f32.const 0x1.0000010000000001p0 drop  ;; just above halfway, via f64 it would be a tie
+        f32.const 0x1.000001p0 drop            ;; tie, rounds to even
+        f32.const 0x1.8p-150 drop              ;; subnormal, rounds up
+        f32.const 0x1p-150 drop                ;; tie, rounds to zero
+        f32.const -0x1.fffffep127 drop
+        f32.const 0x1p128 drop
+        f64.const 0x1.00000000000008p0 drop
+        f64.const 0x1p-1074 drop
+        f64.const 0x1.fffffffffffffp1023 drop))"#).unwrap();
+    let bits: Vec<u64> = module.functions[0].code.as_ref().unwrap().body.iter()
+        .filter_map(|instr| match *instr {
+            highlevel::Instr::Const(Val::F32(value)) => Some(value.to_bits() as u64),
+            highlevel::Instr::Const(Val::F64(value)) => Some(value.to_bits()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(bits, vec![
+        0x3f80_0001, 0x3f80_0000, 0x0000_0001, 0x0000_0000, 0xff7f_ffff, 0x7f80_0000,
+        0x3ff0_0000_0000_0000, 0x0000_0000_0000_0001, 0x7fef_ffff_ffff_ffff]);
+}
+
+#[test]
+fn print_then_parse_wat_roundtrips() {
+    let encode = |module: highlevel::Module| {
+        let mut buf = Vec::new();
+        lowlevel::Module::from(module).encode(&mut buf).unwrap();
+        buf
+    };
+    for path in wat_files(TEST_INPUTS).unwrap() {
+        let module = highlevel::Module::from_wat_file(&path).unwrap();
+        for &instr_indices in &[false, true] {
+            let text = print_module(&module, PrintOptions { instr_indices });
+            let reparsed = parse_module(&text)
+                .expect(&format!("could not parse printed module from '{}':\n{}", path.display(), text));
+            assert_eq!(encode(module.clone()), encode(reparsed), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn print_wat_resolves_names_and_annotates_instructions() {
+    let mut module = highlevel::Module::default();
+    let function = module.add_function(FunctionType::new(vec![I32], vec![]), vec![], vec![
+        highlevel::Instr::Local(highlevel::LocalOp::GetLocal, 0.into()),
+        highlevel::Instr::Drop,
+        highlevel::Instr::End]);
+    module.function(function).name = Some("f".into());
+    module.function(function).local_names.insert(0.into(), "x".into());
+    // printed before f, because imports come first in the text format
+    module.add_function_import(FunctionType::new(vec![], vec![]), "env".into(), "g".into());
+
+    assert_eq!(print_module(&module, PrintOptions { instr_indices: true }), r#"(module
+  (func (;0;) (import "env" "g") (;func 1;))
+  (func $f (param $x i32) (;func 0;)
+    get_local $x (;instr 0;)
+    drop (;instr 1;)))
+"#);
+}
+
+#[test]
+fn name_section_survives_index_changes() {
+    let mut module = highlevel::Module::default();
+    let function = module.add_function(FunctionType::new(vec![I32], vec![]), vec![], vec![highlevel::Instr::End]);
+    module.function(function).name = Some("f".into());
+    module.function(function).local_names.insert(0.into(), "param".into());
+    // imported functions come first in the binary, so this shifts the index of f by one
+    module.add_function_import(FunctionType::new(vec![], vec![]), "env".into(), "g".into());
+
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+    let module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+
+    assert_eq!(module.functions[0].name, None);
+    assert_eq!(module.functions[1].name, Some("f".into()));
+    assert_eq!(module.functions[1].local_names.get(&0.into()), Some(&"param".into()));
+}
+
+#[test]
+fn name_section_with_unknown_subsections() {
+    let mut buf = Vec::new();
+    lowlevel::Module::from(parse_module("(module (func))").unwrap()).encode(&mut buf).unwrap();
+    // global names (id 7, from the extended name section proposal) before the function names
+    buf.extend_from_slice(&[0, 17, 4, b'n', b'a', b'm', b'e', 7, 4, 1, 0, 1, b'g', 1, 4, 1, 0, 1, b'f']);
+
+    let decoded = lowlevel::Module::decode(&mut &buf[..]).unwrap();
+    let subsections = decoded.sections.iter()
+        .filter_map(|section| match *section {
+            lowlevel::Section::Custom(lowlevel::CustomSection::Name(ref names)) => Some(names.subsections.len()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(subsections, vec![2]);
+    let mut reencoded = Vec::new();
+    decoded.clone().encode(&mut reencoded).unwrap();
+    assert_eq!(reencoded, buf);
+
+    // function names are still remapped when indices change
+    let mut module: highlevel::Module = decoded.into();
+    assert_eq!(module.functions[0].name, Some("f".into()));
+    module.add_function_import(FunctionType::new(vec![], vec![]), "env".into(), "g".into());
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+    let module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    assert_eq!(module.functions[1].name, Some("f".into()));
+
+    // a truncated subsection is malformed, so the section is kept as raw bytes
+    let mut buf = Vec::new();
+    lowlevel::Module::from(parse_module("(module (func))").unwrap()).encode(&mut buf).unwrap();
+    buf.extend_from_slice(&[0, 9, 4, b'n', b'a', b'm', b'e', 1, 4, 1, 0]);
+    let module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    assert_eq!(module.functions[0].name, None);
+    assert!(module.custom_sections.iter().any(|&(_, ref section)| match *section {
+        highlevel::CustomSection::Raw(ref raw) => raw.name == "name",
+        _ => false,
+    }));
+}
+
+#[derive(WasmBinary, Debug, Clone, PartialEq)]
+struct VersionSection {
+    version: u32,
+}
+
+impl CustomSectionType for VersionSection {
+    const NAME: &'static str = "version";
+}
+
+#[test]
+fn decode_errors_point_to_section_and_function() {
+    let module = parse_module(r#"(module
+        (func nop)
+        (func i32.const 42 drop))"#).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+
+    // replace drop with an invalid opcode
+    let drop_offset = buf.windows(3).position(|bytes| bytes == [0x41, 42, 0x1a]).unwrap() + 2;
+    buf[drop_offset] = 0xff;
+
+    let error: DecodeError = lowlevel::Module::decode(&mut &buf[..]).unwrap_err();
+    assert_eq!(error.offset, Some(drop_offset));
+    assert_eq!(error.section, Some(10));
+    assert_eq!(error.function, Some(1));
+    assert_eq!(error.context, vec!["Instr", "Expr", "Code", "Section"]);
+    assert_eq!(error.to_string(), format!("expected tag for Instr, got 0xff, at byte offset 0x{:x}, in section 10, in function body #1 (while decoding Instr in Expr in Code in Section)", drop_offset));
+
+    // truncated modules are an error, not silently cut off
+    let error = lowlevel::Module::decode(&mut &buf[..drop_offset]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(error.section, Some(10));
+}
+
+#[test]
+fn prefixed_opcodes_roundtrip() {
+    let module = parse_module(r#"(module (func
+        f32.const 0 i32.trunc_sat_f32_s drop
+        f64.const 0 i64.trunc_sat_f64_u drop))"#).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+    assert!(buf.windows(2).any(|bytes| bytes == [0xfc, 0x00]));
+    assert!(buf.windows(2).any(|bytes| bytes == [0xfc, 0x07]));
+
+    let module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    let names: Vec<&str> = module.functions[0].code.as_ref().unwrap().body.iter().map(|instr| instr.to_name()).collect();
+    assert_eq!(names, vec!["f32.const", "i32.trunc_sat_f32_s", "drop", "f64.const", "i64.trunc_sat_f64_u", "drop", "end"]);
+
+    // unknown opcode after the prefix
+    let offset = buf.windows(2).position(|bytes| bytes == [0xfc, 0x07]).unwrap();
+    buf[offset + 1] = 0x7f;
+    let error = lowlevel::Module::decode(&mut &buf[..]).unwrap_err();
+    assert_eq!(error.message, "expected tag for Instr after prefix 0xfc, got 127");
+    assert_eq!(error.offset, Some(offset));
+}
+
+#[test]
+fn multi_value_block_types_roundtrip() {
+    let module = parse_module(r#"(module
+        (func (result i32 i64)
+            i32.const 1
+            block (param i32) (result i32 i64)
+                i64.const 2
+            end
+            block (result f32)
+                f32.const 3
+            end
+            drop))"#).unwrap();
+    validate(&module).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    // the multi-value block type is encoded as an index into the type section...
+    let lowlevel = lowlevel::Module::decode(&mut &buf[..]).unwrap();
+    let block_types: Vec<lowlevel::BlockType> = lowlevel.sections.iter()
+        .filter_map(|section| if let lowlevel::Section::Code(code) = section { Some(code) } else { None })
+        .flat_map(|code| (code.0).iter().flat_map(|code| (code.0).body.0.iter()))
+        .filter_map(|instr| if let lowlevel::Instr::Block(ty) = instr { Some(*ty) } else { None })
+        .collect();
+    assert_eq!(block_types, vec![lowlevel::BlockType::Index(1.into()), lowlevel::BlockType::Value(F32)]);
+
+    // ...and inlined again in the high-level AST
+    let decoded: highlevel::Module = lowlevel.into();
+    assert_eq!(decoded.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    assert_eq!(decoded.functions[0].type_, FunctionType::new(vec![], vec![I32, I64]));
+}
+
+#[test]
+fn bulk_memory_segments_roundtrip() {
+    let module = parse_module(r#"(module
+        (memory 1)
+        (table 1 anyfunc)
+        (data (i32.const 0) "active")
+        (data $p "passive")
+        (elem func $f)
+        (elem declare func $f)
+        (func $f
+            i32.const 0 i32.const 0 i32.const 7 memory.init $p
+            data.drop $p
+            i32.const 0 i32.const 0 i32.const 1 table.init 0))"#).unwrap();
+    validate(&module).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+
+    let lowlevel = lowlevel::Module::decode(&mut &buf[..]).unwrap();
+    let section_ids: Vec<SectionId> = lowlevel.sections.iter().filter_map(lowlevel::Section::id).collect();
+    assert!(section_ids.contains(&SectionId::DataCount));
+    assert!(section_ids.windows(2).all(|ids| ids[0] < ids[1]), "sections out of order: {:?}", section_ids);
+
+    let module: highlevel::Module = lowlevel.into();
+    match (&module.data[0].mode, &module.data[1].mode) {
+        (highlevel::DataMode::Active { memory, .. }, highlevel::DataMode::Passive) => assert_eq!(memory.0, 0),
+        modes => panic!("unexpected data modes {:?}", modes),
+    }
+    match (&module.elements[0].mode, &module.elements[1].mode) {
+        (highlevel::ElementMode::Passive, highlevel::ElementMode::Declarative) => {}
+        modes => panic!("unexpected element modes {:?}", modes),
+    }
+    let names: Vec<&str> = module.functions[0].code.as_ref().unwrap().body.iter().map(|instr| instr.to_name()).collect();
+    assert!(names.contains(&"memory.init") && names.contains(&"data.drop") && names.contains(&"table.init"));
+
+    // without bulk memory operations in the code, no DataCount section is necessary
+    let mut buf = Vec::new();
+    lowlevel::Module::from(parse_module(r#"(module (memory 1) (data "passive"))"#).unwrap()).encode(&mut buf).unwrap();
+    let lowlevel = lowlevel::Module::decode(&mut &buf[..]).unwrap();
+    assert!(lowlevel.sections.iter().all(|section| section.id() != Some(SectionId::DataCount)));
+}
+
+#[test]
+fn reference_types_roundtrip() {
+    let text = r#"(module
+        (table $funcs 2 funcref)
+        (table $externs 1 externref)
+        (elem (table $funcs) (i32.const 0) funcref (ref.func $f) (ref.null func))
+        (elem $e externref (ref.null extern))
+        (func $f (param externref) (result i32)
+            i32.const 0
+            get_local 0
+            table.set $externs
+            ref.func $f
+            ref.null func
+            i32.const 1
+            select (result funcref)
+            ref.is_null
+            drop
+            get_local 0
+            table.size $funcs
+            call_indirect $funcs (param externref) (result i32)))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    assert_eq!(decoded.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    match (&decoded.elements[0].items, &decoded.elements[1].items) {
+        (highlevel::ElementItems::Expressions(ElemType::FuncRef, funcs), highlevel::ElementItems::Expressions(ElemType::ExternRef, externs)) => {
+            assert_eq!(funcs[0], vec![highlevel::Instr::RefFunc(0.into()), highlevel::Instr::End]);
+            assert_eq!(externs[0], vec![highlevel::Instr::RefNull(ElemType::ExternRef), highlevel::Instr::End]);
+        }
+        items => panic!("unexpected element items {:?}", items),
+    }
+
+    // the printed text parses back to the same instructions
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    assert_eq!(reparsed.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    assert_eq!(reparsed.tables[1].type_.0, ElemType::ExternRef);
+}
+
+#[test]
+fn simd_roundtrip() {
+    let text = r#"(module
+        (memory 1)
+        (func (param i32 v128) (result i32)
+            v128.const i8x16 -1 2 0 0 0 0 0 0 0 0 0 0 0 0 0 0x7f
+            get_local 1
+            i8x16.shuffle 0 1 16 17 2 3 18 19 4 5 20 21 6 7 22 23
+            get_local 0
+            i32x4.splat
+            i32x4.add
+            get_local 0
+            get_local 1
+            v128.load32_lane offset=4 3
+            f32x4.convert_i32x4_s
+            f32x4.mul
+            get_local 0
+            v128.load8_splat
+            get_local 1
+            v128.bitselect
+            i16x8.extract_lane_u 7))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    assert_eq!(module.functions[0].code.as_ref().unwrap().body[0],
+               highlevel::Instr::Const(Val::V128(0x7f00_0000_0000_0000_0000_0000_0000_02ff)));
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    assert_eq!(decoded.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    assert_eq!(reparsed.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+
+    let out_of_range = parse_module(r#"(module
+        (func (param v128) (result i64)
+            get_local 0
+            i64x2.extract_lane 2))"#).unwrap();
+    assert!(validate(&out_of_range).unwrap_err().to_string().contains("lane index 2 out of range"));
+}
+
+#[test]
+fn atomics_roundtrip() {
+    let text = r#"(module
+        (memory 1 2 shared)
+        (func (param i32 i64) (result i32)
+            get_local 0
+            get_local 1
+            i64.atomic.store32 offset=8
+            atomic.fence
+            get_local 0
+            i32.const 1
+            i32.const 2
+            i32.atomic.rmw8.cmpxchg_u
+            get_local 0
+            i32.atomic.rmw.add
+            get_local 0
+            i32.const 0
+            get_local 1
+            memory.atomic.wait32
+            i32.add
+            get_local 0
+            i32.const 1
+            memory.atomic.notify
+            i32.add))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    assert!(module.memories[0].type_.0.shared);
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    assert!(decoded.memories[0].type_.0.shared);
+    assert_eq!(decoded.memories[0].type_.0.max_size, Some(2));
+    assert_eq!(decoded.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    assert_eq!(reparsed.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    assert!(reparsed.memories[0].type_.0.shared);
+
+    let unaligned = parse_module(r#"(module
+        (memory 1)
+        (func (param i32) (result i64)
+            get_local 0
+            i64.atomic.load align=4))"#).unwrap();
+    assert!(validate(&unaligned).unwrap_err().to_string().contains("atomic accesses must be naturally aligned"));
+    let unbounded = parse_module("(module (memory 1 shared))").unwrap();
+    assert!(validate(&unbounded).unwrap_err().to_string().contains("shared memory must have a maximum size"));
+}
+
+#[test]
+fn tail_calls_roundtrip() {
+    let text = r#"(module
+        (type $i_i (func (param i32) (result i32)))
+        (table 1 funcref)
+        (elem (i32.const 0) $countdown)
+        (func $countdown (param i32) (result i32)
+            get_local 0
+            i32.eqz
+            if
+                i32.const 42
+                return
+            end
+            get_local 0
+            i32.const 1
+            i32.sub
+            i32.const 0
+            return_call_indirect (type $i_i))
+        (func (param i32) (result i32)
+            get_local 0
+            return_call $countdown))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    for (decoded, original) in decoded.functions.iter().zip(module.functions.iter()) {
+        assert_eq!(decoded.code.as_ref().unwrap().body, original.code.as_ref().unwrap().body);
+    }
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    assert_eq!(reparsed.functions[1].code.as_ref().unwrap().body, module.functions[1].code.as_ref().unwrap().body);
+
+    let wrong_results = parse_module(r#"(module
+        (func $f (result i64)
+            i64.const 0)
+        (func (result i32)
+            return_call $f))"#).unwrap();
+    assert!(validate(&wrong_results).unwrap_err().to_string().contains("same result types"));
+}
+
+#[test]
+fn exceptions_roundtrip() {
+    let text = r#"(module
+        (tag $imported (import "env" "error") (param i32))
+        (tag $e (export "e") (param i32 i64))
+        (func $thrower (param i32)
+            get_local 0
+            i64.const 7
+            throw $e)
+        (func (param i32) (result i32)
+            (try $outer (result i32)
+                (do
+                    (try
+                        (do
+                            get_local 0
+                            call $thrower)
+                        (delegate $outer))
+                    i32.const 0)
+                (catch $e
+                    drop)
+                (catch $imported
+                    rethrow 0)
+                (catch_all
+                    i32.const -1)))
+        (func
+            try
+                i32.const 1
+                throw $imported
+            catch_all
+            end))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    assert_eq!(module.tags.len(), 2);
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    assert_eq!(decoded.tags[0].import, Some(("env".to_string(), "error".to_string())));
+    assert_eq!(decoded.tags[1].export, vec!["e".to_string()]);
+    assert_eq!(decoded.tags[1].type_, FunctionType::new(vec![I32, I64], vec![]));
+    for (decoded, original) in decoded.functions.iter().zip(module.functions.iter()) {
+        assert_eq!(decoded.code.as_ref().unwrap().body, original.code.as_ref().unwrap().body);
+    }
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    for (reparsed, original) in reparsed.functions.iter().zip(module.functions.iter()) {
+        assert_eq!(reparsed.code.as_ref().unwrap().body, original.code.as_ref().unwrap().body);
+    }
+
+    let rethrow_outside_catch = parse_module(r#"(module
+        (func
+            try
+                rethrow 0
+            end))"#).unwrap();
+    assert!(validate(&rethrow_outside_catch).unwrap_err().to_string().contains("not a catch block"));
+}
+
+#[test]
+fn multi_memory_and_memory64_roundtrip() {
+    let text = r#"(module
+        (memory $small 1)
+        (memory $large (export "large") i64 1 70000)
+        (data (memory $large) (i64.const 8) "hi")
+        (func (param i32 i64) (result i64)
+            get_local 0
+            i32.load8_u $small
+            drop
+            get_local 1
+            get_local 1
+            i64.load $large offset=4294967296
+            i64.store $large
+            get_local 0
+            get_local 1
+            v128.const i64x2 0 0
+            v128.load8_lane $large 1
+            v128.store8_lane $small offset=2 0
+            i64.const 0
+            i32.const 0
+            i32.const 2
+            memory.init $large 0
+            get_local 1
+            memory.grow $large
+            memory.size $large
+            i64.add))"#;
+    let module = parse_module(text).unwrap();
+    validate(&module).unwrap();
+    assert_eq!(module.memories[1].type_.0.index_type, I64);
+    assert_eq!(module.memories[1].type_.0.max_size, Some(70000));
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module.clone()).encode(&mut buf).unwrap();
+
+    let decoded: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    validate(&decoded).unwrap();
+    assert_eq!(decoded.memories[1].type_.0.index_type, I64);
+    assert_eq!(decoded.memories[1].type_.0.max_size, Some(70000));
+    assert_eq!(decoded.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    let reparsed = parse_module(&print_module(&decoded, PrintOptions::default())).unwrap();
+    assert_eq!(reparsed.functions[0].code.as_ref().unwrap().body, module.functions[0].code.as_ref().unwrap().body);
+    assert_eq!(reparsed.memories[1].type_.0.index_type, I64);
+
+    let i32_address = parse_module(r#"(module
+        (memory i64 1)
+        (func (param i32) (result i32)
+            get_local 0
+            i32.load))"#).unwrap();
+    assert!(validate(&i32_address).unwrap_err().to_string().contains("expected type i64"));
+    let large_offset = parse_module(r#"(module
+        (memory 1)
+        (func (param i32) (result i32)
+            get_local 0
+            i32.load offset=4294967296))"#).unwrap();
+    assert!(validate(&large_offset).unwrap_err().to_string().contains("too large"));
+}
+
+#[test]
+fn const_eval_memory_image_and_table_layout() {
+    let module = parse_module(r#"(module
+        (global $base (import "env" "base") i32)
+        (global $offset i32 (i32.const 4))
+        (memory 1)
+        (data (i32.const 2) "ab")
+        (data (get_global $base) "cd")
+        (data "passive")
+        (table 4 funcref)
+        (elem (i32.const 1) $f $g)
+        (elem (table 0) (i32.const 3) funcref (ref.null func))
+        (func $f)
+        (func $g))"#).unwrap();
+    let mut eval = ConstEvaluator::new(&module);
+    assert_eq!(eval.global(1.into()), Some(ConstValue::Num(Val::I32(4))));
+    assert_eq!(eval.global(0.into()), None);
+    assert_eq!(eval.data_ranges(), vec![Some(DataRange { memory: 0.into(), start: 2, end: 4 }), None, None]);
+    // the second segment could be anywhere, so the memory content is unknown
+    assert_eq!(eval.memory_image(0.into()), None);
+    assert_eq!(eval.table_layout(0.into()), vec![
+        Some(ConstValue::RefNull(ElemType::FuncRef)),
+        Some(ConstValue::RefFunc(0.into())),
+        Some(ConstValue::RefFunc(1.into())),
+        Some(ConstValue::RefNull(ElemType::FuncRef)),
+    ]);
+    assert_eq!(eval.table_functions(0.into()).into_iter().collect::<Vec<_>>(), vec![(1, 0.into()), (2, 1.into())]);
+    assert!(eval.table_layout(1.into()).is_empty());
+    assert!(eval.table_functions(1.into()).is_empty());
+
+    eval.imported_global(0.into(), ConstValue::Num(Val::I32(5)));
+    assert_eq!(eval.data_ranges()[1], Some(DataRange { memory: 0.into(), start: 5, end: 7 }));
+    assert_eq!(eval.memory_image(0.into()), Some(b"\0\0ab\0cd".to_vec()));
+}
+
+#[test]
+fn borrowed_module_hands_out_sub_slices() {
+    let mut module = parse_module(r#"(module
+        (import "env" "print" (func $print (param i32)))
+        (memory 1)
+        (data (i32.const 8) "hello")
+        (func $main (export "main") i32.const 42 call $print))"#).unwrap();
+    module.custom_sections.push((SectionPosition::Last, highlevel::CustomSection::Raw(RawCustomSection { name: "version".into(), content: vec![42] })));
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+
+    let module = borrowed::Module::decode(&buf).unwrap();
+    let within_buf = |bytes: &[u8]| buf.as_ptr() <= bytes.as_ptr() && bytes.as_ptr() as usize + bytes.len() <= buf.as_ptr() as usize + buf.len();
+
+    let bodies = module.function_bodies().unwrap();
+    assert_eq!(bodies.len(), 1);
+    assert!(within_buf(bodies[0].bytes));
+    assert_eq!(&buf[bodies[0].offset..bodies[0].offset + bodies[0].bytes.len()], bodies[0].bytes);
+
+    let data = module.data().unwrap();
+    assert_eq!(data[0].bytes, b"hello");
+    assert!(within_buf(data[0].bytes));
+
+    let imports = module.imports().unwrap();
+    assert_eq!((imports[0].module, imports[0].name), ("env", "print"));
+    assert_eq!(module.exports().unwrap()[0].name, "main");
+    let custom = module.custom_sections().unwrap();
+    assert_eq!((custom[0].name, custom[0].content), ("version", &[42u8][..]));
+
+    // full decoding is the same, whether from the borrowed module or an io::Read
+    let mut from_borrowed = Vec::new();
+    module.to_lowlevel().unwrap().encode(&mut from_borrowed).unwrap();
+    let mut from_reader = Vec::new();
+    lowlevel::Module::decode(&mut io::Cursor::new(&buf)).unwrap().encode(&mut from_reader).unwrap();
+    assert_eq!(from_borrowed, buf);
+    assert_eq!(from_reader, buf);
+
+    // truncated modules are an error, also when splitting into sections
+    let error = borrowed::Module::decode(&buf[..buf.len() - 1]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(error.section, Some(0));
+}
+
+#[test]
+fn cfg_of_branches_and_loops() {
+    let module = parse_module(r#"(module
+        (func (param i32) (result i32)
+            loop
+                get_local 0
+                br_if 0
+            end
+            block
+                get_local 0
+                br_table 0 1
+            end
+            get_local 0
+            if (result i32)
+                i32.const 1
+            else
+                unreachable
+            end))"#).unwrap();
+    let body = &module.functions[0].code.as_ref().unwrap().body;
+    let cfg = Cfg::new(body);
+
+    let ranges: Vec<(usize, usize)> = cfg.blocks.iter().map(|block| (block.start.0, block.end.0)).collect();
+    // the loop header is the entry block itself
+    assert_eq!(ranges, vec![(0, 3), (3, 7), (7, 10), (10, 12), (12, 13), (13, 14), (14, 15), (15, 15)]);
+    let edges: Vec<(usize, usize, EdgeKind)> = cfg.edges.iter().map(|edge| (edge.from.0, edge.to.0, edge.kind)).collect();
+    assert_eq!(edges, vec![
+        (0, 0, EdgeKind::BranchTaken),
+        (0, 1, EdgeKind::BranchNotTaken),
+        (1, 2, EdgeKind::BrTableCase(0)),
+        (1, 6, EdgeKind::BrTableDefault),
+        (2, 3, EdgeKind::BranchTaken),
+        (2, 4, EdgeKind::BranchNotTaken),
+        (3, 5, EdgeKind::Fallthrough),
+        (4, 7, EdgeKind::Unreachable),
+        (5, 6, EdgeKind::Fallthrough),
+        (6, 7, EdgeKind::Return),
+    ]);
+    assert_eq!(cfg.block_of(9.into()), 2.into());
+    assert_eq!(cfg.successors(2.into()).count(), 2);
+    assert_eq!(cfg.predecessors(cfg.exit()).count(), 2);
+    assert!(cfg.to_dot(body).contains("b1 -> b2 [label=\"case 0\"];"));
+    let ends = block_ends(body);
+    assert_eq!((ends[0], ends[4], ends[9], ends[1]), (Some(3.into()), Some(7.into()), Some(13.into()), None));
+
+    let empty = Cfg::new(&[]);
+    assert_eq!(empty.blocks, vec![BasicBlock { start: 0.into(), end: 0.into() }]);
+    assert_eq!((empty.entry(), empty.exit()), (0.into(), 0.into()));
+    assert!(empty.edges.is_empty());
+}
+
+#[test]
+fn dominators_and_nested_loops() {
+    let module = parse_module(r#"(module
+        (func (param i32)
+            loop
+                loop
+                    get_local 0
+                    br_if 0
+                    get_local 0
+                    br_if 1
+                end
+            end))"#).unwrap();
+    let cfg = Cfg::new(&module.functions[0].code.as_ref().unwrap().body);
+    let blocks = |blocks: &[usize]| -> Vec<Idx<BasicBlock>> { blocks.iter().map(|&block| block.into()).collect() };
+
+    let dominators = DominatorTree::dominators(&cfg);
+    assert_eq!((0..5).map(|block| dominators.idom(block.into()).map(|idom| idom.0)).collect::<Vec<_>>(), vec![None, Some(0), Some(1), Some(2), Some(3)]);
+    assert!(dominators.dominates(0.into(), 2.into()));
+    assert!(!dominators.dominates(2.into(), 1.into()));
+    let post_dominators = DominatorTree::post_dominators(&cfg);
+    assert_eq!(post_dominators.root(), cfg.exit());
+    assert_eq!(post_dominators.idom(0.into()), Some(1.into()));
+
+    let loops = natural_loops(&cfg, &dominators);
+    assert_eq!(loops.len(), 2);
+    assert_eq!((loops[0].header, loops[0].depth, &loops[0].blocks), (0.into(), 1, &blocks(&[0, 1, 2])));
+    assert_eq!((loops[1].header, loops[1].depth, &loops[1].blocks), (1.into(), 2, &blocks(&[1])));
+    assert_eq!(loops[0].exits.iter().map(|edge| (edge.from.0, edge.to.0)).collect::<Vec<_>>(), vec![(2, 3)]);
+    assert_eq!(loops[1].exits.iter().map(|edge| (edge.from.0, edge.to.0)).collect::<Vec<_>>(), vec![(1, 2)]);
+
+    // the outer loop body is executed again only if the outer back edge is taken
+    let dependences = control_dependences(&cfg, &post_dominators);
+    assert_eq!(dependences[0].iter().map(|edge| (edge.from.0, edge.to.0, edge.kind)).collect::<Vec<_>>(), vec![(2, 0, EdgeKind::BranchTaken)]);
+    assert!(dependences[3].is_empty());
+}
+
+#[test]
+fn call_graph_resolves_indirect_call_candidates() {
+    let module = parse_module(r#"(module
+        (type $unary (func (param i32) (result i32)))
+        (import "env" "log" (func $log (param i32)))
+        (table 2 funcref)
+        (elem (i32.const 0) $double $print)
+        (func $main (export "main")
+            i32.const 1
+            i32.const 0
+            call_indirect (type $unary)
+            call $print)
+        (func $double (type $unary) get_local 0 i32.const 2 i32.mul)
+        (func $print (param i32) get_local 0 call $log))"#).unwrap();
+    let call_graph = CallGraph::new(&module);
+
+    let edges: Vec<(usize, usize, usize, CallKind)> = call_graph.edges.iter().map(|edge| (edge.caller.0, edge.instr.0, edge.callee.0, edge.kind)).collect();
+    assert_eq!(edges, vec![
+        // only $double matches the type of the call
+        (1, 2, 2, CallKind::Indirect),
+        (1, 3, 3, CallKind::Direct),
+        (3, 1, 0, CallKind::Direct),
+    ]);
+    assert_eq!(call_graph.callers(3.into()).count(), 1);
+    assert_eq!(call_graph.callees(2.into()).count(), 0);
+    let dot = call_graph.to_dot(&module);
+    assert!(dot.contains("f1 -> f2 [style=dashed];"));
+    assert!(dot.contains("f0 [label=\"log\"];"));
+}
+
+#[test]
+fn structured_bodies_roundtrip() {
+    let wasm_modules = wasm_files(TEST_INPUTS).unwrap().into_iter().map(|path| highlevel::Module::from_file(&path).unwrap());
+    let wat_modules = wat_files(TEST_INPUTS).unwrap().into_iter().map(|path| highlevel::Module::from_wat_file(&path).unwrap());
+    for module in wasm_modules.chain(wat_modules) {
+        for code in module.functions.iter().filter_map(|function| function.code.as_ref()) {
+            let nodes = structure(&code.body).unwrap();
+            assert_eq!(nodes.iter().map(Node::instr_count).sum::<usize>() + 1, code.body.len());
+            // compare debug output, since NaN constants are not equal to themselves
+            assert_eq!(format!("{:?}", flatten(&nodes)), format!("{:?}", code.body));
+        }
+    }
+
+    let module = parse_module(r#"(module
+        (tag $e)
+        (func (param i32)
+            block
+                get_local 0
+                if
+                    nop
+                end
+            end
+            try
+                throw $e
+            catch $e
+            catch_all
+            end))"#).unwrap();
+    let mut nodes = structure(&module.functions[0].code.as_ref().unwrap().body).unwrap();
+    match nodes[1] {
+        Node::Try { ref catches, delegate: None, .. } => assert_eq!(catches.iter().map(|catch| catch.tag).collect::<Vec<_>>(), vec![Some(0.into()), None]),
+        ref node => panic!("expected try, got {:?}", node),
+    }
+    // removing a whole block needs no index bookkeeping
+    nodes.remove(0);
+    assert_eq!(flatten(&nodes)[..2], [highlevel::Instr::Try(FunctionType::default()), highlevel::Instr::Throw(0.into())]);
+
+    let error = structure(&[highlevel::Instr::Nop, highlevel::Instr::Else, highlevel::Instr::End]).unwrap_err();
+    assert_eq!(error.to_string(), "unexpected else, at instruction #1");
+}
+
+#[test]
+fn visitors_get_context_and_can_insert() {
+    use ast::{self, ValType};
+    use ast::highlevel::{GlobalOp, Instr::*, LocalOp, VisitContext, Visitor, VisitorMut};
+
+    let mut module = parse_module(r#"(module
+        (global $g (mut i32) (i32.const 0))
+        (func (param i64) (local f32)
+            block
+                get_local 0
+                drop
+            end
+            get_local 1
+            drop))"#).unwrap();
+
+    struct LocalReads(Vec<(usize, usize, ValType)>);
+    impl Visitor for LocalReads {
+        fn visit_local(&mut self, ctx: &VisitContext, _: LocalOp, local: Idx<ast::Local>) {
+            self.0.push((ctx.instr.0, ctx.block_depth, ctx.local_type(local)));
+        }
+    }
+    let mut reads = LocalReads(Vec::new());
+    module.visit(&mut reads);
+    assert_eq!(reads.0, vec![(1, 1, I64), (4, 0, F32)]);
+
+    // count drops in a global, and replace the drop at the top level by storing into a new local
+    struct Rewrite(Idx<highlevel::Global>);
+    impl VisitorMut for Rewrite {
+        fn visit_other(&mut self, ctx: &mut VisitContext, instr: &highlevel::Instr) {
+            if let Drop = *instr {
+                ctx.insert_before(vec![Nop]);
+                ctx.insert_after(vec![Global(GlobalOp::GetGlobal, self.0), Const(Val::I32(1)), Numeric(highlevel::NumericOp::I32Add), Global(GlobalOp::SetGlobal, self.0)]);
+                if ctx.block_depth == 0 {
+                    let local = ctx.add_local(F32);
+                    ctx.replace(vec![Local(LocalOp::SetLocal, local)]);
+                }
+            }
+        }
+    }
+    module.visit_mut(&mut Rewrite(0.into()));
+    let code = module.functions[0].code.as_ref().unwrap();
+    assert_eq!(code.locals, vec![F32, F32]);
+    assert_eq!(code.body[..5], [Block(FunctionType::default()), Local(LocalOp::GetLocal, 0.into()), Nop, Drop, Global(GlobalOp::GetGlobal, 0.into())]);
+    assert_eq!(code.body[8..13], [End, Local(LocalOp::GetLocal, 1.into()), Nop, Local(LocalOp::SetLocal, 2.into()), Global(GlobalOp::GetGlobal, 0.into())]);
+    validate(&module).unwrap();
+}
+
+#[test]
+fn original_offsets_point_to_instructions() {
+    let module = parse_module(r#"(module
+        (func nop)
+        (func (result i32) i32.const 42 i32.const 1 i32.add))"#).unwrap();
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+
+    let module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    let code = module.functions[1].code.as_ref().unwrap();
+    let offsets = code.original_offsets.as_ref().unwrap();
+    assert_eq!(offsets.len(), code.body.len());
+    let opcodes: Vec<u8> = offsets.iter().map(|&offset| buf[offset]).collect();
+    assert_eq!(opcodes, vec![0x41, 0x41, 0x6a, 0x0b]);
+
+    // not decoded from a binary
+    let module = parse_module("(module (func nop))").unwrap();
+    assert!(module.functions[0].code.as_ref().unwrap().original_offsets.is_none());
+}
+
+#[test]
+fn typed_custom_sections_roundtrip() {
+    let mut module = highlevel::Module::default();
+    module.producers_mut().add("processed-by", "wasabi", "0.1.0");
+    module.producers_mut().add("processed-by", "wasabi", "0.2.0");
+    module.custom_sections.push((SectionPosition::Last, highlevel::CustomSection::Raw(RawCustomSection { name: "version".into(), content: vec![42] })));
+
+    let mut buf = Vec::new();
+    lowlevel::Module::from(module).encode(&mut buf).unwrap();
+    let mut module: highlevel::Module = lowlevel::Module::decode(&mut &buf[..]).unwrap().into();
+    CustomSectionRegistry::new().register::<VersionSection>().parse_module(&mut module).unwrap();
+
+    let producers = module.producers_mut();
+    assert_eq!(producers.fields.len(), 1);
+    assert_eq!(producers.fields[0].values.len(), 1);
+    assert_eq!(producers.fields[0].values[0].version, "0.2.0");
+    assert_eq!(module.custom_section::<VersionSection>(), Some(&VersionSection { version: 42 }));
+}
+
+#[test]
+fn custom_sections_keep_their_position() {
+    let raw = |name: &str| highlevel::CustomSection::Raw(RawCustomSection { name: name.into(), content: vec![] });
+    let mut module = highlevel::Module::default();
+    module.add_function(FunctionType::new(vec![], vec![]), vec![], vec![highlevel::Instr::End]);
+    module.custom_sections.push((SectionPosition::First, raw("first")));
+    // there is no import section, so this must come right after the type section
+    module.custom_sections.push((SectionPosition::After(SectionId::Import), raw("after-import")));
+    module.custom_sections.push((SectionPosition::After(SectionId::Code), raw("after-code")));
+
+    let lowlevel = lowlevel::Module::from(module);
+    let order: Vec<String> = lowlevel.sections.iter()
+        .map(|section| match section {
+            lowlevel::Section::Custom(custom) => custom.name().to_string(),
+            section => format!("{:?}", section.id().unwrap()),
+        })
+        .collect();
+    assert_eq!(order, vec!["first", "Type", "after-import", "Function", "Code", "after-code"]);
+
+    let module: highlevel::Module = lowlevel.into();
+    let positions: Vec<SectionPosition> = module.custom_sections.iter().map(|&(position, _)| position).collect();
+    assert_eq!(positions, vec![SectionPosition::First, SectionPosition::After(SectionId::Type), SectionPosition::After(SectionId::Code)]);
+}
+
+/// re-read the written module, so that we validate what actually ends up in the binary
+fn assert_valid_file(path: &Path) {
+    let module = highlevel::Module::from_file(path)
+        .expect(&format!("could not decode wasm output file '{}'", path.display()));
+    validate(&module)
+        .expect(&format!("invalid wasm output file '{}'", path.display()));
+}
+
 /*
  * Speed benchmarks (for parallelization of decoding/encoding) on a "large" wasm file (~2MB for now)
  */
@@ -74,3 +1084,5 @@ fn clone_highlevel_module_speed(bencher: &mut Bencher) {
     let module = highlevel::Module::from_file(LARGE_WASM_FILE).unwrap();
     bencher.iter(|| module.clone())
 }
+
+
@@ -0,0 +1,221 @@
+use super::ParseError;
+
+/* Lexer for the text format, directly producing S-expressions (the structure of the text format). */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// e.g., instruction names, "module", "offset=4", but also "inf" and "nan"
+    Keyword(String),
+    /// symbolic identifier, without the leading $
+    Id(String),
+    /// not interpreted yet, because that depends on the expected type
+    Number(String),
+    String(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sexpr {
+    Atom(Token, Pos),
+    List(Vec<Sexpr>, Pos),
+}
+
+impl Sexpr {
+    pub fn pos(&self) -> Pos {
+        match *self {
+            Sexpr::Atom(_, pos) | Sexpr::List(_, pos) => pos,
+        }
+    }
+}
+
+pub fn parse_sexprs(text: &str) -> Result<Vec<Sexpr>, ParseError> {
+    let mut lexer = Lexer { chars: text.chars().collect(), offset: 0, pos: Pos { line: 1, column: 1 } };
+    // stack of unfinished lists, bottom is the implicit "list" of all top-level S-expressions
+    let mut stack: Vec<(Vec<Sexpr>, Pos)> = vec![(Vec::new(), lexer.pos)];
+
+    loop {
+        lexer.skip_whitespace_and_comments()?;
+        let pos = lexer.pos;
+        match lexer.peek() {
+            None => break,
+            Some('(') => {
+                lexer.bump();
+                stack.push((Vec::new(), pos));
+            }
+            Some(')') => {
+                lexer.bump();
+                if stack.len() == 1 {
+                    return Err(ParseError::new(pos, "unmatched closing parenthesis"));
+                }
+                let (list, list_pos) = stack.pop().unwrap();
+                stack.last_mut().unwrap().0.push(Sexpr::List(list, list_pos));
+            }
+            Some('"') => {
+                let string = lexer.string()?;
+                stack.last_mut().unwrap().0.push(Sexpr::Atom(Token::String(string), pos));
+            }
+            Some(_) => {
+                let word = lexer.word();
+                if word.is_empty() {
+                    return Err(ParseError::new(pos, format!("unexpected character '{}'", lexer.peek().unwrap())));
+                }
+                let token = if word.starts_with('$') {
+                    if word.len() == 1 {
+                        return Err(ParseError::new(pos, "empty identifier"));
+                    }
+                    Token::Id(word[1..].to_string())
+                } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+                    Token::Number(word)
+                } else {
+                    Token::Keyword(word)
+                };
+                stack.last_mut().unwrap().0.push(Sexpr::Atom(token, pos));
+            }
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(ParseError::new(stack.last().unwrap().1, "unclosed parenthesis"));
+    }
+    Ok(stack.pop().unwrap().0)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    offset: usize,
+    pos: Pos,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.offset).cloned()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.chars.get(self.offset + 1).cloned()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += 1;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), ParseError> {
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => { self.bump(); }
+                (Some(';'), Some(';')) => {
+                    while self.peek().map(|c| c != '\n').unwrap_or(false) {
+                        self.bump();
+                    }
+                }
+                (Some('('), Some(';')) => self.block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// block comments can be nested
+    fn block_comment(&mut self) -> Result<(), ParseError> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some('('), Some(';')) => {
+                    self.bump();
+                    self.bump();
+                    depth += 1;
+                }
+                (Some(';'), Some(')')) => {
+                    self.bump();
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                (Some(_), _) => { self.bump(); }
+                (None, _) => return Err(ParseError::new(start, "unclosed block comment")),
+            }
+        }
+    }
+
+    /// keywords, numbers, and identifiers all consist of the same characters
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c) {
+                word.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, ParseError> {
+        let start = self.pos;
+        let error = |message: &str| Err(ParseError::new(start, message));
+        self.bump(); // opening "
+
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return error("unclosed string"),
+                Some('"') => return Ok(bytes),
+                Some('\\') => match self.bump() {
+                    Some('n') => bytes.push(b'\n'),
+                    Some('t') => bytes.push(b'\t'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('\\') => bytes.push(b'\\'),
+                    Some('\'') => bytes.push(b'\''),
+                    Some('"') => bytes.push(b'"'),
+                    Some('u') => {
+                        if self.bump() != Some('{') {
+                            return error("expected { after \\u in string");
+                        }
+                        let mut hex = String::new();
+                        while let Some(c) = self.bump() {
+                            if c == '}' { break; }
+                            hex.push(c);
+                        }
+                        let c = u32::from_str_radix(&hex.replace('_', ""), 16).ok()
+                            .and_then(::std::char::from_u32);
+                        match c {
+                            Some(c) => {
+                                let mut buf = [0; 4];
+                                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            }
+                            None => return error("invalid unicode escape in string"),
+                        }
+                    }
+                    Some(high) => {
+                        let low = self.bump();
+                        match (high.to_digit(16), low.and_then(|low| low.to_digit(16))) {
+                            (Some(high), Some(low)) => bytes.push((high * 16 + low) as u8),
+                            _ => return error("invalid escape sequence in string"),
+                        }
+                    }
+                    None => return error("unclosed string"),
+                },
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/* WebAssembly text format (see https://webassembly.github.io/spec/core/text/index.html) */
+
+mod lexer;
+mod parser;
+mod printer;
+
+pub use self::parser::parse_module;
+pub use self::printer::{print_function, print_module, PrintOptions};
+use self::lexer::Pos;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(pos: Pos, message: impl Into<String>) -> Self {
+        ParseError {
+            line: pos.line,
+            column: pos.column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
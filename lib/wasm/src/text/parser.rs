@@ -0,0 +1,1508 @@
+use ast::*;
+use ast::highlevel::*;
+use std::collections::{BTreeMap, HashMap};
+use std::slice;
+use super::lexer::{parse_sexprs, Pos, Sexpr, Token};
+use super::ParseError;
+
+/* Parser from the text format directly to the high-level AST.
+    - accepts folded and flat instructions, symbolic identifiers ($names) and numeric indices,
+      inline imports/exports/elements/data, and both old and new instruction names
+      (e.g., get_local and local.get).
+    - symbolic names of functions and locals are kept as debug names (without the $).
+    - works in three passes over the module fields: types first, then all index spaces (so that
+      fields can refer to later fields by name), then function bodies and everything else that
+      refers to indices.
+*/
+
+type Result<T> = ::std::result::Result<T, ParseError>;
+
+pub fn parse_module(text: &str) -> Result<Module> {
+    let sexprs = parse_sexprs(text)?;
+    let start = Pos { line: 1, column: 1 };
+
+    // the (module ...) around the fields is optional
+    let mut fields = match sexprs.as_slice() {
+        [Sexpr::List(items, pos)] if head_keyword(items) == Some("module") => Cursor::new(&items[1..], *pos),
+        sexprs => Cursor::new(sexprs, start),
+    };
+    let mut parser = ModuleParser {
+        module: Module::default(),
+        names: Names::default(),
+        instrs: InstrNames::new(),
+        element_count: 0,
+        data_count: 0,
+    };
+    parser.module.name = fields.id().map(str::to_string);
+
+    let mut field_list = Vec::new();
+    while !fields.is_empty() {
+        match fields.next_list() {
+            Some(mut field) => match field.keyword() {
+                Some(keyword) => field_list.push((keyword, field)),
+                None => return field.error("expected module field"),
+            },
+            None => return fields.error("expected module field"),
+        }
+    }
+
+    for (keyword, field) in &field_list {
+        if *keyword == "type" {
+            parser.type_(field.clone())?;
+        }
+    }
+    let mut bodies = Vec::new();
+    for (keyword, field) in &field_list {
+        if let Some(body) = parser.declaration(keyword, field.clone())? {
+            bodies.push(body);
+        }
+    }
+    for body in bodies {
+        parser.body(body)?;
+    }
+
+    Ok(parser.module)
+}
+
+/// everything in a module field that can only be parsed once all indices are known
+enum Body<'a> {
+    Function(Idx<Function>, Cursor<'a>),
+    Global(Idx<Global>, Cursor<'a>),
+    InlineElements(Idx<Table>, Cursor<'a>),
+    InlineData(Idx<Memory>, Vec<u8>),
+    Export(Cursor<'a>),
+    Start(Cursor<'a>),
+    Element(Cursor<'a>),
+    Data(Cursor<'a>),
+}
+
+struct ModuleParser {
+    module: Module,
+    names: Names,
+    instrs: InstrNames,
+    /// segments are only added to the module with their bodies, but their indices must be known before
+    element_count: usize,
+    data_count: usize,
+}
+
+/// symbolic names of all index spaces
+#[derive(Default)]
+struct Names {
+    types: Vec<FunctionType>,
+    type_names: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+    tables: HashMap<String, usize>,
+    memories: HashMap<String, usize>,
+    globals: HashMap<String, usize>,
+    tags: HashMap<String, usize>,
+    elements: HashMap<String, usize>,
+    data: HashMap<String, usize>,
+}
+
+impl ModuleParser {
+    fn type_(&mut self, mut c: Cursor) -> Result<()> {
+        let pos = c.pos;
+        let id = c.id();
+        let mut func = c.expect_list("func")?;
+        let params = params(&mut func)?;
+        let results = results(&mut func)?;
+        func.end()?;
+        c.end()?;
+
+        declare(&mut self.names.type_names, id, self.names.types.len(), pos)?;
+        self.names.types.push(FunctionType::new(params.into_iter().map(|(_, ty)| ty).collect(), results));
+        Ok(())
+    }
+
+    fn declaration<'a>(&mut self, keyword: &str, mut c: Cursor<'a>) -> Result<Option<Body<'a>>> {
+        let pos = c.pos;
+        Ok(match keyword {
+            "type" => None,
+            "import" => {
+                let import = Some((c.name()?, c.name()?));
+                let mut desc = match c.next_list() {
+                    Some(desc) => desc,
+                    None => return c.error("expected import description"),
+                };
+                c.end()?;
+                match desc.keyword() {
+                    Some("func") => { self.function(desc, import, pos)?; }
+                    Some("table") => { self.table(desc, import, pos)?; }
+                    Some("memory") => { self.memory(desc, import, pos)?; }
+                    Some("global") => { self.global(desc, import, pos)?; }
+                    Some("tag") => self.tag(desc, import, pos)?,
+                    _ => return desc.error("expected func, table, memory, global, or tag"),
+                }
+                None
+            }
+            "func" => self.function(c, None, pos)?,
+            "table" => self.table(c, None, pos)?,
+            "memory" => self.memory(c, None, pos)?,
+            "global" => self.global(c, None, pos)?,
+            "tag" => {
+                self.tag(c, None, pos)?;
+                None
+            }
+            "export" => Some(Body::Export(c)),
+            "start" => Some(Body::Start(c)),
+            "elem" => {
+                declare(&mut self.names.elements, c.id(), self.element_count, pos)?;
+                self.element_count += 1;
+                Some(Body::Element(c))
+            }
+            "data" => {
+                declare(&mut self.names.data, c.id(), self.data_count, pos)?;
+                self.data_count += 1;
+                Some(Body::Data(c))
+            }
+            _ => return Err(ParseError::new(pos, format!("unknown module field '{}'", keyword))),
+        })
+    }
+
+    /// import is only given for the (import "module" "name" (func ...)) form
+    fn function<'a>(&mut self, mut c: Cursor<'a>, import: Option<(String, String)>, pos: Pos) -> Result<Option<Body<'a>>> {
+        let idx = self.module.functions.len();
+        let id = c.id();
+        declare(&mut self.names.functions, id, idx, pos)?;
+        let export = inline_exports(&mut c)?;
+        let import = match import {
+            Some(import) => Some(import),
+            None => inline_import(&mut c)?,
+        };
+        let (type_, param_names) = self.names.type_use(&mut c)?;
+
+        let is_import = import.is_some();
+        if is_import {
+            c.end()?;
+        }
+        self.module.functions.push(Function {
+            type_,
+            import,
+            code: None,
+            export,
+            name: id.map(str::to_string),
+            local_names: param_names.into_iter()
+                .enumerate()
+                .filter_map(|(i, name)| Some((i.into(), name?)))
+                .collect(),
+        });
+
+        Ok(if is_import { None } else { Some(Body::Function(idx.into(), c)) })
+    }
+
+    fn table<'a>(&mut self, mut c: Cursor<'a>, import: Option<(String, String)>, pos: Pos) -> Result<Option<Body<'a>>> {
+        let idx = self.module.tables.len();
+        declare(&mut self.names.tables, c.id(), idx, pos)?;
+        let export = inline_exports(&mut c)?;
+        let import = match import {
+            Some(import) => Some(import),
+            None => inline_import(&mut c)?,
+        };
+
+        // abbreviation with inline elements: (table funcref (elem $f $g ...)) or with expressions
+        // (table externref (elem (ref.null extern) ...))
+        let (type_, body) = if import.is_none() && c.peek_keyword().and_then(elem_type).is_some() {
+            let elem_type = c.keyword().and_then(elem_type).unwrap();
+            let elements = c.expect_list("elem")?;
+            c.end()?;
+            let size = elements.items.len() as u64;
+            self.element_count += 1;
+            (TableType(elem_type, Limits { initial_size: size, max_size: Some(size), shared: false, index_type: ValType::I32 }),
+             Some(Body::InlineElements(idx.into(), elements)))
+        } else {
+            let limits = limits(&mut c, ValType::I32)?;
+            let elem_type = match c.keyword().and_then(elem_type) {
+                Some(elem_type) => elem_type,
+                None => return c.error("expected element type"),
+            };
+            c.end()?;
+            (TableType(elem_type, limits), None)
+        };
+
+        self.module.tables.push(Table {
+            type_,
+            import,
+            export,
+        });
+        Ok(body)
+    }
+
+    fn memory<'a>(&mut self, mut c: Cursor<'a>, import: Option<(String, String)>, pos: Pos) -> Result<Option<Body<'a>>> {
+        let idx = self.module.memories.len();
+        declare(&mut self.names.memories, c.id(), idx, pos)?;
+        let export = inline_exports(&mut c)?;
+        let import = match import {
+            Some(import) => Some(import),
+            None => inline_import(&mut c)?,
+        };
+
+        // memory64 proposal: (memory i64 ...)
+        let index_type = match c.peek_keyword() {
+            Some("i64") => ValType::I64,
+            _ => ValType::I32,
+        };
+        if let Some("i32") | Some("i64") = c.peek_keyword() {
+            c.next();
+        }
+
+        // abbreviation with inline data: (memory (data "..." ...))
+        let (limits, body) = match c.list("data") {
+            Some(mut data) if import.is_none() => {
+                let bytes = strings(&mut data)?;
+                let pages = ((bytes.len() + 0xffff) / 0x10000) as u64;
+                self.data_count += 1;
+                (Limits { initial_size: pages, max_size: Some(pages), shared: false, index_type },
+                 Some(Body::InlineData(idx.into(), bytes)))
+            }
+            Some(data) => return data.error("imported memories cannot have inline data"),
+            None => (limits(&mut c, index_type)?, None),
+        };
+        c.end()?;
+
+        self.module.memories.push(Memory {
+            type_: MemoryType(limits),
+            import,
+            export,
+        });
+        Ok(body)
+    }
+
+    fn global<'a>(&mut self, mut c: Cursor<'a>, import: Option<(String, String)>, pos: Pos) -> Result<Option<Body<'a>>> {
+        let idx = self.module.globals.len();
+        declare(&mut self.names.globals, c.id(), idx, pos)?;
+        let export = inline_exports(&mut c)?;
+        let import = match import {
+            Some(import) => Some(import),
+            None => inline_import(&mut c)?,
+        };
+        let type_ = match c.list("mut") {
+            Some(mut mut_) => {
+                let ty = mut_.val_type()?;
+                mut_.end()?;
+                GlobalType(ty, Mutability::Mut)
+            }
+            None => GlobalType(c.val_type()?, Mutability::Const),
+        };
+
+        let is_import = import.is_some();
+        if is_import {
+            c.end()?;
+        }
+        self.module.globals.push(Global {
+            type_,
+            import,
+            init: None,
+            export,
+        });
+        Ok(if is_import { None } else { Some(Body::Global(idx.into(), c)) })
+    }
+
+    /// tags have no body, the type is all there is
+    fn tag(&mut self, mut c: Cursor, import: Option<(String, String)>, pos: Pos) -> Result<()> {
+        let idx = self.module.tags.len();
+        declare(&mut self.names.tags, c.id(), idx, pos)?;
+        let export = inline_exports(&mut c)?;
+        let import = match import {
+            Some(import) => Some(import),
+            None => inline_import(&mut c)?,
+        };
+        let (type_, _) = self.names.type_use(&mut c)?;
+        c.end()?;
+
+        self.module.tags.push(Tag {
+            type_,
+            import,
+            export,
+        });
+        Ok(())
+    }
+
+    fn body(&mut self, body: Body) -> Result<()> {
+        match body {
+            Body::Function(idx, mut c) => {
+                let function = &self.module.functions[idx.0];
+                let mut code_parser = CodeParser::new(&self.names, &self.instrs);
+                for (i, name) in &function.local_names {
+                    code_parser.locals.insert(name.clone(), i.0);
+                }
+                let param_count = function.type_.params.len();
+
+                let mut locals = Vec::new();
+                let mut local_names = BTreeMap::new();
+                while let Some(mut local) = c.list("local") {
+                    let pos = local.pos;
+                    if let Some(id) = local.id() {
+                        let local_idx = param_count + locals.len();
+                        declare(&mut code_parser.locals, Some(id), local_idx, pos)?;
+                        local_names.insert(local_idx.into(), id.to_string());
+                        locals.push(local.val_type()?);
+                        local.end()?;
+                    } else {
+                        while !local.is_empty() {
+                            locals.push(local.val_type()?);
+                        }
+                    }
+                }
+                let body = code_parser.body(&mut c)?;
+
+                let function = &mut self.module.functions[idx.0];
+                function.local_names.append(&mut local_names);
+                function.code = Some(Code { locals, body, original_offsets: None });
+            }
+            Body::Global(idx, mut c) => {
+                let init = CodeParser::new(&self.names, &self.instrs).body(&mut c)?;
+                self.module.globals[idx.0].init = Some(init);
+            }
+            Body::InlineElements(idx, mut c) => {
+                let items = if c.peek_list().is_some() {
+                    self.element_exprs(&mut c, self.module.tables[idx.0].type_.0)?
+                } else {
+                    self.element_functions(&mut c)?
+                };
+                self.module.elements.push(Element {
+                    mode: ElementMode::Active { table: idx, offset: vec![Instr::Const(Val::I32(0)), Instr::End] },
+                    items,
+                });
+            }
+            Body::InlineData(idx, bytes) => {
+                let offset = match self.module.memory_index_type(idx) {
+                    ValType::I64 => Val::I64(0),
+                    _ => Val::I32(0),
+                };
+                self.module.data.push(Data {
+                    mode: DataMode::Active { memory: idx, offset: vec![Instr::Const(offset), Instr::End] },
+                    bytes,
+                });
+            }
+            Body::Export(mut c) => {
+                let name = c.name()?;
+                let mut desc = match c.next_list() {
+                    Some(desc) => desc,
+                    None => return c.error("expected export description"),
+                };
+                c.end()?;
+                let keyword = desc.keyword();
+                let pos = desc.current_pos();
+                let (what, idx, exports) = match keyword {
+                    Some("func") => {
+                        let idx = index(&mut desc, &self.names.functions, "function")?;
+                        ("function", idx, self.module.functions.get_mut(idx).map(|function| &mut function.export))
+                    }
+                    Some("table") => {
+                        let idx = index(&mut desc, &self.names.tables, "table")?;
+                        ("table", idx, self.module.tables.get_mut(idx).map(|table| &mut table.export))
+                    }
+                    Some("memory") => {
+                        let idx = index(&mut desc, &self.names.memories, "memory")?;
+                        ("memory", idx, self.module.memories.get_mut(idx).map(|memory| &mut memory.export))
+                    }
+                    Some("global") => {
+                        let idx = index(&mut desc, &self.names.globals, "global")?;
+                        ("global", idx, self.module.globals.get_mut(idx).map(|global| &mut global.export))
+                    }
+                    Some("tag") => {
+                        let idx = index(&mut desc, &self.names.tags, "tag")?;
+                        ("tag", idx, self.module.tags.get_mut(idx).map(|tag| &mut tag.export))
+                    }
+                    _ => return desc.error("expected func, table, memory, global, or tag"),
+                };
+                match exports {
+                    Some(exports) => exports.push(name),
+                    None => return Err(ParseError::new(pos, format!("unknown {} index {}", what, idx))),
+                }
+                desc.end()?;
+            }
+            Body::Start(mut c) => {
+                self.module.start = Some(index(&mut c, &self.names.functions, "function")?.into());
+                c.end()?;
+            }
+            Body::Element(mut c) => {
+                // (elem declare items), (elem items), or (elem (table $t)? offset items), where items
+                // are either func? $f* or a reference type followed by expressions
+                let mode = match c.peek_keyword() {
+                    Some("declare") => {
+                        c.next();
+                        ElementMode::Declarative
+                    }
+                    Some(keyword) if keyword == "func" || elem_type(keyword).is_some() => ElementMode::Passive,
+                    _ => {
+                        let table = self.segment_target(&mut c, "table", &self.names.tables, self.module.tables.len())?;
+                        ElementMode::Active { table, offset: self.offset(&mut c)? }
+                    }
+                };
+                let items = match c.peek_keyword() {
+                    Some("func") => {
+                        c.next();
+                        self.element_functions(&mut c)?
+                    }
+                    Some(keyword) => match elem_type(keyword) {
+                        Some(type_) => {
+                            c.next();
+                            self.element_exprs(&mut c, type_)?
+                        }
+                        None => return c.error("expected func or reference type"),
+                    },
+                    None => self.element_functions(&mut c)?,
+                };
+                self.module.elements.push(Element { mode, items });
+            }
+            Body::Data(mut c) => {
+                // (data "..."*) or (data (memory $m)? offset "..."*)
+                let mode = match c.peek() {
+                    Some(Sexpr::Atom(Token::String(_), _)) | None => DataMode::Passive,
+                    _ => {
+                        let memory = self.segment_target(&mut c, "memory", &self.names.memories, self.module.memories.len())?;
+                        DataMode::Active { memory, offset: self.offset(&mut c)? }
+                    }
+                };
+                let bytes = strings(&mut c)?;
+                self.module.data.push(Data { mode, bytes });
+            }
+        }
+        Ok(())
+    }
+
+    fn element_functions(&self, c: &mut Cursor) -> Result<ElementItems> {
+        let mut functions = Vec::new();
+        while !c.is_empty() {
+            functions.push(index(c, &self.names.functions, "function")?.into());
+        }
+        Ok(ElementItems::Functions(functions))
+    }
+
+    /// every item is either (item instr*) or a single folded instruction
+    fn element_exprs(&self, c: &mut Cursor, type_: ElemType) -> Result<ElementItems> {
+        let mut exprs = Vec::new();
+        while !c.is_empty() {
+            if let Some(mut item) = c.list("item") {
+                exprs.push(CodeParser::new(&self.names, &self.instrs).body(&mut item)?);
+                continue;
+            }
+            match c.peek() {
+                Some(item @ Sexpr::List(..)) => {
+                    c.next();
+                    exprs.push(CodeParser::new(&self.names, &self.instrs).body(&mut Cursor::new(slice::from_ref(item), item.pos()))?);
+                }
+                _ => return c.error("expected element expression"),
+            }
+        }
+        Ok(ElementItems::Expressions(type_, exprs))
+    }
+
+    /// table/memory of an active segment: (table $t), just $t (old syntax), or implicitly 0
+    fn segment_target<T>(&self, c: &mut Cursor, what: &str, names: &HashMap<String, usize>, count: usize) -> Result<Idx<T>> {
+        let idx = match c.list(what) {
+            Some(mut list) => {
+                let idx = index(&mut list, names, what)?;
+                list.end()?;
+                idx
+            }
+            None if c.peek_index() => index(c, names, what)?,
+            None => 0,
+        };
+        if idx >= count {
+            return c.error(format!("unknown {} {}", what, idx));
+        }
+        Ok(idx.into())
+    }
+
+    /// either (offset instr*) or a single folded instruction
+    fn offset(&self, c: &mut Cursor) -> Result<Expr> {
+        if let Some(mut offset) = c.list("offset") {
+            return CodeParser::new(&self.names, &self.instrs).body(&mut offset);
+        }
+        match c.peek() {
+            Some(item @ Sexpr::List(..)) => {
+                c.next();
+                CodeParser::new(&self.names, &self.instrs).body(&mut Cursor::new(slice::from_ref(item), item.pos()))
+            }
+            _ => c.error("expected offset expression"),
+        }
+    }
+}
+
+impl Names {
+    /// (type $t)? (param ...)* (result ...)*, returns also the names of the parameters (if given)
+    fn type_use(&self, c: &mut Cursor) -> Result<(FunctionType, Vec<Option<String>>)> {
+        let type_idx = match c.list("type") {
+            Some(mut type_) => {
+                let idx = index(&mut type_, &self.type_names, "type")?;
+                type_.end()?;
+                Some((idx, type_.pos))
+            }
+            None => None,
+        };
+        let params = params(c)?;
+        let results = results(c)?;
+        let has_inline_type = !params.is_empty() || !results.is_empty();
+        let (param_names, params): (Vec<_>, Vec<_>) = params.into_iter().unzip();
+        let inline_type = FunctionType::new(params, results);
+
+        let type_ = match type_idx {
+            Some((idx, pos)) => {
+                let type_ = self.types.get(idx)
+                    .ok_or_else(|| ParseError::new(pos, format!("unknown type {}", idx)))?;
+                if has_inline_type && &inline_type != type_ {
+                    return Err(ParseError::new(pos, "inline function type does not match referenced type"));
+                }
+                type_.clone()
+            }
+            None => inline_type,
+        };
+        let param_names = if param_names.is_empty() { vec![None; type_.params.len()] } else { param_names };
+        Ok((type_, param_names))
+    }
+}
+
+
+/* Instructions */
+
+/// lookup tables for all instructions that are identified only by their name
+struct InstrNames {
+    simple: HashMap<String, Instr>,
+    loads: HashMap<String, LoadOp>,
+    stores: HashMap<String, StoreOp>,
+    load_lanes: HashMap<String, LoadLaneOp>,
+    store_lanes: HashMap<String, StoreLaneOp>,
+    lanes: HashMap<String, SimdLaneOp>,
+    atomic_loads: HashMap<String, AtomicLoadOp>,
+    atomic_stores: HashMap<String, AtomicStoreOp>,
+    atomic_rmws: HashMap<String, AtomicRmwOp>,
+    atomic_cmpxchgs: HashMap<String, AtomicCmpxchgOp>,
+}
+
+impl InstrNames {
+    fn new() -> Self {
+        let mut simple = HashMap::new();
+        let instrs = [Instr::Unreachable, Instr::Nop, Instr::Return, Instr::Drop, Instr::RefIsNull, Instr::AtomicFence];
+        let ops = NumericOp::all().iter().map(|&op| Instr::Numeric(op))
+            .chain(SimdOp::all().iter().map(|&op| Instr::Simd(op)));
+        for instr in instrs.iter().cloned().chain(ops) {
+            insert_with_new_name(&mut simple, instr.to_name(), instr.clone());
+        }
+        let mut loads = HashMap::new();
+        for &op in LoadOp::all() {
+            insert_with_new_name(&mut loads, Instr::Load(op, Memarg::default()).to_name(), op);
+        }
+        let mut stores = HashMap::new();
+        for &op in StoreOp::all() {
+            insert_with_new_name(&mut stores, Instr::Store(op, Memarg::default()).to_name(), op);
+        }
+        let load_lanes = LoadLaneOp::all().iter()
+            .map(|&op| (Instr::LoadLane(op, Memarg::default(), 0).to_name().to_string(), op))
+            .collect();
+        let store_lanes = StoreLaneOp::all().iter()
+            .map(|&op| (Instr::StoreLane(op, Memarg::default(), 0).to_name().to_string(), op))
+            .collect();
+        let lanes = SimdLaneOp::all().iter()
+            .map(|&op| (Instr::SimdLane(op, 0).to_name().to_string(), op))
+            .collect();
+        let atomic_loads = AtomicLoadOp::all().iter()
+            .map(|&op| (Instr::AtomicLoad(op, Memarg::default()).to_name().to_string(), op))
+            .collect();
+        let atomic_stores = AtomicStoreOp::all().iter()
+            .map(|&op| (Instr::AtomicStore(op, Memarg::default()).to_name().to_string(), op))
+            .collect();
+        let atomic_rmws = AtomicRmwOp::all().iter()
+            .map(|&op| (Instr::AtomicRmw(op, Memarg::default()).to_name().to_string(), op))
+            .collect();
+        let atomic_cmpxchgs = AtomicCmpxchgOp::all().iter()
+            .map(|&op| (Instr::AtomicCmpxchg(op, Memarg::default()).to_name().to_string(), op))
+            .collect();
+        InstrNames { simple, loads, stores, load_lanes, store_lanes, lanes, atomic_loads, atomic_stores, atomic_rmws, atomic_cmpxchgs }
+    }
+}
+
+/// the spec renamed conversion instructions, e.g., i32.trunc_s/f32 to i32.trunc_f32_s, accept both
+fn insert_with_new_name<T: Clone>(map: &mut HashMap<String, T>, name: &str, value: T) {
+    if let Some(slash) = name.find('/') {
+        let (op, source_type) = (&name[..slash], &name[slash + 1..]);
+        let new_name = if op.ends_with("_s") || op.ends_with("_u") {
+            format!("{}_{}{}", &op[..op.len() - 2], source_type, &op[op.len() - 2..])
+        } else {
+            format!("{}_{}", op, source_type)
+        };
+        map.insert(new_name, value.clone());
+    }
+    map.insert(name.to_string(), value);
+}
+
+struct CodeParser<'a, 'n> {
+    names: &'n Names,
+    instrs: &'n InstrNames,
+    locals: HashMap<String, usize>,
+    /// innermost block last, None for blocks without a symbolic label
+    labels: Vec<Option<&'a str>>,
+    body: Vec<Instr>,
+}
+
+impl<'a, 'n> CodeParser<'a, 'n> {
+    fn new(names: &'n Names, instrs: &'n InstrNames) -> Self {
+        CodeParser {
+            names,
+            instrs,
+            locals: HashMap::new(),
+            labels: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// all instructions until the end of c, plus the implicit end of functions/constant expressions
+    fn body(mut self, c: &mut Cursor<'a>) -> Result<Expr> {
+        self.instrs(c)?;
+        if !self.labels.is_empty() {
+            return c.error("missing end of block");
+        }
+        self.body.push(Instr::End);
+        Ok(self.body)
+    }
+
+    fn instrs(&mut self, c: &mut Cursor<'a>) -> Result<()> {
+        while let Some(item) = c.next() {
+            match item {
+                Sexpr::List(items, pos) => self.folded(Cursor::new(items, *pos))?,
+                Sexpr::Atom(Token::Keyword(keyword), pos) => self.flat(keyword, *pos, c)?,
+                item => return Err(ParseError::new(item.pos(), "expected instruction")),
+            }
+        }
+        Ok(())
+    }
+
+    fn flat(&mut self, keyword: &'a str, pos: Pos, c: &mut Cursor<'a>) -> Result<()> {
+        match keyword {
+            "block" | "loop" | "if" | "try" => {
+                self.labels.push(c.id());
+                let (block_type, _) = self.names.type_use(c)?;
+                self.body.push(match keyword {
+                    "block" => Instr::Block(block_type),
+                    "loop" => Instr::Loop(block_type),
+                    "if" => Instr::If(block_type),
+                    _ => Instr::Try(block_type),
+                });
+            }
+            "else" => {
+                self.check_label(c, pos)?;
+                self.body.push(Instr::Else);
+            }
+            "catch" => {
+                let tag = index(c, &self.names.tags, "tag")?;
+                self.body.push(Instr::Catch(tag.into()));
+            }
+            "catch_all" => self.body.push(Instr::CatchAll),
+            "delegate" => {
+                // the label is relative to the blocks outside of the try block
+                if self.labels.pop().is_none() {
+                    return Err(ParseError::new(pos, "delegate without matching try"));
+                }
+                let label = self.label(c)?;
+                self.body.push(Instr::Delegate(label));
+            }
+            "end" => {
+                self.check_label(c, pos)?;
+                if self.labels.pop().is_none() {
+                    return Err(ParseError::new(pos, "end without matching block"));
+                }
+                self.body.push(Instr::End);
+            }
+            _ => {
+                let instr = self.plain(keyword, pos, c)?;
+                self.body.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    fn folded(&mut self, mut c: Cursor<'a>) -> Result<()> {
+        let pos = c.pos;
+        let keyword = match c.keyword() {
+            Some(keyword) => keyword,
+            None => return c.error("expected instruction"),
+        };
+        match keyword {
+            "block" | "loop" => {
+                self.labels.push(c.id());
+                let (block_type, _) = self.names.type_use(&mut c)?;
+                self.body.push(if keyword == "block" { Instr::Block(block_type) } else { Instr::Loop(block_type) });
+                self.instrs(&mut c)?;
+                self.labels.pop();
+                self.body.push(Instr::End);
+            }
+            "if" => {
+                let label = c.id();
+                let (block_type, _) = self.names.type_use(&mut c)?;
+                // condition is evaluated before the if, i.e., outside of the block
+                while c.peek_list() != Some("then") {
+                    match c.next_list() {
+                        Some(condition) => self.folded(condition)?,
+                        None => return c.error("expected (then ...)"),
+                    }
+                }
+                let mut then = c.expect_list("then")?;
+                self.labels.push(label);
+                self.body.push(Instr::If(block_type));
+                self.instrs(&mut then)?;
+                if let Some(mut else_) = c.list("else") {
+                    self.body.push(Instr::Else);
+                    self.instrs(&mut else_)?;
+                }
+                c.end()?;
+                self.labels.pop();
+                self.body.push(Instr::End);
+            }
+            // (try $l? blocktype (do ...) (catch $t ...)* (catch_all ...)?) or with (delegate l)
+            "try" => {
+                self.labels.push(c.id());
+                let (block_type, _) = self.names.type_use(&mut c)?;
+                self.body.push(Instr::Try(block_type));
+                let mut do_ = c.expect_list("do")?;
+                self.instrs(&mut do_)?;
+                if let Some(mut delegate) = c.list("delegate") {
+                    self.labels.pop();
+                    let label = self.label(&mut delegate)?;
+                    delegate.end()?;
+                    c.end()?;
+                    self.body.push(Instr::Delegate(label));
+                    return Ok(());
+                }
+                while let Some(mut catch) = c.list("catch") {
+                    let tag = index(&mut catch, &self.names.tags, "tag")?;
+                    self.body.push(Instr::Catch(tag.into()));
+                    self.instrs(&mut catch)?;
+                }
+                if let Some(mut catch_all) = c.list("catch_all") {
+                    self.body.push(Instr::CatchAll);
+                    self.instrs(&mut catch_all)?;
+                }
+                c.end()?;
+                self.labels.pop();
+                self.body.push(Instr::End);
+            }
+            _ => {
+                let instr = self.plain(keyword, pos, &mut c)?;
+                // operands come first
+                while !c.is_empty() {
+                    match c.next_list() {
+                        Some(operand) => self.folded(operand)?,
+                        None => return c.error("expected folded instruction"),
+                    }
+                }
+                self.body.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    /// instructions that do not influence the block structure
+    fn plain(&mut self, keyword: &str, pos: Pos, c: &mut Cursor<'a>) -> Result<Instr> {
+        use ast::highlevel::{GlobalOp::*, LocalOp::*, TableOp::*};
+        Ok(match keyword {
+            "br" => Instr::Br(self.label(c)?),
+            "br_if" => Instr::BrIf(self.label(c)?),
+            "br_table" => {
+                let mut labels = vec![self.label(c)?];
+                while c.peek_index() {
+                    labels.push(self.label(c)?);
+                }
+                let default = labels.pop().unwrap();
+                Instr::BrTable(labels, default)
+            }
+
+            "throw" => Instr::Throw(index(c, &self.names.tags, "tag")?.into()),
+            "rethrow" => Instr::Rethrow(self.label(c)?),
+
+            "call" => Instr::Call(index(c, &self.names.functions, "function")?.into()),
+            "select" => match c.list("result") {
+                Some(mut result) => {
+                    let ty = result.val_type()?;
+                    result.end()?;
+                    Instr::TypedSelect(ty)
+                }
+                None => Instr::Select,
+            },
+
+            "call_indirect" => {
+                let table_idx = if c.peek_index() { index(c, &self.names.tables, "table")? } else { 0 };
+                let (type_, _) = self.names.type_use(c)?;
+                Instr::CallIndirect(type_, table_idx.into())
+            }
+            "return_call" => Instr::ReturnCall(index(c, &self.names.functions, "function")?.into()),
+            "return_call_indirect" => {
+                let table_idx = if c.peek_index() { index(c, &self.names.tables, "table")? } else { 0 };
+                let (type_, _) = self.names.type_use(c)?;
+                Instr::ReturnCallIndirect(type_, table_idx.into())
+            }
+
+            "get_local" | "local.get" => Instr::Local(GetLocal, index(c, &self.locals, "local")?.into()),
+            "set_local" | "local.set" => Instr::Local(SetLocal, index(c, &self.locals, "local")?.into()),
+            "tee_local" | "local.tee" => Instr::Local(TeeLocal, index(c, &self.locals, "local")?.into()),
+            "get_global" | "global.get" => Instr::Global(GetGlobal, index(c, &self.names.globals, "global")?.into()),
+            "set_global" | "global.set" => Instr::Global(SetGlobal, index(c, &self.names.globals, "global")?.into()),
+            "table.get" => Instr::Table(TableGet, self.optional_table(c)?),
+            "table.set" => Instr::Table(TableSet, self.optional_table(c)?),
+            "table.size" => Instr::Table(TableSize, self.optional_table(c)?),
+            "table.grow" => Instr::Table(TableGrow, self.optional_table(c)?),
+            "table.fill" => Instr::Table(TableFill, self.optional_table(c)?),
+
+            "memory.size" | "current_memory" => Instr::MemorySize(self.optional_memory(c)?),
+            "memory.grow" | "grow_memory" => Instr::MemoryGrow(self.optional_memory(c)?),
+
+            "memory.init" => {
+                // memory.init $m? $d, i.e., with only one index, it is the data segment
+                let mut lookahead = c.clone();
+                lookahead.next();
+                let memory_idx = if lookahead.peek_index() { self.optional_memory(c)? } else { 0.into() };
+                Instr::MemoryInit(index(c, &self.names.data, "data")?.into(), memory_idx)
+            }
+            "data.drop" => Instr::DataDrop(index(c, &self.names.data, "data")?.into()),
+            "memory.copy" => {
+                let (dst, src) = if c.peek_index() {
+                    (self.optional_memory(c)?, self.optional_memory(c)?)
+                } else {
+                    (0.into(), 0.into())
+                };
+                Instr::MemoryCopy(dst, src)
+            }
+            "memory.fill" => Instr::MemoryFill(self.optional_memory(c)?),
+            "table.init" => {
+                // table.init $t? $e, i.e., with only one index, it is the element segment
+                let mut lookahead = c.clone();
+                lookahead.next();
+                let table_idx = if lookahead.peek_index() { index(c, &self.names.tables, "table")? } else { 0 };
+                Instr::TableInit(index(c, &self.names.elements, "element")?.into(), table_idx.into())
+            }
+            "elem.drop" => Instr::ElemDrop(index(c, &self.names.elements, "element")?.into()),
+            "table.copy" => {
+                let (dst, src) = if c.peek_index() {
+                    (index(c, &self.names.tables, "table")?, index(c, &self.names.tables, "table")?)
+                } else {
+                    (0, 0)
+                };
+                Instr::TableCopy(dst.into(), src.into())
+            }
+
+            "ref.null" => match c.keyword().and_then(heap_type) {
+                Some(type_) => Instr::RefNull(type_),
+                None => return Err(ParseError::new(pos, "expected func or extern after ref.null")),
+            },
+            "ref.func" => Instr::RefFunc(index(c, &self.names.functions, "function")?.into()),
+
+            "i32.const" => Instr::Const(Val::I32(number(c, parse_i32, "i32")?)),
+            "i64.const" => Instr::Const(Val::I64(number(c, parse_i64, "i64")?)),
+            "f32.const" => Instr::Const(Val::F32(number(c, parse_f32, "f32")?)),
+            "f64.const" => Instr::Const(Val::F64(number(c, parse_f64, "f64")?)),
+            "v128.const" => Instr::Const(Val::V128(v128(c)?)),
+
+            "memory.atomic.notify" => Instr::MemoryAtomicNotify(self.memarg(c, 2)?),
+            "memory.atomic.wait32" => Instr::MemoryAtomicWait32(self.memarg(c, 2)?),
+            "memory.atomic.wait64" => Instr::MemoryAtomicWait64(self.memarg(c, 3)?),
+
+            "i8x16.shuffle" => {
+                let mut lanes = [0; 16];
+                for lane in lanes.iter_mut() {
+                    *lane = number(c, parse_u8, "lane index")?;
+                }
+                Instr::Shuffle(lanes)
+            }
+
+            _ => if let Some(instr) = self.instrs.simple.get(keyword) {
+                instr.clone()
+            } else if let Some(&op) = self.instrs.loads.get(keyword) {
+                Instr::Load(op, self.memarg(c, op.natural_alignment())?)
+            } else if let Some(&op) = self.instrs.stores.get(keyword) {
+                Instr::Store(op, self.memarg(c, op.natural_alignment())?)
+            } else if let Some(&op) = self.instrs.lanes.get(keyword) {
+                Instr::SimdLane(op, number(c, parse_u8, "lane index")?)
+            } else if let Some(&op) = self.instrs.load_lanes.get(keyword) {
+                let memarg = self.lane_memarg(c, op.natural_alignment())?;
+                Instr::LoadLane(op, memarg, number(c, parse_u8, "lane index")?)
+            } else if let Some(&op) = self.instrs.store_lanes.get(keyword) {
+                let memarg = self.lane_memarg(c, op.natural_alignment())?;
+                Instr::StoreLane(op, memarg, number(c, parse_u8, "lane index")?)
+            } else if let Some(&op) = self.instrs.atomic_loads.get(keyword) {
+                Instr::AtomicLoad(op, self.memarg(c, op.natural_alignment())?)
+            } else if let Some(&op) = self.instrs.atomic_stores.get(keyword) {
+                Instr::AtomicStore(op, self.memarg(c, op.natural_alignment())?)
+            } else if let Some(&op) = self.instrs.atomic_rmws.get(keyword) {
+                Instr::AtomicRmw(op, self.memarg(c, op.natural_alignment())?)
+            } else if let Some(&op) = self.instrs.atomic_cmpxchgs.get(keyword) {
+                Instr::AtomicCmpxchg(op, self.memarg(c, op.natural_alignment())?)
+            } else {
+                return Err(ParseError::new(pos, format!("unknown instruction '{}'", keyword)));
+            }
+        })
+    }
+
+    /// table index of table.get etc., 0 if omitted
+    fn optional_table(&self, c: &mut Cursor) -> Result<Idx<Table>> {
+        Ok(if c.peek_index() { index(c, &self.names.tables, "table")? } else { 0 }.into())
+    }
+
+    /// memory index of memory.size etc. (multi-memory proposal), 0 if omitted
+    fn optional_memory(&self, c: &mut Cursor) -> Result<Idx<Memory>> {
+        Ok(if c.peek_index() { index(c, &self.names.memories, "memory")? } else { 0 }.into())
+    }
+
+    /// $m? offset=N? align=N?
+    fn memarg(&self, c: &mut Cursor, natural_alignment: u32) -> Result<Memarg> {
+        let memory = self.optional_memory(c)?;
+        memarg(c, natural_alignment, memory)
+    }
+
+    /// like memarg(), but a single index is the lane index that follows the memarg, not a memory
+    fn lane_memarg(&self, c: &mut Cursor, natural_alignment: u32) -> Result<Memarg> {
+        let mut lookahead = c.clone();
+        lookahead.next();
+        let memory_given = c.peek_index() && (lookahead.peek_index() || lookahead.peek_keyword()
+            .map_or(false, |keyword| keyword.starts_with("offset=") || keyword.starts_with("align=")));
+        let memory = if memory_given { self.optional_memory(c)? } else { 0.into() };
+        memarg(c, natural_alignment, memory)
+    }
+
+    /// labels are relative, i.e., symbolic labels are resolved to the depth of the block
+    fn label(&self, c: &mut Cursor) -> Result<Idx<Label>> {
+        match c.next() {
+            Some(Sexpr::Atom(Token::Number(number), pos)) => parse_u32(number)
+                .map(|label| (label as usize).into())
+                .ok_or_else(|| ParseError::new(*pos, format!("invalid label '{}'", number))),
+            Some(Sexpr::Atom(Token::Id(id), pos)) => self.labels.iter().rev()
+                .position(|label| *label == Some(id.as_str()))
+                .map(Idx::from)
+                .ok_or_else(|| ParseError::new(*pos, format!("unknown label ${}", id))),
+            Some(item) => Err(ParseError::new(item.pos(), "expected label")),
+            None => c.error("expected label"),
+        }
+    }
+
+    /// optional label after else and end must match the label of the block
+    fn check_label(&self, c: &mut Cursor, pos: Pos) -> Result<()> {
+        match c.id() {
+            Some(id) if self.labels.last() != Some(&Some(id)) => Err(ParseError::new(pos, format!("mismatching label ${}", id))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// offset=N? align=N?
+fn memarg(c: &mut Cursor, natural_alignment: u32, memory: Idx<Memory>) -> Result<Memarg> {
+    let mut memarg = Memarg { alignment: natural_alignment, offset: 0, memory };
+    if let Some(keyword) = c.peek_keyword().filter(|keyword| keyword.starts_with("offset=")) {
+        // 64-bit offsets are only valid for memory64, but this is checked during validation
+        memarg.offset = match parse_uint(&keyword["offset=".len()..]) {
+            Some(offset) => offset,
+            None => return c.error("invalid offset"),
+        };
+        c.next();
+    }
+    if let Some(keyword) = c.peek_keyword().filter(|keyword| keyword.starts_with("align=")) {
+        memarg.alignment = match parse_u32(&keyword["align=".len()..]) {
+            // in the binary, alignment is given as exponent of 2
+            Some(alignment) if alignment.is_power_of_two() => alignment.trailing_zeros(),
+            _ => return c.error("invalid alignment"),
+        };
+        c.next();
+    }
+    Ok(memarg)
+}
+
+
+/* Types and other parts of module fields */
+
+fn val_type(keyword: &str) -> Option<ValType> {
+    match keyword {
+        "i32" => Some(ValType::I32),
+        "i64" => Some(ValType::I64),
+        "f32" => Some(ValType::F32),
+        "f64" => Some(ValType::F64),
+        "v128" => Some(ValType::V128),
+        "funcref" => Some(ValType::FuncRef),
+        "externref" => Some(ValType::ExternRef),
+        _ => None,
+    }
+}
+
+fn elem_type(keyword: &str) -> Option<ElemType> {
+    match keyword {
+        "anyfunc" | "funcref" => Some(ElemType::FuncRef),
+        "externref" => Some(ElemType::ExternRef),
+        _ => None,
+    }
+}
+
+/// immediate of ref.null, e.g., ref.null func
+fn heap_type(keyword: &str) -> Option<ElemType> {
+    match keyword {
+        "func" => Some(ElemType::FuncRef),
+        "extern" => Some(ElemType::ExternRef),
+        _ => None,
+    }
+}
+
+/// (param $x t) or (param t*), possibly multiple times
+fn params(c: &mut Cursor) -> Result<Vec<(Option<String>, ValType)>> {
+    let mut params = Vec::new();
+    while let Some(mut param) = c.list("param") {
+        if let Some(id) = param.id() {
+            params.push((Some(id.to_string()), param.val_type()?));
+            param.end()?;
+        } else {
+            while !param.is_empty() {
+                params.push((None, param.val_type()?));
+            }
+        }
+    }
+    Ok(params)
+}
+
+fn results(c: &mut Cursor) -> Result<Vec<ValType>> {
+    let mut results = Vec::new();
+    while let Some(mut result) = c.list("result") {
+        while !result.is_empty() {
+            results.push(result.val_type()?);
+        }
+    }
+    Ok(results)
+}
+
+/// memory64 proposal: sizes of memories with 64-bit addresses can be larger than u32
+fn limits(c: &mut Cursor, index_type: ValType) -> Result<Limits> {
+    let parse_size: fn(&str) -> Option<u64> = match index_type {
+        ValType::I64 => parse_uint,
+        _ => |text| parse_u32(text).map(u64::from),
+    };
+    let initial_size = number(c, parse_size, "limit")?;
+    let max_size = if c.peek_index() { Some(number(c, parse_size, "limit")?) } else { None };
+    let shared = c.peek_keyword() == Some("shared");
+    if shared {
+        c.next();
+    }
+    Ok(Limits { initial_size, max_size, shared, index_type })
+}
+
+fn inline_exports(c: &mut Cursor) -> Result<Vec<String>> {
+    let mut exports = Vec::new();
+    while let Some(mut export) = c.list("export") {
+        exports.push(export.name()?);
+        export.end()?;
+    }
+    Ok(exports)
+}
+
+fn inline_import(c: &mut Cursor) -> Result<Option<(String, String)>> {
+    Ok(match c.list("import") {
+        Some(mut import) => {
+            let import_ = (import.name()?, import.name()?);
+            import.end()?;
+            Some(import_)
+        }
+        None => None,
+    })
+}
+
+/// concatenation of all remaining strings, e.g., in data segments
+fn strings(c: &mut Cursor) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while !c.is_empty() {
+        match c.next() {
+            Some(Sexpr::Atom(Token::String(string), _)) => bytes.extend_from_slice(string),
+            Some(item) => return Err(ParseError::new(item.pos(), "expected string")),
+            None => unreachable!(),
+        }
+    }
+    Ok(bytes)
+}
+
+/// numeric index or symbolic name
+fn index(c: &mut Cursor, names: &HashMap<String, usize>, what: &str) -> Result<usize> {
+    match c.next() {
+        Some(Sexpr::Atom(Token::Number(number), pos)) => parse_u32(number)
+            .map(|idx| idx as usize)
+            .ok_or_else(|| ParseError::new(*pos, format!("invalid {} index '{}'", what, number))),
+        Some(Sexpr::Atom(Token::Id(id), pos)) => names.get(id)
+            .cloned()
+            .ok_or_else(|| ParseError::new(*pos, format!("unknown {} ${}", what, id))),
+        Some(item) => Err(ParseError::new(item.pos(), format!("expected {} index", what))),
+        None => c.error(format!("expected {} index", what)),
+    }
+}
+
+fn declare(names: &mut HashMap<String, usize>, id: Option<&str>, idx: usize, pos: Pos) -> Result<()> {
+    if let Some(id) = id {
+        if names.insert(id.to_string(), idx).is_some() {
+            return Err(ParseError::new(pos, format!("duplicate identifier ${}", id)));
+        }
+    }
+    Ok(())
+}
+
+/// shape (e.g., i32x4) followed by one literal per lane
+fn v128(c: &mut Cursor) -> Result<u128> {
+    let pos = c.current_pos();
+    // parse functions return the bits of a single lane
+    let (lane_count, parse): (u32, fn(&str) -> Option<u64>) = match c.keyword() {
+        Some("i8x16") => (16, |text| parse_lane_int(text, 8)),
+        Some("i16x8") => (8, |text| parse_lane_int(text, 16)),
+        Some("i32x4") => (4, |text| parse_i32(text).map(|i| i as u32 as u64)),
+        Some("i64x2") => (2, |text| parse_i64(text).map(|i| i as u64)),
+        Some("f32x4") => (4, |text| parse_f32(text).map(|f| f.to_bits() as u64)),
+        Some("f64x2") => (2, |text| parse_f64(text).map(f64::to_bits)),
+        _ => return Err(ParseError::new(pos, "expected vector shape i8x16, i16x8, i32x4, i64x2, f32x4, or f64x2")),
+    };
+    let lane_bits = 128 / lane_count;
+    let mut value = 0;
+    for lane in 0..lane_count {
+        value |= (number(c, parse, "lane")? as u128) << (lane * lane_bits);
+    }
+    Ok(value)
+}
+
+fn number<T>(c: &mut Cursor, parse: fn(&str) -> Option<T>, what: &str) -> Result<T> {
+    let pos = c.current_pos();
+    let text = match c.next() {
+        // inf and nan are lexed as keywords
+        Some(Sexpr::Atom(Token::Number(text), _)) | Some(Sexpr::Atom(Token::Keyword(text), _)) => text,
+        _ => return Err(ParseError::new(pos, format!("expected {} literal", what))),
+    };
+    parse(text).ok_or_else(|| ParseError::new(pos, format!("invalid {} literal '{}'", what, text)))
+}
+
+
+/* Cursor over the elements of an S-expression list */
+
+#[derive(Debug, Clone)]
+struct Cursor<'a> {
+    items: &'a [Sexpr],
+    /// of the list itself, for errors when there are no items left
+    pos: Pos,
+}
+
+fn head_keyword(items: &[Sexpr]) -> Option<&str> {
+    match items.first() {
+        Some(Sexpr::Atom(Token::Keyword(keyword), _)) => Some(keyword),
+        _ => None,
+    }
+}
+
+impl<'a> Cursor<'a> {
+    fn new(items: &'a [Sexpr], pos: Pos) -> Self {
+        Cursor { items, pos }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn peek(&self) -> Option<&'a Sexpr> {
+        self.items.first()
+    }
+
+    fn next(&mut self) -> Option<&'a Sexpr> {
+        let (first, rest) = self.items.split_first()?;
+        self.items = rest;
+        Some(first)
+    }
+
+    fn current_pos(&self) -> Pos {
+        self.peek().map(Sexpr::pos).unwrap_or(self.pos)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(ParseError::new(self.current_pos(), message))
+    }
+
+    /// error if there are remaining items
+    fn end(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => self.error("unexpected item"),
+        }
+    }
+
+    fn peek_keyword(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Sexpr::Atom(Token::Keyword(keyword), _)) => Some(keyword),
+            _ => None,
+        }
+    }
+
+    fn keyword(&mut self) -> Option<&'a str> {
+        let keyword = self.peek_keyword()?;
+        self.next();
+        Some(keyword)
+    }
+
+    /// whether the next item is a numeric index or symbolic name
+    fn peek_index(&self) -> bool {
+        match self.peek() {
+            Some(Sexpr::Atom(Token::Number(_), _)) | Some(Sexpr::Atom(Token::Id(_), _)) => true,
+            _ => false,
+        }
+    }
+
+    /// optional $id
+    fn id(&mut self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Sexpr::Atom(Token::Id(id), _)) => {
+                self.next();
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// string that must be valid UTF-8, e.g., import and export names
+    fn name(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Sexpr::Atom(Token::String(bytes), _)) => {
+                let name = String::from_utf8(bytes.clone()).or_else(|_| self.error("name is not valid UTF-8"))?;
+                self.next();
+                Ok(name)
+            }
+            _ => self.error("expected string"),
+        }
+    }
+
+    fn val_type(&mut self) -> Result<ValType> {
+        match self.peek_keyword().and_then(val_type) {
+            Some(ty) => {
+                self.next();
+                Ok(ty)
+            }
+            None => self.error("expected value type"),
+        }
+    }
+
+    /// head keyword of the next item, if that is a list
+    fn peek_list(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Sexpr::List(items, _)) => head_keyword(items),
+            _ => None,
+        }
+    }
+
+    /// any list, cursor includes the head
+    fn next_list(&mut self) -> Option<Cursor<'a>> {
+        match self.peek() {
+            Some(Sexpr::List(items, pos)) => {
+                self.next();
+                Some(Cursor::new(items, *pos))
+            }
+            _ => None,
+        }
+    }
+
+    /// only a list with the given head keyword, cursor is after the head
+    fn list(&mut self, keyword: &str) -> Option<Cursor<'a>> {
+        if self.peek_list() != Some(keyword) {
+            return None;
+        }
+        let mut list = self.next_list().unwrap();
+        list.next();
+        Some(list)
+    }
+
+    fn expect_list(&mut self, keyword: &str) -> Result<Cursor<'a>> {
+        match self.list(keyword) {
+            Some(list) => Ok(list),
+            None => self.error(format!("expected ({} ...)", keyword)),
+        }
+    }
+}
+
+
+/* Numbers */
+
+fn split_sign(text: &str) -> (bool, &str) {
+    if text.starts_with('-') {
+        (true, &text[1..])
+    } else if text.starts_with('+') {
+        (false, &text[1..])
+    } else {
+        (false, text)
+    }
+}
+
+/// without sign, decimal or hexadecimal, possibly with _ as separator
+/// underscores are only allowed between digits, i.e., also not directly after the 0x prefix
+fn parse_uint(text: &str) -> Option<u64> {
+    let (radix, digits) = if text.starts_with("0x") { (16, &text[2..]) } else { (10, text) };
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') || digits.contains("__")
+        || !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return None;
+    }
+    u64::from_str_radix(&digits.replace('_', ""), radix).ok()
+}
+
+fn parse_u32(text: &str) -> Option<u32> {
+    let value = parse_uint(text)?;
+    if value > u32::max_value() as u64 { None } else { Some(value as u32) }
+}
+
+fn parse_u8(text: &str) -> Option<u8> {
+    let value = parse_uint(text)?;
+    if value > u8::max_value() as u64 { None } else { Some(value as u8) }
+}
+
+/// like parse_i32, but for the 8 and 16 bit lanes of v128.const
+fn parse_lane_int(text: &str, bits: u32) -> Option<u64> {
+    let (negative, text) = split_sign(text);
+    let value = parse_uint(text)?;
+    if negative {
+        if value > 1 << (bits - 1) { None } else { Some(value.wrapping_neg() & ((1 << bits) - 1)) }
+    } else {
+        if value >= 1 << bits { None } else { Some(value) }
+    }
+}
+
+/// integer literals can be given signed or unsigned, i.e., -1 and 0xffffffff are the same
+fn parse_i32(text: &str) -> Option<i32> {
+    let (negative, text) = split_sign(text);
+    let value = parse_uint(text)?;
+    if negative {
+        if value > 1 << 31 { None } else { Some((value as i64).wrapping_neg() as i32) }
+    } else {
+        if value > u32::max_value() as u64 { None } else { Some(value as u32 as i32) }
+    }
+}
+
+fn parse_i64(text: &str) -> Option<i64> {
+    let (negative, text) = split_sign(text);
+    let value = parse_uint(text)?;
+    if negative {
+        if value > 1 << 63 { None } else { Some((value as i64).wrapping_neg()) }
+    } else {
+        Some(value as i64)
+    }
+}
+
+fn parse_f32(text: &str) -> Option<f32> {
+    let (negative, unsigned) = split_sign(text);
+    let value = if unsigned == "inf" {
+        ::std::f32::INFINITY
+    } else if unsigned == "nan" {
+        ::std::f32::NAN
+    } else if unsigned.starts_with("nan:0x") {
+        let payload = parse_uint(&unsigned["nan:".len()..])?;
+        if payload == 0 || payload >= 1 << 23 {
+            return None;
+        }
+        f32::from_bits(0x7f80_0000 | payload as u32)
+    } else if unsigned.starts_with("0x") {
+        f32::from_bits(parse_hex_float(&unsigned[2..], 23, 8)? as u32)
+    } else if unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        unsigned.replace('_', "").parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_f64(text: &str) -> Option<f64> {
+    let (negative, unsigned) = split_sign(text);
+    let value = if unsigned == "inf" {
+        ::std::f64::INFINITY
+    } else if unsigned == "nan" {
+        ::std::f64::NAN
+    } else if unsigned.starts_with("nan:0x") {
+        let payload = parse_uint(&unsigned["nan:".len()..])?;
+        if payload == 0 || payload >= 1 << 52 {
+            return None;
+        }
+        f64::from_bits(0x7ff0_0000_0000_0000 | payload)
+    } else if unsigned.starts_with("0x") {
+        f64::from_bits(parse_hex_float(&unsigned[2..], 52, 11)?)
+    } else if unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        unsigned.replace('_', "").parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// e.g., 1.8p3 (without leading 0x), mantissa is hexadecimal, exponent is decimal and base 2.
+/// Returns the bits of the (positive) float with the given number of significand (without the
+/// implicit leading bit) and exponent bits, rounded once to nearest even (overflow to infinity).
+fn parse_hex_float(text: &str, significand_bits: u32, exponent_bits: u32) -> Option<u64> {
+    if text.starts_with('_') {
+        return None;
+    }
+    let text = text.replace('_', "");
+    let (mantissa_text, exponent_text) = match text.find(|c| c == 'p' || c == 'P') {
+        Some(p) => (&text[..p], Some(&text[p + 1..])),
+        None => (&text[..], None),
+    };
+
+    let mut mantissa: u64 = 0;
+    let mut exponent: i64 = 0;
+    let mut after_point = false;
+    let mut any_digit = false;
+    for c in mantissa_text.chars() {
+        if c == '.' {
+            if after_point {
+                return None;
+            }
+            after_point = true;
+            continue;
+        }
+        let digit = c.to_digit(16)? as u64;
+        any_digit = true;
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa * 16 + digit;
+            if after_point {
+                exponent -= 4;
+            }
+        } else {
+            // mantissa is full, remaining digits only matter for rounding
+            if digit != 0 {
+                mantissa |= 1;
+            }
+            if !after_point {
+                exponent += 4;
+            }
+        }
+    }
+    if !any_digit {
+        return None;
+    }
+    if let Some(exponent_text) = exponent_text {
+        let (negative, exponent_text) = split_sign(exponent_text);
+        let value: i64 = exponent_text.parse().ok()?;
+        exponent = exponent.checked_add(if negative { -value } else { value })?;
+    }
+
+    if mantissa == 0 {
+        return Some(0);
+    }
+
+    // normalize, so that the value is 1.xxx * 2^exponent with the leading 1 in bit 63
+    let leading_zeros = mantissa.leading_zeros();
+    let mantissa = mantissa << leading_zeros;
+    let exponent = exponent.saturating_add(63 - leading_zeros as i64);
+
+    let bias = (1i64 << (exponent_bits - 1)) - 1;
+    let infinity = ((1u64 << exponent_bits) - 1) << significand_bits;
+    if exponent > bias {
+        return Some(infinity);
+    }
+    // subnormals have the minimum exponent and lose further significand bits
+    let min_exponent = 1 - bias;
+    let (biased_exponent, dropped_bits) = if exponent >= min_exponent {
+        ((exponent + bias - 1) as u64, 63 - significand_bits as i64)
+    } else {
+        (0, (63 - significand_bits as i64).saturating_add(min_exponent.saturating_sub(exponent)))
+    };
+
+    // round to nearest, ties to even (wide integers, since all bits may be dropped)
+    let mantissa = mantissa as u128;
+    let dropped_bits = ::std::cmp::min(dropped_bits, 127) as u32;
+    let mut significand = mantissa >> dropped_bits;
+    let remainder = mantissa & ((1 << dropped_bits) - 1);
+    let half = 1 << (dropped_bits - 1);
+    if remainder > half || (remainder == half && significand & 1 == 1) {
+        significand += 1;
+    }
+
+    // for normal values, the implicit leading bit of the significand is added to the exponent
+    // (which is why it is biased by one less), a carry from rounding increments the exponent
+    let bits = (biased_exponent << significand_bits) + significand as u64;
+    Some(::std::cmp::min(bits, infinity))
+}
@@ -0,0 +1,565 @@
+use ast::*;
+use ast::highlevel::*;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/* Printer from the high-level AST to the text format.
+    - imports are printed before all definitions (as required by the text format), so numeric
+      indices are the ones of the encoded binary, not necessarily the ones in the high-level AST.
+    - debug names (e.g., from the name section) are printed as symbolic identifiers, if they are
+      unique and valid identifiers. Otherwise, numeric indices are used.
+    - labels are always printed as relative depth, the final end of a function body is omitted.
+*/
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions {
+    /// annotate every function with its index and every instruction with its Idx<Instr> in the
+    /// high-level AST, i.e., the {func, instr} locations hooks report
+    pub instr_indices: bool,
+}
+
+pub fn print_module(module: &Module, options: PrintOptions) -> String {
+    let mut printer = Printer::new(Some(module), options);
+    printer.module(module);
+    printer.out
+}
+
+/// calls and global accesses use the numeric indices of the high-level AST, since there is no module
+pub fn print_function(function: &Function, options: PrintOptions) -> String {
+    let mut printer = Printer::new(None, options);
+    printer.function(function, None);
+    printer.out
+}
+
+struct Printer {
+    options: PrintOptions,
+    out: String,
+    indent: usize,
+    /// printed (i.e., binary) index for every index in the high-level AST
+    function_indices: Vec<usize>,
+    table_indices: Vec<usize>,
+    memory_indices: Vec<usize>,
+    global_indices: Vec<usize>,
+    tag_indices: Vec<usize>,
+    function_ids: Vec<Option<String>>,
+}
+
+impl Printer {
+    fn new(module: Option<&Module>, options: PrintOptions) -> Self {
+        let mut printer = Printer {
+            options,
+            out: String::new(),
+            indent: 0,
+            function_indices: Vec::new(),
+            table_indices: Vec::new(),
+            memory_indices: Vec::new(),
+            global_indices: Vec::new(),
+            tag_indices: Vec::new(),
+            function_ids: Vec::new(),
+        };
+        if let Some(module) = module {
+            printer.function_indices = imports_first(module.functions.iter().map(|f| f.import.is_some()));
+            printer.table_indices = imports_first(module.tables.iter().map(|t| t.import.is_some()));
+            printer.memory_indices = imports_first(module.memories.iter().map(|m| m.import.is_some()));
+            printer.global_indices = imports_first(module.globals.iter().map(|g| g.import.is_some()));
+            printer.tag_indices = imports_first(module.tags.iter().map(|t| t.import.is_some()));
+            printer.function_ids = unique_ids(module.functions.iter().map(|f| f.name.as_ref()));
+        }
+        printer
+    }
+
+    fn line(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn module(&mut self, module: &Module) {
+        self.out.push_str("(module");
+        if let Some(id) = module.name.as_ref().and_then(|name| to_id(name)) {
+            write!(self.out, " ${}", id).unwrap();
+        }
+        self.indent += 1;
+
+        // imports first, then definitions, each in the order of the index spaces
+        for import in &[true, false] {
+            for (i, function) in module.functions.iter().enumerate() {
+                if function.import.is_some() == *import {
+                    self.function(function, Some(i.into()));
+                }
+            }
+            for (i, table) in module.tables.iter().enumerate() {
+                if table.import.is_some() == *import {
+                    self.table(table, i);
+                }
+            }
+            for (i, memory) in module.memories.iter().enumerate() {
+                if memory.import.is_some() == *import {
+                    self.memory(memory, i);
+                }
+            }
+            for (i, tag) in module.tags.iter().enumerate() {
+                if tag.import.is_some() == *import {
+                    self.tag(tag, i);
+                }
+            }
+            for (i, global) in module.globals.iter().enumerate() {
+                if global.import.is_some() == *import {
+                    self.global(global, i);
+                }
+            }
+        }
+
+        if let Some(start) = module.start {
+            self.line();
+            self.out.push_str("(start ");
+            self.function_ref(start);
+            self.out.push(')');
+        }
+
+        for element in &module.elements {
+            self.line();
+            self.out.push_str("(elem");
+            let is_active = match element.mode {
+                ElementMode::Active { table, ref offset } => {
+                    if self.table_indices[table.0] != 0 {
+                        write!(self.out, " {}", self.table_indices[table.0]).unwrap();
+                    }
+                    self.offset(offset);
+                    true
+                }
+                ElementMode::Passive => false,
+                ElementMode::Declarative => {
+                    self.out.push_str(" declare");
+                    false
+                }
+            };
+            match element.items {
+                ElementItems::Functions(ref functions) => {
+                    // the func keyword is optional only for the MVP form
+                    if !is_active {
+                        self.out.push_str(" func");
+                    }
+                    for &function in functions {
+                        self.out.push(' ');
+                        self.function_ref(function);
+                    }
+                }
+                ElementItems::Expressions(type_, ref exprs) => {
+                    write!(self.out, " {}", type_).unwrap();
+                    for expr in exprs {
+                        if expr.len() == 2 {
+                            self.const_expr(expr);
+                        } else {
+                            self.out.push_str(" (item");
+                            self.const_expr(expr);
+                            self.out.push(')');
+                        }
+                    }
+                }
+            }
+            self.out.push(')');
+        }
+        for data in &module.data {
+            self.line();
+            self.out.push_str("(data");
+            if let DataMode::Active { memory, ref offset } = data.mode {
+                if self.memory_indices[memory.0] != 0 {
+                    write!(self.out, " {}", self.memory_indices[memory.0]).unwrap();
+                }
+                self.offset(offset);
+            }
+            self.out.push(' ');
+            self.string(&data.bytes);
+            self.out.push(')');
+        }
+
+        self.indent -= 1;
+        self.out.push_str(")\n");
+    }
+
+    /// idx is None when printing a single function without module
+    fn function(&mut self, function: &Function, idx: Option<Idx<Function>>) {
+        self.line();
+        self.out.push_str("(func");
+        if let Some(idx) = idx {
+            match self.function_ids[idx.0].clone() {
+                Some(id) => write!(self.out, " ${}", id).unwrap(),
+                None => write!(self.out, " (;{};)", self.function_indices[idx.0]).unwrap(),
+            }
+        } else if let Some(id) = function.name.as_ref().and_then(|name| to_id(name)) {
+            write!(self.out, " ${}", id).unwrap();
+        }
+        self.exports(&function.export);
+        self.import(&function.import);
+
+        let param_count = function.type_.params.len();
+        let local_count = function.code.as_ref().map(|code| code.locals.len()).unwrap_or(0);
+        let local_ids = unique_ids((0..param_count + local_count).map(|i| function.local_names.get(&i.into())));
+        for (i, &ty) in function.type_.params.iter().enumerate() {
+            self.out.push(' ');
+            self.local_decl("param", local_ids[i].as_ref(), ty);
+        }
+        if !function.type_.results.is_empty() {
+            self.out.push_str(" (result");
+            for ty in function.type_.results.iter() {
+                write!(self.out, " {}", ty).unwrap();
+            }
+            self.out.push(')');
+        }
+        if let (true, Some(idx)) = (self.options.instr_indices, idx) {
+            write!(self.out, " (;func {};)", idx.0).unwrap();
+        }
+
+        if let Some(ref code) = function.code {
+            self.indent += 1;
+            if !code.locals.is_empty() {
+                self.line();
+                for (i, &ty) in code.locals.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(' ');
+                    }
+                    self.local_decl("local", local_ids[param_count + i].as_ref(), ty);
+                }
+            }
+            for (i, instr) in code.body.iter().enumerate() {
+                // final end is implicit in the closing parenthesis of func
+                if i + 1 == code.body.len() && *instr == Instr::End {
+                    break;
+                }
+                if let Instr::Else | Instr::End | Instr::Catch(_) | Instr::CatchAll | Instr::Delegate(_) = instr {
+                    self.indent -= 1;
+                }
+                self.line();
+                self.instr(instr, &local_ids);
+                if self.options.instr_indices {
+                    write!(self.out, " (;instr {};)", i).unwrap();
+                }
+                if let Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Else | Instr::Try(_) | Instr::Catch(_) | Instr::CatchAll = instr {
+                    self.indent += 1;
+                }
+            }
+            self.indent -= 1;
+        }
+        self.out.push(')');
+    }
+
+    fn local_decl(&mut self, keyword: &str, id: Option<&String>, ty: ValType) {
+        match id {
+            Some(id) => write!(self.out, "({} ${} {})", keyword, id, ty).unwrap(),
+            None => write!(self.out, "({} {})", keyword, ty).unwrap(),
+        }
+    }
+
+    fn table(&mut self, table: &Table, idx: usize) {
+        self.line();
+        write!(self.out, "(table (;{};)", self.table_indices[idx]).unwrap();
+        self.exports(&table.export);
+        self.import(&table.import);
+        let TableType(elem_type, limits) = table.type_;
+        self.limits(limits);
+        write!(self.out, " {})", elem_type).unwrap();
+    }
+
+    fn memory(&mut self, memory: &Memory, idx: usize) {
+        self.line();
+        write!(self.out, "(memory (;{};)", self.memory_indices[idx]).unwrap();
+        self.exports(&memory.export);
+        self.import(&memory.import);
+        self.limits(memory.type_.0);
+        self.out.push(')');
+    }
+
+    fn tag(&mut self, tag: &Tag, idx: usize) {
+        self.line();
+        write!(self.out, "(tag (;{};)", self.tag_indices[idx]).unwrap();
+        self.exports(&tag.export);
+        self.import(&tag.import);
+        self.inline_type(&tag.type_);
+        self.out.push(')');
+    }
+
+    fn global(&mut self, global: &Global, idx: usize) {
+        self.line();
+        write!(self.out, "(global (;{};)", self.global_indices[idx]).unwrap();
+        self.exports(&global.export);
+        self.import(&global.import);
+        match global.type_ {
+            GlobalType(ty, Mutability::Const) => write!(self.out, " {}", ty).unwrap(),
+            GlobalType(ty, Mutability::Mut) => write!(self.out, " (mut {})", ty).unwrap(),
+        }
+        if let Some(ref init) = global.init {
+            self.const_expr(init);
+        }
+        self.out.push(')');
+    }
+
+    fn exports(&mut self, exports: &[String]) {
+        for export in exports {
+            self.out.push_str(" (export ");
+            self.string(export.as_bytes());
+            self.out.push(')');
+        }
+    }
+
+    fn import(&mut self, import: &Option<(String, String)>) {
+        if let Some((ref module, ref name)) = *import {
+            self.out.push_str(" (import ");
+            self.string(module.as_bytes());
+            self.out.push(' ');
+            self.string(name.as_bytes());
+            self.out.push(')');
+        }
+    }
+
+    fn limits(&mut self, limits: Limits) {
+        if limits.index_type == ValType::I64 {
+            self.out.push_str(" i64");
+        }
+        write!(self.out, " {}", limits.initial_size).unwrap();
+        if let Some(max_size) = limits.max_size {
+            write!(self.out, " {}", max_size).unwrap();
+        }
+        if limits.shared {
+            self.out.push_str(" shared");
+        }
+    }
+
+    /// folded, i.e., every instruction in parentheses, without the final end
+    fn const_expr(&mut self, expr: &[Instr]) {
+        for instr in expr.iter().filter(|instr| **instr != Instr::End) {
+            self.out.push_str(" (");
+            self.instr(instr, &[]);
+            self.out.push(')');
+        }
+    }
+
+    fn offset(&mut self, expr: &[Instr]) {
+        if expr.len() == 2 {
+            self.const_expr(expr);
+        } else {
+            self.out.push_str(" (offset");
+            self.const_expr(expr);
+            self.out.push(')');
+        }
+    }
+
+    fn string(&mut self, bytes: &[u8]) {
+        self.out.push('"');
+        for &byte in bytes {
+            match byte {
+                b'"' => self.out.push_str("\\\""),
+                b'\\' => self.out.push_str("\\\\"),
+                0x20..=0x7e => self.out.push(byte as char),
+                _ => write!(self.out, "\\{:02x}", byte).unwrap(),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn function_ref(&mut self, idx: Idx<Function>) {
+        match self.function_ids.get(idx.0) {
+            Some(Some(id)) => write!(self.out, "${}", id).unwrap(),
+            Some(None) => write!(self.out, "{}", self.function_indices[idx.0]).unwrap(),
+            None => write!(self.out, "{}", idx.0).unwrap(),
+        }
+    }
+
+    /// (param ...)? (result ...)?, omitting empty lists
+    fn inline_type(&mut self, type_: &FunctionType) {
+        if !type_.params.is_empty() {
+            self.out.push_str(" (param");
+            for ty in &type_.params {
+                write!(self.out, " {}", ty).unwrap();
+            }
+            self.out.push(')');
+        }
+        if !type_.results.is_empty() {
+            self.out.push_str(" (result");
+            for ty in &type_.results {
+                write!(self.out, " {}", ty).unwrap();
+            }
+            self.out.push(')');
+        }
+    }
+
+    fn instr(&mut self, instr: &Instr, local_ids: &[Option<String>]) {
+        self.out.push_str(instr.to_name());
+        match *instr {
+            Instr::Block(ref type_) | Instr::Loop(ref type_) | Instr::If(ref type_) | Instr::Try(ref type_) => self.inline_type(type_),
+            Instr::Br(label) | Instr::BrIf(label) | Instr::Delegate(label) | Instr::Rethrow(label) => write!(self.out, " {}", label.0).unwrap(),
+            Instr::Catch(idx) | Instr::Throw(idx) => write!(self.out, " {}", index(&self.tag_indices, idx.0)).unwrap(),
+            Instr::BrTable(ref table, default) => {
+                for label in table {
+                    write!(self.out, " {}", label.0).unwrap();
+                }
+                write!(self.out, " {}", default.0).unwrap();
+            }
+            Instr::Call(idx) | Instr::ReturnCall(idx) => {
+                self.out.push(' ');
+                self.function_ref(idx);
+            }
+            Instr::CallIndirect(ref type_, table_idx) | Instr::ReturnCallIndirect(ref type_, table_idx) => {
+                if table_idx.0 != 0 {
+                    write!(self.out, " {}", index(&self.table_indices, table_idx.0)).unwrap();
+                }
+                self.inline_type(type_);
+            }
+            Instr::Local(_, idx) => match local_ids.get(idx.0) {
+                Some(Some(id)) => write!(self.out, " ${}", id).unwrap(),
+                _ => write!(self.out, " {}", idx.0).unwrap(),
+            },
+            Instr::Global(_, idx) => write!(self.out, " {}", index(&self.global_indices, idx.0)).unwrap(),
+            Instr::Table(_, idx) => match index(&self.table_indices, idx.0) {
+                0 => {}
+                idx => write!(self.out, " {}", idx).unwrap(),
+            },
+            Instr::TypedSelect(ty) => write!(self.out, " (result {})", ty).unwrap(),
+            Instr::RefNull(ElemType::FuncRef) => self.out.push_str(" func"),
+            Instr::RefNull(ElemType::ExternRef) => self.out.push_str(" extern"),
+            Instr::RefFunc(idx) => {
+                self.out.push(' ');
+                self.function_ref(idx);
+            }
+            Instr::Load(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::Store(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::LoadLane(op, memarg, lane) => {
+                self.memarg(memarg, op.natural_alignment());
+                write!(self.out, " {}", lane).unwrap();
+            }
+            Instr::StoreLane(op, memarg, lane) => {
+                self.memarg(memarg, op.natural_alignment());
+                write!(self.out, " {}", lane).unwrap();
+            }
+            Instr::AtomicLoad(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::AtomicStore(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::AtomicRmw(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::AtomicCmpxchg(op, memarg) => self.memarg(memarg, op.natural_alignment()),
+            Instr::MemoryAtomicNotify(memarg) | Instr::MemoryAtomicWait32(memarg) => self.memarg(memarg, 2),
+            Instr::MemoryAtomicWait64(memarg) => self.memarg(memarg, 3),
+            Instr::SimdLane(_, lane) => write!(self.out, " {}", lane).unwrap(),
+            Instr::Shuffle(ref lanes) => for lane in lanes {
+                write!(self.out, " {}", lane).unwrap();
+            },
+            Instr::Const(val) => {
+                self.out.push(' ');
+                self.val(val);
+            }
+            Instr::MemorySize(idx) | Instr::MemoryGrow(idx) | Instr::MemoryFill(idx) => self.optional_memory(idx),
+            Instr::MemoryInit(data_idx, memory_idx) => {
+                self.optional_memory(memory_idx);
+                write!(self.out, " {}", data_idx.0).unwrap();
+            }
+            Instr::MemoryCopy(dst_idx, src_idx) => if dst_idx.0 != 0 || src_idx.0 != 0 {
+                write!(self.out, " {} {}", index(&self.memory_indices, dst_idx.0), index(&self.memory_indices, src_idx.0)).unwrap();
+            },
+            Instr::DataDrop(data_idx) => write!(self.out, " {}", data_idx.0).unwrap(),
+            Instr::ElemDrop(elem_idx) => write!(self.out, " {}", elem_idx.0).unwrap(),
+            Instr::TableInit(elem_idx, table_idx) => {
+                if table_idx.0 != 0 {
+                    write!(self.out, " {}", index(&self.table_indices, table_idx.0)).unwrap();
+                }
+                write!(self.out, " {}", elem_idx.0).unwrap();
+            }
+            Instr::TableCopy(dst_idx, src_idx) => if dst_idx.0 != 0 || src_idx.0 != 0 {
+                write!(self.out, " {} {}", index(&self.table_indices, dst_idx.0), index(&self.table_indices, src_idx.0)).unwrap();
+            },
+            Instr::Unreachable | Instr::Nop | Instr::Else | Instr::End | Instr::CatchAll | Instr::Return | Instr::Drop | Instr::Select
+            | Instr::RefIsNull | Instr::Numeric(_) | Instr::Simd(_) | Instr::AtomicFence => {}
+        }
+    }
+
+    /// the memory index is omitted for the first memory, i.e., the only one without multi-memory
+    fn optional_memory(&mut self, idx: Idx<Memory>) {
+        match index(&self.memory_indices, idx.0) {
+            0 => {}
+            idx => write!(self.out, " {}", idx).unwrap(),
+        }
+    }
+
+    fn memarg(&mut self, memarg: Memarg, natural_alignment: u32) {
+        self.optional_memory(memarg.memory);
+        if memarg.offset != 0 {
+            write!(self.out, " offset={}", memarg.offset).unwrap();
+        }
+        if memarg.alignment != natural_alignment {
+            write!(self.out, " align={}", 1u64 << memarg.alignment).unwrap();
+        }
+    }
+
+    fn val(&mut self, val: Val) {
+        match val {
+            Val::I32(i) => write!(self.out, "{}", i).unwrap(),
+            Val::I64(i) => write!(self.out, "{}", i).unwrap(),
+            Val::F32(f) if f.is_nan() => {
+                let bits = f.to_bits();
+                self.nan(bits >> 31 != 0, (bits & 0x7f_ffff) as u64, 1 << 22);
+            }
+            Val::F64(f) if f.is_nan() => {
+                let bits = f.to_bits();
+                self.nan(bits >> 63 != 0, bits & 0xf_ffff_ffff_ffff, 1 << 51);
+            }
+            Val::F32(f) if f.is_infinite() => self.out.push_str(if f < 0.0 { "-inf" } else { "inf" }),
+            Val::F64(f) if f.is_infinite() => self.out.push_str(if f < 0.0 { "-inf" } else { "inf" }),
+            // Debug output is the shortest representation that parses back to the same value
+            Val::F32(f) => write!(self.out, "{:?}", f).unwrap(),
+            Val::F64(f) => write!(self.out, "{:?}", f).unwrap(),
+            // as four i32 lanes, lowest lane first
+            Val::V128(v) => {
+                self.out.push_str("i32x4");
+                for lane in 0..4 {
+                    write!(self.out, " 0x{:08x}", (v >> (32 * lane)) as u32).unwrap();
+                }
+            }
+        }
+    }
+
+    fn nan(&mut self, negative: bool, payload: u64, canonical_payload: u64) {
+        if negative {
+            self.out.push('-');
+        }
+        if payload == canonical_payload {
+            self.out.push_str("nan");
+        } else {
+            write!(self.out, "nan:0x{:x}", payload).unwrap();
+        }
+    }
+}
+
+/// index without mapping if printing without module
+fn index(indices: &[usize], idx: usize) -> usize {
+    indices.get(idx).cloned().unwrap_or(idx)
+}
+
+/// binary index for every high-level index, given whether the item at that index is an import
+fn imports_first(is_import: impl Iterator<Item=bool> + Clone) -> Vec<usize> {
+    let import_count = is_import.clone().filter(|&import| import).count();
+    let (mut next_import, mut next_definition) = (0, import_count);
+    is_import.map(|import| {
+        let next = if import { &mut next_import } else { &mut next_definition };
+        *next += 1;
+        *next - 1
+    }).collect()
+}
+
+/// symbolic identifiers for debug names, None if the name is not unique or not a valid identifier
+fn unique_ids<'a>(names: impl Iterator<Item=Option<&'a String>>) -> Vec<Option<String>> {
+    let ids: Vec<Option<String>> = names.map(|name| name.and_then(|name| to_id(name))).collect();
+    let mut seen = HashSet::new();
+    let duplicates: HashSet<String> = ids.iter()
+        .filter_map(|id| id.clone())
+        .filter(|id| !seen.insert(id.clone()))
+        .collect();
+    ids.into_iter()
+        .map(|id| id.filter(|id| !duplicates.contains(id)))
+        .collect()
+}
+
+fn to_id(name: &str) -> Option<String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c)) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,758 @@
+use ast::*;
+use ast::highlevel::*;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/* Validation of high-level modules, following the algorithm in the appendix of the spec
+   (see https://webassembly.github.io/spec/core/appendix/algorithm.html).
+   Function bodies are validated in parallel, only the first error (in function order) is reported.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// None for errors outside of function bodies, e.g., in global initializers or exports
+    pub function: Option<Idx<Function>>,
+    pub instr: Option<Idx<Instr>>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(function) = self.function {
+            write!(f, "function #{}, ", function.0)?;
+        }
+        if let Some(instr) = self.instr {
+            write!(f, "instruction #{}: ", instr.0)?;
+        } else if self.function.is_some() {
+            write!(f, "signature: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ValidationError {}
+
+impl From<ValidationError> for io::Error {
+    fn from(e: ValidationError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+fn module_error<T>(message: impl Into<String>) -> Result<T, ValidationError> {
+    Err(ValidationError { function: None, instr: None, message: message.into() })
+}
+
+/// maximum number of pages (64 KiB each) in a 32-bit address space
+const MAX_PAGES: u64 = 1 << 16;
+/// memory64 proposal: at most 2^64 bytes can be addressed, i.e., 2^48 pages
+const MAX_PAGES_64: u64 = 1 << 48;
+
+pub fn validate(module: &Module) -> Result<(), ValidationError> {
+    /* Tables and memories */
+
+    for (i, table) in module.tables.iter().enumerate() {
+        if table.type_.1.shared {
+            return module_error(format!("table #{}: tables cannot be shared", i));
+        }
+        validate_limits(table.type_.1, u32::max_value().into())
+            .or_else(|message| module_error(format!("table #{}: {}", i, message)))?;
+    }
+    for (i, memory) in module.memories.iter().enumerate() {
+        if memory.type_.0.shared && memory.type_.0.max_size.is_none() {
+            return module_error(format!("memory #{}: shared memory must have a maximum size", i));
+        }
+        let max_pages = match memory.type_.0.index_type {
+            ValType::I64 => MAX_PAGES_64,
+            _ => MAX_PAGES,
+        };
+        validate_limits(memory.type_.0, max_pages)
+            .or_else(|message| module_error(format!("memory #{}: {}", i, message)))?;
+    }
+
+    /* Tags */
+
+    for (i, tag) in module.tags.iter().enumerate() {
+        if !tag.type_.results.is_empty() {
+            return module_error(format!("tag #{}: type must not have results", i));
+        }
+    }
+
+    /* Element and data segments */
+
+    for (i, element) in module.elements.iter().enumerate() {
+        if let ElementMode::Active { table, ref offset } = element.mode {
+            match module.tables.get(table.0) {
+                None => return module_error(format!("element #{}: unknown table #{}", i, table.0)),
+                Some(Table { type_: TableType(elem_type, _), .. }) if *elem_type != element.type_() =>
+                    return module_error(format!("element #{}: cannot initialize table #{} of type {} with {} elements", i, table.0, elem_type, element.type_())),
+                Some(_) => {}
+            }
+            validate_const_expr(module, offset, ValType::I32)
+                .or_else(|message| module_error(format!("element #{}, offset: {}", i, message)))?;
+        }
+        match element.items {
+            ElementItems::Functions(ref functions) => for function in functions {
+                if function.0 >= module.functions.len() {
+                    return module_error(format!("element #{}: unknown function #{}", i, function.0));
+                }
+            },
+            ElementItems::Expressions(type_, ref exprs) => for (j, expr) in exprs.iter().enumerate() {
+                validate_const_expr(module, expr, type_.into())
+                    .or_else(|message| module_error(format!("element #{}, item #{}: {}", i, j, message)))?;
+            },
+        }
+    }
+    for (i, data) in module.data.iter().enumerate() {
+        if let DataMode::Active { memory, ref offset } = data.mode {
+            if memory.0 >= module.memories.len() {
+                return module_error(format!("data #{}: unknown memory #{}", i, memory.0));
+            }
+            validate_const_expr(module, offset, module.memory_index_type(memory))
+                .or_else(|message| module_error(format!("data #{}, offset: {}", i, message)))?;
+        }
+    }
+
+    /* Globals */
+
+    for (i, global) in module.globals.iter().enumerate() {
+        match (&global.import, &global.init) {
+            (Some(_), None) => {}
+            (None, Some(init)) => validate_const_expr(module, init, global.type_.0)
+                .or_else(|message| module_error(format!("global #{}, initializer: {}", i, message)))?,
+            _ => return module_error(format!("global #{} must be either imported or initialized", i)),
+        }
+    }
+
+    /* Start and exports */
+
+    if let Some(start) = module.start {
+        match module.functions.get(start.0) {
+            None => return module_error(format!("unknown start function #{}", start.0)),
+            Some(function) if function.type_ != FunctionType::new(vec![], vec![]) =>
+                return module_error(format!("start function #{} must have type [] -> []", start.0)),
+            Some(_) => {}
+        }
+    }
+
+    let mut export_names = HashSet::new();
+    let exports = module.functions.iter().flat_map(|f| f.export.iter())
+        .chain(module.tables.iter().flat_map(|t| t.export.iter()))
+        .chain(module.memories.iter().flat_map(|m| m.export.iter()))
+        .chain(module.globals.iter().flat_map(|g| g.export.iter()))
+        .chain(module.tags.iter().flat_map(|t| t.export.iter()));
+    for name in exports {
+        if !export_names.insert(name) {
+            return module_error(format!("duplicate export name \"{}\"", name));
+        }
+    }
+
+    /* Functions */
+
+    let declared_functions = declared_functions(module);
+    let errors: Vec<ValidationError> = module.functions.par_iter()
+        .enumerate()
+        .filter_map(|(i, function)| validate_function(module, function, &declared_functions).err()
+            .map(|(instr, message)| ValidationError { function: Some(i.into()), instr, message }))
+        .collect();
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+fn validate_limits(limits: Limits, max: u64) -> Result<(), String> {
+    if limits.initial_size > max {
+        return Err(format!("initial size {} is larger than {}", limits.initial_size, max));
+    }
+    if let Some(max_size) = limits.max_size {
+        if max_size > max {
+            return Err(format!("maximum size {} is larger than {}", max_size, max));
+        }
+        if max_size < limits.initial_size {
+            return Err(format!("maximum size {} is smaller than initial size {}", max_size, limits.initial_size));
+        }
+    }
+    Ok(())
+}
+
+/// only t.const, ref.null, ref.func, and get_global of imported, immutable globals are allowed
+fn validate_const_expr(module: &Module, expr: &[Instr], expected: ValType) -> Result<(), String> {
+    let ty = match expr {
+        [Instr::Const(val), Instr::End] => val.to_type(),
+        [Instr::RefNull(ty), Instr::End] => (*ty).into(),
+        [Instr::RefFunc(idx), Instr::End] => match module.functions.get(idx.0) {
+            Some(_) => ValType::FuncRef,
+            None => return Err(format!("unknown function #{}", idx.0)),
+        },
+        [Instr::Global(GlobalOp::GetGlobal, idx), Instr::End] => match module.globals.get(idx.0) {
+            Some(Global { type_: GlobalType(ty, Mutability::Const), import: Some(_), .. }) => *ty,
+            Some(_) => return Err(format!("global #{} must be imported and immutable to be used in a constant expression", idx.0)),
+            None => return Err(format!("unknown global #{}", idx.0)),
+        },
+        _ => return Err("constant expression must be a single t.const, ref.null, ref.func, or get_global, followed by end".to_string()),
+    };
+    if ty != expected {
+        return Err(format!("expected type {}, but constant expression has type {}", expected, ty));
+    }
+    Ok(())
+}
+
+/// functions that ref.func may refer to in function bodies, i.e., those that appear in element
+/// segments, exports, or global initializers (the spec's C.refs)
+fn declared_functions(module: &Module) -> HashSet<Idx<Function>> {
+    let mut declared = HashSet::new();
+    for element in &module.elements {
+        match element.items {
+            ElementItems::Functions(ref functions) => declared.extend(functions.iter().cloned()),
+            ElementItems::Expressions(_, ref exprs) => for expr in exprs {
+                if let [Instr::RefFunc(idx), Instr::End] = expr.as_slice() {
+                    declared.insert(*idx);
+                }
+            },
+        }
+    }
+    for (i, function) in module.functions.iter().enumerate() {
+        if !function.export.is_empty() {
+            declared.insert(i.into());
+        }
+    }
+    for global in &module.globals {
+        if let Some([Instr::RefFunc(idx), Instr::End]) = global.init.as_ref().map(Vec::as_slice) {
+            declared.insert(*idx);
+        }
+    }
+    declared
+}
+
+
+/* Function bodies */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind { Function, Block, Loop, If, Else, Try, Catch, CatchAll }
+
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// height of the operand stack when the block was entered
+    height: usize,
+    /// after unconditional branches etc., the rest of the block is type-checked with a polymorphic stack
+    unreachable: bool,
+}
+
+impl Frame {
+    /// types a branch to this block must provide
+    fn label_types(&self) -> &[ValType] {
+        match self.kind {
+            // branching to a loop re-starts it, so it needs the loop parameters
+            FrameKind::Loop => &self.params,
+            _ => &self.results,
+        }
+    }
+}
+
+struct FunctionValidator<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    declared_functions: &'a HashSet<Idx<Function>>,
+    locals: Vec<ValType>,
+    /// None for values of unknown type, i.e., in unreachable code
+    operands: Vec<Option<ValType>>,
+    frames: Vec<Frame>,
+}
+
+/// error with instruction index (None if the function as a whole is invalid)
+type FunctionResult<T> = Result<T, (Option<Idx<Instr>>, String)>;
+
+fn validate_function(module: &Module, function: &Function, declared_functions: &HashSet<Idx<Function>>) -> FunctionResult<()> {
+    let code = match (&function.import, &function.code) {
+        (Some(_), None) => return Ok(()),
+        (None, Some(code)) => code,
+        _ => return Err((None, "function must be either imported or have code".to_string())),
+    };
+
+    let mut validator = FunctionValidator {
+        module,
+        function,
+        declared_functions,
+        locals: function.type_.params.iter().chain(code.locals.iter()).cloned().collect(),
+        operands: Vec::new(),
+        frames: vec![Frame {
+            kind: FrameKind::Function,
+            params: Vec::new(),
+            results: function.type_.results.clone(),
+            height: 0,
+            unreachable: false,
+        }],
+    };
+    for (i, instr) in code.body.iter().enumerate() {
+        if validator.frames.is_empty() {
+            return Err((Some(i.into()), "instruction after the end of the function".to_string()));
+        }
+        validator.instr(instr)
+            .map_err(|message| (Some(i.into()), format!("{}: {}", instr.to_name(), message)))?;
+    }
+    if !validator.frames.is_empty() {
+        return Err((None, "missing end of function body".to_string()));
+    }
+    Ok(())
+}
+
+impl<'a> FunctionValidator<'a> {
+    fn push(&mut self, ty: ValType) {
+        self.operands.push(Some(ty));
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, String> {
+        let frame = self.frames.last().unwrap();
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err("expected value on the stack, but stack is empty".to_string());
+        }
+        Ok(self.operands.pop().unwrap())
+    }
+
+    fn pop_expected(&mut self, expected: ValType) -> Result<(), String> {
+        match self.pop()? {
+            Some(actual) if actual != expected => Err(format!("expected type {} on the stack, but got {}", expected, actual)),
+            _ => Ok(()),
+        }
+    }
+
+    fn pop_all_expected(&mut self, expected: &[ValType]) -> Result<(), String> {
+        for &ty in expected.iter().rev() {
+            self.pop_expected(ty)?;
+        }
+        Ok(())
+    }
+
+    fn instr_type(&mut self, ty: InstrType) -> Result<(), String> {
+        self.pop_all_expected(&ty.inputs)?;
+        for &ty in ty.results.iter() {
+            self.push(ty);
+        }
+        Ok(())
+    }
+
+    /// block parameters are taken from the outer stack and are available again inside the block
+    fn push_frame(&mut self, kind: FrameKind, type_: &BlockType) -> Result<(), String> {
+        self.pop_all_expected(&type_.params)?;
+        self.frames.push(Frame {
+            kind,
+            params: type_.params.clone(),
+            results: type_.results.clone(),
+            height: self.operands.len(),
+            unreachable: false,
+        });
+        for &ty in &type_.params {
+            self.push(ty);
+        }
+        Ok(())
+    }
+
+    fn pop_frame(&mut self) -> Result<Frame, String> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_all_expected(&results)?;
+        let frame = self.frames.pop().unwrap();
+        if self.operands.len() != frame.height {
+            return Err(format!("{} values remaining on the stack at the end of the block", self.operands.len() - frame.height));
+        }
+        Ok(frame)
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&self, label: Idx<Label>) -> Result<Vec<ValType>, String> {
+        match self.frames.iter().rev().nth(label.0) {
+            Some(frame) => Ok(frame.label_types().to_vec()),
+            None => Err(format!("unknown label {} (block depth is {})", label.0, self.frames.len())),
+        }
+    }
+
+    /// returns the types of the values thrown with the tag
+    fn require_tag_idx(&self, idx: Idx<Tag>) -> Result<Vec<ValType>, String> {
+        match self.module.tags.get(idx.0) {
+            Some(tag) => Ok(tag.type_.params.clone()),
+            None => Err(format!("unknown tag #{}", idx.0)),
+        }
+    }
+
+    /// offsets larger than u32 are only allowed for memories with 64-bit addresses (memory64 proposal)
+    fn require_memarg(&self, memarg: Memarg) -> Result<(), String> {
+        if self.module.memories.is_empty() {
+            return Err("module has no memory".to_string());
+        }
+        self.require_memory_idx(memarg.memory)?;
+        if self.module.memory_index_type(memarg.memory) == ValType::I32 && memarg.offset > u32::max_value().into() {
+            return Err(format!("offset {} is too large for memory #{} with 32-bit addresses", memarg.offset, memarg.memory.0));
+        }
+        Ok(())
+    }
+
+    fn require_memory_idx(&self, idx: Idx<Memory>) -> Result<(), String> {
+        if idx.0 >= self.module.memories.len() {
+            return Err(format!("unknown memory #{}", idx.0));
+        }
+        Ok(())
+    }
+
+    /// returns the element type of the table
+    fn require_table_idx(&self, idx: Idx<Table>) -> Result<ElemType, String> {
+        match self.module.tables.get(idx.0) {
+            Some(table) => Ok(table.type_.0),
+            None => Err(format!("unknown table #{}", idx.0)),
+        }
+    }
+
+    fn require_data_idx(&self, idx: Idx<Data>) -> Result<(), String> {
+        if idx.0 >= self.module.data.len() {
+            return Err(format!("unknown data segment #{}", idx.0));
+        }
+        Ok(())
+    }
+
+    /// returns the element type of the segment
+    fn require_element_idx(&self, idx: Idx<Element>) -> Result<ElemType, String> {
+        match self.module.elements.get(idx.0) {
+            Some(element) => Ok(element.type_()),
+            None => Err(format!("unknown element segment #{}", idx.0)),
+        }
+    }
+
+    fn require_alignment(alignment: u32, natural_alignment: u32) -> Result<(), String> {
+        if alignment > natural_alignment {
+            return Err(format!("alignment 2^{} is larger than natural alignment 2^{}", alignment, natural_alignment));
+        }
+        Ok(())
+    }
+
+    fn require_atomic_alignment(alignment: u32, natural_alignment: u32) -> Result<(), String> {
+        if alignment != natural_alignment {
+            return Err(format!("atomic accesses must be naturally aligned (2^{}), got alignment 2^{}", natural_alignment, alignment));
+        }
+        Ok(())
+    }
+
+    fn require_lane(lane: u8, lane_count: u8) -> Result<(), String> {
+        if lane >= lane_count {
+            return Err(format!("lane index {} out of range, there are only {} lanes", lane, lane_count));
+        }
+        Ok(())
+    }
+
+    fn instr(&mut self, instr: &Instr) -> Result<(), String> {
+        match *instr {
+            Instr::Unreachable => self.set_unreachable(),
+            Instr::Nop => {}
+
+            Instr::Block(ref ty) => self.push_frame(FrameKind::Block, ty)?,
+            Instr::Loop(ref ty) => self.push_frame(FrameKind::Loop, ty)?,
+            Instr::If(ref ty) => {
+                self.pop_expected(ValType::I32)?;
+                self.push_frame(FrameKind::If, ty)?;
+            }
+            Instr::Else => {
+                let frame = self.pop_frame()?;
+                if frame.kind != FrameKind::If {
+                    return Err("else without matching if".to_string());
+                }
+                for &ty in &frame.params {
+                    self.push(ty);
+                }
+                self.frames.push(Frame {
+                    kind: FrameKind::Else,
+                    unreachable: false,
+                    ..frame
+                });
+            }
+            Instr::End => {
+                let frame = self.pop_frame()?;
+                // the missing else branch just passes the parameters through
+                if frame.kind == FrameKind::If && frame.params != frame.results {
+                    return Err("if without else must have the same parameter and result types".to_string());
+                }
+                for ty in frame.results {
+                    self.push(ty);
+                }
+            }
+
+            Instr::Try(ref ty) => self.push_frame(FrameKind::Try, ty)?,
+            Instr::Catch(tag_idx) => {
+                let tag_types = self.require_tag_idx(tag_idx)?;
+                let frame = self.pop_frame()?;
+                if frame.kind != FrameKind::Try && frame.kind != FrameKind::Catch {
+                    return Err("catch without matching try".to_string());
+                }
+                self.frames.push(Frame {
+                    kind: FrameKind::Catch,
+                    unreachable: false,
+                    ..frame
+                });
+                // the thrown values are available inside the catch block
+                for ty in tag_types {
+                    self.push(ty);
+                }
+            }
+            Instr::CatchAll => {
+                let frame = self.pop_frame()?;
+                if frame.kind != FrameKind::Try && frame.kind != FrameKind::Catch {
+                    return Err("catch_all without matching try".to_string());
+                }
+                self.frames.push(Frame {
+                    kind: FrameKind::CatchAll,
+                    unreachable: false,
+                    ..frame
+                });
+            }
+            Instr::Delegate(label) => {
+                let frame = self.pop_frame()?;
+                if frame.kind != FrameKind::Try {
+                    return Err("delegate without matching try".to_string());
+                }
+                // the label is relative to the blocks outside of the try block
+                if label.0 >= self.frames.len() {
+                    return Err(format!("unknown label {} (block depth is {})", label.0, self.frames.len()));
+                }
+                for ty in frame.results {
+                    self.push(ty);
+                }
+            }
+            Instr::Throw(tag_idx) => {
+                let tag_types = self.require_tag_idx(tag_idx)?;
+                self.pop_all_expected(&tag_types)?;
+                self.set_unreachable();
+            }
+            Instr::Rethrow(label) => {
+                match self.frames.iter().rev().nth(label.0) {
+                    Some(frame) if frame.kind == FrameKind::Catch || frame.kind == FrameKind::CatchAll => {}
+                    Some(_) => return Err(format!("label {} is not a catch block", label.0)),
+                    None => return Err(format!("unknown label {} (block depth is {})", label.0, self.frames.len())),
+                }
+                self.set_unreachable();
+            }
+
+            Instr::Br(label) => {
+                let label_types = self.label(label)?;
+                self.pop_all_expected(&label_types)?;
+                self.set_unreachable();
+            }
+            Instr::BrIf(label) => {
+                self.pop_expected(ValType::I32)?;
+                let label_types = self.label(label)?;
+                self.pop_all_expected(&label_types)?;
+                for ty in label_types {
+                    self.push(ty);
+                }
+            }
+            Instr::BrTable(ref table, default) => {
+                self.pop_expected(ValType::I32)?;
+                let default_types = self.label(default)?;
+                for &label in table {
+                    if self.label(label)? != default_types {
+                        return Err(format!("label {} has different types than default label {}", label.0, default.0));
+                    }
+                }
+                self.pop_all_expected(&default_types)?;
+                self.set_unreachable();
+            }
+            Instr::Return => {
+                let results = self.function.type_.results.clone();
+                self.pop_all_expected(&results)?;
+                self.set_unreachable();
+            }
+
+            Instr::Call(idx) => {
+                let ty = match self.module.functions.get(idx.0) {
+                    Some(function) => InstrType::from(&function.type_),
+                    None => return Err(format!("unknown function #{}", idx.0)),
+                };
+                self.instr_type(ty)?;
+            }
+            Instr::CallIndirect(_, table_idx) => {
+                if self.require_table_idx(table_idx)? != ElemType::FuncRef {
+                    return Err(format!("table #{} must have element type funcref", table_idx.0));
+                }
+                self.instr_type(instr.to_type().unwrap())?;
+            }
+            Instr::ReturnCall(idx) => {
+                let ty = match self.module.functions.get(idx.0) {
+                    Some(function) => function.type_.clone(),
+                    None => return Err(format!("unknown function #{}", idx.0)),
+                };
+                self.tail_call(&ty)?;
+            }
+            Instr::ReturnCallIndirect(ref ty, table_idx) => {
+                if self.require_table_idx(table_idx)? != ElemType::FuncRef {
+                    return Err(format!("table #{} must have element type funcref", table_idx.0));
+                }
+                self.pop_expected(ValType::I32)?;
+                self.tail_call(ty)?;
+            }
+
+            Instr::Drop => { self.pop()?; }
+            Instr::Select => {
+                self.pop_expected(ValType::I32)?;
+                let ty1 = self.pop()?;
+                let ty2 = self.pop()?;
+                match (ty1, ty2) {
+                    (Some(ty1), Some(ty2)) if ty1 != ty2 => return Err(format!("operands have different types {} and {}", ty1, ty2)),
+                    (Some(ValType::FuncRef), _) | (Some(ValType::ExternRef), _) | (_, Some(ValType::FuncRef)) | (_, Some(ValType::ExternRef)) =>
+                        return Err("operands of reference type require a typed select".to_string()),
+                    (Some(ty), _) | (_, Some(ty)) => self.push(ty),
+                    (None, None) => self.operands.push(None),
+                }
+            }
+            Instr::TypedSelect(_) => self.instr_type(instr.to_type().unwrap())?,
+
+            Instr::Local(op, idx) => {
+                let ty = match self.locals.get(idx.0) {
+                    Some(&ty) => ty,
+                    None => return Err(format!("unknown local #{}", idx.0)),
+                };
+                self.instr_type(op.to_type(ty))?;
+            }
+            Instr::Global(op, idx) => {
+                let GlobalType(ty, mutability) = match self.module.globals.get(idx.0) {
+                    Some(global) => global.type_,
+                    None => return Err(format!("unknown global #{}", idx.0)),
+                };
+                if let (GlobalOp::SetGlobal, Mutability::Const) = (op, mutability) {
+                    return Err(format!("global #{} is immutable", idx.0));
+                }
+                self.instr_type(op.to_type(ty))?;
+            }
+            Instr::Table(op, idx) => {
+                let elem_ty = self.require_table_idx(idx)?;
+                self.instr_type(op.to_type(elem_ty))?;
+            }
+
+            Instr::Load(op, memarg) => {
+                self.require_memarg(memarg)?;
+                Self::require_alignment(memarg.alignment, op.natural_alignment())?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::Store(op, memarg) => {
+                self.require_memarg(memarg)?;
+                Self::require_alignment(memarg.alignment, op.natural_alignment())?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::LoadLane(op, memarg, lane) => {
+                self.require_memarg(memarg)?;
+                Self::require_alignment(memarg.alignment, op.natural_alignment())?;
+                Self::require_lane(lane, op.lane_count())?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::StoreLane(op, memarg, lane) => {
+                self.require_memarg(memarg)?;
+                Self::require_alignment(memarg.alignment, op.natural_alignment())?;
+                Self::require_lane(lane, op.lane_count())?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::MemorySize(idx) | Instr::MemoryGrow(idx) | Instr::MemoryFill(idx) => {
+                self.require_memory_idx(idx)?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::MemoryCopy(dst_idx, src_idx) => {
+                self.require_memory_idx(dst_idx)?;
+                self.require_memory_idx(src_idx)?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::MemoryInit(data_idx, memory_idx) => {
+                self.require_memory_idx(memory_idx)?;
+                self.require_data_idx(data_idx)?;
+                self.memory_instr_type(instr)?;
+            }
+            Instr::DataDrop(data_idx) => self.require_data_idx(data_idx)?,
+            Instr::TableInit(elem_idx, table_idx) => {
+                let table_ty = self.require_table_idx(table_idx)?;
+                let elem_ty = self.require_element_idx(elem_idx)?;
+                if table_ty != elem_ty {
+                    return Err(format!("cannot initialize table #{} of type {} with {} elements", table_idx.0, table_ty, elem_ty));
+                }
+                self.instr_type(instr.to_type().unwrap())?;
+            }
+            Instr::ElemDrop(elem_idx) => { self.require_element_idx(elem_idx)?; }
+            Instr::TableCopy(dst_idx, src_idx) => {
+                let dst_ty = self.require_table_idx(dst_idx)?;
+                let src_ty = self.require_table_idx(src_idx)?;
+                if dst_ty != src_ty {
+                    return Err(format!("cannot copy from table #{} of type {} to table #{} of type {}", src_idx.0, src_ty, dst_idx.0, dst_ty));
+                }
+                self.instr_type(instr.to_type().unwrap())?;
+            }
+
+            Instr::RefNull(_) => self.instr_type(instr.to_type().unwrap())?,
+            Instr::RefIsNull => {
+                match self.pop()? {
+                    Some(ValType::FuncRef) | Some(ValType::ExternRef) | None => {}
+                    Some(ty) => return Err(format!("expected reference type on the stack, but got {}", ty)),
+                }
+                self.push(ValType::I32);
+            }
+            Instr::RefFunc(idx) => {
+                if idx.0 >= self.module.functions.len() {
+                    return Err(format!("unknown function #{}", idx.0));
+                }
+                if !self.declared_functions.contains(&idx) {
+                    return Err(format!("function #{} must be declared in an element segment, export, or global initializer", idx.0));
+                }
+                self.instr_type(instr.to_type().unwrap())?;
+            }
+
+            Instr::Const(_) | Instr::Numeric(_) | Instr::Simd(_) => self.instr_type(instr.to_type().unwrap())?,
+            Instr::SimdLane(op, lane) => {
+                Self::require_lane(lane, op.lane_count())?;
+                self.instr_type(op.to_type())?;
+            }
+            Instr::Shuffle(ref lanes) => {
+                // lanes index into the concatenation of both inputs
+                for &lane in lanes {
+                    Self::require_lane(lane, 32)?;
+                }
+                self.instr_type(instr.to_type().unwrap())?;
+            }
+
+            Instr::AtomicLoad(op, memarg) => self.atomic(instr, memarg, op.natural_alignment())?,
+            Instr::AtomicStore(op, memarg) => self.atomic(instr, memarg, op.natural_alignment())?,
+            Instr::AtomicRmw(op, memarg) => self.atomic(instr, memarg, op.natural_alignment())?,
+            Instr::AtomicCmpxchg(op, memarg) => self.atomic(instr, memarg, op.natural_alignment())?,
+            Instr::MemoryAtomicNotify(memarg) | Instr::MemoryAtomicWait32(memarg) => self.atomic(instr, memarg, 2)?,
+            Instr::MemoryAtomicWait64(memarg) => self.atomic(instr, memarg, 3)?,
+            Instr::AtomicFence => {}
+        }
+        Ok(())
+    }
+
+    /// like a call followed by a return, but the callee results are directly returned
+    fn tail_call(&mut self, callee_ty: &FunctionType) -> Result<(), String> {
+        if callee_ty.results != self.function.type_.results {
+            return Err("callee must have the same result types as the calling function".to_string());
+        }
+        self.pop_all_expected(&callee_ty.params)?;
+        self.set_unreachable();
+        Ok(())
+    }
+
+    fn atomic(&mut self, instr: &Instr, memarg: Memarg, natural_alignment: u32) -> Result<(), String> {
+        self.require_memarg(memarg)?;
+        Self::require_atomic_alignment(memarg.alignment, natural_alignment)?;
+        self.memory_instr_type(instr)
+    }
+
+    /// addresses (and sizes) are i64 for memories with 64-bit addresses, see the memory64 proposal
+    fn memory_instr_type(&mut self, instr: &Instr) -> Result<(), String> {
+        let module = self.module;
+        let ty = instr.to_type_with_memories(|memory| module.memory_index_type(memory)).unwrap();
+        self.instr_type(ty)
+    }
+}
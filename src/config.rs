@@ -35,8 +35,40 @@ pub enum HighLevelHook {
     MemorySize,
     MemoryGrow,
 
+    // bulk memory operations
+    MemoryInit,
+    DataDrop,
+    MemoryCopy,
+    MemoryFill,
+    TableInit,
+    ElemDrop,
+    TableCopy,
+
     Local,
     Global,
+
+    // reference types
+    TableGet,
+    TableSet,
+    TableSize,
+    TableGrow,
+    TableFill,
+
+    // SIMD proposal, all instructions on v128 that are not loads, stores, or consts
+    Simd,
+
+    // threads proposal
+    AtomicLoad,
+    AtomicStore,
+    AtomicRmw,
+    AtomicCmpxchg,
+    AtomicWait,
+    AtomicNotify,
+    AtomicFence,
+
+    // exception handling proposal, together for throw and rethrow
+    Throw,
+    Catch,
 }
 
 #[derive(Debug)]
@@ -45,7 +77,7 @@ pub struct EnabledHooks(HashSet<HighLevelHook>);
 impl EnabledHooks {
     pub fn all() -> Self {
         use self::HighLevelHook::*;
-        static VARIANTS: [HighLevelHook; 22] = [Start, Nop, Unreachable, Br, BrIf, BrTable, If, Begin, End, Call, Return, Drop, Select, Const, Unary, Binary, Load, Store, MemorySize, MemoryGrow, Local, Global];
+        static VARIANTS: [HighLevelHook; 44] = [Start, Nop, Unreachable, Br, BrIf, BrTable, If, Begin, End, Call, Return, Drop, Select, Const, Unary, Binary, Load, Store, MemorySize, MemoryGrow, MemoryInit, DataDrop, MemoryCopy, MemoryFill, TableInit, ElemDrop, TableCopy, Local, Global, TableGet, TableSet, TableSize, TableGrow, TableFill, Simd, AtomicLoad, AtomicStore, AtomicRmw, AtomicCmpxchg, AtomicWait, AtomicNotify, AtomicFence, Throw, Catch];
         EnabledHooks(VARIANTS.iter().cloned().collect())
     }
 
@@ -73,4 +105,22 @@ impl EnabledHooks {
     pub fn is_enabled(&self, hook: HighLevelHook) -> bool {
         self.0.contains(&hook)
     }
+}
+
+/// optional static information that is not needed by the hooks, but by some analyses, and would
+/// otherwise make the generated JavaScript larger
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StaticInfoOptions {
+    /// control-flow graph of every function, see wasm::cfg
+    pub cfg: bool,
+    /// static call graph, see wasm::call_graph
+    pub call_graph: bool,
+    /// loop instructions of every function, see LoopInfo
+    pub loops: bool,
+}
+
+impl StaticInfoOptions {
+    pub fn all() -> Self {
+        StaticInfoOptions { cfg: true, call_graph: true, loops: true }
+    }
 }
\ No newline at end of file
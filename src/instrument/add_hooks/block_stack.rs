@@ -6,7 +6,7 @@ use wasm::ast::{highlevel::Instr, Idx, Label};
  * Data structure for representing the "control stack", i.e., the implicit nested block structure
  * of control-flow instructions.
  * Needed for:
- *  - resolving End instructions to their corresponding begins (i.e., Block, Loop, If, Else)
+ *  - resolving End instructions to their corresponding begins (i.e., Block, Loop, If, Else, Try, Catch)
  *  - resolving labels of branches to actual instruction indices (which requires the previous as a first step)
  */
 
@@ -40,6 +40,17 @@ pub enum BlockStackElement {
         begin_if: Idx<Instr>,
         end: Idx<Instr>,
     },
+    /// end is the end of the whole try-catch construct (or the delegate), not of the try body
+    Try {
+        begin: Idx<Instr>,
+        end: Idx<Instr>,
+    },
+    /// for catch and catch_all
+    Catch {
+        begin_catch: Idx<Instr>,
+        begin_try: Idx<Instr>,
+        end: Idx<Instr>,
+    },
 }
 
 impl BlockStack {
@@ -51,12 +62,12 @@ impl BlockStack {
         for (iidx, instr) in instrs[..instrs.len() - 1].iter().enumerate() {
             let iidx = iidx.into();
             match *instr {
-                Instr::Block(_) | Instr::Loop(_) | Instr::If(_) => begin_stack.push(iidx),
-                Instr::Else | Instr::End => {
+                Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Try(_) => begin_stack.push(iidx),
+                Instr::Else | Instr::End | Instr::Catch(_) | Instr::CatchAll | Instr::Delegate(_) => {
                     let begin_iidx = begin_stack.pop().expect("invalid block nesting: could not end block, stack was empty");
                     begin_end_map.insert(begin_iidx, iidx);
-                    // special case: Else also starts its own block
-                    if let Instr::Else = instr {
+                    // special case: Else (and Catch) also start their own block
+                    if let Instr::Else | Instr::Catch(_) | Instr::CatchAll = instr {
                         begin_stack.push(iidx);
                     }
                 }
@@ -107,6 +118,30 @@ impl BlockStack {
         self.block_stack.push(if_);
     }
 
+    pub fn begin_try(&mut self, begin: Idx<Instr>) {
+        // skip over all catch blocks to the end of the whole construct
+        let mut end = *self.begin_end_map.get(&begin)
+            .expect(&format!("invalid block nesting: could not find end/catch for try begin at {:?}", begin));
+        while let Some(&next) = self.begin_end_map.get(&end) {
+            end = next;
+        }
+        self.block_stack.push(Try { begin, end });
+    }
+
+    /// returns the ended try body or previous catch block
+    pub fn catch_(&mut self, begin_catch: Idx<Instr>) -> BlockStackElement {
+        match self.block_stack.pop() {
+            Some(block_element) => match block_element {
+                Try { begin: begin_try, end } | Catch { begin_try, end, .. } => {
+                    self.block_stack.push(Catch { begin_catch, begin_try, end });
+                    block_element
+                }
+                block => panic!("invalid block nesting: expected try or catch on block stack, but got {:?}", block),
+            }
+            None => panic!("invalid block nesting: expected try, but stack was empty"),
+        }
+    }
+
     /// returns matching If block (of which this else is a "sibling")
     pub fn else_(&mut self) -> BlockStackElement {
         match self.block_stack.pop() {
@@ -125,6 +160,12 @@ impl BlockStack {
         self.block_stack.pop().expect("invalid block nesting: could not end block, stack was empty")
     }
 
+    /// the current block and the one it is nested in (None for the function block)
+    pub fn innermost(&self) -> Option<(&BlockStackElement, Option<&BlockStackElement>)> {
+        let mut blocks = self.block_stack.iter().rev();
+        blocks.next().map(|innermost| (innermost, blocks.next()))
+    }
+
     /// resolves a relative label at the current instruction to an absolute instruction index
     /// this requires forward scanning for non-loop block ends (implemented as a precomputed HashMap lookup, so O(1))
     pub fn br_target(&self, label: Idx<Label>) -> BranchTarget {
@@ -140,7 +181,8 @@ impl BlockStack {
 
             match *target_block {
                 Loop { begin, .. } => begin,
-                Function { end } | Block { end, .. } | If { end, .. } | Else { end, .. } => end,
+                Function { end } | Block { end, .. } | If { end, .. } | Else { end, .. }
+                | Try { end, .. } | Catch { end, .. } => end,
             }
         };
 
@@ -1,14 +1,16 @@
-use wasm::ast::{Val, ValType, ValType::I32, ValType::I64};
-use wasm::ast::highlevel::{Instr, Instr::Const, Instr::Numeric, NumericOp::I32WrapI64, NumericOp::I64ShrS};
+use wasm::ast::{Val, ValType, ValType::I32, ValType::I64, ValType::V128};
+use wasm::ast::highlevel::{Instr, Instr::Const, Instr::Numeric, Instr::SimdLane, NumericOp::I32WrapI64, NumericOp::I64ShrS, SimdLaneOp::I32x4ExtractLane};
 use std::slice::from_ref;
 
 /*
  * Helper functions for turning i64's into two i32's so that we can pass them to JavaScript
+ * (similarly, v128's are passed as their four i32 lanes)
  */
 
 pub fn convert_i64_type(ty: &ValType) -> &[ValType] {
     match ty {
         &I64 => &[I32, I32],
+        &V128 => &[I32, I32, I32, I32],
         ty => from_ref(ty),
     }
 }
@@ -26,6 +28,8 @@ pub fn convert_i64_instr(instr: Instr, ty: ValType) -> Vec<Instr> {
             Numeric(I64ShrS),
             Numeric(I32WrapI64), // high bits
         ],
+        // lowest lane first, executes instr once per lane
+        V128 => (0..4).flat_map(|lane| vec![instr.clone(), SimdLane(I32x4ExtractLane, lane)]).collect(),
         _ => vec![instr],
     }
 }
@@ -1,5 +1,5 @@
 use wasm::ast::{FunctionType, Idx, ValType, ValType::*};
-use wasm::ast::highlevel::{Function, Instr, Instr::*, Module};
+use wasm::ast::highlevel::{Function, Instr, Instr::*, Module, TableOp::*};
 use std::collections::HashMap;
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use super::block_stack::BlockStackElement;
@@ -24,14 +24,25 @@ impl Arg {
     fn to_lowlevel_param_name(&self) -> String {
         match self.ty {
             I64 => self.name.clone() + "_low, " + &self.name + "_high",
+            V128 => self.to_lowlevel_local_names().join(", "),
             _ => self.name.clone()
         }
     }
 
+    /// for the local names of the low-level Wasm function (in the name section)
+    fn to_lowlevel_local_names(&self) -> Vec<String> {
+        match self.ty {
+            I64 => vec![self.name.clone() + "_low", self.name.clone() + "_high"],
+            V128 => (0..4).map(|lane| format!("{}_{}", self.name, lane)).collect(),
+            _ => vec![self.name.clone()]
+        }
+    }
+
     /// for the actual argument when forwarding to the high-level hook
     fn to_lowlevel_long_expr(&self) -> String {
         match self.ty {
             I64 => format!("new Long({})", self.to_lowlevel_param_name()),
+            V128 => format!("Int32Array.of({})", self.to_lowlevel_param_name()),
             _ => self.name.clone()
         }
     }
@@ -52,30 +63,44 @@ impl Hook {
     /// args: do not include the (i32, i32) instruction location, also before i64 -> (i32, i32) lowering
     /// js_args: (quick and dirty, highly unsafe) JavaScript fragment, pasted into the high-level user hook call
     pub fn new(lowlevel_name: impl Into<String>, args: Vec<Arg>, highlevel_name: &str, js_args: &str) -> Self {
+        // JavaScript low-level hook that is called from Wasm and in turn calls the high-level user analysis hook
+        let js_body = format!("Wasabi.analysis.{}({{func, instr}}, {});", highlevel_name, js_args);
+        Hook::with_js_body(lowlevel_name, args, &js_body)
+    }
+
+    /// for low-level hooks that do not (only) forward to a single high-level hook
+    /// js_body: JavaScript statement(s) of the low-level hook, can use func, instr, and the args
+    pub fn with_js_body(lowlevel_name: impl Into<String>, args: Vec<Arg>, js_body: &str) -> Self {
         let lowlevel_name = lowlevel_name.into();
 
-        // generate JavaScript low-level hook that is called from Wasm and in turn calls the
-        // high-level user analysis hook
-        let js = format!("\"{}\": function (func, instr, {}) {{\n    Wasabi.analysis.{}({{func, instr}}, {});\n}},",
+        let js = format!("\"{}\": function (func, instr, {}) {{\n    {}\n}},",
                          &lowlevel_name,
                          args.iter().map(Arg::to_lowlevel_param_name).collect::<Vec<_>>().join(", "),
-                         highlevel_name,
-                         js_args);
+                         js_body);
 
         // generate low-level Wasm function to insert into the intrumented module
         let wasm = {
             // prepend two I32 for (function idx, instr idx)
             let mut lowlevel_args = vec![I32, I32];
             lowlevel_args.extend(args.iter()
-                // and expand i64 to a tuple of (i32, i32) since there is no JS interop for i64
+                // and expand i64 to a tuple of (i32, i32) since there is no JS interop for i64 (same for v128)
                 .flat_map(|Arg { name: _name, ref ty }| convert_i64_type(ty)));
 
+            // same parameter names as in the low-level JavaScript function, for debugging
+            let mut local_names = vec!["func".to_string(), "instr".to_string()];
+            local_names.extend(args.iter().flat_map(Arg::to_lowlevel_local_names));
+
             Function {
                 // hooks do not return anything
                 type_: FunctionType::new(lowlevel_args, vec![]),
-                import: Some(("__wasabi_hooks".to_string(), lowlevel_name)),
+                import: Some(("__wasabi_hooks".to_string(), lowlevel_name.clone())),
                 code: None,
                 export: Vec::new(),
+                name: Some(format!("__wasabi_hooks.{}", lowlevel_name)),
+                local_names: local_names.into_iter()
+                    .enumerate()
+                    .map(|(i, name)| (i.into(), name))
+                    .collect(),
             }
         };
 
@@ -137,21 +162,75 @@ impl HookMap {
             // NOTE js_args is very hacky! We rely on the Hook constructor to close the parenthesis and insert the call statement to endBrTableBlock() here
             BrTable(_, _) => Hook::new(name, args!(tableIdx: I32, brTablesInfoIdx: I32), name, "Wasabi.module.info.brTables[brTablesInfoIdx].table, Wasabi.module.info.brTables[brTablesInfoIdx].default, tableIdx); Wasabi.endBrTableBlocks(brTablesInfoIdx, tableIdx, func"),
 
-            MemorySize(_) => Hook::new(name, args!(currentSizePages: I32), name, "currentSizePages"),
-            MemoryGrow(_) => Hook::new(name, args!(deltaPages: I32, previousSizePages: I32), name, "deltaPages, previousSizePages"),
+            /*
+                memory instructions: addresses and sizes are i64 for memories with 64-bit addresses
+                (memory64 proposal), in that case the index type is given through polymorphic_tys
+            */
+
+            MemorySize(_) => {
+                let args = args!(memory: I32, currentSizePages: index_ty(polymorphic_tys, 0));
+                let js_args = &format!("{}, memory", args[1].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_size", js_args)
+            }
+            MemoryGrow(_) => {
+                let ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, deltaPages: ty, previousSizePages: ty);
+                let js_args = &format!("{}, {}, memory", args[1].to_lowlevel_long_expr(), args[2].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_grow", js_args)
+            }
+
+            MemoryInit(_, _) => {
+                let args = args!(memory: I32, dataIdx: I32, destination: index_ty(polymorphic_tys, 0), source: I32, length: I32);
+                let js_args = &format!("dataIdx, {}, source, length, memory", args[2].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_init", js_args)
+            }
+            DataDrop(_) => Hook::new(name, args!(dataIdx: I32), "data_drop", "dataIdx"),
+            MemoryCopy(_, _) => {
+                let (destination_ty, source_ty) = (index_ty(polymorphic_tys, 0), index_ty(polymorphic_tys, 1));
+                // the length is only i64 if both memories have 64-bit addresses
+                let length_ty = if destination_ty == I64 && source_ty == I64 { I64 } else { I32 };
+                let args = args!(destinationMemory: I32, sourceMemory: I32, destination: destination_ty, source: source_ty, length: length_ty);
+                let js_args = &format!("{}, destinationMemory, sourceMemory", args[2..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "memory_copy", js_args)
+            }
+            MemoryFill(_) => {
+                let ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, destination: ty, value: I32, length: ty);
+                let js_args = &format!("{}, value, {}, memory", args[1].to_lowlevel_long_expr(), args[3].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_fill", js_args)
+            }
+            TableInit(_, _) => Hook::new(name, args!(table: I32, elemIdx: I32, destination: I32, source: I32, length: I32), "table_init", "elemIdx, destination, source, length, table"),
+            ElemDrop(_) => Hook::new(name, args!(elemIdx: I32), "elem_drop", "elemIdx"),
+            TableCopy(_, _) => Hook::new(name, args!(destinationTable: I32, sourceTable: I32, destination: I32, source: I32, length: I32), "table_copy", "destination, source, length, destinationTable, sourceTable"),
 
             Load(op, _) => {
                 let ty = op.to_type().results[0];
-                let args = args!(offset: I32, align: I32, addr: I32, value: ty);
-                let instr_name = instr.to_name();
-                let js_args = &format!("\"{}\", {{addr, offset, align}}, {}", instr_name, &args[3].to_lowlevel_long_expr());
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr());
                 Hook::new(name, args, "load", js_args)
             }
             Store(op, _) => {
                 let ty = op.to_type().inputs[1];
-                let args = args!(offset: I32, align: I32, addr: I32, value: ty);
-                let instr_name = instr.to_name();
-                let js_args = &format!("\"{}\", {{addr, offset, align}}, {}", instr_name, &args[3].to_lowlevel_long_expr());
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr());
+                Hook::new(name, args, "store", js_args)
+            }
+            LoadLane(op, _, _) => {
+                let ty = op.to_type().results[0];
+                let mut args = args!(lane: I32);
+                args.extend(memarg_args(polymorphic_tys));
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}", instr.to_name(), memarg_js(&args[1..], ", lane"), &args[5].to_lowlevel_long_expr());
+                Hook::new(name, args, "load", js_args)
+            }
+            StoreLane(op, _, _) => {
+                let ty = op.to_type().inputs[1];
+                let mut args = args!(lane: I32);
+                args.extend(memarg_args(polymorphic_tys));
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}", instr.to_name(), memarg_js(&args[1..], ", lane"), &args[5].to_lowlevel_long_expr());
                 Hook::new(name, args, "store", js_args)
             }
 
@@ -160,6 +239,11 @@ impl HookMap {
                 let js_args = &args[0].to_lowlevel_long_expr();
                 Hook::new(name, args, "const_", js_args)
             }
+            RefNull(ty) => {
+                let args = args!(value: ty.into());
+                Hook::new(mangle_polymorphic_name(name, &[ty.into()]), args, "const_", "value")
+            }
+            RefFunc(_) => Hook::new(name, args!(value: FuncRef), "const_", "value"),
             Numeric(op) => {
                 let ty = op.to_type();
                 let highlevel_name = match ty.inputs.len() {
@@ -174,6 +258,75 @@ impl HookMap {
                 let js_args = &format!("\"{}\", {}", instr_name, args.iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
                 Hook::new(name, args, highlevel_name, js_args)
             }
+            // the threads proposal has only sequentially consistent atomics, but pass the order anyway for future extensions
+            AtomicLoad(op, _) => {
+                let ty = op.to_type().results[0];
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}, \"seq_cst\"", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_load", js_args)
+            }
+            AtomicStore(op, _) => {
+                let ty = op.to_type().inputs[1];
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(value: ty));
+                let js_args = &format!("\"{}\", {}, {}, \"seq_cst\"", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_store", js_args)
+            }
+            AtomicRmw(op, _) => {
+                let ty = op.to_type().results[0];
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(operand: ty, oldValue: ty));
+                let js_args = &format!("\"{}\", {}, {}, {}, \"seq_cst\"", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr(), &args[5].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_rmw", js_args)
+            }
+            AtomicCmpxchg(op, _) => {
+                let ty = op.to_type().results[0];
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(expected: ty, replacement: ty, oldValue: ty));
+                let js_args = &format!("\"{}\", {}, {}, \"seq_cst\"", instr.to_name(), memarg_js(&args, ""), args[4..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "atomic_cmpxchg", js_args)
+            }
+            MemoryAtomicWait32(_) | MemoryAtomicWait64(_) => {
+                let ty = instr.to_type().unwrap().inputs[1];
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(expected: ty, timeout: I64, result: I32));
+                // result: 0 = woken by notify, 1 = value was not the expected one, 2 = timed out
+                let js_args = &format!("\"{}\", {}, {}, {}, result", instr.to_name(), memarg_js(&args, ""), &args[4].to_lowlevel_long_expr(), &args[5].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_wait", js_args)
+            }
+            MemoryAtomicNotify(_) => {
+                let mut args = memarg_args(polymorphic_tys);
+                args.extend(args!(count: I32, woken: I32));
+                let js_args = &format!("{}, count, woken", memarg_js(&args, ""));
+                Hook::new(name, args, "atomic_notify", js_args)
+            }
+            AtomicFence => Hook::new(name, args!(), "atomic_fence", "\"seq_cst\""),
+            Simd(_) | SimdLane(_, _) | Shuffle(_) => {
+                let ty = instr.to_type().unwrap();
+                let inputs = ty.inputs.iter().enumerate().map(|(i, &ty)| Arg { name: format!("input{}", i), ty }).collect::<Vec<_>>();
+                let result = Arg { name: "result0".into(), ty: ty.results[0] };
+                // lane immediates are passed as additional arguments, so that one hook serves all lanes
+                let (mut args, immediate) = match *instr {
+                    Simd(_) => (vec![], "undefined".to_string()),
+                    SimdLane(_, _) => (args!(lane: I32), "lane".to_string()),
+                    // 16 lane indices, packed into 4 i32's (little-endian, like the v128 lanes)
+                    Shuffle(_) => {
+                        let args = args!(lanes: V128);
+                        let lanes = format!("Array.from(new Uint8Array({}.buffer))", args[0].to_lowlevel_long_expr());
+                        (args, lanes)
+                    }
+                    _ => unreachable!(),
+                };
+                let js_args = &format!("\"{}\", [{}], {}, {}",
+                                       instr.to_name(),
+                                       inputs.iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "),
+                                       result.to_lowlevel_long_expr(),
+                                       immediate);
+                args.extend(inputs);
+                args.push(result);
+                Hook::new(name, args, "simd", js_args)
+            }
 
 
             /*
@@ -190,7 +343,7 @@ impl HookMap {
                 let js_args = &args[0].to_lowlevel_long_expr();
                 Hook::new(name, args, "drop", js_args)
             }
-            Select => {
+            Select | TypedSelect(_) => {
                 assert_eq!(polymorphic_tys.len(), 2, "select has two polymorphic arguments");
                 assert_eq!(polymorphic_tys[0], polymorphic_tys[1], "select arguments must be equal");
                 let args = args!(condition: I32, input0: polymorphic_tys[0], input1: polymorphic_tys[1]);
@@ -211,29 +364,64 @@ impl HookMap {
                 let js_args = &format!("\"{}\", {}", instr_name, args.iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
                 Hook::new(name, args, "global", js_args)
             }
+            Table(op, _) => {
+                assert_eq!(polymorphic_tys.len(), 1, "table instructions are polymorphic only in the element type");
+                let elem_ty = polymorphic_tys[0];
+                let (args, highlevel_name, js_args) = match op {
+                    TableGet => (args!(table: I32, index: I32, value: elem_ty), "table_get", "table, index, value"),
+                    TableSet => (args!(table: I32, index: I32, value: elem_ty), "table_set", "table, index, value"),
+                    TableSize => (args!(table: I32, currentSize: I32), "table_size", "table, currentSize"),
+                    TableGrow => (args!(table: I32, initValue: elem_ty, delta: I32, previousSize: I32), "table_grow", "table, initValue, delta, previousSize"),
+                    TableFill => (args!(table: I32, index: I32, value: elem_ty, length: I32), "table_fill", "table, index, value, length"),
+                };
+                Hook::new(name, args, highlevel_name, js_args)
+            }
+            RefIsNull => {
+                assert_eq!(polymorphic_tys.len(), 1, "ref.is_null has only one argument");
+                let args = args!(input0: polymorphic_tys[0], result0: I32);
+                Hook::new(name, args, "unary", "\"ref.is_null\", input0, result0")
+            }
+            // tag values are polymorphic, i.e., polymorphic_tys are the params of the tag type
+            Throw(_) | Catch(_) => {
+                let mut args = args!(tag: I32);
+                args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("value{}", i), ty }));
+                let js_args = &format!("tag, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                let highlevel_name = if let Throw(_) = *instr { "throw_" } else { "catch_" };
+                Hook::new(name, args, highlevel_name, js_args)
+            }
+            // the values of a caught exception are not accessible in a catch_all block
+            CatchAll => Hook::new(name, args!(), "catch_", "undefined, []"),
+            Rethrow(_) => Hook::new(name, args!(catchInstr: I32), "rethrow", "{func, instr: catchInstr}"),
             Return => {
                 let args = polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("result{}", i), ty }).collect::<Vec<_>>();
                 let js_args = &format!("[{}]", args.iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
                 Hook::new(name, args, "return_", js_args)
             }
-            Call(_) => {
+            Call(_) | ReturnCall(_) => {
                 let mut args = args!(targetFunc: I32);
                 args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
                 // NOTE calls the high-level call_pre hook with one argument less than call_indirect, thus tableIdx === undefined since this is a direct call
-                let js_args = &format!("targetFunc, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
-                Hook::new(name, args, "call_pre", js_args)
+                let mut js_args = format!("targetFunc, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                if let ReturnCall(_) = *instr {
+                    // tail calls: no call_post follows, since the callee returns directly to our caller
+                    js_args.push_str(", undefined, true");
+                }
+                Hook::new(name, args, "call_pre", &js_args)
             }
-            CallIndirect(_, _) => {
-                let mut args = args!(tableIndex: I32);
+            CallIndirect(_, _) | ReturnCallIndirect(_, _) => {
+                let mut args = args!(table: I32, tableIndex: I32);
                 args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
-                let js_args = &format!("Wasabi.resolveTableIdx(tableIndex), [{}], tableIndex", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
-                Hook::new(name, args, "call_pre", js_args)
+                let mut js_args = format!("Wasabi.resolveTableIdx(tableIndex, table), [{}], tableIndex", args[2..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                if let ReturnCallIndirect(_, _) = *instr {
+                    js_args.push_str(", true");
+                }
+                Hook::new(name, args, "call_pre", &js_args)
             }
 
 
             /* instructions that need additional information and thus have own method */
 
-            Block(_) | Loop(_) | Else | End => panic!("cannot get hook for block-type instruction with this method, please use the other methods specialized to the block type"),
+            Block(_) | Loop(_) | Else | End | Try(_) | Delegate(_) => panic!("cannot get hook for block-type instruction with this method, please use the other methods specialized to the block type"),
         };
         self.get_or_insert(hook)
     }
@@ -271,14 +459,37 @@ impl HookMap {
         self.get_or_insert(Hook::new("begin_else", args!(ifInstr: I32), "begin", "\"else\", {func, instr: ifInstr}"))
     }
 
-    pub fn end(&self, block: &BlockStackElement) -> Instr {
-        self.get_or_insert(match *block {
-            BlockStackElement::Function { .. } => Hook::new("end_function", vec![], "end", "\"function\", {func, instr: -1}"),
-            BlockStackElement::Block { .. } => Hook::new("end_block", args!(beginInstr: I32), "end", "\"block\", {func, instr: beginInstr}"),
-            BlockStackElement::Loop { .. } => Hook::new("end_loop", args!(beginInstr: I32), "end", "\"loop\", {func, instr: beginInstr}"),
-            BlockStackElement::If { .. } => Hook::new("end_if", args!(beginInstr: I32), "end", "\"if\", {func, instr: beginInstr}"),
-            BlockStackElement::Else { .. } => Hook::new("end_else", args!(elseInstr: I32, ifInstr: I32), "end", "\"else\", {func, instr: elseInstr}, {func, instr: ifInstr}"),
-        })
+    pub fn begin_try(&self) -> Instr {
+        self.get_or_insert(Hook::new("begin_try", vec![], "begin", "\"try\""))
+    }
+
+    /// for both catch and catch_all blocks
+    pub fn begin_catch(&self) -> Instr {
+        self.get_or_insert(Hook::new("begin_catch", args!(tryInstr: I32), "begin", "\"catch\", {func, instr: tryInstr}"))
+    }
+
+    /// when an exception is caught (or leaves the function), the runtime calls the end hooks of all
+    /// blocks from the innermost block at the throw site up to and including the handler block
+    /// (both given as block ids, see FunctionInfo::blocks)
+    pub fn unwind(&self) -> Instr {
+        self.get_or_insert(Hook::with_js_body("unwind", args!(innermostBlock: I32, handlerBlock: I32), "Wasabi.endUnwoundBlocks(func, innermostBlock, handlerBlock);"))
+    }
+
+    /// result_tys: of the values the block produces, empty if the block is left via a branch or return
+    pub fn end(&self, block: &BlockStackElement, result_tys: &[ValType]) -> Instr {
+        let (name, mut args, js_args) = match *block {
+            BlockStackElement::Function { .. } => ("end_function", vec![], "\"function\", {func, instr: -1}, undefined"),
+            BlockStackElement::Block { .. } => ("end_block", args!(beginInstr: I32), "\"block\", {func, instr: beginInstr}, undefined"),
+            BlockStackElement::Loop { .. } => ("end_loop", args!(beginInstr: I32), "\"loop\", {func, instr: beginInstr}, undefined"),
+            BlockStackElement::If { .. } => ("end_if", args!(beginInstr: I32), "\"if\", {func, instr: beginInstr}, undefined"),
+            BlockStackElement::Else { .. } => ("end_else", args!(elseInstr: I32, ifInstr: I32), "\"else\", {func, instr: elseInstr}, {func, instr: ifInstr}"),
+            BlockStackElement::Try { .. } => ("end_try", args!(beginInstr: I32), "\"try\", {func, instr: beginInstr}, undefined"),
+            BlockStackElement::Catch { .. } => ("end_catch", args!(catchInstr: I32, tryInstr: I32), "\"catch\", {func, instr: catchInstr}, {func, instr: tryInstr}"),
+        };
+        let result_args_start = args.len();
+        args.extend(result_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("result{}", i), ty }));
+        let js_args = &format!("{}, [{}]", js_args, args[result_args_start..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+        self.get_or_insert(Hook::new(mangle_polymorphic_name(name, result_tys), args, "end", js_args))
     }
 
     /// returns a Call instruction to the requested hook, which either
@@ -320,6 +531,22 @@ impl HookMap {
 /* utility functions */
 
 /// e.g. "call" + [I32, F64] -> "call_iF"
+/// index type of the (i-th) accessed memory, memories with 32-bit addresses are not given in polymorphic_tys
+fn index_ty(polymorphic_tys: &[ValType], i: usize) -> ValType {
+    polymorphic_tys.get(i).cloned().unwrap_or(I32)
+}
+
+/// arguments for the memarg immediate and the accessed address, in the order the instrumentation pushes them
+fn memarg_args(polymorphic_tys: &[ValType]) -> Vec<Arg> {
+    let ty = index_ty(polymorphic_tys, 0);
+    args!(memory: I32, offset: ty, align: I32, addr: ty)
+}
+
+/// JavaScript memarg object from the first four (memarg) args, extra_fields: e.g., ", lane"
+fn memarg_js(args: &[Arg], extra_fields: &str) -> String {
+    format!("{{addr: {}, offset: {}, align, memory{}}}", args[3].to_lowlevel_long_expr(), args[1].to_lowlevel_long_expr(), extra_fields)
+}
+
 fn mangle_polymorphic_name(name: &str, tys: &[ValType]) -> String {
     let mut mangled = name.to_string();
     if !tys.is_empty() {
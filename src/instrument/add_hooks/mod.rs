@@ -1,4 +1,4 @@
-use config::{EnabledHooks, HighLevelHook};
+use config::{EnabledHooks, HighLevelHook, StaticInfoOptions};
 use self::block_stack::{BlockStack, BlockStackElement};
 use self::convert_i64::convert_i64_instr;
 use self::duplicate_stack::*;
@@ -6,10 +6,13 @@ use self::hook_map::HookMap;
 use self::static_info::*;
 use self::type_stack::TypeStack;
 use serde_json;
-use wasm::ast::{BlockType, Idx, InstrType, Mutability, Val, ValType::*, FunctionType};
-use wasm::ast::highlevel::{Function, GlobalOp::*, Instr, Instr::*, LocalOp::*, Module};
+use wasm::ast::{self, Idx, InstrType, Mutability, Val, ValType, ValType::*, FunctionType};
+use wasm::ast::highlevel::{BlockType, Function, GlobalOp::*, Instr, Instr::*, LocalOp::*, Memarg, Memory, Module, TableOp::*};
+use wasm::call_graph::CallGraph;
+use wasm::cfg::Cfg;
 use rayon::prelude::*;
 use parking_lot::RwLock;
+use std::collections::BTreeMap;
 
 mod convert_i64;
 mod static_info;
@@ -20,11 +23,11 @@ mod duplicate_stack;
 
 /// instruments every instruction in Jalangi-style with a callback that takes inputs, outputs, and
 /// other relevant information.
-pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<String> {
-    // make sure table is exported, needed for Wasabi runtime to resolve table indices to function indices.
-    for table in &mut module.tables {
+pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks, static_info: &StaticInfoOptions) -> Option<String> {
+    // make sure all tables are exported, needed for Wasabi runtime to resolve table indices to function indices.
+    for (i, table) in module.tables.iter_mut().enumerate() {
         if table.export.is_empty() {
-            table.export.push("__wasabi_table".into());
+            table.export.push(format!("__wasabi_table_{}", i));
         }
     }
     // FIXME is this a valid workaround for wrong Firefox exported function .name property?
@@ -34,10 +37,41 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 //        }
 //    }
 
+    // so that downstream tools can tell that the binary was rewritten
+    module.producers_mut().add("processed-by", "wasabi", env!("CARGO_PKG_VERSION"));
+
     // NOTE must be after exporting table and function, so that their export names are in the static info object
-    let module_info: ModuleInfo = (&*module).into();
+    let mut module_info: ModuleInfo = (&*module).into();
+    // of the original bodies, so must be before instrumentation
+    if static_info.cfg {
+        for (function_info, function) in module_info.functions.iter_mut().zip(&module.functions) {
+            function_info.cfg = function.code.as_ref().map(|code| Cfg::new(&code.body));
+        }
+    }
+    if static_info.loops {
+        for (function_info, function) in module_info.functions.iter_mut().zip(&module.functions) {
+            function_info.loops = function.code.as_ref().map(|code| LoopInfo::from_body(&code.body)).unwrap_or_default();
+        }
+    }
+    if static_info.call_graph {
+        module_info.call_graph = Some(CallGraph::new(module));
+    }
     let module_info = RwLock::new(module_info);
     let hooks = HookMap::new(&module);
+    // for table instructions (tables are not accessible from the parallel loop over functions below)
+    let table_elem_tys: Vec<_> = module.tables.iter().map(|table| table.type_.0).collect();
+    // for throw and catch instructions (same reason)
+    let tag_value_tys: Vec<_> = module.tags.iter().map(|tag| tag.type_.params.clone()).collect();
+    // for memory instructions, whose addresses are i64 for memories with 64-bit addresses (memory64 proposal)
+    let memory_index_tys: Vec<_> = module.memories.iter().map(|memory| memory.type_.0.index_type).collect();
+    let memory_index_ty = |memory: Idx<Memory>| memory_index_tys[memory.0];
+
+    // exceptions can unwind blocks (and whole functions) without reaching their end instruction,
+    // so for calling the end hooks of these blocks, we need to track the innermost block at runtime
+    let track_unwound_blocks = enabled_hooks.is_enabled(HighLevelHook::End)
+        && (!module.tags.is_empty() || module.functions.iter()
+            .flat_map(|function| function.code.iter().flat_map(|code| code.body.iter()))
+            .any(|instr| if let Try(_) = *instr { true } else { false }));
 
     // add global for start, set to false on the first execution of the start function
     let start_not_executed_global = module.add_global(I32, Mutability::Mut, vec![Const(Val::I32(1)), End]);
@@ -49,6 +83,9 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             return;
         }
 
+        // all locals from here on are temporaries added by the instrumentation
+        let original_local_count = function.type_.params.len() + function.code.as_ref().unwrap().locals.len();
+
         // move body out of function, so that function is not borrowed during iteration over the original body
         let original_body = {
             let dummy_body = Vec::new();
@@ -64,13 +101,17 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
         // for drop/select monomorphization (cannot determine their input types only from instruction, but need this additional type information)
         let mut type_stack = TypeStack::new();
 
+        // id of the innermost block at runtime (see set_innermost_block()) and static info about these blocks
+        let block_local = if track_unwound_blocks { Some(function.add_fresh_local(I32)) } else { None };
+        let mut blocks = BTreeMap::new();
+
         // execute start hook before anything else
         if module_info.read().start == Some(fidx)
             && enabled_hooks.is_enabled(HighLevelHook::Start) {
             instrumented_body.extend_from_slice(&[
                 Global(GetGlobal, start_not_executed_global),
                 // ...(if this is the start function and it hasn't run yet)
-                If(BlockType(None)),
+                If(BlockType::default()),
                 Const(Val::I32(0)),
                 Global(SetGlobal, start_not_executed_global),
                 fidx.to_const(),
@@ -90,6 +131,12 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             ]);
         }
 
+        // catch exceptions leaving the function (and rethrow them) to call the end hooks of all unwound blocks
+        instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
+        if block_local.is_some() {
+            instrumented_body.push(Try(FunctionType::new(vec![], function.type_.results.clone())));
+        }
+
         // remember implicit return for instrumentation: add "synthetic" return hook call to last end
         let implicit_return = !original_body.ends_with(&[Return, End]);
 
@@ -104,8 +151,10 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             // "unreachable depth" abomination.
             if unreachable > 0 {
                 match instr {
-                    Block(_) | Loop(_) | If(_) => unreachable += 1,
-                    End => unreachable -= 1,
+                    Block(_) | Loop(_) | If(_) | Try(_) => unreachable += 1,
+                    End | Delegate(_) => unreachable -= 1,
+                    // the handlers of a try block are reachable, even if the end of its body is not
+                    Catch(_) | CatchAll if unreachable == 1 => unreachable = 0,
                     _ => {}
                 };
                 if unreachable > 0 {
@@ -154,9 +203,9 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 
                 /* Control Instructions: Blocks */
 
-                Block(block_ty) => {
+                Block(ref block_ty) => {
                     block_stack.begin_block(iidx);
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
                     instrumented_body.push(instr);
 
@@ -167,10 +216,12 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             hooks.begin_block(),
                         ])
                     }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
                 }
-                Loop(block_ty) => {
+                Loop(ref block_ty) => {
                     block_stack.begin_loop(iidx);
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
                     instrumented_body.push(instr);
 
@@ -181,11 +232,13 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             hooks.begin_loop(),
                         ])
                     }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
                 }
-                If(block_ty) => {
+                If(ref block_ty) => {
                     block_stack.begin_if(iidx);
                     type_stack.instr(&InstrType::new(&[I32], &[]));
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
                     // if_ hook for the condition (always executed on either branch)
                     if enabled_hooks.is_enabled(HighLevelHook::If) {
@@ -211,6 +264,8 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             hooks.begin_if()
                         ]);
                     }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
                 }
                 Else => {
                     let if_block = block_stack.else_();
@@ -220,15 +275,20 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         unreachable!()
                     };
 
-                    type_stack.else_();
+                    let block_ty = type_stack.else_();
 
+                    // end hook for the then branch, including its results
                     if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        let result_tmps = function.add_fresh_locals(&block_ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
                         instrumented_body.extend_from_slice(&[
                             location.0.clone(),
                             location.1.clone(),
                             begin_if.to_const(),
-                            hooks.end(&if_block),
                         ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&result_tmps, &function));
+                        instrumented_body.push(hooks.end(&if_block, &block_ty.results));
                     }
 
                     instrumented_body.push(instr);
@@ -241,11 +301,101 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             hooks.begin_else(),
                         ])
                     }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
+                }
+                Try(ref block_ty) => {
+                    block_stack.begin_try(iidx);
+                    type_stack.begin(block_ty.clone());
+
+                    instrumented_body.push(instr);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Begin) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            hooks.begin_try(),
+                        ])
+                    }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
                 }
-                End => {
+                Catch(_) | CatchAll => {
+                    let previous_block = block_stack.catch_(iidx);
+                    let (begin_try, previous_begin_catch) = match previous_block {
+                        BlockStackElement::Try { begin, .. } => (begin, None),
+                        BlockStackElement::Catch { begin_catch, begin_try, .. } => (begin_try, Some(begin_catch)),
+                        _ => unreachable!(),
+                    };
+
+                    let value_tys = match instr {
+                        Catch(tag_idx) => tag_value_tys[tag_idx.0].clone(),
+                        _ => vec![],
+                    };
+                    let block_ty = type_stack.catch_(&value_tys);
+
+                    // end hook for the try body (or previous catch block) if it completes normally, like for else
+                    if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        let result_tmps = function.add_fresh_locals(&block_ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                        ]);
+                        instrumented_body.extend(previous_begin_catch.map(ToConst::to_const));
+                        instrumented_body.push(begin_try.to_const());
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&result_tmps, &function));
+                        instrumented_body.push(hooks.end(&previous_block, &block_ty.results));
+                    }
+
+                    instrumented_body.push(instr.clone());
+
+                    // end hooks for all blocks from the throwing one up to (and including) the try block
+                    if let Some(block_local) = block_local {
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                            Local(GetLocal, block_local),
+                            begin_try.to_const(),
+                            hooks.unwind(),
+                        ]);
+                    }
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Catch) {
+                        let value_tmps = function.add_fresh_locals(&value_tys);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&value_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                        ]);
+                        if let Catch(tag_idx) = instr {
+                            instrumented_body.push(tag_idx.to_const());
+                        }
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&value_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &value_tys));
+                    }
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Begin) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            begin_try.to_const(),
+                            hooks.begin_catch(),
+                        ])
+                    }
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
+                }
+                // delegate ends a try block, just like end (the exception is forwarded to an outer handler)
+                End | Delegate(_) => {
                     let block = block_stack.end();
                     assert_eq!(iidx, block.end());
-                    type_stack.end();
+                    let result_tys = match type_stack.end() {
+                        Some(block_ty) => block_ty.results,
+                        None => function.type_.results.clone(),
+                    };
 
                     // add "synthetic" return hook call for implicit returns
                     if implicit_return
@@ -268,11 +418,17 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     // because the end hook that is inserted now is never called (dead code)
 
                     if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        let result_tmps = function.add_fresh_locals(&result_tys);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
                         instrumented_body.append(&mut block.to_end_hook_args(fidx));
-                        instrumented_body.push(hooks.end(&block))
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&result_tmps, &function));
+                        instrumented_body.push(hooks.end(&block, &result_tys))
                     }
 
                     instrumented_body.push(instr);
+
+                    instrumented_body.append(&mut set_innermost_block(&block_stack, block_local, &mut blocks));
                 }
 
 
@@ -297,7 +453,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     if enabled_hooks.is_enabled(HighLevelHook::End) {
                         for block in br_target.ended_blocks {
                             instrumented_body.append(&mut block.to_end_hook_args(fidx));
-                            instrumented_body.push(hooks.end(&block));
+                            instrumented_body.push(hooks.end(&block, &[]));
                         }
                     }
 
@@ -337,11 +493,11 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             instrumented_body.extend_from_slice(&[
                                 // NOTE see tee_local above
                                 Local(GetLocal, condition_tmp),
-                                If(BlockType(None)),
+                                If(BlockType::default()),
                             ]);
                             for block in br_target.ended_blocks {
                                 instrumented_body.append(&mut block.to_end_hook_args(fidx));
-                                instrumented_body.push(hooks.end(&block));
+                                instrumented_body.push(hooks.end(&block, &[]));
                             }
                             // of the artificially inserted if block before
                             instrumented_body.push(End);
@@ -405,7 +561,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     if enabled_hooks.is_enabled(HighLevelHook::End) {
                         for block in block_stack.return_target().ended_blocks {
                             instrumented_body.append(&mut block.to_end_hook_args(fidx));
-                            instrumented_body.push(hooks.end(&block));
+                            instrumented_body.push(hooks.end(&block, &[]));
                         }
                     }
 
@@ -449,7 +605,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(instr);
                     }
                 }
-                CallIndirect(ref func_ty, _ /* table idx == 0 in WASM version 1 */) => {
+                CallIndirect(ref func_ty, table_idx) => {
                     type_stack.instr(&instr.to_type().unwrap());
 
                     if enabled_hooks.is_enabled(HighLevelHook::Call) {
@@ -464,6 +620,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             Local(GetLocal, target_table_idx_tmp),
                             location.0.clone(),
                             location.1.clone(),
+                            table_idx.to_const(),
                             Local(GetLocal, target_table_idx_tmp),
                         ]);
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
@@ -488,6 +645,103 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     }
                 }
 
+                ReturnCall(_) | ReturnCallIndirect(_, _) => {
+                    let (func_ty, table_idx) = match instr {
+                        ReturnCall(target_func_idx) => (module_info.read().functions[target_func_idx.0].type_.clone(), None),
+                        ReturnCallIndirect(ref func_ty, table_idx) => (func_ty.clone(), Some(table_idx)),
+                        _ => unreachable!(),
+                    };
+                    // the callee results are returned from the current function
+                    if table_idx.is_some() {
+                        type_stack.pop_val();
+                    }
+                    type_stack.instr(&InstrType::new(&func_ty.params, &function.type_.results));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Call) || enabled_hooks.is_enabled(HighLevelHook::End) {
+                        let target_table_idx_tmp = table_idx.map(|_| function.add_fresh_local(I32));
+                        let arg_tmps = function.add_fresh_locals(&func_ty.params);
+
+                        instrumented_body.extend(target_table_idx_tmp.map(|tmp| Local(SetLocal, tmp)));
+                        instrumented_body.append(&mut save_stack_to_locals(&arg_tmps));
+
+                        // the tail call ends the current function, so first end all blocks "jumped over"
+                        // (as for return), and only then call the callee
+                        if enabled_hooks.is_enabled(HighLevelHook::End) {
+                            for block in block_stack.return_target().ended_blocks {
+                                instrumented_body.append(&mut block.to_end_hook_args(fidx));
+                                instrumented_body.push(hooks.end(&block, &[]));
+                            }
+                        }
+
+                        // pre call hook, marked as tail call (there is no post call hook in this function)
+                        if enabled_hooks.is_enabled(HighLevelHook::Call) {
+                            instrumented_body.extend_from_slice(&[
+                                location.0,
+                                location.1,
+                            ]);
+                            match (&instr, table_idx, target_table_idx_tmp) {
+                                (&ReturnCall(target_func_idx), _, _) => instrumented_body.push(target_func_idx.to_const()),
+                                (_, Some(table_idx), Some(tmp)) => instrumented_body.extend_from_slice(&[
+                                    table_idx.to_const(),
+                                    Local(GetLocal, tmp),
+                                ]),
+                                _ => unreachable!(),
+                            }
+                            instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
+                            instrumented_body.push(hooks.instr(&instr, &func_ty.params));
+                        }
+
+                        instrumented_body.extend(target_table_idx_tmp.map(|tmp| Local(GetLocal, tmp)));
+                    }
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+
+
+                /* Control Instructions: Exceptions */
+                // NOTE hooks must come before instr, unwinding is handled at the catching side
+
+                Throw(tag_idx) => {
+                    let value_tys = &tag_value_tys[tag_idx.0];
+                    type_stack.instr(&InstrType::new(value_tys, &[]));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Throw) {
+                        let value_tmps = function.add_fresh_locals(value_tys);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&value_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            tag_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&value_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, value_tys));
+                    }
+
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+                Rethrow(catch_label) => {
+                    if enabled_hooks.is_enabled(HighLevelHook::Throw) {
+                        let begin_catch = match block_stack.br_target(catch_label).ended_blocks.last() {
+                            Some(&BlockStackElement::Catch { begin_catch, .. }) => begin_catch,
+                            block => panic!("rethrow label must refer to a catch block, but got {:?}", block),
+                        };
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            begin_catch.to_const(),
+                            hooks.instr(&instr, &[]),
+                        ]);
+                    }
+
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+
 
                 /* Parametric Instructions */
 
@@ -509,7 +763,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(instr);
                     }
                 }
-                Select => {
+                Select | TypedSelect(_) => {
                     assert_eq!(type_stack.pop_val(), I32, "select condition should be i32");
                     let ty = type_stack.pop_val();
                     assert_eq!(type_stack.pop_val(), ty, "select arguments should have same type");
@@ -574,11 +828,46 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     }
                 }
 
+                Table(op, table_idx) => {
+                    let elem_ty = table_elem_tys[table_idx.0];
+                    let ty = op.to_type(elem_ty);
+                    type_stack.instr(&ty);
+
+                    let hook = match op {
+                        TableGet => HighLevelHook::TableGet,
+                        TableSet => HighLevelHook::TableSet,
+                        TableSize => HighLevelHook::TableSize,
+                        TableGrow => HighLevelHook::TableGrow,
+                        TableFill => HighLevelHook::TableFill,
+                    };
+
+                    // hook after the instruction, so it is only called if the operation did not trap
+                    if enabled_hooks.is_enabled(hook) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            table_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&result_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &[elem_ty.into()]));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+
 
                 /* Memory Instructions */
 
-                MemorySize(_ /* memory idx == 0 in WASM version 1 */) => {
-                    type_stack.instr(&instr.to_type().unwrap());
+                MemorySize(memory_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
 
                     instrumented_body.push(instr.clone());
 
@@ -586,18 +875,20 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.extend_from_slice(&[
                             location.0,
                             location.1,
-                            // optimization: just call memory_size again instead of duplicating result into local
-                            instr.clone(),
-                            hooks.instr(&instr, &[])
+                            memory_idx.to_const(),
                         ]);
+                        // optimization: just call memory_size again instead of duplicating result into local
+                        instrumented_body.append(&mut convert_i64_instr(instr.clone(), ty.results[0]));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&ty.results)));
                     }
                 }
-                MemoryGrow(_ /* memory idx == 0 in WASM version 1 */) => {
-                    type_stack.instr(&instr.to_type().unwrap());
+                MemoryGrow(memory_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
 
                     if enabled_hooks.is_enabled(HighLevelHook::MemoryGrow) {
-                        let input_tmp = function.add_fresh_local(I32);
-                        let result_tmp = function.add_fresh_local(I32);
+                        let input_tmp = function.add_fresh_local(ty.inputs[0]);
+                        let result_tmp = function.add_fresh_local(ty.results[0]);
 
                         instrumented_body.extend_from_slice(&[
                             Local(TeeLocal, input_tmp),
@@ -605,19 +896,72 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             Local(TeeLocal, result_tmp),
                             location.0,
                             location.1,
-                            Local(GetLocal, input_tmp),
-                            Local(GetLocal, result_tmp),
-                            hooks.instr(&instr, &[])
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmp, result_tmp], &function));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&ty.inputs)));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+
+                /* Bulk memory and table instructions */
+
+                MemoryInit(_, _) | MemoryCopy(_, _) | MemoryFill(_) | TableInit(_, _) | TableCopy(_, _) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
+
+                    // memory, table, and segment indices, index types of the accessed memories (for memory64)
+                    let (hook, immediates, index_tys) = match instr {
+                        MemoryInit(data_idx, memory_idx) => (HighLevelHook::MemoryInit, vec![memory_idx.to_const(), data_idx.to_const()], vec![memory_index_ty(memory_idx)]),
+                        MemoryCopy(dst_idx, src_idx) => (HighLevelHook::MemoryCopy, vec![dst_idx.to_const(), src_idx.to_const()], vec![memory_index_ty(dst_idx), memory_index_ty(src_idx)]),
+                        MemoryFill(memory_idx) => (HighLevelHook::MemoryFill, vec![memory_idx.to_const()], vec![memory_index_ty(memory_idx)]),
+                        TableInit(elem_idx, table_idx) => (HighLevelHook::TableInit, vec![table_idx.to_const(), elem_idx.to_const()], vec![]),
+                        TableCopy(dst_idx, src_idx) => (HighLevelHook::TableCopy, vec![dst_idx.to_const(), src_idx.to_const()], vec![]),
+                        _ => unreachable!(),
+                    };
+
+                    // hook after the instruction, so it is only called if the operation did not trap
+                    if enabled_hooks.is_enabled(hook) {
+                        // (destination, source or value, length)
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
                         ]);
+                        instrumented_body.extend(immediates);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&index_tys)));
                     } else {
                         instrumented_body.push(instr);
                     }
                 }
+                DataDrop(_) | ElemDrop(_) => {
+                    let (hook, segment_idx) = match instr {
+                        DataDrop(data_idx) => (HighLevelHook::DataDrop, data_idx.to_const()),
+                        ElemDrop(elem_idx) => (HighLevelHook::ElemDrop, elem_idx.to_const()),
+                        _ => unreachable!(),
+                    };
+
+                    instrumented_body.push(instr.clone());
+
+                    if enabled_hooks.is_enabled(hook) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            segment_idx,
+                            hooks.instr(&instr, &[])
+                        ]);
+                    }
+                }
 
                 /* rest are "grouped instructions", i.e., where many instructions can be handled in a similar manner */
 
-                Load(op, memarg) => {
-                    let ty = op.to_type();
+                Load(_, memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
                     type_stack.instr(&ty);
 
                     if enabled_hooks.is_enabled(HighLevelHook::Load) {
@@ -630,17 +974,16 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             Local(TeeLocal, value_tmp),
                             location.0,
                             location.1,
-                            Const(Val::I32(memarg.offset as i32)),
-                            Const(Val::I32(memarg.alignment as i32)),
                         ]);
+                        instrumented_body.append(&mut memarg_consts(memarg, ty.inputs[0]));
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
-                        instrumented_body.push(hooks.instr(&instr, &[]));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&[ty.inputs[0]])));
                     } else {
                         instrumented_body.push(instr);
                     }
                 }
-                Store(op, memarg) => {
-                    let ty = op.to_type();
+                Store(_, memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
                     type_stack.instr(&ty);
 
                     if enabled_hooks.is_enabled(HighLevelHook::Store) {
@@ -652,11 +995,58 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             instr.clone(),
                             location.0,
                             location.1,
-                            Const(Val::I32(memarg.offset as i32)),
-                            Const(Val::I32(memarg.alignment as i32)),
                         ]);
+                        instrumented_body.append(&mut memarg_consts(memarg, ty.inputs[0]));
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
-                        instrumented_body.push(hooks.instr(&instr, &[]));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&[ty.inputs[0]])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                LoadLane(_, memarg, lane) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Load) {
+                        let addr_tmp = function.add_fresh_local(ty.inputs[0]);
+                        let value_tmp = function.add_fresh_local(ty.results[0]);
+
+                        // the input vector does not need to be saved, the hook gets the resulting vector
+                        instrumented_body.extend_from_slice(&[
+                            Local(SetLocal, value_tmp),
+                            Local(TeeLocal, addr_tmp),
+                            Local(GetLocal, value_tmp),
+                            instr.clone(),
+                            Local(TeeLocal, value_tmp),
+                            location.0,
+                            location.1,
+                            Const(Val::I32(lane as i32)),
+                        ]);
+                        instrumented_body.append(&mut memarg_consts(memarg, ty.inputs[0]));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&[ty.inputs[0]])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                StoreLane(_, memarg, lane) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Store) {
+                        let addr_tmp = function.add_fresh_local(ty.inputs[0]);
+                        let value_tmp = function.add_fresh_local(ty.inputs[1]);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&[addr_tmp, value_tmp]));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
+                            Const(Val::I32(lane as i32)),
+                        ]);
+                        instrumented_body.append(&mut memarg_consts(memarg, ty.inputs[0]));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&[ty.inputs[0]])));
                     } else {
                         instrumented_body.push(instr);
                     }
@@ -665,8 +1055,9 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 
                 /* Numeric Instructions */
 
-                Const(val) => {
-                    type_stack.instr(&instr.to_type().unwrap());
+                Const(_) | RefNull(_) | RefFunc(_) => {
+                    let ty = instr.to_type().unwrap();
+                    type_stack.instr(&ty);
 
                     instrumented_body.push(instr.clone());
 
@@ -676,10 +1067,32 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             location.1,
                         ]);
                         // optimization: just call T.const again, instead of duplicating result into local
-                        instrumented_body.append(&mut convert_i64_instr(instr.clone(), val.to_type()));
+                        instrumented_body.append(&mut convert_i64_instr(instr.clone(), ty.results[0]));
                         instrumented_body.push(hooks.instr(&instr, &[]));
                     }
                 }
+                RefIsNull => {
+                    let ty = type_stack.pop_val();
+                    type_stack.push_val(I32);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Unary) {
+                        let input_tmp = function.add_fresh_local(ty);
+                        let result_tmp = function.add_fresh_local(I32);
+
+                        instrumented_body.extend_from_slice(&[
+                            Local(TeeLocal, input_tmp),
+                            instr.clone(),
+                            Local(TeeLocal, result_tmp),
+                            location.0,
+                            location.1,
+                            Local(GetLocal, input_tmp),
+                            Local(GetLocal, result_tmp),
+                            hooks.instr(&instr, &[ty]),
+                        ]);
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
                 Numeric(op) => {
                     let ty = op.to_type();
                     type_stack.instr(&ty);
@@ -704,11 +1117,122 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(instr);
                     }
                 }
+
+                /* Atomic Instructions */
+
+                AtomicLoad(_, memarg) | AtomicStore(_, memarg) | AtomicRmw(_, memarg) | AtomicCmpxchg(_, memarg)
+                | MemoryAtomicWait32(memarg) | MemoryAtomicWait64(memarg) | MemoryAtomicNotify(memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_ty).unwrap();
+                    type_stack.instr(&ty);
+
+                    let hook = match instr {
+                        AtomicLoad(_, _) => HighLevelHook::AtomicLoad,
+                        AtomicStore(_, _) => HighLevelHook::AtomicStore,
+                        AtomicRmw(_, _) => HighLevelHook::AtomicRmw,
+                        AtomicCmpxchg(_, _) => HighLevelHook::AtomicCmpxchg,
+                        MemoryAtomicWait32(_) | MemoryAtomicWait64(_) => HighLevelHook::AtomicWait,
+                        MemoryAtomicNotify(_) => HighLevelHook::AtomicNotify,
+                        _ => unreachable!(),
+                    };
+
+                    // hook after the instruction, so that the old value (or wait/notify result) is known
+                    if enabled_hooks.is_enabled(hook) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                        ]);
+                        instrumented_body.append(&mut memarg_consts(memarg, ty.inputs[0]));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(
+                            &[input_tmps, result_tmps].concat(),
+                            &function));
+                        instrumented_body.push(hooks.instr(&instr, &memory64_tys(&[ty.inputs[0]])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                AtomicFence => {
+                    instrumented_body.push(instr.clone());
+
+                    if enabled_hooks.is_enabled(HighLevelHook::AtomicFence) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            hooks.instr(&instr, &[])
+                        ]);
+                    }
+                }
+
+                /* SIMD Instructions (except for loads, stores, and v128.const, see above) */
+
+                Simd(_) | SimdLane(_, _) | Shuffle(_) => {
+                    let ty = instr.to_type().unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Simd) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                        ]);
+                        // immediates, see HookMap::instr()
+                        match instr {
+                            SimdLane(_, lane) => instrumented_body.push(Const(Val::I32(lane as i32))),
+                            Shuffle(lanes) => instrumented_body.extend(lanes.chunks(4).map(|chunk|
+                                Const(Val::I32(i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))))),
+                            _ => {}
+                        }
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(
+                            &[input_tmps, result_tmps].concat(),
+                            &function));
+                        instrumented_body.push(hooks.instr(&instr, &[]));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
             }
         }
 
+        // close the try block around the function body, see above
+        if let Some(block_local) = block_local {
+            let function_end = instrumented_body.pop();
+            assert_eq!(function_end, Some(End), "instrumented function body must end with end");
+            instrumented_body.extend_from_slice(&[
+                CatchAll,
+                fidx.to_const(),
+                Const(Val::I32(-1)),
+                Local(GetLocal, block_local),
+                // the function "block"
+                Const(Val::I32(-1)),
+                hooks.unwind(),
+                Rethrow(0.into()),
+                End,
+                End,
+            ]);
+            module_info.write().functions[fidx.0].blocks = blocks;
+        }
+
         // finally, switch dummy body out against instrumented body
         ::std::mem::replace(&mut function.code.as_mut().unwrap().body, instrumented_body);
+        // the original offsets are kept in the static info (see FunctionInfo and Wasabi.originalOffset())
+        function.code.as_mut().unwrap().original_offsets = None;
+
+        // give temporary locals recognizable debug names (e.g., in browser devtools)
+        let local_count = function.type_.params.len() + function.code.as_ref().unwrap().locals.len();
+        for local_idx in original_local_count..local_count {
+            let name = format!("__wasabi_tmp{}_{}", local_idx - original_local_count, function.local_type(local_idx.into()));
+            function.local_names.insert(local_idx.into(), name);
+        }
     });
 
     // actually add the hooks to module and check that inserted Idx is the one on the Hook struct
@@ -750,7 +1274,22 @@ impl BlockStackElement {
             | BlockStackElement::Block { begin, end }
             | BlockStackElement::Loop { begin, end }
             | BlockStackElement::If { begin_if: begin, end, .. } => vec![fidx.to_const(), end.to_const(), begin.to_const()],
-            | BlockStackElement::Else { begin_else, begin_if, end } => vec![fidx.to_const(), end.to_const(), begin_else.to_const(), begin_if.to_const()]
+            | BlockStackElement::Else { begin_else, begin_if, end } => vec![fidx.to_const(), end.to_const(), begin_else.to_const(), begin_if.to_const()],
+            | BlockStackElement::Try { begin, end } => vec![fidx.to_const(), end.to_const(), begin.to_const()],
+            | BlockStackElement::Catch { begin_catch, begin_try, end } => vec![fidx.to_const(), end.to_const(), begin_catch.to_const(), begin_try.to_const()],
+        }
+    }
+    /// to identify blocks at runtime, see set_innermost_block()
+    fn id(&self) -> i32 {
+        use self::block_stack::BlockStackElement::*;
+        match self {
+            | Function { .. } => -1,
+            | Block { begin, .. }
+            | Loop { begin, .. }
+            | If { begin_if: begin, .. }
+            | Else { begin_else: begin, .. }
+            | Try { begin, .. }
+            | Catch { begin_catch: begin, .. } => begin.0 as i32
         }
     }
     fn end(&self) -> Idx<Instr> {
@@ -760,8 +1299,47 @@ impl BlockStackElement {
             | Block { end, .. }
             | Loop { end, .. }
             | If { end, .. }
-            | Else { end, .. } => *end
+            | Else { end, .. }
+            | Try { end, .. }
+            | Catch { end, .. } => *end
+        }
+    }
+}
+
+/// memory index, offset, and alignment of a memory access (in this order), the offset has the same
+/// type as the address, i.e., is an i64 for memories with 64-bit addresses
+fn memarg_consts(memarg: Memarg, index_ty: ValType) -> Vec<Instr> {
+    let offset = match index_ty {
+        I64 => Val::I64(memarg.offset as i64),
+        _ => Val::I32(memarg.offset as i32),
+    };
+    let mut instrs = vec![memarg.memory.to_const()];
+    instrs.append(&mut convert_i64_instr(Const(offset), index_ty));
+    instrs.push(Const(Val::I32(memarg.alignment as i32)));
+    instrs
+}
+
+/// hooks for memories with 32-bit addresses keep their plain name, all others are mangled with
+/// the index types of the accessed memories
+fn memory64_tys(index_tys: &[ValType]) -> Vec<ValType> {
+    if index_tys.contains(&I64) {
+        index_tys.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+/// only if unwound blocks are tracked: returns the instructions for storing the id of the current
+/// innermost block (i.e., its begin instruction index) in block_local, and remembers that block
+/// together with its parent, such that the runtime can walk from the innermost block outwards
+fn set_innermost_block(block_stack: &BlockStack, block_local: Option<Idx<ast::Local>>, blocks: &mut BTreeMap<i32, UnwindBlock>) -> Vec<Instr> {
+    match (block_local, block_stack.innermost()) {
+        (Some(block_local), Some((block, parent))) => {
+            let id = block.id();
+            blocks.entry(id).or_insert_with(|| UnwindBlock(block.clone(), parent.map(BlockStackElement::id).unwrap_or(-1)));
+            vec![Const(Val::I32(id)), Local(SetLocal, block_local)]
         }
+        _ => vec![],
     }
 }
 
@@ -1,7 +1,13 @@
 use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use super::block_stack::{BlockStack, BlockStackElement};
 use wasm::ast::{FunctionType, Idx, Label, ValType};
 use wasm::ast::highlevel::{Function, Instr, Module};
+use wasm::call_graph::CallGraph;
+use wasm::cfg::{block_ends, BasicBlock, Cfg};
+use wasm::cfg::dominators::DominatorTree;
+use wasm::cfg::loops::{natural_loops, NaturalLoop};
+use wasm::const_eval::{ConstEvaluator, DataRange};
 
 /*
  * Structs for static information that is generated during instrumentation and output as JSON
@@ -14,25 +20,39 @@ pub struct ModuleInfo {
     #[serde(serialize_with = "serialize_types")]
     pub globals: Vec<ValType>,
     pub start: Option<Idx<Function>>,
-    #[serde(rename = "tableExportName")]
-    pub table_export_name: Option<String>,
+    #[serde(rename = "tableExportNames")]
+    pub table_export_names: Vec<Option<String>>,
 //    #[serde(rename = "firstFunctionExportName")]
 //    pub first_function_export_name: Option<String>,
     #[serde(rename = "brTables")]
     pub br_tables: Vec<BrTableInfo>,
+    /// where active data segments are placed in memory, null if passive or not statically known
+    #[serde(rename = "dataSegments")]
+    pub data_segments: Vec<Option<DataRange>>,
+    /// per table: the statically known function in each slot, by slot index
+    #[serde(rename = "tableContents")]
+    pub table_contents: Vec<BTreeMap<usize, Idx<Function>>>,
+    /// only with StaticInfoOptions::call_graph
+    #[serde(rename = "callGraph", skip_serializing_if = "Option::is_none")]
+    pub call_graph: Option<CallGraph>,
 }
 
 impl<'a> From<&'a Module> for ModuleInfo {
     fn from(module: &Module) -> Self {
+        // values of imported globals are unknown here, so some offsets may not be statically known
+        let const_eval = ConstEvaluator::new(module);
         ModuleInfo {
             functions: module.functions.iter().map(Into::into).collect(),
             globals: module.globals.iter().map(|g| g.type_.0).collect(),
             start: module.start,
-            // if the module has no table, there cannot be a call_indirect, so this null will never be read from JS runtime
-            table_export_name: module.tables.get(0).and_then(|table| table.export.iter().cloned().next()),
+            // one entry per table, since call_indirect may reference any of them
+            table_export_names: module.tables.iter().map(|table| table.export.iter().cloned().next()).collect(),
             // FIXME is this a valid workaround for wrong Firefox exported function .name property?
 //            first_function_export_name: module.functions.get(0).and_then(|func| func.export.iter().cloned().next()),
             br_tables: vec![],
+            data_segments: const_eval.data_ranges(),
+            table_contents: (0..module.tables.len()).map(|table| const_eval.table_functions(table.into())).collect(),
+            call_graph: None,
         }
     }
 }
@@ -48,6 +68,19 @@ pub struct FunctionInfo {
     #[serde(serialize_with = "serialize_types")]
     pub locals: Vec<ValType>,
     pub instr_count: usize,
+    /// byte offset of each instruction in the original binary, delta-encoded to keep it small
+    #[serde(serialize_with = "serialize_offset_deltas")]
+    pub instr_offsets: Option<Vec<usize>>,
+    /// only for modules using exceptions: all blocks by their id (begin instruction index), for
+    /// calling the end hooks of blocks that are unwound by an exception at runtime
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks: BTreeMap<i32, UnwindBlock>,
+    /// only with StaticInfoOptions::cfg, not for imported functions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cfg: Option<Cfg>,
+    /// only with StaticInfoOptions::loops
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub loops: Vec<LoopInfo>,
 }
 
 impl<'a> From<&'a Function> for FunctionInfo {
@@ -58,10 +91,69 @@ impl<'a> From<&'a Function> for FunctionInfo {
             export: function.export.clone(),
             locals: function.code.iter().flat_map(|code| code.locals.clone()).collect(),
             instr_count: function.instr_count(),
+            instr_offsets: function.code.as_ref().and_then(|code| code.original_offsets.clone()),
+            blocks: BTreeMap::new(),
+            cfg: None,
+            loops: Vec::new(),
         }
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+/// for loop profiling: every loop instruction, with the natural loop data (back edges and exits)
+/// if some branch targets it, i.e., if it can iterate at all
+pub struct LoopInfo {
+    pub begin: Idx<Instr>,
+    pub end: Idx<Instr>,
+    /// number of enclosing loop instructions (including this one), i.e., 1 for outermost loops
+    pub depth: usize,
+    /// the branch instructions that continue with the next iteration, empty if the loop cannot
+    /// iterate (then it executes its body at most once, just like a block)
+    pub back_edges: Vec<Idx<Instr>>,
+    /// the instruction that leaves the loop and the one execution continues at (the instruction
+    /// count if it leaves the function), empty if the loop cannot iterate
+    pub exits: Vec<(Idx<Instr>, Idx<Instr>)>,
+}
+
+impl LoopInfo {
+    pub fn from_body(body: &[Instr]) -> Vec<Self> {
+        let cfg = Cfg::new(body);
+        let dominators = DominatorTree::dominators(&cfg);
+        let last_instr = |block: Idx<BasicBlock>| Idx::from(cfg.blocks[block.0].end.0 - 1);
+        // every header starts with a loop instruction
+        let natural_loops: HashMap<Idx<Instr>, NaturalLoop> = natural_loops(&cfg, &dominators).into_iter()
+            .map(|loop_| (cfg.blocks[loop_.header.0].start, loop_))
+            .collect();
+
+        let ends = block_ends(body);
+        let mut enclosing_ends: Vec<Idx<Instr>> = Vec::new();
+        let mut loops = Vec::new();
+        for (begin, instr) in body.iter().enumerate() {
+            let begin = Idx::from(begin);
+            // not closed only in invalid bodies
+            let end = match (instr, ends[begin.0]) {
+                (Instr::Loop(_), Some(end)) => end,
+                _ => continue,
+            };
+            while enclosing_ends.last().map_or(false, |&enclosing_end| enclosing_end < begin) {
+                enclosing_ends.pop();
+            }
+            enclosing_ends.push(end);
+
+            let (back_edges, exits) = match natural_loops.get(&begin) {
+                Some(loop_) => (
+                    loop_.back_edges.iter().map(|edge| last_instr(edge.from)).collect(),
+                    loop_.exits.iter().map(|edge| (last_instr(edge.from), cfg.blocks[edge.to.0].start)).collect(),
+                ),
+                None => (Vec::new(), Vec::new()),
+            };
+            loops.push(LoopInfo { begin, end, depth: enclosing_ends.len(), back_edges, exits });
+        }
+        loops
+    }
+}
+
 fn serialize_function_type<S>(ty: &FunctionType, s: S) -> Result<S::Ok, S::Error> where S: Serializer {
     let mut type_str = String::new();
     for ty in &ty.params {
@@ -74,6 +166,17 @@ fn serialize_function_type<S>(ty: &FunctionType, s: S) -> Result<S::Ok, S::Error
     s.serialize_str(&type_str)
 }
 
+fn serialize_offset_deltas<S>(offsets: &Option<Vec<usize>>, s: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    offsets.as_ref().map(|offsets| {
+        let mut previous = 0;
+        offsets.iter().map(|&offset| {
+            let delta = offset - previous;
+            previous = offset;
+            delta
+        }).collect::<Vec<_>>()
+    }).serialize(s)
+}
+
 fn serialize_types<S>(tys: &[ValType], s: S) -> Result<S::Ok, S::Error> where S: Serializer {
     let mut type_str = String::new();
     for ty in tys {
@@ -118,6 +221,10 @@ pub struct ResolvedLabel {
     pub end_blocks: Vec<BlockStackElement>,
 }
 
+#[derive(Serialize)]
+/// a block and the id of the block it is nested in (-1 for the function block, which has no parent)
+pub struct UnwindBlock(pub BlockStackElement, pub i32);
+
 #[derive(Serialize)]
 pub struct Location(pub Idx<Function>, pub Idx<Instr>);
 
@@ -131,6 +238,8 @@ impl Serialize for BlockStackElement {
             Loop { begin, end } => ("loop", begin, end).serialize(serializer),
             If { begin_if, end, .. } => ("if", begin_if, end).serialize(serializer),
             Else { begin_else, end, begin_if } => ("else", begin_else, end, begin_if).serialize(serializer),
+            Try { begin, end } => ("try", begin, end).serialize(serializer),
+            Catch { begin_catch, end, begin_try } => ("catch", begin_catch, end, begin_try).serialize(serializer),
         }
     }
 }
\ No newline at end of file
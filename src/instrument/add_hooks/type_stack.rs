@@ -1,4 +1,5 @@
-use wasm::ast::{BlockType, ValType, InstrType};
+use wasm::ast::{ValType, InstrType};
+use wasm::ast::highlevel::BlockType;
 use self::TypeStackElement::*;
 
 /*
@@ -52,12 +53,20 @@ impl TypeStack {
         }
     }
 
+    /// pops the block parameters from the stack and pushes them again "inside" the block
     pub fn begin(&mut self, block_ty: BlockType) {
-        self.0.push(BlockBegin(block_ty))
+        for &param_ty in block_ty.params.iter().rev() {
+            assert_eq!(param_ty, self.pop_val(), "block expected parameter type, but stack top was");
+        }
+        let params = block_ty.params.clone();
+        self.0.push(BlockBegin(block_ty));
+        for param_ty in params {
+            self.push_val(param_ty);
+        }
     }
 
     /// implicitly pops all types from the stack until the last block begin
-    /// pushes that blocks result type on the stack
+    /// pushes that blocks result types on the stack
     /// returns the BlockType of that last block, or None if the last block was the whole function
     pub fn end(&mut self) -> Option<BlockType> {
         loop {
@@ -67,8 +76,8 @@ impl TypeStack {
                 Some(BlockBegin(block_ty)) => {
                     // NOTE there is no validation that the stack is correct at the end of a block
                     // it is unclear to me how it exactly works with, e.g., br/return + drops
-                    if let BlockType(Some(ty)) = block_ty {
-                        self.push_val(ty);
+                    for &result_ty in &block_ty.results {
+                        self.push_val(result_ty);
                     }
                     return Some(block_ty);
                 }
@@ -77,14 +86,34 @@ impl TypeStack {
         }
     }
 
-    pub fn else_(&mut self) {
+    /// returns the BlockType of the if block
+    pub fn else_(&mut self) -> BlockType {
         // reuse code from end...
         let block_ty = self.end().expect("else cannot end a function");
-        // but undo pushing of block result (this will be done by the "real" end)
-        if let BlockType(Some(ty)) = block_ty {
-            assert_eq!(ty, self.pop_val());
+        // but undo pushing of block results (this will be done by the "real" end)...
+        for &result_ty in block_ty.results.iter().rev() {
+            assert_eq!(result_ty, self.pop_val());
+        }
+        // ...and the else branch gets the same parameters as the then branch
+        for &param_ty in &block_ty.params {
+            self.push_val(param_ty);
+        }
+        self.begin(block_ty.clone());
+        block_ty
+    }
+
+    /// like else_(), but the catch block starts with the values thrown with the tag (or none for
+    /// catch_all) instead of the block parameters, returns the BlockType of the try block
+    pub fn catch_(&mut self, values: &[ValType]) -> BlockType {
+        let block_ty = self.end().expect("catch cannot end a function");
+        for &result_ty in block_ty.results.iter().rev() {
+            assert_eq!(result_ty, self.pop_val());
+        }
+        self.0.push(BlockBegin(block_ty.clone()));
+        for &ty in values {
+            self.push_val(ty);
         }
-        self.begin(block_ty);
+        block_ty
     }
 
 // TODO see add_hooks/mod.rs
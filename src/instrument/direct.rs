@@ -1,5 +1,5 @@
-use wasm::ast::{FunctionType, Mutability, Val, ValType::*};
-use wasm::ast::highlevel::{Instr::*, Module, GlobalOp::*, NumericOp::*};
+use wasm::ast::{FunctionType, Idx, Mutability, Val, ValType::*};
+use wasm::ast::highlevel::{Function, Instr::*, Module, GlobalOp::*, NumericOp::*, Table, VisitContext, VisitorMut};
 
 /* direct or "low-level" instrumentations, i.e., where the byte code is manually modified */
 
@@ -31,15 +31,32 @@ pub fn count_calls(module: &mut Module) -> Option<String> {
             End
         ]);
 
-    for (i, function) in module.functions() {
+    module.visit_mut(&mut CountCalls { getter, increment });
+
+    None
+}
+
+struct CountCalls {
+    getter: Idx<Function>,
+    increment: Idx<Function>,
+}
+
+impl CountCalls {
+    fn increment_before(&self, ctx: &mut VisitContext) {
         // ignore the functions we added
-        if i != getter && i != increment {
-            function.modify_instr(|instr| match instr {
-                Call(..) | CallIndirect(..) => vec![Call(increment), instr],
-                instr => vec![instr]
-            })
+        if ctx.function != self.getter && ctx.function != self.increment {
+            ctx.insert_before(vec![Call(self.increment)]);
         }
     }
+}
 
-    None
-}
\ No newline at end of file
+// tail calls are visited as calls by default
+impl VisitorMut for CountCalls {
+    fn visit_call(&mut self, ctx: &mut VisitContext, _: Idx<Function>) {
+        self.increment_before(ctx);
+    }
+
+    fn visit_call_indirect(&mut self, ctx: &mut VisitContext, _: &FunctionType, _: Idx<Table>) {
+        self.increment_before(ctx);
+    }
+}
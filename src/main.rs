@@ -2,9 +2,10 @@ extern crate wasabi;
 extern crate wasm;
 
 use std::{env, fs, io, path::PathBuf};
-use wasabi::config::EnabledHooks;
+use wasabi::config::{EnabledHooks, StaticInfoOptions};
 use wasabi::instrument::add_hooks;
 use wasm::ast::highlevel::Module;
+use wasm::validate::validate;
 
 // TODO use proper command-line option parser like clap, or structopt on top of it (https://docs.rs/structopt/0.2.10/structopt/)
 // TODO use failure crate and failure::Error type for error handling
@@ -22,7 +23,13 @@ Produces two files in <output_dir> (default: out/):
 Options:
   --hooks=<comma-separated list>     Instrument ONLY for the given hooks.
   --no-hooks=<comma-separated list>  Instrument for all BUT the given hooks.
-                                     (Default: Instrument for all hooks.)"#,
+                                     (Default: Instrument for all hooks.)
+  --cfg                              Add the control-flow graph of each function to the static information.
+  --call-graph                       Add the static call graph to the static information.
+  --loops                            Add the loops of each function (with nesting depth and exits)
+                                     to the static information.
+  --validate                         Validate the input module before instrumenting it.
+                                     (The instrumented module is always validated.)"#,
                   error);
     }
 }
@@ -31,8 +38,8 @@ fn main_inner() -> io::Result<()> {
     let (options, args): (Vec<String>, Vec<String>) = env::args()
         // skip first argument (program name)
         .skip(1)
-        // --hooks and --no-hooks options
-        .partition(|arg| arg.starts_with("--hooks") || arg.starts_with("--no-hooks"));
+        // --hooks, --no-hooks, --validate, and static info options
+        .partition(|arg| arg.starts_with("--"));
     let mut args = args.into_iter();
     let input_file = PathBuf::from(args.next().ok_or(io_err("expected at least one argument"))?);
     let output_dir = PathBuf::from(args.next().unwrap_or("out".to_string()));
@@ -44,6 +51,18 @@ fn main_inner() -> io::Result<()> {
     let output_file_wasm = output_file_stem.with_extension("wasm");
     let output_file_js = output_file_stem.with_extension("wasabi.js");
 
+    let mut static_info = StaticInfoOptions::default();
+    let mut validate_input = false;
+    let options: Vec<String> = options.into_iter()
+        .filter(|option| match option.as_str() {
+            "--validate" => { validate_input = true; false }
+            "--cfg" => { static_info.cfg = true; false }
+            "--call-graph" => { static_info.call_graph = true; false }
+            "--loops" => { static_info.loops = true; false }
+            _ => true,
+        })
+        .collect();
+
     let enabled_hooks = match options.as_slice() {
         [] => EnabledHooks::all(),
         [option] if option.starts_with("--hooks=") =>
@@ -55,7 +74,12 @@ fn main_inner() -> io::Result<()> {
 
     // instrument Wasm and generate JavaScript
     let mut module = Module::from_file(input_file.clone())?;
-    let js = add_hooks(&mut module, &enabled_hooks).unwrap();
+    if validate_input {
+        validate(&module).map_err(|e| io_err(&format!("invalid input module: {}", e)))?;
+    }
+    let js = add_hooks(&mut module, &enabled_hooks, &static_info).unwrap();
+    // NOTE this should never fail, if it does, it is a bug in the instrumentation
+    validate(&module).map_err(|e| io_err(&format!("instrumented module is invalid: {}", e)))?;
 
     // write output files
     fs::create_dir_all(output_dir)?;
@@ -1,6 +1,8 @@
+use config::{EnabledHooks, StaticInfoOptions};
 use instrument::{add_hooks, direct::*};
 use test_utilities::*;
 use wasm::ast::highlevel::Module;
+use wasm::validate::validate;
 
 const TEST_INPUTS: &'static str = "tests/inputs";
 
@@ -16,19 +18,23 @@ fn count_calls_instrumentation_produces_valid_wasm() {
 
 #[test]
 fn add_hooks_instrumentation_produces_valid_wasm() {
-    test_instrument(add_hooks, "add-hooks");
+    test_instrument(|module| add_hooks(module, &EnabledHooks::all(), &StaticInfoOptions::all()), "add-hooks");
 }
 
 /// utility function
 fn test_instrument(instrument: impl Fn(&mut Module) -> Option<String>, instrument_name: &'static str) {
-    for path in wasm_files(TEST_INPUTS).unwrap() {
-        let mut module = Module::from_file(&path).unwrap();
+    let wasm_modules = wasm_files(TEST_INPUTS).unwrap().into_iter()
+        .map(|path| (Module::from_file(&path).unwrap(), path));
+    let wat_modules = wat_files(TEST_INPUTS).unwrap().into_iter()
+        .map(|path| (Module::from_wat_file(&path).unwrap(), path));
+    for (mut module, path) in wasm_modules.chain(wat_modules) {
         let javascript = instrument(&mut module);
 
-        let output_path = output_file(&path, instrument_name).unwrap();
+        let output_path = output_file(path.with_extension("wasm"), instrument_name).unwrap();
         module.to_file(&output_path).unwrap();
 
-        wasm_validate(&output_path)
+        let output_module = Module::from_file(&output_path).unwrap();
+        validate(&output_module)
             .expect(&format!("could not instrument wasm file '{}' with {}", path.display(), instrument_name));
 
         for javascript in javascript {
@@ -0,0 +1,35 @@
+(module
+  (memory 1 1 shared)
+  (func $start
+    ;; atomic loads and stores
+    i32.const 8
+    i64.const 42
+    i64.atomic.store
+    i32.const 8
+    i32.atomic.load8_u
+    drop
+
+    ;; read-modify-write and compare-exchange return the old value
+    i32.const 0
+    i32.const 5
+    i32.atomic.rmw.add
+    drop
+    i32.const 8
+    i64.const 42
+    i64.const 43
+    i64.atomic.rmw.cmpxchg
+    drop
+    atomic.fence
+
+    ;; wait does not block, since the value is not the expected one
+    i32.const 0
+    i32.const 0
+    i64.const 1000
+    memory.atomic.wait32
+    drop
+    i32.const 0
+    i32.const 1
+    memory.atomic.notify
+    drop)
+  (start $start)
+)
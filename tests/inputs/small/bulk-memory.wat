@@ -0,0 +1,37 @@
+(module
+  (memory 1)
+  (table 4 anyfunc)
+  (data (i32.const 0) "hello")
+  (data $passive "world")
+  (elem (i32.const 0) $f)
+  (elem $passive_elem func $f $g)
+  (elem declare func $g)
+  (func $f)
+  (func $g)
+  (func $start
+    ;; copy "hello" to offset 16, then overwrite it partially
+    i32.const 16
+    i32.const 0
+    i32.const 5
+    memory.copy
+    i32.const 18
+    i32.const 0x2a
+    i32.const 2
+    memory.fill
+    i32.const 32
+    i32.const 1
+    i32.const 4
+    memory.init $passive
+    data.drop $passive
+
+    i32.const 1
+    i32.const 0
+    i32.const 2
+    table.init $passive_elem
+    elem.drop $passive_elem
+    i32.const 3
+    i32.const 0
+    i32.const 1
+    table.copy)
+  (start $start)
+)
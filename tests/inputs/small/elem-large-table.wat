@@ -0,0 +1,8 @@
+(module
+  (table $t 10000000 funcref)
+  (func $f)
+  (func $g)
+  ;; only the written slots end up in the static info, not the whole table
+  (elem (i32.const 9999998) $f $g)
+  (elem (i32.const 9999999) funcref (ref.null func))
+)
@@ -0,0 +1,51 @@
+(module
+  (tag $e (param i32 i64))
+  (tag $empty)
+  ;; throws from within nested blocks, which are unwound
+  (func $thrower (param i32)
+    block
+      loop
+        get_local 0
+        i64.const 42
+        throw $e
+      end
+    end)
+  ;; exception leaves the try body from a callee, is caught and rethrown
+  (func $rethrower (param i32) (result i32)
+    try (result i32)
+      get_local 0
+      call $thrower
+      i32.const 0
+    catch $e
+      drop
+      drop
+      rethrow 0
+    catch_all
+      i32.const 1
+    end)
+  (func $catcher (result i32)
+    try (result i32)
+      block
+        ;; forwarded to the outer try
+        try
+          i32.const 7
+          call $rethrower
+          drop
+        delegate 1
+      end
+      i32.const 0
+    catch $e
+      drop
+    end)
+  (func $catch_all
+    try
+      throw $empty
+    catch_all
+      nop
+    end)
+  (func $start
+    call $catcher
+    drop
+    call $catch_all)
+  (start $start)
+)
@@ -0,0 +1,22 @@
+(module
+  (func $start
+    (local $i i32)
+    loop $outer
+      ;; never branched to, executes once, but still counts for the depth
+      loop $once
+        loop $inner
+          get_local $i
+          i32.const 1
+          i32.add
+          tee_local $i
+          i32.const 3
+          i32.lt_s
+          br_if $inner
+        end
+      end
+      get_local $i
+      i32.const 9
+      i32.lt_s
+      br_if $outer
+    end)
+  (start $start))
@@ -0,0 +1,33 @@
+(module
+  (memory $small 1)
+  (memory $large i64 1 2)
+  (data (memory $large) (i64.const 16) "\2a\00\00\00")
+  (func $start
+    ;; copy from the 64-bit memory into the first one
+    i32.const 0
+    i64.const 16
+    i32.const 4
+    memory.copy $small $large
+    i32.const 0
+    i32.load $small
+    drop
+    ;; 64-bit address and offset
+    i64.const 8
+    i64.const 0x1_0000_0001
+    i64.store $large offset=8
+    i64.const 0
+    i64.load $large offset=16
+    drop
+    i64.const 32
+    i32.const 7
+    i64.const 4
+    memory.fill $large
+    i64.const 1
+    memory.grow $large
+    drop
+    memory.size $large
+    drop
+    memory.size
+    drop)
+  (start $start)
+)
@@ -0,0 +1,49 @@
+(module
+  (func $swap (param i32 i64) (result i64 i32)
+    local.get 1
+    local.get 0)
+  (func $pair (result i32 f64)
+    i32.const 1
+    f64.const 2.5)
+  (func $start (local i32)
+    ;; function with multiple results
+    i32.const 3
+    i64.const 4
+    call $swap
+    drop
+    drop
+
+    ;; block with parameters and multiple results
+    i32.const 5
+    block (param i32) (result i32 i32)
+      i32.const 6
+    end
+    i32.add
+    drop
+
+    ;; loop parameters are the branch target type
+    i32.const 10
+    loop $l (param i32) (result i32)
+      i32.const 1
+      i32.sub
+      local.tee 0
+      local.get 0
+      br_if $l
+    end
+    drop
+
+    ;; if with parameters, folded and flat
+    call $pair
+    drop
+    (if (param i32) (result i32 i32) (i32.const 1)
+      (then i32.const 7)
+      (else i32.const 8))
+    drop
+    drop
+    i64.const 9
+    i32.const 0
+    if (param i64) (result i64)
+    end
+    drop)
+  (start $start)
+)
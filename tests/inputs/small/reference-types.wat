@@ -0,0 +1,51 @@
+(module
+  (type $i_i (func (param i32) (result i32)))
+  (table $funcs 2 funcref)
+  (table $callbacks 2 funcref)
+  (table $objects 1 externref)
+  (elem (table $funcs) (i32.const 0) funcref (ref.func $one) (ref.null func))
+  (elem (table $callbacks) (i32.const 0) func $inc $one)
+  (func $one (param i32) (result i32)
+    i32.const 1)
+  (func $inc (param i32) (result i32)
+    get_local 0
+    i32.const 1
+    i32.add)
+  (func $start (local externref)
+    ;; table.get/set and ref instructions
+    i32.const 1
+    i32.const 0
+    table.get $funcs
+    table.set $funcs
+    i32.const 0
+    get_local 0
+    table.set $objects
+    i32.const 0
+    table.get $objects
+    ref.is_null
+    drop
+    ref.func $inc
+    i32.const 1
+    table.grow $callbacks
+    drop
+    i32.const 0
+    ref.null func
+    i32.const 1
+    table.fill $funcs
+    table.size $callbacks
+    drop
+
+    ;; typed select on references
+    ref.null extern
+    get_local 0
+    i32.const 1
+    select (result externref)
+    drop
+
+    ;; call_indirect through the second table
+    i32.const 41
+    i32.const 0
+    call_indirect $callbacks (type $i_i)
+    drop)
+  (start $start)
+)
@@ -0,0 +1,15 @@
+(module
+  (func $start
+    i32.const 255
+    i32.extend8_s
+    i32.extend16_s
+    drop
+
+    i64.const 0xffff_ffff
+    i64.extend8_s
+    i64.extend16_s
+    i64.extend32_s
+    drop
+  )
+  (start $start)
+)
@@ -0,0 +1,43 @@
+(module
+  (memory 1)
+  (func $dot (param v128 v128) (result v128)
+    get_local 0
+    get_local 1
+    i32x4.dot_i16x8_s)
+  (func $start (local v128)
+    ;; const, splat, and lane operations
+    v128.const i32x4 1 2 3 4
+    set_local 0
+    get_local 0
+    i32.const 7
+    i16x8.splat
+    call $dot
+    i64x2.extract_lane 1
+    drop
+    get_local 0
+    f32.const 1.5
+    f32x4.replace_lane 2
+    get_local 0
+    i8x16.shuffle 0 1 2 3 16 17 18 19 4 5 6 7 20 21 22 23
+    v128.any_true
+    drop
+
+    ;; vector loads and stores
+    i32.const 16
+    get_local 0
+    v128.store
+    i32.const 8
+    i32.const 20
+    i32.const 16
+    v128.load16x4_s
+    v128.load32_lane 3
+    v128.store64_lane offset=8 1
+
+    ;; polymorphic instructions on v128
+    get_local 0
+    get_local 0
+    i32.const 0
+    select
+    drop)
+  (start $start)
+)
@@ -0,0 +1,17 @@
+(module
+  (table $a 4 funcref)
+  (table $b 4 funcref)
+  (func $f)
+  (elem $passive func $f $f)
+  (func $start
+    ;; into the second table, so the hooks must distinguish the tables
+    i32.const 0
+    i32.const 0
+    i32.const 2
+    table.init $b $passive
+    i32.const 1
+    i32.const 0
+    i32.const 2
+    table.copy $a $b)
+  (start $start)
+)
@@ -0,0 +1,34 @@
+(module
+  (type $i_i (func (param i32) (result i32)))
+  (table 1 funcref)
+  (elem (i32.const 0) $even)
+  (func $even (param i32) (result i32)
+    get_local 0
+    i32.eqz
+    if
+      i32.const 1
+      return
+    end
+    get_local 0
+    i32.const 1
+    i32.sub
+    return_call $odd)
+  (func $odd (param i32) (result i32)
+    block
+      get_local 0
+      i32.eqz
+      br_if 0
+      get_local 0
+      i32.const 1
+      i32.sub
+      ;; indirect tail call to $even from within a block
+      i32.const 0
+      return_call_indirect (type $i_i)
+    end
+    i32.const 0)
+  (func $start
+    i32.const 3
+    call $even
+    drop)
+  (start $start)
+)
@@ -0,0 +1,30 @@
+(module
+  (func $start
+    f32.const nan
+    i32.trunc_sat_f32_s
+    drop
+    f32.const -1
+    i32.trunc_sat_f32_u
+    drop
+    f64.const inf
+    i32.trunc_sat_f64_s
+    drop
+    f64.const 1e100
+    i32.trunc_sat_f64_u
+    drop
+
+    f32.const -inf
+    i64.trunc_sat_f32_s
+    drop
+    f32.const 3.5
+    i64.trunc_sat_f32_u
+    drop
+    f64.const -0x1p70
+    i64.trunc_sat_f64_s
+    drop
+    f64.const 0x1p70
+    i64.trunc_sat_f64_u
+    drop
+  )
+  (start $start)
+)